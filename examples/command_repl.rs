@@ -0,0 +1,33 @@
+// Command interpreter built on the `repl` module
+//
+// Run with: cargo run --example command_repl --features repl
+
+use core::fmt::Write;
+
+use editline::repl::Repl;
+use editline::terminals::StdioTerminal;
+
+fn main() {
+    let mut repl = Repl::new(StdioTerminal::new());
+    repl.set_banner(
+        "Command REPL - type 'help' for a list of commands, 'exit' or Ctrl-D to quit\n",
+    );
+    repl.set_prompt("cmd> ");
+    repl.add_command("echo", "Print the arguments", |args, out| {
+        writeln!(out, "{}", args.join(" "))
+    });
+    repl.add_command("add", "Add integers", |args, out| {
+        let mut sum: i64 = 0;
+        for arg in args {
+            match arg.parse::<i64>() {
+                Ok(n) => sum += n,
+                Err(_) => return writeln!(out, "add: '{}' is not an integer", arg),
+            }
+        }
+        writeln!(out, "{}", sum)
+    });
+
+    if let Err(e) = repl.run() {
+        eprintln!("Error reading input: {}", e);
+    }
+}
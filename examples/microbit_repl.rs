@@ -14,14 +14,17 @@
 
 extern crate alloc;
 
-use cortex_m_rt::entry;
-use panic_halt as _;
-use core::ptr::addr_of_mut;
+use alloc_cortex_m::CortexMHeap;
 use core::fmt::Write as FmtWrite;
+use core::ptr::addr_of_mut;
+use cortex_m_rt::entry;
+use editline::{Error, IoCause, KeyEvent, LineEditor, Result, Terminal};
 use embedded_io::Read as EmbeddedRead;
-use microbit::{Board, hal::uarte::{Baudrate, Parity, Uarte, UarteRx, UarteTx, Instance}};
-use editline::{LineEditor, Terminal, KeyEvent, Result, Error};
-use alloc_cortex_m::CortexMHeap;
+use microbit::{
+    hal::uarte::{Baudrate, Instance, Parity, Uarte, UarteRx, UarteTx},
+    Board,
+};
+use panic_halt as _;
 
 static mut TX_BUF: [u8; 1] = [0; 1];
 static mut RX_BUF: [u8; 1] = [0; 1];
@@ -47,7 +50,9 @@ impl<T: Instance> UarteTerminal<T> {
 
     fn read_byte_blocking(&mut self) -> Result<u8> {
         let mut buf = [0u8];
-        self.rx.read_exact(&mut buf).map_err(|_| Error::Io("UART read failed"))?;
+        self.rx
+            .read_exact(&mut buf)
+            .map_err(|_| Error::from(IoCause::Other))?;
         Ok(buf[0])
     }
 }
@@ -58,8 +63,9 @@ impl<T: Instance> Terminal for UarteTerminal<T> {
     }
 
     fn write(&mut self, data: &[u8]) -> Result<()> {
-        self.tx.write_str(core::str::from_utf8(data).map_err(|_| Error::InvalidUtf8)?)
-            .map_err(|_| Error::Io("UART write failed"))
+        self.tx
+            .write_str(core::str::from_utf8(data).map_err(|_| Error::InvalidUtf8)?)
+            .map_err(|_| Error::from(IoCause::Other))
     }
 
     fn flush(&mut self) -> Result<()> {
@@ -136,7 +142,8 @@ impl<T: Instance> Terminal for UarteTerminal<T> {
                                 if let Ok(semicolon) = self.read_byte_blocking() {
                                     if semicolon == b';' {
                                         if let Ok(modifier) = self.read_byte_blocking() {
-                                            if modifier == b'5' { // Ctrl modifier
+                                            if modifier == b'5' {
+                                                // Ctrl modifier
                                                 if let Ok(final_byte) = self.read_byte_blocking() {
                                                     match final_byte {
                                                         b'D' => return Ok(KeyEvent::CtrlLeft),
@@ -181,6 +188,10 @@ impl<T: Instance> Terminal for UarteTerminal<T> {
         // Unknown/control character - treat as null
         Ok(KeyEvent::Normal('\0'))
     }
+
+    fn capabilities(&self) -> editline::Caps {
+        editline::Caps::ANSI
+    }
 }
 
 #[entry]
@@ -200,10 +211,14 @@ fn main() -> ! {
     );
 
     let mut terminal = UarteTerminal::new(serial);
-    let mut editor = LineEditor::new(256, 20);  // 256 byte buffer, 20 history entries
-
-    terminal.write(b"micro:bit Rust REPL with editline!\r\n").ok();
-    terminal.write(b"Features: history (up/down), line editing, backspace\r\n\r\n").ok();
+    let mut editor = LineEditor::new(256, 20); // 256 byte buffer, 20 history entries
+
+    terminal
+        .write(b"micro:bit Rust REPL with editline!\r\n")
+        .ok();
+    terminal
+        .write(b"Features: history (up/down), line editing, backspace\r\n\r\n")
+        .ok();
 
     loop {
         terminal.write(b"> ").ok();
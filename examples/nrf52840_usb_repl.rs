@@ -0,0 +1,113 @@
+//! nRF52840 USB CDC REPL example using editline
+//!
+//! This example demonstrates line editing over USB CDC on nRF52840 boards
+//! such as the nRF52840 Dongle. The board will appear as a virtual COM port
+//! on your computer.
+//!
+//! To build this example:
+//! ```
+//! cargo build --example nrf52840_usb_repl --target thumbv7em-none-eabihf --no-default-features --features nrf52840_usb --release
+//! ```
+//!
+//! Then flash using the instructions in the README.
+//!
+//! Connect to the board's USB serial port:
+//! ```
+//! minicom -D /dev/ttyACM0 -b 115200
+//! ```
+
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc_cortex_m::CortexMHeap;
+use cortex_m_rt::entry;
+use panic_halt as _;
+
+use nrf52840_hal::{
+    clocks::Clocks,
+    pac,
+    usbd::{UsbPeripheral, Usbd},
+};
+
+use usb_device::prelude::*;
+use usbd_serial::SerialPort;
+
+use editline::{terminals::nrf52840_usb::UsbCdcTerminal, LineEditor, Terminal};
+
+#[global_allocator]
+static ALLOCATOR: CortexMHeap = CortexMHeap::empty();
+
+#[entry]
+fn main() -> ! {
+    // Initialize the allocator
+    const HEAP_SIZE: usize = 8192;
+    static mut HEAP: [u8; HEAP_SIZE] = [0; HEAP_SIZE];
+    unsafe { ALLOCATOR.init(&raw mut HEAP as *const u8 as usize, HEAP_SIZE) }
+
+    let periph = pac::Peripherals::take().unwrap();
+
+    // USB needs the external high-frequency oscillator
+    let clocks = Clocks::new(periph.CLOCK).enable_ext_hfosc();
+
+    // Set up the USB driver
+    let usb_bus = Usbd::new(UsbPeripheral::new(periph.USBD, &clocks));
+
+    // Set up the USB Communications Class Device driver
+    let serial = SerialPort::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .strings(&[StringDescriptors::new(LangID::EN)
+            .manufacturer("Nordic Semiconductor")
+            .product("nRF52840 REPL")
+            .serial_number("TEST")])
+        .unwrap()
+        .device_class(usbd_serial::USB_CLASS_CDC)
+        .build();
+
+    // Create our terminal and line editor
+    let mut terminal = UsbCdcTerminal::new(usb_dev, serial);
+    let mut editor = LineEditor::new(512, 50); // 512 byte buffer, 50 history entries
+
+    // Wait for first byte from terminal (don't echo it - just use it as connection signal)
+    let _ = terminal.read_byte();
+
+    // Send banner now that we know terminal is connected
+    terminal
+        .write(b"\r\n\r\nnRF52840 USB REPL with editline!\r\n")
+        .ok();
+    terminal.write(b"Commands:\r\n").ok();
+    terminal.write(b"  help - Show this help message\r\n").ok();
+    terminal.write(b"\r\n").ok();
+    terminal.flush().ok();
+
+    loop {
+        terminal.write(b"nrf> ").ok();
+
+        match editor.read_line(&mut terminal) {
+            Ok(line) => {
+                if line == "help" {
+                    terminal.write(b"Available commands:\r\n").ok();
+                    terminal.write(b"  help - Show this help message\r\n").ok();
+                    terminal.write(b"\r\nKey bindings:\r\n").ok();
+                    terminal
+                        .write(b"  Arrow keys: Navigate cursor and history\r\n")
+                        .ok();
+                    terminal.write(b"  Ctrl+Left/Right: Move by word\r\n").ok();
+                    terminal
+                        .write(b"  Home/End: Jump to start/end of line\r\n")
+                        .ok();
+                } else if !line.is_empty() {
+                    terminal.write(b"You typed: ").ok();
+                    terminal.write(line.as_bytes()).ok();
+                    terminal.write(b"\r\n").ok();
+                }
+            }
+            Err(_) => {
+                terminal.write(b"\r\nError reading line\r\n").ok();
+            }
+        }
+    }
+}
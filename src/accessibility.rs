@@ -0,0 +1,119 @@
+//! Output for screen readers.
+//!
+//! The editor normally keeps the display in sync by moving the cursor back and
+//! repainting the rest of the line after every edit, which screen readers and
+//! braille displays read out as a jumble. With
+//! [`LineEditor::set_accessible`](crate::LineEditor::set_accessible) the
+//! output only ever moves forward: text typed at the end of the line is echoed,
+//! Backspace at the end erases with `\b \b`, cursor movement writes nothing,
+//! and any other change to the line writes it again on a new row. An
+//! [`Announcer`] installed with
+//! [`LineEditor::set_announcer`](crate::LineEditor::set_announcer) is told about
+//! these edits, e.g. to speak them through a speech synthesizer.
+//!
+//! The mode can be switched at any time, also between two reads. It takes
+//! precedence over [horizontal scrolling](crate::LineEditor::set_horizontal_scroll),
+//! and hints are not shown while it is on.
+
+/// A significant edit, passed to an [`Announcer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Announcement<'a> {
+    /// Text was deleted and nothing inserted in its place.
+    Deleted(&'a str),
+    /// The line changed other than by typing or deleting at its end, e.g.
+    /// through history or completion; `word` is the word at the cursor.
+    Changed {
+        /// The whole line
+        line: &'a str,
+        /// The word at the cursor
+        word: &'a str,
+    },
+    /// The cursor moved without changing the line.
+    Moved {
+        /// The word at the cursor
+        word: &'a str,
+    },
+}
+
+/// Receiver of [`Announcement`]s in accessible mode.
+///
+/// Closures of the form `FnMut(&Announcement)` implement this trait.
+///
+/// # Examples
+///
+/// ```
+/// use editline::accessibility::Announcement;
+/// use editline::LineEditor;
+///
+/// let mut editor = LineEditor::new(1024, 50);
+/// editor.set_accessible(true);
+/// editor.set_announcer(|announcement: &Announcement| {
+///     if let Announcement::Deleted(text) = announcement {
+///         // speak(&format!("deleted {}", text));
+///         let _ = text;
+///     }
+/// });
+/// ```
+pub trait Announcer {
+    /// Handles one announcement.
+    fn announce(&mut self, announcement: &Announcement<'_>);
+}
+
+impl<F: FnMut(&Announcement<'_>)> Announcer for F {
+    fn announce(&mut self, announcement: &Announcement<'_>) {
+        self(announcement)
+    }
+}
+
+/// Returns the word around byte offset `pos` of `line`.
+pub(crate) fn word_at(line: &str, pos: usize) -> &str {
+    let start = line[..pos]
+        .rfind(char::is_whitespace)
+        .map_or(0, |at| at + 1);
+    let end = line[pos..]
+        .find(char::is_whitespace)
+        .map_or(line.len(), |at| pos + at);
+    &line[start..end]
+}
+
+/// Byte ranges of `before` and `after` that differ, as `(start, removed_end,
+/// inserted_end)`, ignoring the common prefix and suffix.
+pub(crate) fn difference(before: &str, after: &str) -> (usize, usize, usize) {
+    let mut prefix = before
+        .bytes()
+        .zip(after.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !before.is_char_boundary(prefix) || !after.is_char_boundary(prefix) {
+        prefix -= 1;
+    }
+    let longest = before.len().min(after.len()) - prefix;
+    let mut suffix = before
+        .bytes()
+        .rev()
+        .zip(after.bytes().rev())
+        .take(longest)
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !before.is_char_boundary(before.len() - suffix)
+        || !after.is_char_boundary(after.len() - suffix)
+    {
+        suffix -= 1;
+    }
+    (prefix, before.len() - suffix, after.len() - suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_difference() {
+        assert_eq!(difference("gpio set", "gpio get"), (5, 6, 6));
+        assert_eq!(difference("aaa", "aa"), (2, 3, 2));
+        assert_eq!(difference("é", "è"), (0, 2, 2));
+        assert_eq!(word_at("gpio set 17", 6), "set");
+        assert_eq!(word_at("gpio set 17", 4), "gpio");
+    }
+}
@@ -0,0 +1,1268 @@
+//! Shared ANSI/VT100 key sequence decoder.
+//!
+//! Byte-stream backends (Unix termios, UART, USB CDC) all receive the same escape
+//! sequences from the terminal emulator on the other end, so they share a single
+//! decoder instead of each carrying their own copy. [`Terminal::parse_key_event`]
+//! uses [`parse_key_event`] by default; backends with a native key API (such as the
+//! Windows console) override it.
+
+#[cfg(feature = "alloc")]
+use alloc::borrow::Cow;
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::{KeyEvent, Result, Terminal};
+
+/// Reads and decodes one key event from a byte-oriented terminal.
+///
+/// Handles plain characters, control characters (reported as [`KeyEvent::Ctrl`]),
+/// CSI sequences (`ESC [ ... final`) including modifier parameters such as
+/// `ESC[1;5C`, and SS3 sequences (`ESC O x`) sent in application cursor mode.
+/// Unrecognized CSI sequences are consumed completely so their trailing bytes are
+/// not misread as typed characters.
+///
+/// # Examples
+///
+/// ```
+/// use editline::{ansi, KeyEvent, Result, Terminal};
+///
+/// struct Bytes(Vec<u8>);
+///
+/// impl Terminal for Bytes {
+///     fn read_byte(&mut self) -> Result<u8> {
+///         Ok(self.0.remove(0))
+///     }
+/// #   fn write(&mut self, _: &[u8]) -> Result<()> { Ok(()) }
+/// #   fn flush(&mut self) -> Result<()> { Ok(()) }
+/// #   fn enter_raw_mode(&mut self) -> Result<()> { Ok(()) }
+/// #   fn exit_raw_mode(&mut self) -> Result<()> { Ok(()) }
+/// #   fn cursor_left(&mut self) -> Result<()> { Ok(()) }
+/// #   fn cursor_right(&mut self) -> Result<()> { Ok(()) }
+/// #   fn clear_eol(&mut self) -> Result<()> { Ok(()) }
+/// }
+///
+/// let mut term = Bytes(b"\x1b[1;5D".to_vec());
+/// assert_eq!(ansi::parse_key_event(&mut term)?, KeyEvent::CtrlLeft);
+/// # Ok::<(), editline::Error>(())
+/// ```
+pub fn parse_key_event<T: Terminal + ?Sized>(terminal: &mut T) -> Result<KeyEvent> {
+    let mut decoder = Decoder::new();
+    loop {
+        let byte = if decoder.is_ambiguous() {
+            match terminal.try_read_byte()? {
+                Some(byte) => byte,
+                None => return Ok(KeyEvent::Escape),
+            }
+        } else {
+            terminal.read_byte()?
+        };
+        if let Some(event) = decoder.push(byte) {
+            return Ok(event);
+        }
+    }
+}
+
+/// Reads and decodes one key event, giving up on a sequence whose next byte
+/// doesn't arrive in time.
+///
+/// The first byte is read with [`Terminal::read_byte`], the rest of a sequence
+/// with `read_next`, which returns `None` once the inter-byte timeout has
+/// passed. The sequence then ends as [`Decoder::expire`] describes, so a lone
+/// ESC is reported as [`KeyEvent::Escape`] without waiting for a second one.
+/// Backends with a read timeout use this to implement
+/// [`Terminal::parse_key_event`].
+pub fn parse_key_event_within<T: Terminal + ?Sized>(
+    terminal: &mut T,
+    mut read_next: impl FnMut(&mut T) -> Result<Option<u8>>,
+) -> Result<KeyEvent> {
+    let mut decoder = Decoder::new();
+    let mut byte = terminal.read_byte()?;
+    loop {
+        if let Some(event) = decoder.push(byte) {
+            return Ok(event);
+        }
+        byte = match read_next(terminal)? {
+            Some(byte) => byte,
+            None => return Ok(decoder.expire().unwrap_or(KeyEvent::Normal('\0'))),
+        };
+    }
+}
+
+/// Encodes `event` as the bytes an xterm-compatible terminal sends for it,
+/// the inverse of [`Decoder`].
+///
+/// Used by backends whose input arrives as key events rather than bytes, so
+/// they can still answer [`Terminal::read_byte`]. [`KeyEvent::Escape`] is sent
+/// as `ESC [ 27 u`, which the decoder reports without a timeout or waiting to
+/// see what follows, and [`KeyEvent::Unknown`] as the bytes it holds. Ctrl with a letter that has its
+/// own key, such as `Ctrl('m')`, decodes as that key, as it does from a real
+/// terminal.
+///
+/// # Examples
+///
+/// ```
+/// use editline::{ansi, KeyEvent};
+///
+/// let mut buf = [0; 16];
+/// assert_eq!(ansi::encode_key(KeyEvent::CtrlLeft, &mut buf), b"\x1b[1;5D");
+/// assert_eq!(ansi::encode_key(KeyEvent::Normal('ä'), &mut buf), "ä".as_bytes());
+/// ```
+pub fn encode_key(event: KeyEvent, buf: &mut [u8; 16]) -> &[u8] {
+    let fixed: &[u8] = match event {
+        KeyEvent::Normal(c) => return c.encode_utf8(buf).as_bytes(),
+        KeyEvent::Alt(c) => {
+            buf[0] = 0x1b;
+            let len = c.encode_utf8(&mut buf[1..]).len();
+            return &buf[..=len];
+        }
+        KeyEvent::Ctrl(c) => {
+            buf[0] = if c == ' ' { 0 } else { c as u8 & 0x1f };
+            return &buf[..1];
+        }
+        KeyEvent::Unknown(sequence) => {
+            let bytes = sequence.as_bytes();
+            buf[..bytes.len()].copy_from_slice(bytes);
+            return &buf[..bytes.len()];
+        }
+        KeyEvent::Left => b"\x1b[D",
+        KeyEvent::Right => b"\x1b[C",
+        KeyEvent::Up => b"\x1b[A",
+        KeyEvent::Down => b"\x1b[B",
+        KeyEvent::Home => b"\x1b[H",
+        KeyEvent::End => b"\x1b[F",
+        KeyEvent::Backspace => b"\x7f",
+        KeyEvent::Delete => b"\x1b[3~",
+        KeyEvent::Enter => b"\r",
+        KeyEvent::LineFeed => b"\n",
+        KeyEvent::PasteStart => b"\x1b[200~",
+        KeyEvent::PasteEnd => b"\x1b[201~",
+        KeyEvent::Tab => b"\t",
+        KeyEvent::BackTab => b"\x1b[Z",
+        KeyEvent::Insert => b"\x1b[2~",
+        KeyEvent::PageUp => b"\x1b[5~",
+        KeyEvent::PageDown => b"\x1b[6~",
+        KeyEvent::F(1) => b"\x1bOP",
+        KeyEvent::F(2) => b"\x1bOQ",
+        KeyEvent::F(3) => b"\x1bOR",
+        KeyEvent::F(4) => b"\x1bOS",
+        KeyEvent::F(5) => b"\x1b[15~",
+        KeyEvent::F(6) => b"\x1b[17~",
+        KeyEvent::F(7) => b"\x1b[18~",
+        KeyEvent::F(8) => b"\x1b[19~",
+        KeyEvent::F(9) => b"\x1b[20~",
+        KeyEvent::F(10) => b"\x1b[21~",
+        KeyEvent::F(11) => b"\x1b[23~",
+        KeyEvent::F(12) => b"\x1b[24~",
+        // No terminal sends other function keys
+        KeyEvent::F(_) => b"",
+        KeyEvent::Escape => b"\x1b[27u",
+        KeyEvent::CtrlLeft => b"\x1b[1;5D",
+        KeyEvent::CtrlRight => b"\x1b[1;5C",
+        KeyEvent::CtrlDelete => b"\x1b[3;5~",
+        KeyEvent::CtrlHome => b"\x1b[1;5H",
+        KeyEvent::CtrlEnd => b"\x1b[1;5F",
+        KeyEvent::ShiftHome => b"\x1b[1;2H",
+        KeyEvent::ShiftEnd => b"\x1b[1;2F",
+        KeyEvent::AltBackspace => b"\x1b\x7f",
+    };
+    buf[..fixed.len()].copy_from_slice(fixed);
+    &buf[..fixed.len()]
+}
+
+/// Shows `text` on the row below the cursor using ANSI sequences.
+///
+/// An index (`ESC D`) followed by cursor-up first makes sure that row exists,
+/// scrolling the screen if the cursor is on the bottom line. The cursor position
+/// is saved and restored around the write (`ESC 7` / `ESC 8`), so the caller's
+/// column is kept without having to know the prompt width.
+pub fn show_status<T: Terminal + ?Sized>(terminal: &mut T, text: &[u8]) -> Result<()> {
+    terminal.write(b"\x1bD\x1b[A\x1b7\x1b[B\r")?;
+    terminal.write(text)?;
+    terminal.write(b"\x1b[K\x1b8")
+}
+
+/// Erases the row below the cursor, undoing [`show_status`].
+pub fn clear_status<T: Terminal + ?Sized>(terminal: &mut T) -> Result<()> {
+    terminal.write(b"\x1b7\x1b[B\r\x1b[K\x1b8")
+}
+
+/// Asks the terminal where the cursor is with a device status report
+/// (`ESC [ 6 n`) and reads the `ESC [ row ; column R` reply.
+///
+/// Returns the 0-based `(column, row)`. Bytes that arrive ahead of the reply,
+/// such as keys typed or pasted before it, are passed to `typed_ahead` in
+/// order, for the caller to decode before reading new input. This blocks in
+/// [`Terminal::read_byte`] until the reply arrives, so it must only be used in
+/// raw mode on a terminal known to answer, or with a read timeout that makes
+/// `read_byte` fail when nothing comes back.
+pub fn query_cursor_position<T: Terminal + ?Sized>(
+    terminal: &mut T,
+    mut typed_ahead: impl FnMut(u8),
+) -> Result<(u16, u16)> {
+    terminal.write(b"\x1b[6n")?;
+    terminal.flush()?;
+
+    let mut matched = 0;
+    loop {
+        skip_to_reply(terminal, b"\x1b[", matched, &mut typed_ahead)?;
+
+        let mut sequence = [0u8; MAX_SEQUENCE];
+        let mut len = 0;
+        let mut numbers = [0u16; 2];
+        let mut index = 0;
+        let byte = loop {
+            let byte = terminal.read_byte()?;
+            match byte {
+                b'0'..=b'9' if len < MAX_SEQUENCE => {
+                    numbers[index] = numbers[index]
+                        .saturating_mul(10)
+                        .saturating_add((byte - b'0') as u16);
+                }
+                b';' if index == 0 => index = 1,
+                b'R' if index == 1 => {
+                    let [row, column] = numbers;
+                    return Ok((column.saturating_sub(1), row.saturating_sub(1)));
+                }
+                _ => break byte,
+            }
+            sequence[len] = byte;
+            len += 1;
+        };
+
+        // Not the reply but a key typed ahead of it, such as an arrow
+        typed_ahead(0x1b);
+        typed_ahead(b'[');
+        sequence[..len].iter().for_each(|&b| typed_ahead(b));
+        matched = usize::from(byte == 0x1b);
+        if matched == 0 {
+            typed_ahead(byte);
+        }
+    }
+}
+
+/// Reads up to and including `prefix`, of which `matched` bytes have already
+/// been read, passing the bytes before it to `typed_ahead`.
+///
+/// `prefix` must start with its only ESC, so a partial match that breaks off
+/// was typed, and a new ESC may start the reply.
+fn skip_to_reply<T: Terminal + ?Sized>(
+    terminal: &mut T,
+    prefix: &[u8],
+    mut matched: usize,
+    typed_ahead: &mut impl FnMut(u8),
+) -> Result<()> {
+    while matched < prefix.len() {
+        let byte = terminal.read_byte()?;
+        if byte == prefix[matched] {
+            matched += 1;
+            continue;
+        }
+        prefix[..matched].iter().for_each(|&b| typed_ahead(b));
+        matched = usize::from(byte == 0x1b);
+        if matched == 0 {
+            typed_ahead(byte);
+        }
+    }
+    Ok(())
+}
+
+/// Puts `text` on the system clipboard with an OSC 52 sequence.
+///
+/// Many terminal emulators support this, also for programs running over SSH;
+/// the others ignore the sequence.
+#[cfg(feature = "alloc")]
+pub fn copy_to_clipboard<T: Terminal + ?Sized>(terminal: &mut T, text: &str) -> Result<()> {
+    terminal.write(b"\x1b]52;c;")?;
+    terminal.write(base64_encode(text.as_bytes()).as_bytes())?;
+    terminal.write(b"\x07")
+}
+
+/// Asks the terminal for the clipboard contents with an OSC 52 query and reads
+/// the `ESC ] 52 ; c ; data` reply.
+///
+/// Returns `None` if the reply is malformed or not UTF-8 text. Like
+/// [`query_cursor_position`], this blocks until the reply arrives and passes
+/// the bytes that arrive ahead of it to `typed_ahead`. Many terminals only
+/// answer once the user allows it, or not at all, so it must only be used on a
+/// terminal known to answer.
+#[cfg(feature = "alloc")]
+pub fn query_clipboard<T: Terminal + ?Sized>(
+    terminal: &mut T,
+    mut typed_ahead: impl FnMut(u8),
+) -> Result<Option<String>> {
+    terminal.write(b"\x1b]52;c;?\x07")?;
+    terminal.flush()?;
+    skip_to_reply(terminal, b"\x1b]52;", 0, &mut typed_ahead)?;
+
+    // The reply ends with BEL or ST (ESC \)
+    let mut reply = Vec::new();
+    loop {
+        match terminal.read_byte()? {
+            0x07 => break,
+            0x1b => {
+                terminal.read_byte()?;
+                break;
+            }
+            byte => reply.push(byte),
+        }
+    }
+    let data = reply
+        .iter()
+        .position(|&b| b == b';')
+        .map(|i| &reply[i + 1..]);
+    Ok(data
+        .and_then(base64_decode)
+        .and_then(|bytes| String::from_utf8(bytes).ok()))
+}
+
+#[cfg(feature = "alloc")]
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[cfg(feature = "alloc")]
+fn base64_encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let bits = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |bits, (i, &b)| bits | (u32::from(b) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(char::from(BASE64[((bits >> (18 - 6 * i)) & 63) as usize]));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(feature = "alloc")]
+fn base64_decode(text: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(text.len() / 4 * 3);
+    let (mut bits, mut count) = (0u32, 0);
+    for &c in text {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            _ => return None,
+        };
+        bits = (bits << 6) | u32::from(value);
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            decoded.push((bits >> count) as u8);
+            bits &= (1 << count) - 1;
+        }
+    }
+    Some(decoded)
+}
+
+/// Removes escape sequences and other control characters from `text`, keeping
+/// newlines and tabs.
+///
+/// Text from outside the program, such as a history file or network messages,
+/// could otherwise move the cursor, change the window title or set the
+/// clipboard when written to the terminal. CSI sequences (`ESC [ ... final`)
+/// and string sequences (OSC, DCS, SOS, PM and APC, up to BEL or ST) are
+/// dropped as a whole, in their 7-bit and C1 forms alike. Returns the text
+/// unchanged if there's nothing to remove.
+///
+/// # Examples
+///
+/// ```
+/// use editline::ansi::strip_escapes;
+///
+/// assert_eq!(strip_escapes("\x1b[31mred\x1b[0m"), "red");
+/// assert_eq!(strip_escapes("\x1b]0;title\x07ok"), "ok");
+/// assert_eq!(strip_escapes("plain"), "plain");
+/// ```
+#[cfg(feature = "alloc")]
+pub fn strip_escapes(text: &str) -> Cow<'_, str> {
+    if !text
+        .chars()
+        .any(|c| c.is_control() && c != '\n' && c != '\t')
+    {
+        return Cow::Borrowed(text);
+    }
+
+    let mut clean = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        // Normalize the 7-bit introducer to its C1 form
+        let c = if c == '\x1b' {
+            match chars.peek() {
+                Some(&next @ '@'..='_') => {
+                    chars.next();
+                    char::from_u32(next as u32 + 0x40).unwrap_or('\u{80}')
+                }
+                Some(' '..='/') => {
+                    // nF sequence: intermediates, then one final byte
+                    while let Some(' '..='/') = chars.peek() {
+                        chars.next();
+                    }
+                    chars.next();
+                    continue;
+                }
+                Some('0'..='~') => {
+                    chars.next();
+                    continue;
+                }
+                _ => continue,
+            }
+        } else {
+            c
+        };
+        match c {
+            // CSI: parameters and intermediates, then a final byte
+            '\u{9b}' => {
+                for c in chars.by_ref() {
+                    if let '@'..='~' = c {
+                        break;
+                    }
+                }
+            }
+            // DCS, SOS, OSC, PM and APC run up to BEL or ST
+            '\u{90}' | '\u{98}' | '\u{9d}' | '\u{9e}' | '\u{9f}' => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07'
+                        || c == '\u{9c}'
+                        || (c == '\x1b' && chars.next_if_eq(&'\\').is_some())
+                    {
+                        break;
+                    }
+                }
+            }
+            '\n' | '\t' => clean.push(c),
+            c if c.is_control() => {}
+            c => clean.push(c),
+        }
+    }
+    Cow::Owned(clean)
+}
+
+/// Maximum number of parameter bytes kept from a CSI sequence.
+///
+/// Longer sequences are still consumed, but can't match any known key.
+const MAX_CSI_PARAMS: usize = 8;
+
+/// Number of bytes an [`UnknownSequence`] keeps.
+const MAX_SEQUENCE: usize = 16;
+
+/// Input the decoder didn't recognize, as reported by [`KeyEvent::Unknown`].
+///
+/// Holds the whole sequence up to its first 16 bytes, ESC included, so an
+/// application can log it or handle keys editline doesn't know; see
+/// [`LineEditor::set_unknown_sequence_handler`](crate::LineEditor::set_unknown_sequence_handler).
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct UnknownSequence {
+    bytes: [u8; MAX_SEQUENCE],
+    len: u8,
+}
+
+impl UnknownSequence {
+    /// Creates a sequence from `bytes`, keeping the first 16.
+    pub fn new(bytes: &[u8]) -> Self {
+        let len = bytes.len().min(MAX_SEQUENCE);
+        let mut sequence = UnknownSequence {
+            bytes: [0; MAX_SEQUENCE],
+            len: len as u8,
+        };
+        sequence.bytes[..len].copy_from_slice(&bytes[..len]);
+        sequence
+    }
+
+    /// Returns the bytes of the sequence.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+}
+
+impl core::fmt::Debug for UnknownSequence {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        use core::fmt::Write;
+        f.write_str("UnknownSequence(\"")?;
+        for &b in self.as_bytes() {
+            for c in core::ascii::escape_default(b) {
+                f.write_char(c as char)?;
+            }
+        }
+        f.write_str("\")")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Ground,
+    Escape,
+    /// After ESC ESC, which may be Escape or Alt with a key's sequence
+    EscapeEscape,
+    Csi,
+    Ss3,
+    /// Inside a multi-byte UTF-8 character, with this many bytes still to come.
+    Utf8(u8),
+}
+
+/// Incremental ANSI key decoder fed one byte at a time.
+///
+/// This is the state machine behind [`parse_key_event`], for input sources that
+/// can't block on [`Terminal::read_byte`], such as async streams or polled
+/// UARTs. Bytes are pushed as they arrive and a [`KeyEvent`] comes out once a
+/// sequence is complete.
+///
+/// # Examples
+///
+/// ```
+/// use editline::{ansi::Decoder, KeyEvent};
+///
+/// let mut decoder = Decoder::new();
+/// assert_eq!(decoder.push(0x1b), None);
+/// assert_eq!(decoder.push(b'['), None);
+/// assert_eq!(decoder.push(b'A'), Some(KeyEvent::Up));
+/// assert_eq!(decoder.push(b'x'), Some(KeyEvent::Normal('x')));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Decoder {
+    state: State,
+    params: [u8; MAX_CSI_PARAMS],
+    len: usize,
+    /// The CSI or SS3 sequence followed ESC ESC, meaning Alt
+    alt: bool,
+    code: u32,
+    /// Bytes of the key being decoded, for reporting it if it isn't known
+    sequence: [u8; MAX_SEQUENCE],
+    sequence_len: usize,
+}
+
+impl Decoder {
+    /// Creates a decoder waiting for the start of a key.
+    pub const fn new() -> Self {
+        Decoder {
+            state: State::Ground,
+            params: [0; MAX_CSI_PARAMS],
+            len: 0,
+            alt: false,
+            code: 0,
+            sequence: [0; MAX_SEQUENCE],
+            sequence_len: 0,
+        }
+    }
+
+    /// Returns `true` if the decoder is in the middle of a sequence.
+    pub fn is_pending(&self) -> bool {
+        self.state != State::Ground
+    }
+
+    /// Returns `true` after ESC ESC, which is [`KeyEvent::Escape`] unless the
+    /// sequence of another key follows at once.
+    ///
+    /// Some terminals send Alt+Left/Right as ESC and the arrow's sequence. A
+    /// caller that can tell no more input is waiting should [`expire`](Self::expire)
+    /// the sequence rather than wait for the next byte, so Escape pressed
+    /// twice takes effect right away.
+    pub fn is_ambiguous(&self) -> bool {
+        self.state == State::EscapeEscape
+    }
+
+    /// Ends the pending sequence because its next byte didn't arrive in time.
+    ///
+    /// A lone ESC becomes [`KeyEvent::Escape`]; any other partial sequence is
+    /// reported as [`KeyEvent::Unknown`], which the editor drops. Returns `None`
+    /// if no sequence is pending.
+    ///
+    /// On a link with latency, the rest of an arrow key may take longer than a
+    /// short timeout, and then arrives as typed characters; pick the timeout
+    /// with the link in mind.
+    pub fn expire(&mut self) -> Option<KeyEvent> {
+        let event = match self.state {
+            State::Ground => return None,
+            State::Escape | State::EscapeEscape => KeyEvent::Escape,
+            _ => {
+                debug!("incomplete sequence timed out");
+                self.unknown()
+            }
+        };
+        self.state = State::Ground;
+        Some(event)
+    }
+
+    /// Feeds one input byte, returning the key it completes, if any.
+    ///
+    /// Input that isn't a known key comes out as [`KeyEvent::Unknown`] once
+    /// the sequence is complete, rather than as stray characters.
+    pub fn push(&mut self, byte: u8) -> Option<KeyEvent> {
+        if self.state == State::Ground {
+            self.sequence_len = 0;
+        }
+        if self.sequence_len < MAX_SEQUENCE {
+            self.sequence[self.sequence_len] = byte;
+            self.sequence_len += 1;
+        }
+        let event = match self.state {
+            State::Ground => self.ground(byte),
+            State::Escape => self.escape(byte),
+            State::EscapeEscape => self.escape_escape(byte),
+            State::Csi => self.csi(byte),
+            State::Ss3 => {
+                self.state = State::Ground;
+                Some(ss3_key(byte).map_or_else(|| self.unknown(), |key| self.with_alt(key)))
+            }
+            State::Utf8(remaining) => self.utf8(byte, remaining),
+        };
+        if let Some(event) = event {
+            debug!("key {:?}", event);
+        }
+        event
+    }
+
+    /// Reports the sequence decoded so far as unknown.
+    fn unknown(&self) -> KeyEvent {
+        KeyEvent::Unknown(UnknownSequence::new(&self.sequence[..self.sequence_len]))
+    }
+
+    fn start_utf8(&mut self, bits: u8, remaining: u8) -> Option<KeyEvent> {
+        self.code = bits as u32;
+        self.state = State::Utf8(remaining);
+        None
+    }
+
+    fn utf8(&mut self, byte: u8, remaining: u8) -> Option<KeyEvent> {
+        if byte & 0xc0 != 0x80 {
+            // Malformed sequence; drop it like other unknown input
+            debug!("malformed UTF-8 at byte {:#04x}, resynchronizing", byte);
+            self.state = State::Ground;
+            return Some(self.unknown());
+        }
+        self.code = self.code << 6 | (byte & 0x3f) as u32;
+        if remaining > 1 {
+            self.state = State::Utf8(remaining - 1);
+            return None;
+        }
+        self.state = State::Ground;
+        Some(char::from_u32(self.code).map_or_else(|| self.unknown(), KeyEvent::Normal))
+    }
+
+    fn ground(&mut self, c: u8) -> Option<KeyEvent> {
+        let event = match c {
+            // Enter/Return
+            b'\r' => KeyEvent::Enter,
+            b'\n' => KeyEvent::LineFeed,
+            // Backspace (DEL or Ctrl+H)
+            127 | 8 => KeyEvent::Backspace,
+            // ESC sequences
+            27 => {
+                self.state = State::Escape;
+                return None;
+            }
+            b'\t' => KeyEvent::Tab,
+            // Ctrl+Space (NUL, also sent for Ctrl+@)
+            0 => KeyEvent::Ctrl(' '),
+            // Ctrl+A through Ctrl+Z
+            1..=26 => KeyEvent::Ctrl((b'a' + c - 1) as char),
+            // Normal printable character
+            32..=126 => KeyEvent::Normal(c as char),
+            // Start of a multi-byte UTF-8 character
+            0xc2..=0xdf => return self.start_utf8(c & 0x1f, 1),
+            0xe0..=0xef => return self.start_utf8(c & 0x0f, 2),
+            0xf0..=0xf4 => return self.start_utf8(c & 0x07, 3),
+            // Unknown/control character - ignore
+            _ => {
+                debug!("ignoring byte {:#04x}", c);
+                self.unknown()
+            }
+        };
+        Some(event)
+    }
+
+    /// Second byte of a sequence that started with ESC.
+    fn escape(&mut self, c2: u8) -> Option<KeyEvent> {
+        self.state = State::Ground;
+        let event = match c2 {
+            // Alt+Backspace
+            127 | 8 => KeyEvent::AltBackspace,
+            // A lone ESC can't be told from the start of a sequence; a second
+            // one confirms it, unless a key's sequence follows
+            27 => {
+                self.state = State::EscapeEscape;
+                return None;
+            }
+            b'[' => {
+                self.state = State::Csi;
+                self.len = 0;
+                self.alt = false;
+                return None;
+            }
+            b'O' => {
+                self.state = State::Ss3;
+                self.alt = false;
+                return None;
+            }
+            // Alt+character, including Alt+B/Alt+F, which macOS Terminal.app
+            // and iTerm2 send for Option+Left/Right; the keymap moves by word
+            32..=126 => KeyEvent::Alt(c2 as char),
+            _ => {
+                debug!("unknown sequence ESC {:#04x}, resynchronizing", c2);
+                self.unknown()
+            }
+        };
+        Some(event)
+    }
+
+    /// Third byte of a sequence that started with ESC ESC.
+    fn escape_escape(&mut self, c3: u8) -> Option<KeyEvent> {
+        self.state = State::Ground;
+        match c3 {
+            // Alt with a key's sequence, e.g. Option+Left/Right in some
+            // terminals
+            b'[' | b'O' => {
+                self.state = if c3 == b'[' { State::Csi } else { State::Ss3 };
+                self.len = 0;
+                self.alt = true;
+                None
+            }
+            _ => {
+                // Escape, followed at once by another key. A decoder kept
+                // across keys carries on with a sequence that key starts; a
+                // single-byte key is dropped
+                if self.ground(c3).is_some() {
+                    debug!("byte {:#04x} after ESC ESC dropped", c3);
+                }
+                self.sequence[0] = c3;
+                self.sequence_len = 1;
+                Some(KeyEvent::Escape)
+            }
+        }
+    }
+
+    /// Applies Alt from ESC ESC to a key, making arrows move by word.
+    fn with_alt(&self, key: KeyEvent) -> KeyEvent {
+        match key {
+            KeyEvent::Left if self.alt => KeyEvent::CtrlLeft,
+            KeyEvent::Right if self.alt => KeyEvent::CtrlRight,
+            key => key,
+        }
+    }
+
+    /// Parameter, intermediate or final byte of a CSI sequence (`ESC [`).
+    fn csi(&mut self, b: u8) -> Option<KeyEvent> {
+        // rxvt ends Shift+Home/End and the like with `$`, which would otherwise
+        // be an intermediate byte; no other key sends it after plain digits
+        let rxvt_shift = b == b'$'
+            && self.len > 0
+            && self.len <= MAX_CSI_PARAMS
+            && self.params[..self.len].iter().all(u8::is_ascii_digit);
+        // Parameter and intermediate bytes run until a final byte in 0x40..=0x7E
+        if !rxvt_shift && !(0x40..=0x7e).contains(&b) {
+            if self.len < MAX_CSI_PARAMS {
+                self.params[self.len] = b;
+            }
+            self.len += 1;
+            return None;
+        }
+
+        self.state = State::Ground;
+        if self.len > MAX_CSI_PARAMS {
+            debug!("CSI sequence with {} parameter bytes dropped", self.len);
+            return Some(self.unknown());
+        }
+        Some(
+            csi_key(&self.params[..self.len], b)
+                .map_or_else(|| self.unknown(), |key| self.with_alt(key)),
+        )
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Maps a complete CSI sequence to a key.
+///
+/// Keys are told apart by the final byte and, for `~` sequences, the first
+/// parameter, so the spellings of xterm, tmux and GNU screen (`ESC [ 1 ~` and
+/// `ESC [ H` for Home) all decode. A second parameter carries modifiers as 1
+/// plus a mask of Shift (1), Alt (2), Ctrl (4) and Meta (8): Ctrl, Alt or Meta
+/// with Left/Right moves by word, and modifiers a key has no event for are
+/// dropped rather than making the whole key unknown. rxvt spells Home/End
+/// `ESC [ 7 ~`/`ESC [ 8 ~`, Shift+arrows `ESC [ a`..`d`, and marks Ctrl and
+/// Shift on `~` keys by ending them with `^` and `$` instead.
+fn csi_key(params: &[u8], final_byte: u8) -> Option<KeyEvent> {
+    let (number, modifiers) = match csi_params(params) {
+        Some(parsed) => parsed,
+        None => return unknown_csi(params, final_byte),
+    };
+    let (key_byte, rxvt_mask) = match final_byte {
+        b'^' => (b'~', 4),
+        b'$' => (b'~', 1),
+        other => (other, 0),
+    };
+    let mask = modifiers.saturating_sub(1) | rxvt_mask;
+    let shift = mask == 1;
+    let ctrl = mask & 4 != 0;
+    // Alt+arrow in xterm and tmux, Option+arrow in iTerm2 (Meta)
+    let word = mask & (2 | 4 | 8) != 0;
+    let event = match (number, key_byte) {
+        (_, b'A') | (_, b'a') => KeyEvent::Up,
+        (_, b'B') | (_, b'b') => KeyEvent::Down,
+        (_, b'c') => KeyEvent::Right,
+        (_, b'd') => KeyEvent::Left,
+        (_, b'C') if word => KeyEvent::CtrlRight,
+        (_, b'C') => KeyEvent::Right,
+        (_, b'D') if word => KeyEvent::CtrlLeft,
+        (_, b'D') => KeyEvent::Left,
+        (_, b'H') | (1 | 7, b'~') if ctrl => KeyEvent::CtrlHome,
+        (_, b'H') | (1 | 7, b'~') if shift => KeyEvent::ShiftHome,
+        (_, b'H') | (1 | 7, b'~') => KeyEvent::Home,
+        (_, b'F') | (4 | 8, b'~') if ctrl => KeyEvent::CtrlEnd,
+        (_, b'F') | (4 | 8, b'~') if shift => KeyEvent::ShiftEnd,
+        (_, b'F') | (4 | 8, b'~') => KeyEvent::End,
+        (_, b'Z') => KeyEvent::BackTab,
+        (2, b'~') => KeyEvent::Insert,
+        (3, b'~') if ctrl => KeyEvent::CtrlDelete,
+        (3, b'~') => KeyEvent::Delete,
+        (5, b'~') => KeyEvent::PageUp,
+        (6, b'~') => KeyEvent::PageDown,
+        // F1 to F12, skipping the unused 16 and 22
+        (11..=15, b'~') => KeyEvent::F((number - 10) as u8),
+        (17..=21, b'~') => KeyEvent::F((number - 11) as u8),
+        (23 | 24, b'~') => KeyEvent::F((number - 12) as u8),
+        // The Escape key in the fixterms and kitty keyboard protocols
+        (27, b'u') => KeyEvent::Escape,
+        (200, b'~') => KeyEvent::PasteStart,
+        (201, b'~') => KeyEvent::PasteEnd,
+        _ => return unknown_csi(params, final_byte),
+    };
+    Some(event)
+}
+
+/// Splits CSI parameters into the key number and modifiers, each 1 when
+/// omitted, or `None` for anything but one or two decimal numbers.
+fn csi_params(params: &[u8]) -> Option<(u16, u16)> {
+    let mut fields = params.split(|&b| b == b';');
+    let number = csi_number(fields.next()?)?;
+    let modifiers = match fields.next() {
+        Some(field) => csi_number(field)?,
+        None => 1,
+    };
+    if fields.next().is_some() {
+        return None;
+    }
+    Some((number, modifiers))
+}
+
+fn csi_number(field: &[u8]) -> Option<u16> {
+    if field.is_empty() {
+        return Some(1);
+    }
+    field.iter().try_fold(0u16, |n, &b| {
+        if b.is_ascii_digit() {
+            n.checked_mul(10)?.checked_add(u16::from(b - b'0'))
+        } else {
+            None
+        }
+    })
+}
+
+fn unknown_csi(params: &[u8], final_byte: u8) -> Option<KeyEvent> {
+    debug!(
+        "unknown sequence ESC [{}{}",
+        core::str::from_utf8(params).unwrap_or("?"),
+        final_byte as char
+    );
+    None
+}
+
+/// Maps the final byte of an SS3 sequence (`ESC O`) to a key.
+fn ss3_key(byte: u8) -> Option<KeyEvent> {
+    let event = match byte {
+        b'A' => KeyEvent::Up,
+        b'B' => KeyEvent::Down,
+        b'C' => KeyEvent::Right,
+        b'D' => KeyEvent::Left,
+        // Ctrl+arrows in rxvt
+        b'a' => KeyEvent::Up,
+        b'b' => KeyEvent::Down,
+        b'c' => KeyEvent::CtrlRight,
+        b'd' => KeyEvent::CtrlLeft,
+        b'H' => KeyEvent::Home,
+        b'F' => KeyEvent::End,
+        b'P'..=b'S' => KeyEvent::F(byte - b'P' + 1),
+        _ => {
+            debug!("unknown sequence ESC O{}", byte as char);
+            return None;
+        }
+    };
+    Some(event)
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::Error;
+    use alloc::format;
+    use alloc::vec::Vec;
+
+    struct Bytes(Vec<u8>);
+
+    impl Terminal for Bytes {
+        fn read_byte(&mut self) -> Result<u8> {
+            if self.0.is_empty() {
+                Err(Error::Eof)
+            } else {
+                Ok(self.0.remove(0))
+            }
+        }
+        fn try_read_byte(&mut self) -> Result<Option<u8>> {
+            Ok(if self.0.is_empty() {
+                None
+            } else {
+                Some(self.0.remove(0))
+            })
+        }
+        fn write(&mut self, _: &[u8]) -> Result<()> {
+            Ok(())
+        }
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn enter_raw_mode(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn exit_raw_mode(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn cursor_left(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn cursor_right(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn clear_eol(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn unknown(bytes: &[u8]) -> KeyEvent {
+        KeyEvent::Unknown(UnknownSequence::new(bytes))
+    }
+
+    fn parse(bytes: &[u8]) -> KeyEvent {
+        let mut term = Bytes(bytes.to_vec());
+        let event = parse_key_event(&mut term).unwrap();
+        assert!(term.0.is_empty(), "sequence {:?} not fully consumed", bytes);
+        event
+    }
+
+    #[test]
+    fn test_query_cursor_position() {
+        let mut typed = Vec::new();
+        let mut term = Bytes(b"x\x1b[12;7R".to_vec());
+        assert_eq!(
+            query_cursor_position(&mut term, |b| typed.push(b)).unwrap(),
+            (6, 11)
+        );
+        assert_eq!(typed, b"x");
+        let mut term = Bytes(b"\x1b[12R".to_vec());
+        assert_eq!(query_cursor_position(&mut term, |_| {}), Err(Error::Eof));
+    }
+
+    #[test]
+    fn test_typed_ahead_of_reply() {
+        // Pasted lines and keys that look like the start of the reply come back
+        let mut typed = Vec::new();
+        let mut term = Bytes(b"one\rtwo\r\x1b[A\x1b\x1b[1;5D\x1b[2;\x1b[3;4Rthree".to_vec());
+        assert_eq!(
+            query_cursor_position(&mut term, |b| typed.push(b)).unwrap(),
+            (3, 2)
+        );
+        assert_eq!(typed, b"one\rtwo\r\x1b[A\x1b\x1b[1;5D\x1b[2;");
+        assert_eq!(term.0, b"three");
+
+        let mut typed = Vec::new();
+        let mut term = Bytes(b"ab\x1b]0;\x1b]52;c;aGk=\x07c".to_vec());
+        assert_eq!(
+            query_clipboard(&mut term, |b| typed.push(b))
+                .unwrap()
+                .as_deref(),
+            Some("hi")
+        );
+        assert_eq!(typed, b"ab\x1b]0;");
+        assert_eq!(term.0, b"c");
+    }
+
+    #[test]
+    fn test_clipboard() {
+        for text in ["", "a", "hi", "world", "ä ✓"] {
+            assert_eq!(
+                base64_decode(base64_encode(text.as_bytes()).as_bytes()).unwrap(),
+                text.as_bytes()
+            );
+        }
+        assert_eq!(base64_encode(b"world"), "d29ybGQ=");
+
+        let mut term = Bytes(b"x\x1b]52;c;aGk=\x07".to_vec());
+        assert_eq!(
+            query_clipboard(&mut term, |_| {}).unwrap().as_deref(),
+            Some("hi")
+        );
+        let mut term = Bytes(b"\x1b]52;c;aGk=\x1b\\".to_vec());
+        assert_eq!(
+            query_clipboard(&mut term, |_| {}).unwrap().as_deref(),
+            Some("hi")
+        );
+        let mut term = Bytes(b"\x1b]52;c;a*k=\x07".to_vec());
+        assert_eq!(query_clipboard(&mut term, |_| {}).unwrap(), None);
+    }
+
+    #[test]
+    fn test_terminal_families() {
+        type Family = (&'static str, &'static [(&'static [u8], KeyEvent)]);
+        let families: &[Family] = &[
+            (
+                "xterm",
+                &[
+                    (b"\x1b[H", KeyEvent::Home),
+                    (b"\x1b[F", KeyEvent::End),
+                    (b"\x1bOH", KeyEvent::Home),
+                    (b"\x1b[1;3C", KeyEvent::CtrlRight),
+                    (b"\x1b[1;2D", KeyEvent::Left),
+                    (b"\x1b[1;6D", KeyEvent::CtrlLeft),
+                    (b"\x1b[1;5H", KeyEvent::CtrlHome),
+                    (b"\x1b[1;2F", KeyEvent::ShiftEnd),
+                    (b"\x1b[3;2~", KeyEvent::Delete),
+                ],
+            ),
+            (
+                "tmux/screen",
+                &[
+                    (b"\x1b[1~", KeyEvent::Home),
+                    (b"\x1b[4~", KeyEvent::End),
+                    (b"\x1b[1;5~", KeyEvent::CtrlHome),
+                    (b"\x1b[4;2~", KeyEvent::ShiftEnd),
+                    (b"\x1b[1;3D", KeyEvent::CtrlLeft),
+                    (b"\x1b[1;5C", KeyEvent::CtrlRight),
+                    (b"\x1b[1;3A", KeyEvent::Up),
+                    (b"\x1b[3;5~", KeyEvent::CtrlDelete),
+                ],
+            ),
+            (
+                "rxvt",
+                &[
+                    (b"\x1b[7~", KeyEvent::Home),
+                    (b"\x1b[8~", KeyEvent::End),
+                    (b"\x1b[7^", KeyEvent::CtrlHome),
+                    (b"\x1b[8^", KeyEvent::CtrlEnd),
+                    (b"\x1b[7$", KeyEvent::ShiftHome),
+                    (b"\x1b[8$", KeyEvent::ShiftEnd),
+                    (b"\x1b[3^", KeyEvent::CtrlDelete),
+                    (b"\x1b[a", KeyEvent::Up),
+                    (b"\x1b[d", KeyEvent::Left),
+                    (b"\x1bOc", KeyEvent::CtrlRight),
+                    (b"\x1bOd", KeyEvent::CtrlLeft),
+                    (b"\x1bOa", KeyEvent::Up),
+                ],
+            ),
+        ];
+        for (family, keys) in families {
+            for (bytes, key) in keys.iter() {
+                assert_eq!(parse(bytes), *key, "{} {:?}", family, bytes);
+            }
+        }
+        assert_eq!(parse(b"\x1b[?1;2C"), unknown(b"\x1b[?1;2C"));
+        assert_eq!(parse(b"\x1b[1;2;3C"), unknown(b"\x1b[1;2;3C"));
+    }
+
+    #[test]
+    fn test_utf8_characters() {
+        assert_eq!(parse("ä".as_bytes()), KeyEvent::Normal('ä'));
+        assert_eq!(parse("漢".as_bytes()), KeyEvent::Normal('漢'));
+        assert_eq!(parse("😀".as_bytes()), KeyEvent::Normal('😀'));
+
+        let mut decoder = Decoder::new();
+        assert_eq!(decoder.push(0xc3), None);
+        assert!(decoder.is_pending());
+        assert_eq!(decoder.push(b'a'), Some(unknown(b"\xc3a")));
+    }
+
+    #[test]
+    fn test_plain_keys() {
+        assert_eq!(parse(b"a"), KeyEvent::Normal('a'));
+        assert_eq!(parse(b"\r"), KeyEvent::Enter);
+        assert_eq!(parse(b"\n"), KeyEvent::LineFeed);
+        assert_eq!(parse(b"\x1b[200~"), KeyEvent::PasteStart);
+        assert_eq!(parse(b"\x1b[201~"), KeyEvent::PasteEnd);
+        assert_eq!(parse(b"\x7f"), KeyEvent::Backspace);
+        assert_eq!(parse(b"\x00"), KeyEvent::Ctrl(' '));
+        assert_eq!(parse(b"\x1b\x7f"), KeyEvent::AltBackspace);
+        assert_eq!(parse(b"\x1b_"), KeyEvent::Alt('_'));
+    }
+
+    #[test]
+    fn test_control_keys() {
+        assert_eq!(parse(b"\x01"), KeyEvent::Ctrl('a'));
+        assert_eq!(parse(b"\x03"), KeyEvent::Ctrl('c'));
+        assert_eq!(parse(b"\x18"), KeyEvent::Ctrl('x'));
+        assert_eq!(parse(b"\t"), KeyEvent::Tab);
+    }
+
+    #[test]
+    fn test_csi_sequences() {
+        assert_eq!(parse(b"\x1b[A"), KeyEvent::Up);
+        assert_eq!(parse(b"\x1b[D"), KeyEvent::Left);
+        assert_eq!(parse(b"\x1b[1;5C"), KeyEvent::CtrlRight);
+        assert_eq!(parse(b"\x1b[3~"), KeyEvent::Delete);
+        assert_eq!(parse(b"\x1b[3;5~"), KeyEvent::CtrlDelete);
+        assert_eq!(parse(b"\x1b[4~"), KeyEvent::End);
+        assert_eq!(parse(b"\x1bOH"), KeyEvent::Home);
+        assert_eq!(parse(b"\x1b\x1b"), KeyEvent::Escape);
+        assert_eq!(parse(b"\x1bb"), KeyEvent::Alt('b'));
+        assert_eq!(parse(b"\x1bf"), KeyEvent::Alt('f'));
+        assert_eq!(parse(b"\x1b[1;3D"), KeyEvent::CtrlLeft);
+        assert_eq!(parse(b"\x1b[1;9C"), KeyEvent::CtrlRight);
+        assert_eq!(parse(b"\x1b[1;5H"), KeyEvent::CtrlHome);
+        assert_eq!(parse(b"\x1b[1;5F"), KeyEvent::CtrlEnd);
+        assert_eq!(parse(b"\x1b[1;2H"), KeyEvent::ShiftHome);
+        assert_eq!(parse(b"\x1b[1;2F"), KeyEvent::ShiftEnd);
+        assert_eq!(parse(b"\x1b[Z"), KeyEvent::BackTab);
+        assert_eq!(parse(b"\x1b[2~"), KeyEvent::Insert);
+        assert_eq!(parse(b"\x1b[5~"), KeyEvent::PageUp);
+        assert_eq!(parse(b"\x1b[6~"), KeyEvent::PageDown);
+        assert_eq!(parse(b"\x1bOP"), KeyEvent::F(1));
+        assert_eq!(parse(b"\x1b[15~"), KeyEvent::F(5));
+        assert_eq!(parse(b"\x1b[24~"), KeyEvent::F(12));
+    }
+
+    #[test]
+    fn test_escape_escape() {
+        // Alt+Left/Right sent as ESC and the arrow
+        assert_eq!(parse(b"\x1b\x1b[C"), KeyEvent::CtrlRight);
+        assert_eq!(parse(b"\x1b\x1b[D"), KeyEvent::CtrlLeft);
+        assert_eq!(parse(b"\x1b\x1bOD"), KeyEvent::CtrlLeft);
+        assert_eq!(parse(b"\x1b\x1b[A"), KeyEvent::Up);
+        assert_eq!(parse(b"\x1b[27u"), KeyEvent::Escape);
+
+        let mut decoder = Decoder::new();
+        assert_eq!(decoder.push(0x1b), None);
+        assert_eq!(decoder.push(0x1b), None);
+        assert!(decoder.is_ambiguous());
+        assert_eq!(decoder.expire(), Some(KeyEvent::Escape));
+        assert_eq!(decoder.push(b'x'), Some(KeyEvent::Normal('x')));
+
+        // Escape followed at once by a key that starts a sequence of its own
+        let keys: Vec<_> = b"\x1b\x1b\x1b[D"
+            .iter()
+            .filter_map(|&b| decoder.push(b))
+            .collect();
+        assert_eq!(keys, [KeyEvent::Escape, KeyEvent::Left]);
+    }
+
+    #[test]
+    fn test_encode_key() {
+        let keys = [
+            KeyEvent::Normal('a'),
+            KeyEvent::Normal('😀'),
+            KeyEvent::Left,
+            KeyEvent::Right,
+            KeyEvent::Up,
+            KeyEvent::Down,
+            KeyEvent::Home,
+            KeyEvent::End,
+            KeyEvent::Backspace,
+            KeyEvent::Delete,
+            KeyEvent::Enter,
+            KeyEvent::LineFeed,
+            KeyEvent::PasteStart,
+            KeyEvent::PasteEnd,
+            KeyEvent::Tab,
+            KeyEvent::BackTab,
+            KeyEvent::Insert,
+            KeyEvent::PageUp,
+            KeyEvent::PageDown,
+            KeyEvent::F(1),
+            KeyEvent::F(4),
+            KeyEvent::F(5),
+            KeyEvent::F(11),
+            KeyEvent::F(12),
+            KeyEvent::Escape,
+            KeyEvent::CtrlLeft,
+            KeyEvent::CtrlRight,
+            KeyEvent::CtrlDelete,
+            KeyEvent::CtrlHome,
+            KeyEvent::CtrlEnd,
+            KeyEvent::ShiftHome,
+            KeyEvent::ShiftEnd,
+            KeyEvent::AltBackspace,
+            KeyEvent::Alt('x'),
+            KeyEvent::Ctrl('a'),
+            KeyEvent::Ctrl('z'),
+            KeyEvent::Ctrl(' '),
+            unknown(b"\x1b[99~"),
+        ];
+        let mut buf = [0; 16];
+        for key in keys {
+            assert_eq!(parse(encode_key(key, &mut buf)), key);
+        }
+        // Sent as the key a terminal would send
+        assert_eq!(
+            parse(encode_key(KeyEvent::Ctrl('m'), &mut buf)),
+            KeyEvent::Enter
+        );
+    }
+
+    #[test]
+    fn test_escape_timeout() {
+        let within = |bytes: &[u8]| {
+            let mut term = Bytes(bytes.to_vec());
+            parse_key_event_within(&mut term, |term| {
+                Ok(if term.0.is_empty() {
+                    None
+                } else {
+                    Some(term.0.remove(0))
+                })
+            })
+            .unwrap()
+        };
+        assert_eq!(within(b"\x1b"), KeyEvent::Escape);
+        assert_eq!(within(b"\x1b[1;5"), unknown(b"\x1b[1;5"));
+        assert_eq!(within(b"\x1b[A"), KeyEvent::Up);
+        assert_eq!(Decoder::new().expire(), None);
+    }
+
+    #[test]
+    fn test_strip_escapes() {
+        assert!(matches!(strip_escapes("a\tb\nc"), Cow::Borrowed("a\tb\nc")));
+        assert_eq!(strip_escapes("\x1b[2J\x1b[1;31mhi\x1b[m"), "hi");
+        assert_eq!(
+            strip_escapes("\x1b]52;c;ZXZpbA==\x1b\\x\x1b]0;t\x07y"),
+            "xy"
+        );
+        assert_eq!(strip_escapes("\u{9b}31mC1\u{9d}0;t\u{9c}!"), "C1!");
+        assert_eq!(strip_escapes("\x1bc\x1b(Bsa\x08fe\r\x1b"), "safe");
+        assert_eq!(strip_escapes("\x1bPunterminated"), "");
+    }
+
+    #[test]
+    fn test_unknown_csi_is_consumed() {
+        assert_eq!(parse(b"\x1b[99;2~"), unknown(b"\x1b[99;2~"));
+        assert_eq!(parse(b"\x1b[15;2~"), KeyEvent::F(5));
+        // Kept up to the first 16 bytes
+        assert_eq!(
+            parse(b"\x1b[200;1;2;3;4;5;6;7~"),
+            unknown(b"\x1b[200;1;2;3;4;5;")
+        );
+        assert_eq!(parse(b"\x1bOx"), unknown(b"\x1bOx"));
+        assert_eq!(parse(b"\x1c"), unknown(b"\x1c"));
+        assert_eq!(
+            format!("{:?}", unknown(b"\x1b[99~")),
+            "Unknown(UnknownSequence(\"\\x1b[99~\"))"
+        );
+    }
+}
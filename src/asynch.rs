@@ -0,0 +1,831 @@
+//! Async terminal abstraction and line editor (`async` feature).
+//!
+//! [`AsyncTerminal`] is the poll-based counterpart of [`Terminal`](crate::Terminal)
+//! for executors such as Embassy or Tokio, where blocking on input would stall
+//! other tasks. It is object-safe, so backends can be stored as
+//! `Box<dyn AsyncTerminal>` or passed as `&mut dyn AsyncTerminal`.
+//!
+//! [`AsyncLineEditor`] reads lines from an `AsyncTerminal` with the same editing
+//! behavior as [`LineEditor`], and can await completion and hint callbacks
+//! that need I/O of their own ([`AsyncCompleter`], [`AsyncHinter`]). Messages
+//! from a [`MessageSource`], such as log lines sent by other tasks, are printed
+//! above the prompt while the user types.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::future::Future;
+use core::ops::{Deref, DerefMut};
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use crate::ansi::Decoder;
+use crate::{Action, Caps, IoCause, KeyEvent, LineEditor, Mode, Result, Terminal};
+
+/// Boxed future returned by [`AsyncCompleter`] and [`AsyncHinter`].
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+/// Byte-oriented terminal driven by an async executor.
+///
+/// Input is read with [`poll_read_byte`](Self::poll_read_byte), which registers
+/// the task's waker and returns [`Poll::Pending`] when no byte is available yet.
+/// Output is queued with [`write`](Self::write) and pushed out by
+/// [`poll_flush`](Self::poll_flush). Key decoding uses the shared ANSI decoder,
+/// so implementations only move bytes.
+///
+/// Because the methods poll rather than being `async fn`, the trait is
+/// object-safe: different backends can sit behind one `Box<dyn AsyncTerminal>`
+/// field and be swapped at run time.
+///
+/// # Examples
+///
+/// ```
+/// use editline::asynch::{AsyncLineEditor, AsyncTerminal};
+///
+/// struct Console {
+///     editor: AsyncLineEditor,
+///     // USB while the host is attached, UART otherwise
+///     link: Box<dyn AsyncTerminal>,
+/// }
+///
+/// impl Console {
+///     fn switch_to(&mut self, link: Box<dyn AsyncTerminal>) {
+///         self.link = link;
+///     }
+///
+///     async fn next_command(&mut self) -> editline::Result<String> {
+///         self.editor.read_line_with_prompt(&mut self.link, "> ").await
+///     }
+/// }
+/// ```
+pub trait AsyncTerminal {
+    /// Polls for the next input byte.
+    fn poll_read_byte(&mut self, cx: &mut Context<'_>) -> Poll<Result<u8>>;
+
+    /// Queues bytes for output.
+    fn write(&mut self, data: &[u8]) -> Result<()>;
+
+    /// Polls until all queued output has been sent.
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>>;
+
+    /// Enters raw mode, if the backend has such a notion.
+    ///
+    /// The default does nothing, which suits serial and USB links.
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Restores the mode changed by [`enter_raw_mode`](Self::enter_raw_mode).
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Reports what the terminal can display, like
+    /// [`Terminal::capabilities`](crate::Terminal::capabilities). The default
+    /// is [`Caps::NONE`].
+    fn capabilities(&self) -> Caps {
+        Caps::NONE
+    }
+}
+
+impl<T: AsyncTerminal + ?Sized> AsyncTerminal for &mut T {
+    fn poll_read_byte(&mut self, cx: &mut Context<'_>) -> Poll<Result<u8>> {
+        (**self).poll_read_byte(cx)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        (**self).write(data)
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        (**self).poll_flush(cx)
+    }
+
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        (**self).enter_raw_mode()
+    }
+
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        (**self).exit_raw_mode()
+    }
+
+    fn capabilities(&self) -> Caps {
+        (**self).capabilities()
+    }
+}
+
+impl<T: AsyncTerminal + ?Sized> AsyncTerminal for Box<T> {
+    fn poll_read_byte(&mut self, cx: &mut Context<'_>) -> Poll<Result<u8>> {
+        (**self).poll_read_byte(cx)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        (**self).write(data)
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        (**self).poll_flush(cx)
+    }
+
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        (**self).enter_raw_mode()
+    }
+
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        (**self).exit_raw_mode()
+    }
+
+    fn capabilities(&self) -> Caps {
+        (**self).capabilities()
+    }
+}
+
+/// Source of completions that may have to wait, e.g. for a bus scan.
+///
+/// The async counterpart of [`Completer`](crate::Completer). Closures taking a
+/// [`Context`](crate::Context) and returning a future implement this trait;
+/// the future can't borrow the context, so copy what it needs first.
+///
+/// # Examples
+///
+/// ```
+/// use editline::asynch::AsyncLineEditor;
+/// use editline::Context;
+///
+/// # async fn scan_bus() -> Vec<String> { Vec::new() }
+/// let mut editor = AsyncLineEditor::new(1024, 50);
+/// editor.set_async_completer(|ctx: &Context| {
+///     let word = ctx.word_before_cursor().to_string();
+///     async move {
+///         let devices = scan_bus().await;
+///         devices.into_iter().filter(|name| name.starts_with(&word)).collect()
+///     }
+/// });
+/// ```
+pub trait AsyncCompleter {
+    /// Returns candidates for the word before the cursor, like
+    /// [`Completer::complete`](crate::Completer::complete).
+    fn complete<'a>(&'a mut self, ctx: &'a crate::Context<'a>) -> BoxFuture<'a, Vec<String>>;
+}
+
+impl<F, Fut> AsyncCompleter for F
+where
+    F: FnMut(&crate::Context) -> Fut,
+    Fut: Future<Output = Vec<String>> + 'static,
+{
+    fn complete<'a>(&'a mut self, ctx: &'a crate::Context<'a>) -> BoxFuture<'a, Vec<String>> {
+        Box::pin(self(ctx))
+    }
+}
+
+/// Source of inline hints that may have to wait.
+///
+/// The async counterpart of [`Hinter`](crate::Hinter). Closures taking a
+/// [`Context`](crate::Context) and returning a future implement this trait.
+pub trait AsyncHinter {
+    /// Returns the text to show after the end of the line, like
+    /// [`Hinter::hint`](crate::Hinter::hint).
+    fn hint<'a>(&'a mut self, ctx: &'a crate::Context<'a>) -> BoxFuture<'a, Option<String>>;
+}
+
+impl<F, Fut> AsyncHinter for F
+where
+    F: FnMut(&crate::Context) -> Fut,
+    Fut: Future<Output = Option<String>> + 'static,
+{
+    fn hint<'a>(&'a mut self, ctx: &'a crate::Context<'a>) -> BoxFuture<'a, Option<String>> {
+        Box::pin(self(ctx))
+    }
+}
+
+/// Messages to print above the prompt while a line is being edited.
+///
+/// With the `embassy` feature, this is implemented for receivers of an
+/// `embassy_sync` channel of `String`s, so tasks can log while the console sits
+/// at a prompt:
+///
+/// ```ignore
+/// use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+/// use embassy_sync::channel::Channel;
+///
+/// static LOG: Channel<CriticalSectionRawMutex, String, 8> = Channel::new();
+///
+/// editor.set_message_source(LOG.receiver());
+/// // elsewhere: LOG.send(format!("temperature {}", t)).await;
+/// ```
+pub trait MessageSource {
+    /// Polls for the next message, registering the task's waker if there is none.
+    fn poll_message(&mut self, cx: &mut Context<'_>) -> Poll<String>;
+}
+
+#[cfg(feature = "embassy")]
+impl<M, const N: usize> MessageSource for embassy_sync::channel::Receiver<'_, M, String, N>
+where
+    M: embassy_sync::blocking_mutex::raw::RawMutex,
+{
+    fn poll_message(&mut self, cx: &mut Context<'_>) -> Poll<String> {
+        self.poll_receive(cx)
+    }
+}
+
+/// Line editor reading from an [`AsyncTerminal`].
+///
+/// Dereferences to a [`LineEditor`], whose settings (history, key bindings,
+/// completer, theme, ...) apply unchanged. Waiting for input, flushing output
+/// and awaiting the async callbacks all yield to the executor, so other tasks,
+/// such as USB servicing, keep running while a line is edited.
+///
+/// The async editor can't query the terminal's size or cursor position, so
+/// redraws assume that the prompt and line fit on one row.
+///
+/// # Examples
+///
+/// ```no_run
+/// use editline::asynch::{AsyncLineEditor, AsyncTerminal};
+///
+/// async fn console<T: AsyncTerminal>(terminal: &mut T) -> editline::Result<()> {
+///     let mut editor = AsyncLineEditor::new(256, 20);
+///     loop {
+///         let line = editor.read_line_with_prompt(terminal, "> ").await?;
+///         terminal.write(line.as_bytes())?;
+///     }
+/// }
+/// ```
+pub struct AsyncLineEditor {
+    editor: LineEditor,
+    completer: Option<Box<dyn AsyncCompleter>>,
+    hinter: Option<Box<dyn AsyncHinter>>,
+    messages: Option<Box<dyn MessageSource>>,
+    escape_timer: Option<Box<dyn FnMut() -> BoxFuture<'static, ()>>>,
+}
+
+/// What the read loop waits for.
+enum Input {
+    Byte(u8),
+    Message(String),
+    /// The rest of an escape sequence didn't arrive in time
+    Timeout,
+}
+
+impl AsyncLineEditor {
+    /// Creates an editor with the given line buffer and history capacities, as
+    /// [`LineEditor::new`] does.
+    pub fn new(buffer_capacity: usize, history_capacity: usize) -> Self {
+        Self::from(LineEditor::new(buffer_capacity, history_capacity))
+    }
+
+    /// Installs a completer that is awaited when Tab is pressed.
+    ///
+    /// Takes precedence over a [`Completer`](crate::Completer) set with
+    /// [`LineEditor::set_completer`].
+    pub fn set_async_completer<C: AsyncCompleter + 'static>(&mut self, completer: C) {
+        self.completer = Some(Box::new(completer));
+    }
+
+    /// Installs a hinter that is awaited after each key when the cursor is at
+    /// the end of the line and no [`Hinter`](crate::Hinter) gave a hint.
+    pub fn set_async_hinter<H: AsyncHinter + 'static>(&mut self, hinter: H) {
+        self.hinter = Some(Box::new(hinter));
+    }
+
+    /// Sets where messages to print above the prompt come from.
+    ///
+    /// While a line is edited, each message is printed on its own lines above
+    /// the prompt as soon as it arrives, and the prompt and line are redrawn
+    /// below it. Messages arriving during incremental search or between calls
+    /// wait in the source. They are written as is, so pass text from untrusted
+    /// peers through [`ansi::strip_escapes`](crate::ansi::strip_escapes) first.
+    pub fn set_message_source<S: MessageSource + 'static>(&mut self, source: S) {
+        self.messages = Some(Box::new(source));
+    }
+
+    /// Gives up on an escape sequence when the future returned by `timer`
+    /// completes before its next byte arrives.
+    ///
+    /// A new timer is started after each byte of a sequence. By default the
+    /// editor waits for the rest indefinitely, so ESC alone only counts once a
+    /// second ESC follows; with a timer, a lone ESC is reported as
+    /// [`KeyEvent::Escape`] when it fires. See [`Decoder::expire`]. With
+    /// Embassy, for instance:
+    ///
+    /// ```ignore
+    /// editor.set_escape_timer(|| embassy_time::Timer::after_millis(100));
+    /// ```
+    pub fn set_escape_timer<F, Fut>(&mut self, mut timer: F)
+    where
+        F: FnMut() -> Fut + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.escape_timer = Some(Box::new(move || {
+            Box::pin(timer()) as BoxFuture<'static, ()>
+        }));
+    }
+
+    /// Reads a line, like [`LineEditor::read_line`].
+    pub async fn read_line<T: AsyncTerminal + ?Sized>(
+        &mut self,
+        terminal: &mut T,
+    ) -> Result<String> {
+        self.read_line_with_prompt(terminal, "").await
+    }
+
+    /// Writes `prompt` and reads a line, like [`LineEditor::read_line_with_prompt`].
+    pub async fn read_line_with_prompt<T: AsyncTerminal + ?Sized>(
+        &mut self,
+        terminal: &mut T,
+        prompt: &str,
+    ) -> Result<String> {
+        self.editor.prompt.clear();
+        self.editor.prompt.push_str(prompt);
+        self.editor.overrides.clear();
+
+        terminal.enter_raw_mode()?;
+        let mut policy = self.editor.retry.take();
+        let result = self
+            .read_line_inner(&mut crate::retry::Retrying::new(
+                &mut *terminal,
+                policy.as_mut(),
+            ))
+            .await;
+        self.editor.retry = policy;
+        self.editor.keep_line_on_error(&result);
+        let bridge = &mut Bridge {
+            terminal: &mut *terminal,
+            echo: self.editor.echoes(),
+        };
+        let off = match self.editor.bracket_paste(bridge, false) {
+            Ok(()) => flush(terminal).await,
+            Err(e) => Err(e),
+        };
+        let exited = terminal.exit_raw_mode();
+        result.and_then(|line| off.and(exited).map(|_| line))
+    }
+
+    async fn read_line_inner<T: AsyncTerminal + ?Sized>(
+        &mut self,
+        terminal: &mut T,
+    ) -> Result<String> {
+        #[cfg(feature = "metrics")]
+        let terminal = &mut crate::metrics::Counting::new(terminal);
+        let echo = self.editor.echoes();
+        let mut decoder = Decoder::new();
+        let mut deadline = None;
+        self.editor.begin(&mut Bridge {
+            terminal: &mut *terminal,
+            echo,
+        })?;
+        self.editor.render()?;
+        flush(terminal).await?;
+
+        loop {
+            let editing = matches!(self.editor.mode, Mode::Edit);
+            let messages = self.messages.as_mut().filter(|_| editing);
+            let byte = match next_input(
+                terminal,
+                messages,
+                deadline.as_mut(),
+                decoder.is_ambiguous(),
+            )
+            .await?
+            {
+                Input::Byte(byte) => Some(byte),
+                Input::Timeout => None,
+                Input::Message(text) => {
+                    self.editor.print_above(
+                        &mut Bridge {
+                            terminal: &mut *terminal,
+                            echo,
+                        },
+                        &text,
+                    )?;
+                    self.editor.render()?;
+                    flush(terminal).await?;
+                    continue;
+                }
+            };
+            #[cfg(feature = "metrics")]
+            let watch = self.editor.start_key(terminal);
+            let done = match byte {
+                Some(byte) if matches!(self.editor.mode, Mode::QuotedInsert(_)) => {
+                    let mut bridge = Bridge {
+                        terminal: &mut *terminal,
+                        echo,
+                    };
+                    self.editor.step_byte(&mut bridge, byte)?
+                }
+                _ => {
+                    let event = match byte {
+                        Some(byte) => decoder.push(byte),
+                        None => decoder.expire(),
+                    };
+                    deadline = match self.escape_timer.as_mut() {
+                        Some(timer) if decoder.is_pending() => Some(timer()),
+                        _ => None,
+                    };
+                    match event {
+                        Some(event) => self.step(terminal, event).await?,
+                        None => continue,
+                    }
+                }
+            };
+            self.editor.send_copied(&mut Bridge {
+                terminal: &mut *terminal,
+                echo: self.editor.echoes(),
+            })?;
+            #[cfg(feature = "metrics")]
+            self.editor.end_key(watch, terminal);
+            if done.is_none() {
+                self.async_hint(terminal).await?;
+                self.editor.render()?;
+            }
+            flush(terminal).await?;
+            if let Some(line) = done {
+                return Ok(line);
+            }
+        }
+    }
+
+    /// Processes one key, awaiting the async completer for Tab.
+    async fn step<T: AsyncTerminal + ?Sized>(
+        &mut self,
+        terminal: &mut T,
+        event: crate::KeyEvent,
+    ) -> Result<Option<String>> {
+        if self.editor.unknown_sequence(&event) {
+            return Ok(None);
+        }
+        let mut bridge = Bridge {
+            terminal: &mut *terminal,
+            echo: self.editor.echoes(),
+        };
+        if !matches!(self.editor.mode, Mode::Edit) {
+            return self.editor.step(&mut bridge, event);
+        }
+        let event = match self.editor.translate_newline(event) {
+            Some(event) => event,
+            None => return self.editor.show_paste(&mut bridge),
+        };
+        let off_screen =
+            self.editor.draws_after_step() || self.editor.wraps(&mut bridge) || self.editor.pasting;
+        let action = if off_screen {
+            self.editor
+                .resolve(&mut crate::NoEcho(&mut bridge), event)?
+        } else {
+            self.editor.resolve(&mut bridge, event)?
+        };
+        let completer = match (self.completer.as_mut(), action) {
+            (Some(completer), Some(Action::Complete)) => completer,
+            // The clipboard reply can't be awaited here
+            (_, Some(Action::PasteClipboard)) => return Ok(None),
+            _ if off_screen => return self.editor.step_off_screen(&mut bridge, action),
+            (_, Some(action)) => return self.editor.step_action(&mut bridge, action),
+            (_, None) => return Ok(None),
+        };
+
+        self.editor.clear_hint(&mut bridge)?;
+        flush(bridge.terminal).await?;
+        let editor = &mut self.editor;
+        let ctx = crate::Context::new(
+            editor.line.as_str()?,
+            editor.line.cursor_pos(),
+            &editor.history,
+        );
+        let candidates = completer.complete(&ctx).await;
+        let mut bridge = Bridge {
+            terminal,
+            echo: editor.echoes(),
+        };
+        editor.apply_completion(&mut bridge, candidates)?;
+        editor.show_hint(&mut bridge)?;
+        Ok(None)
+    }
+
+    /// Shows the async hinter's suggestion if nothing else is shown.
+    async fn async_hint<T: AsyncTerminal + ?Sized>(&mut self, terminal: &mut T) -> Result<()> {
+        let editor = &mut self.editor;
+        let hinter = match self.hinter.as_mut() {
+            Some(hinter) => hinter,
+            None => return Ok(()),
+        };
+        let at_end = editor.line.cursor_pos() == editor.line.len();
+        if !matches!(editor.mode, Mode::Edit) || editor.hint.is_some() || !at_end {
+            return Ok(());
+        }
+
+        flush(terminal).await?;
+        let ctx = crate::Context::new(
+            editor.line.as_str()?,
+            editor.line.cursor_pos(),
+            &editor.history,
+        );
+        if let Some(hint) = hinter.hint(&ctx).await {
+            editor.paint_hint(
+                &mut Bridge {
+                    terminal,
+                    echo: editor.echoes(),
+                },
+                hint,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl From<LineEditor> for AsyncLineEditor {
+    fn from(editor: LineEditor) -> Self {
+        AsyncLineEditor {
+            editor,
+            completer: None,
+            hinter: None,
+            messages: None,
+            escape_timer: None,
+        }
+    }
+}
+
+impl Deref for AsyncLineEditor {
+    type Target = LineEditor;
+
+    fn deref(&self) -> &LineEditor {
+        &self.editor
+    }
+}
+
+impl DerefMut for AsyncLineEditor {
+    fn deref_mut(&mut self) -> &mut LineEditor {
+        &mut self.editor
+    }
+}
+
+impl fmt::Debug for AsyncLineEditor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncLineEditor")
+            .field("editor", &self.editor)
+            .field("completer", &self.completer.is_some())
+            .field("hinter", &self.hinter.is_some())
+            .field("messages", &self.messages.is_some())
+            .field("escape_timer", &self.escape_timer.is_some())
+            .finish()
+    }
+}
+
+/// Presents an [`AsyncTerminal`] as a [`Terminal`] for drawing.
+///
+/// Output is queued with [`AsyncTerminal::write`] and flushed by the caller;
+/// input is read by the caller too, so the read methods are never used.
+pub(crate) struct Bridge<'a, T: AsyncTerminal + ?Sized> {
+    pub(crate) terminal: &'a mut T,
+    pub(crate) echo: bool,
+}
+
+impl<T: AsyncTerminal + ?Sized> Terminal for Bridge<'_, T> {
+    fn read_byte(&mut self) -> Result<u8> {
+        Err(IoCause::Unsupported.into())
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        if self.echo {
+            self.terminal.write(data)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn cursor_left(&mut self) -> Result<()> {
+        self.write(b"\x1b[D")
+    }
+
+    fn cursor_right(&mut self) -> Result<()> {
+        self.write(b"\x1b[C")
+    }
+
+    fn clear_eol(&mut self) -> Result<()> {
+        self.write(b"\x1b[K")
+    }
+
+    fn capabilities(&self) -> Caps {
+        self.terminal.capabilities()
+    }
+}
+
+/// Future polling a closure, for the async editor's primitive waits.
+struct PollFn<F>(F);
+
+impl<T, F: FnMut(&mut Context<'_>) -> Poll<T> + Unpin> Future for PollFn<F> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        (self.0)(cx)
+    }
+}
+
+/// Waits for an input byte or, if given a source, a message; messages win.
+/// Given a deadline, gives up once it passes without input, and if `now`,
+/// when no input is waiting.
+async fn next_input<T: AsyncTerminal + ?Sized>(
+    terminal: &mut T,
+    mut messages: Option<&mut Box<dyn MessageSource>>,
+    mut deadline: Option<&mut BoxFuture<'static, ()>>,
+    now: bool,
+) -> Result<Input> {
+    PollFn(|cx: &mut Context<'_>| {
+        if let Some(source) = messages.as_mut() {
+            if let Poll::Ready(text) = source.poll_message(cx) {
+                return Poll::Ready(Ok(Input::Message(text)));
+            }
+        }
+        if let Poll::Ready(byte) = terminal.poll_read_byte(cx) {
+            return Poll::Ready(byte.map(Input::Byte));
+        }
+        if now {
+            return Poll::Ready(Ok(Input::Timeout));
+        }
+        match deadline.as_mut() {
+            Some(deadline) => deadline.as_mut().poll(cx).map(|()| Ok(Input::Timeout)),
+            None => Poll::Pending,
+        }
+    })
+    .await
+}
+
+pub(crate) async fn flush<T: AsyncTerminal + ?Sized>(terminal: &mut T) -> Result<()> {
+    PollFn(|cx: &mut Context<'_>| terminal.poll_flush(cx)).await
+}
+
+/// Reads bytes until `decoder` completes a key event.
+pub(crate) async fn read_key<T: AsyncTerminal + ?Sized>(
+    terminal: &mut T,
+    decoder: &mut Decoder,
+) -> Result<KeyEvent> {
+    loop {
+        // ESC ESC with nothing after it is Escape
+        let ambiguous = decoder.is_ambiguous();
+        let byte = PollFn(|cx: &mut Context<'_>| match terminal.poll_read_byte(cx) {
+            Poll::Pending if ambiguous => Poll::Ready(Ok(None)),
+            poll => poll.map(|byte| byte.map(Some)),
+        })
+        .await?;
+        let event = match byte {
+            Some(byte) => decoder.push(byte),
+            None => decoder.expire(),
+        };
+        if let Some(event) = event {
+            return Ok(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ScriptedTerminal;
+    use crate::Error;
+    use alloc::string::ToString;
+    use alloc::vec;
+    use core::task::{RawWaker, RawWakerVTable, Waker};
+
+    /// Input script whose bytes arrive every other poll.
+    fn script(input: &[u8]) -> ScriptedTerminal {
+        let stalled: Vec<(u32, u8)> = input.iter().map(|&byte| (1, byte)).collect();
+        ScriptedTerminal::with_stalls(&stalled)
+    }
+
+    /// Pending on the first poll, like a bus transfer in progress.
+    struct Transfer<T>(Option<T>, bool);
+
+    impl<T: Unpin> Future for Transfer<T> {
+        type Output = T;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+            if core::mem::replace(&mut self.1, true) {
+                Poll::Ready(self.0.take().unwrap())
+            } else {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        fn clone(_: *const ()) -> RawWaker {
+            RawWaker::new(core::ptr::null(), &VTABLE)
+        }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        let waker = unsafe { Waker::from_raw(clone(core::ptr::null())) };
+        let mut cx = Context::from_waker(&waker);
+
+        let mut future = Box::pin(future);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn test_read_line_edits() {
+        let mut editor = AsyncLineEditor::new(64, 10);
+        let mut term = script(b"helo\x1b[Dl\r");
+        assert_eq!(
+            block_on(editor.read_line_with_prompt(&mut term, "> ")).unwrap(),
+            "hello"
+        );
+        assert!(term.output.starts_with(b"> h"));
+        assert_eq!(editor.history().len(), 1);
+
+        let mut term = script(b"abc");
+        assert!(matches!(
+            block_on(editor.read_line(&mut term)),
+            Err(Error::Eof)
+        ));
+
+        // The timer fires while the script pauses between bytes
+        editor.set_escape_timer(|| core::future::ready(()));
+        let mut term = script(b"a\x1b[D\r");
+        assert_eq!(block_on(editor.read_line(&mut term)).unwrap(), "a[D");
+    }
+
+    #[cfg(feature = "embassy")]
+    #[test]
+    fn test_messages_print_above_prompt() {
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embassy_sync::channel::Channel;
+
+        let log: &'static Channel<NoopRawMutex, String, 4> = Box::leak(Box::new(Channel::new()));
+        let mut editor = AsyncLineEditor::new(64, 10);
+        editor.set_message_source(log.receiver());
+
+        log.try_send("sensor: 21C\n".to_string()).unwrap();
+        let mut term = script(b"ok\r");
+        assert_eq!(
+            block_on(editor.read_line_with_prompt(&mut term, "> ")).unwrap(),
+            "ok"
+        );
+        let output = String::from_utf8_lossy(&term.output).to_string();
+        let newline = "\r\n";
+        assert!(output.contains(&alloc::format!("\r\x1b[Jsensor: 21C{}\r\x1b[J> o", newline)));
+    }
+
+    #[test]
+    fn test_boxed_terminals() {
+        let mut editor = AsyncLineEditor::new(64, 10);
+        let mut links: Vec<Box<dyn AsyncTerminal>> =
+            vec![Box::new(script(b"usb\r")), Box::new(script(b"uart\r"))];
+        let mut lines = Vec::new();
+        for link in links.iter_mut() {
+            lines.push(block_on(editor.read_line(link)).unwrap());
+        }
+        let link: &mut dyn AsyncTerminal = links[0].as_mut();
+        assert!(matches!(block_on(editor.read_line(link)), Err(Error::Eof)));
+        assert_eq!(lines, ["usb", "uart"]);
+    }
+
+    #[test]
+    fn test_async_completer_and_hinter() {
+        let mut editor = AsyncLineEditor::new(64, 10);
+        editor.set_async_completer(|ctx: &crate::Context| {
+            let devices: Vec<String> = ["dht22", "bme280"].iter().map(|d| d.to_string()).collect();
+            let word = ctx.word_before_cursor().to_string();
+            Transfer(
+                Some(
+                    devices
+                        .into_iter()
+                        .filter(|d| d.starts_with(&word))
+                        .collect(),
+                ),
+                false,
+            )
+        });
+        editor.set_async_hinter(|ctx: &crate::Context| {
+            let hint = if ctx.line() == "read " {
+                Some("now".to_string())
+            } else {
+                None
+            };
+            Transfer(Some(hint), false)
+        });
+
+        let mut term = script(b"read b\t\r");
+        assert_eq!(
+            block_on(editor.read_line(&mut term)).unwrap(),
+            "read bme280"
+        );
+
+        let mut term = script(b"read \x1b[C\r");
+        assert_eq!(block_on(editor.read_line(&mut term)).unwrap(), "read now");
+    }
+}
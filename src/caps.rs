@@ -0,0 +1,156 @@
+//! What a terminal can display.
+//!
+//! Backends differ: a Unix terminal emulator understands ANSI colors and
+//! reports its size, the Windows console is driven through its API and shows
+//! escape sequences as text, and a serial link can't tell what sits at the
+//! other end. [`Terminal::capabilities`](crate::Terminal::capabilities) reports
+//! a [`Caps`] so features can leave out what the backend can't show instead of
+//! writing escape sequences blindly.
+//!
+//! The editor consults it for [themes](crate::theme), which are only applied
+//! with [`colors`](Caps::colors), for the bracketed paste mode behind
+//! [`LineEditor::set_pasted_newlines`](crate::LineEditor::set_pasted_newlines),
+//! to edit the middle of a line in place with
+//! [`insert_delete`](Caps::insert_delete), and for the [line
+//! ending](Caps::newline) it writes.
+
+/// Capabilities of a terminal.
+///
+/// Start from [`Caps::NONE`] or [`Caps::ANSI`] and adjust with the `with_`
+/// methods.
+///
+/// # Examples
+///
+/// ```
+/// use editline::Caps;
+///
+/// // An ANSI serial console that can't report its size
+/// let caps = Caps::ANSI;
+/// assert!(caps.colors && !caps.size);
+///
+/// // ...behind a display that ignores colors
+/// let caps = caps.with_colors(false);
+/// assert!(!caps.colors);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Caps {
+    /// SGR colors and text attributes.
+    pub colors: bool,
+    /// Moving the cursor across rows and erasing them, as status lines and
+    /// full-screen editing do.
+    pub cursor_addressing: bool,
+    /// [`Terminal::size`](crate::Terminal::size) reports the actual size.
+    pub size: bool,
+    /// Bracketed paste mode (`ESC [ ? 2004 h`).
+    pub bracketed_paste: bool,
+    /// An audible or visible [`bell`](crate::Terminal::bell).
+    pub bell: bool,
+    /// Inserting and deleting characters within a row (ICH `ESC [ @` and DCH
+    /// `ESC [ P`, from the VT102 on), so that typing or deleting in the middle
+    /// of a line doesn't rewrite the rest of it.
+    pub insert_delete: bool,
+    /// New lines take `\r\n`: bytes reach the other end as written, with no
+    /// output processing to turn a bare `\n` into a carriage return and line
+    /// feed, as a serial or network link does. A Unix terminal in raw mode
+    /// keeps that processing and gets `\n` alone.
+    pub crlf: bool,
+}
+
+impl Caps {
+    /// Nothing beyond writing text; the default for [`Terminal`](crate::Terminal)
+    /// implementations that don't say otherwise.
+    pub const NONE: Caps = Caps {
+        colors: false,
+        cursor_addressing: false,
+        size: false,
+        bracketed_paste: false,
+        bell: false,
+        insert_delete: false,
+        crlf: true,
+    };
+
+    /// An ANSI/VT100 terminal at the other end of a byte stream: everything
+    /// but the size, which a byte stream can't query.
+    pub const ANSI: Caps = Caps {
+        colors: true,
+        cursor_addressing: true,
+        size: false,
+        bracketed_paste: true,
+        bell: true,
+        insert_delete: true,
+        crlf: true,
+    };
+
+    /// Sets [`colors`](Self::colors).
+    pub const fn with_colors(mut self, on: bool) -> Self {
+        self.colors = on;
+        self
+    }
+
+    /// Sets [`cursor_addressing`](Self::cursor_addressing).
+    pub const fn with_cursor_addressing(mut self, on: bool) -> Self {
+        self.cursor_addressing = on;
+        self
+    }
+
+    /// Sets [`size`](Self::size).
+    pub const fn with_size(mut self, on: bool) -> Self {
+        self.size = on;
+        self
+    }
+
+    /// Sets [`bracketed_paste`](Self::bracketed_paste).
+    pub const fn with_bracketed_paste(mut self, on: bool) -> Self {
+        self.bracketed_paste = on;
+        self
+    }
+
+    /// Sets [`bell`](Self::bell).
+    pub const fn with_bell(mut self, on: bool) -> Self {
+        self.bell = on;
+        self
+    }
+
+    /// Sets [`insert_delete`](Self::insert_delete).
+    pub const fn with_insert_delete(mut self, on: bool) -> Self {
+        self.insert_delete = on;
+        self
+    }
+
+    /// Sets [`crlf`](Self::crlf).
+    pub const fn with_crlf(mut self, on: bool) -> Self {
+        self.crlf = on;
+        self
+    }
+
+    /// The bytes that start a new line: `\r\n` with [`crlf`](Self::crlf),
+    /// otherwise `\n`.
+    pub const fn newline(&self) -> &'static [u8] {
+        if self.crlf {
+            b"\r\n"
+        } else {
+            b"\n"
+        }
+    }
+
+    /// Returns what both `self` and `other` can do, for output mirrored to two
+    /// terminals. New lines take `\r\n` if either needs it.
+    pub const fn intersection(self, other: Caps) -> Caps {
+        Caps {
+            colors: self.colors && other.colors,
+            cursor_addressing: self.cursor_addressing && other.cursor_addressing,
+            size: self.size && other.size,
+            bracketed_paste: self.bracketed_paste && other.bracketed_paste,
+            bell: self.bell && other.bell,
+            insert_delete: self.insert_delete && other.insert_delete,
+            crlf: self.crlf || other.crlf,
+        }
+    }
+}
+
+impl Default for Caps {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
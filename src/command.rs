@@ -0,0 +1,48 @@
+//! Application commands bound to keys.
+//!
+//! A [`Command`] bound with [`LineEditor::bind_command`](crate::LineEditor::bind_command)
+//! runs when its key is pressed and may edit the line directly through the
+//! [`LineBuffer`]. The editor redraws the line afterwards if the command changed
+//! it, so commands never deal with the terminal.
+
+use crate::LineBuffer;
+
+/// What the editor does after a [`Command`] has run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandlerResult {
+    /// Keep editing.
+    Continue,
+    /// Submit the line, as if Enter had been pressed.
+    Submit,
+    /// Cancel the line with [`Error::Interrupted`](crate::Error::Interrupted).
+    Interrupt,
+}
+
+/// Application callback bound to a key.
+///
+/// Closures of the form `FnMut(&mut LineBuffer) -> HandlerResult` implement this
+/// trait.
+///
+/// # Examples
+///
+/// ```
+/// use editline::{HandlerResult, KeyEvent, LineBuffer, LineEditor};
+///
+/// let mut editor = LineEditor::new(1024, 50);
+///
+/// // Ctrl+T inserts a template at the cursor
+/// editor.bind_command(KeyEvent::Ctrl('t'), |line: &mut LineBuffer| {
+///     line.insert_str("gpio set ");
+///     HandlerResult::Continue
+/// });
+/// ```
+pub trait Command {
+    /// Runs the command on the line being edited.
+    fn execute(&mut self, line: &mut LineBuffer) -> HandlerResult;
+}
+
+impl<F: FnMut(&mut LineBuffer) -> HandlerResult> Command for F {
+    fn execute(&mut self, line: &mut LineBuffer) -> HandlerResult {
+        self(line)
+    }
+}
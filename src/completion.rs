@@ -0,0 +1,483 @@
+//! Tab completion support.
+//!
+//! Install a [`Completer`] with [`LineEditor::set_completer`](crate::LineEditor::set_completer)
+//! to offer completions when Tab is pressed. The editor completes the word before
+//! the cursor: a single match is inserted, several matches are extended to their
+//! longest common prefix, and when that doesn't make progress the candidates are
+//! listed in columns, paged with a `--More--` prompt when they don't fit on screen.
+//!
+//! Which candidates count as matches is decided by the editor's [`MatchPolicy`],
+//! set with [`LineEditor::set_match_policy`](crate::LineEditor::set_match_policy).
+//! Policies ignoring case compare characters through a [`CaseFold`], set with
+//! [`LineEditor::set_case_fold`](crate::LineEditor::set_case_fold).
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::theme::Style;
+use crate::{display_width, write_newline, write_visible, Context, KeyEvent, Result, Terminal};
+
+/// Source of completion candidates.
+///
+/// Closures of the form `FnMut(&Context) -> Vec<String>` implement this trait.
+///
+/// # Examples
+///
+/// ```
+/// use editline::{Context, LineEditor};
+///
+/// let mut editor = LineEditor::new(1024, 50);
+/// editor.set_completer(|ctx: &Context| {
+///     let words: &[&str] = match ctx.word_index() {
+///         0 => &["gpio", "help", "history", "exit"],
+///         _ => &["get", "set"],
+///     };
+///     words.iter().map(|w| w.to_string()).collect()
+/// });
+/// ```
+pub trait Completer {
+    /// Returns candidates for the word before the cursor
+    /// ([`Context::word_before_cursor`]).
+    ///
+    /// Each candidate replaces that text. Candidates are filtered and ordered by
+    /// the editor's [`MatchPolicy`], so a completer may simply return everything
+    /// valid at this point of the line.
+    fn complete(&mut self, ctx: &Context) -> Vec<String>;
+}
+
+impl<F: FnMut(&Context) -> Vec<String>> Completer for F {
+    fn complete(&mut self, ctx: &Context) -> Vec<String> {
+        self(ctx)
+    }
+}
+
+/// Maps a character to the form compared when matching ignores case.
+///
+/// The default is [`unicode_fold`]. Supply another for rules Unicode's default
+/// mapping doesn't follow, such as Turkish dotted and dotless i:
+///
+/// ```
+/// use editline::completion::{unicode_fold, MatchPolicy};
+///
+/// let turkish = |c: char| match c {
+///     'I' => 'ı',
+///     'İ' => 'i',
+///     c => unicode_fold(c),
+/// };
+/// assert!(MatchPolicy::IgnoreCasePrefix.matches_with("ıs", "ISI", turkish));
+/// assert!(!MatchPolicy::IgnoreCasePrefix.matches_with("is", "ISI", turkish));
+/// ```
+pub type CaseFold = fn(char) -> char;
+
+/// Folds with Unicode's lowercase mapping, leaving characters that lowercase
+/// to more than one character as they are.
+pub fn unicode_fold(c: char) -> char {
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(lower), None) => lower,
+        _ => c,
+    }
+}
+
+/// Folds ASCII letters only, so `É` and `é` stay distinct. Smaller and more
+/// predictable on consoles that only take ASCII commands.
+pub fn ascii_fold(c: char) -> char {
+    c.to_ascii_lowercase()
+}
+
+/// How completion candidates are matched against the word being completed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchPolicy {
+    /// Candidates must start with the word exactly (the default).
+    Prefix,
+    /// Candidates must start with the word, ignoring case.
+    IgnoreCasePrefix,
+    /// The word's characters must appear in order anywhere in the candidate,
+    /// ignoring case (`hs` matches `history`). Results are ranked so that
+    /// matches at the start, at word boundaries, and in consecutive runs
+    /// come first.
+    Fuzzy,
+}
+
+impl Default for MatchPolicy {
+    fn default() -> Self {
+        MatchPolicy::Prefix
+    }
+}
+
+impl MatchPolicy {
+    /// Returns `true` if `candidate` matches `word` under this policy.
+    pub fn matches(self, word: &str, candidate: &str) -> bool {
+        self.matches_with(word, candidate, unicode_fold)
+    }
+
+    /// Like [`matches`](Self::matches), ignoring case as `fold` does.
+    pub fn matches_with(self, word: &str, candidate: &str, fold: CaseFold) -> bool {
+        match self {
+            MatchPolicy::Prefix => candidate.starts_with(word),
+            MatchPolicy::IgnoreCasePrefix => {
+                let mut rest = candidate.chars();
+                word.chars()
+                    .all(|w| rest.next().map_or(false, |c| eq_folded(w, c, fold)))
+            }
+            MatchPolicy::Fuzzy => fuzzy_score(word, candidate, fold).is_some(),
+        }
+    }
+
+    /// Keeps the candidates matching `word` and puts them in display order.
+    ///
+    /// Prefix policies sort alphabetically; [`Fuzzy`](MatchPolicy::Fuzzy) sorts by
+    /// match quality, then length, then alphabetically. Duplicates are removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use editline::completion::MatchPolicy;
+    ///
+    /// let candidates = vec!["show_history".to_string(), "history".to_string(), "help".to_string()];
+    /// let ranked = MatchPolicy::Fuzzy.filter("hs", candidates);
+    /// assert_eq!(ranked, ["history", "show_history"]);
+    /// ```
+    pub fn filter(self, word: &str, candidates: Vec<String>) -> Vec<String> {
+        self.filter_with(word, candidates, unicode_fold)
+    }
+
+    /// Like [`filter`](Self::filter), ignoring case as `fold` does.
+    pub fn filter_with(self, word: &str, candidates: Vec<String>, fold: CaseFold) -> Vec<String> {
+        let mut matched: Vec<(u32, String)> = candidates
+            .into_iter()
+            .filter_map(|c| match self {
+                MatchPolicy::Fuzzy => fuzzy_score(word, &c, fold).map(|score| (score, c)),
+                _ if self.matches_with(word, &c, fold) => Some((0, c)),
+                _ => None,
+            })
+            .collect();
+
+        if self == MatchPolicy::Fuzzy {
+            matched
+                .sort_by(|(sa, a), (sb, b)| sb.cmp(sa).then(a.len().cmp(&b.len())).then(a.cmp(b)));
+        } else {
+            matched.sort_by(|(_, a), (_, b)| a.cmp(b));
+        }
+        matched.dedup_by(|(_, a), (_, b)| a == b);
+        matched.into_iter().map(|(_, c)| c).collect()
+    }
+
+    fn ignores_case(self) -> bool {
+        self != MatchPolicy::Prefix
+    }
+}
+
+fn eq_folded(a: char, b: char, fold: CaseFold) -> bool {
+    a == b || fold(a) == fold(b)
+}
+
+/// Scores a case-insensitive subsequence match; `None` if `word` isn't one.
+///
+/// Each matched character scores a point, with bonuses for matching the first
+/// character, the start of a word (after `_`, `-`, `.`, `/` or a space), or
+/// directly following the previous match.
+fn fuzzy_score(word: &str, candidate: &str, fold: CaseFold) -> Option<u32> {
+    let mut score = 0;
+    let mut prev: Option<char> = None;
+    let mut prev_matched = false;
+    let mut chars = candidate.chars();
+
+    for w in word.chars() {
+        loop {
+            let c = chars.next()?;
+            let at_boundary = prev.map_or(true, |p| matches!(p, '_' | '-' | '.' | '/' | ' '));
+            prev = Some(c);
+
+            if eq_folded(w, c, fold) {
+                score += 1;
+                if at_boundary {
+                    score += 3;
+                }
+                if prev_matched {
+                    score += 5;
+                }
+                prev_matched = true;
+                break;
+            }
+            prev_matched = false;
+        }
+    }
+
+    Some(score)
+}
+
+/// Terminal size assumed when the backend can't report one.
+pub(crate) const DEFAULT_SIZE: (u16, u16) = (80, 24);
+
+/// Returns the longest prefix shared by all candidates (taken from the first one).
+pub(crate) fn common_prefix(candidates: &[String], policy: MatchPolicy, fold: CaseFold) -> &str {
+    let first = match candidates.first() {
+        Some(first) => first.as_str(),
+        None => return "",
+    };
+
+    let mut end = first.len();
+    for candidate in &candidates[1..] {
+        end = first[..end]
+            .char_indices()
+            .zip(candidate.chars())
+            .find(|((_, a), b)| {
+                if policy.ignores_case() {
+                    !eq_folded(*a, *b, fold)
+                } else {
+                    a != b
+                }
+            })
+            .map_or(end.min(candidate.len()), |((i, _), _)| i);
+    }
+    &first[..end]
+}
+
+/// Prints candidates in columns, ordered down then across like readline.
+///
+/// The characters of each candidate that matched `word` under `policy` are drawn
+/// in `highlight`. When the list is taller than the screen, output pauses every
+/// page with a `--More--` prompt: Space shows the next page, Enter the next line,
+/// and `q` (or Ctrl+C / Ctrl+G) stops the listing.
+pub(crate) fn print_candidates<T: Terminal>(
+    terminal: &mut T,
+    candidates: &[String],
+    word: &str,
+    policy: MatchPolicy,
+    fold: CaseFold,
+    highlight: Style,
+) -> Result<()> {
+    let (columns, rows) = terminal.size().unwrap_or(DEFAULT_SIZE);
+    let columns = usize::from(columns).max(1);
+    let page = usize::from(rows).saturating_sub(1).max(1);
+
+    let widest = candidates
+        .iter()
+        .map(|c| display_width(c.as_bytes()))
+        .max()
+        .unwrap_or(0);
+    let cell = widest + 2;
+    let per_row = (columns / cell).max(1);
+    let total_rows = (candidates.len() + per_row - 1) / per_row;
+
+    let mut budget = page;
+    for row in 0..total_rows {
+        if row == budget {
+            match more_prompt(terminal)? {
+                More::Page => budget += page,
+                More::Line => budget += 1,
+                More::Quit => return Ok(()),
+            }
+        }
+
+        for col in 0..per_row {
+            let candidate = match candidates.get(col * total_rows + row) {
+                Some(candidate) => candidate,
+                None => break,
+            };
+            write_candidate(terminal, candidate, word, policy, fold, highlight)?;
+            if col + 1 < per_row && (col + 1) * total_rows + row < candidates.len() {
+                for _ in display_width(candidate.as_bytes())..cell {
+                    terminal.write(b" ")?;
+                }
+            }
+        }
+        write_newline(terminal)?;
+    }
+
+    Ok(())
+}
+
+/// Writes a candidate with the characters matching `word` in `highlight`.
+///
+/// Prefix policies highlight the leading characters; fuzzy matching highlights
+/// the characters picked by the same left-to-right scan that scored it.
+fn write_candidate<T: Terminal>(
+    terminal: &mut T,
+    candidate: &str,
+    word: &str,
+    policy: MatchPolicy,
+    fold: CaseFold,
+    highlight: Style,
+) -> Result<()> {
+    if highlight.is_plain() || word.is_empty() {
+        return write_visible(terminal, candidate.as_bytes());
+    }
+
+    let mut pending = word.chars().peekable();
+    let mut prefix_ended = false;
+    let mut run_start = 0;
+    let mut run_matched = false;
+    for (i, c) in candidate.char_indices() {
+        let matched = match pending.peek() {
+            _ if prefix_ended => false,
+            Some(&w) if policy == MatchPolicy::Prefix => w == c,
+            Some(&w) => eq_folded(w, c, fold),
+            None => false,
+        };
+        if matched {
+            pending.next();
+        } else {
+            // A prefix match is over at the first difference
+            prefix_ended = policy != MatchPolicy::Fuzzy;
+        }
+
+        if matched != run_matched {
+            write_run(terminal, &candidate[run_start..i], run_matched, highlight)?;
+            run_start = i;
+            run_matched = matched;
+        }
+    }
+    write_run(terminal, &candidate[run_start..], run_matched, highlight)
+}
+
+fn write_run<T: Terminal>(
+    terminal: &mut T,
+    text: &str,
+    matched: bool,
+    highlight: Style,
+) -> Result<()> {
+    if matched {
+        highlight.paint(terminal, text.as_bytes())
+    } else {
+        write_visible(terminal, text.as_bytes())
+    }
+}
+
+enum More {
+    Page,
+    Line,
+    Quit,
+}
+
+fn more_prompt<T: Terminal>(terminal: &mut T) -> Result<More> {
+    terminal.write(b"--More--")?;
+    terminal.flush()?;
+
+    let answer = loop {
+        match terminal.parse_key_event()? {
+            KeyEvent::Normal('\0') => continue,
+            KeyEvent::Normal(' ') => break More::Page,
+            KeyEvent::Enter | KeyEvent::LineFeed => break More::Line,
+            KeyEvent::Normal('q')
+            | KeyEvent::Normal('Q')
+            | KeyEvent::Ctrl('c')
+            | KeyEvent::Ctrl('g') => break More::Quit,
+            _ => break More::Page,
+        }
+    };
+
+    // Erase the prompt before continuing
+    terminal.write(b"\r")?;
+    terminal.clear_eol()?;
+    Ok(answer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ScriptedTerminal;
+    use alloc::string::ToString;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_common_prefix() {
+        let exact = MatchPolicy::Prefix;
+        let fold = unicode_fold;
+        assert_eq!(
+            common_prefix(&strings(&["history", "help", "hello"]), exact, fold),
+            "h"
+        );
+        assert_eq!(
+            common_prefix(&strings(&["hello", "help"]), exact, fold),
+            "hel"
+        );
+        assert_eq!(common_prefix(&strings(&["exit"]), exact, fold), "exit");
+        assert_eq!(common_prefix(&strings(&["exit", "ex"]), exact, fold), "ex");
+        assert_eq!(common_prefix(&[], exact, fold), "");
+        assert_eq!(common_prefix(&strings(&["Help", "hello"]), exact, fold), "");
+        assert_eq!(
+            common_prefix(
+                &strings(&["Help", "hello"]),
+                MatchPolicy::IgnoreCasePrefix,
+                fold
+            ),
+            "Hel"
+        );
+    }
+
+    #[test]
+    fn test_prefix_policies() {
+        let candidates = strings(&["Reset", "read", "write", "reboot"]);
+        assert_eq!(
+            MatchPolicy::Prefix.filter("re", candidates.clone()),
+            ["read", "reboot"]
+        );
+        assert_eq!(
+            MatchPolicy::IgnoreCasePrefix.filter("re", candidates),
+            ["Reset", "read", "reboot"]
+        );
+    }
+
+    #[test]
+    fn test_case_folds() {
+        let candidates = strings(&["ÉTAT", "état", "Etat"]);
+        assert_eq!(
+            MatchPolicy::IgnoreCasePrefix.filter("ét", candidates.clone()),
+            ["ÉTAT", "état"]
+        );
+        assert_eq!(
+            MatchPolicy::IgnoreCasePrefix.filter_with("ét", candidates, ascii_fold),
+            ["état"]
+        );
+        assert_eq!(unicode_fold('İ'), 'İ');
+        assert!(MatchPolicy::Fuzzy.matches_with("et", "ETAT", ascii_fold));
+    }
+
+    #[test]
+    fn test_candidate_highlight() {
+        let highlight = Style::new().underline();
+        let render = |word: &str, candidate: &str, policy: MatchPolicy| {
+            let mut term = ScriptedTerminal {
+                caps: crate::Caps::ANSI,
+                ..ScriptedTerminal::new(b"")
+            };
+            write_candidate(&mut term, candidate, word, policy, unicode_fold, highlight).unwrap();
+            term.output()
+        };
+
+        assert_eq!(
+            render("he", "help", MatchPolicy::Prefix),
+            "\x1b[0;4mhe\x1b[0mlp"
+        );
+        assert_eq!(
+            render("HE", "help", MatchPolicy::IgnoreCasePrefix),
+            "\x1b[0;4mhe\x1b[0mlp"
+        );
+        assert_eq!(
+            render("hs", "history", MatchPolicy::Fuzzy),
+            "\x1b[0;4mh\x1b[0mi\x1b[0;4ms\x1b[0mtory"
+        );
+        assert_eq!(
+            render("he", "help", MatchPolicy::Fuzzy).len(),
+            render("he", "help", MatchPolicy::Prefix).len()
+        );
+    }
+
+    #[test]
+    fn test_fuzzy_ranking() {
+        let candidates = strings(&["gpio_set", "get_pin", "set_gpio", "gap", "list"]);
+        // Contiguous matches at word starts beat scattered ones; ties go to the
+        // shorter candidate, then alphabetical order. Non-matches are dropped.
+        assert_eq!(
+            MatchPolicy::Fuzzy.filter("gp", candidates),
+            ["gpio_set", "set_gpio", "get_pin", "gap"]
+        );
+        assert!(MatchPolicy::Fuzzy.matches("GS", "gpio_set"));
+        assert!(!MatchPolicy::Fuzzy.matches("sg", "gpio"));
+    }
+}
@@ -0,0 +1,114 @@
+//! Editing context passed to completion and hint callbacks.
+
+use crate::History;
+
+/// Snapshot of the line being edited, handed to [`Completer`](crate::Completer)
+/// and [`Hinter`](crate::Hinter) implementations.
+///
+/// Provides the line, the cursor position in bytes and characters, the
+/// boundaries of the word under the cursor, and read-only access to the history,
+/// so callbacks don't each have to re-tokenize the line.
+///
+/// Words are separated by spaces and tabs.
+///
+/// # Examples
+///
+/// ```
+/// use editline::{Context, History};
+///
+/// let history = History::new(10);
+/// let ctx = Context::new("gpio set 13", 7, &history);
+///
+/// assert_eq!(ctx.word(), "set");
+/// assert_eq!(ctx.word_before_cursor(), "se");
+/// assert_eq!(ctx.word_index(), 1);
+/// assert_eq!(ctx.words().collect::<Vec<_>>(), ["gpio", "set", "13"]);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Context<'a> {
+    line: &'a str,
+    pos: usize,
+    word_start: usize,
+    word_end: usize,
+    history: &'a History,
+}
+
+fn is_separator(c: char) -> bool {
+    c == ' ' || c == '\t'
+}
+
+impl<'a> Context<'a> {
+    /// Creates a context for `line` with the cursor at byte position `pos`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pos` is past the end of `line` or not on a character boundary.
+    pub fn new(line: &'a str, pos: usize, history: &'a History) -> Self {
+        let word_start = line[..pos].rfind(is_separator).map_or(0, |i| i + 1);
+        let word_end = line[pos..]
+            .find(is_separator)
+            .map_or(line.len(), |i| pos + i);
+        Self {
+            line,
+            pos,
+            word_start,
+            word_end,
+            history,
+        }
+    }
+
+    /// Returns the full line.
+    pub fn line(&self) -> &'a str {
+        self.line
+    }
+
+    /// Returns the cursor position in bytes.
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns the cursor position in characters.
+    pub fn char_pos(&self) -> usize {
+        self.line[..self.pos].chars().count()
+    }
+
+    /// Returns the byte position where the word under the cursor starts.
+    pub fn word_start(&self) -> usize {
+        self.word_start
+    }
+
+    /// Returns the byte position where the word under the cursor ends.
+    pub fn word_end(&self) -> usize {
+        self.word_end
+    }
+
+    /// Returns the whole word under the cursor (empty between words).
+    pub fn word(&self) -> &'a str {
+        &self.line[self.word_start..self.word_end]
+    }
+
+    /// Returns the part of the word under the cursor that precedes it.
+    ///
+    /// This is the text a completion replaces.
+    pub fn word_before_cursor(&self) -> &'a str {
+        &self.line[self.word_start..self.pos]
+    }
+
+    /// Returns the index of the word under the cursor (0 for the command name).
+    pub fn word_index(&self) -> usize {
+        self.line[..self.word_start]
+            .split(is_separator)
+            .filter(|w| !w.is_empty())
+            .count()
+    }
+
+    /// Returns an iterator over the words of the line.
+    pub fn words(&self) -> impl Iterator<Item = &'a str> {
+        self.line.split(is_separator).filter(|w| !w.is_empty())
+    }
+
+    /// Returns the editor's history.
+    pub fn history(&self) -> &'a History {
+        self.history
+    }
+}
@@ -0,0 +1,53 @@
+//! Offering a corrected line when Enter is pressed.
+//!
+//! Install a [`Corrector`] with
+//! [`LineEditor::set_corrector`](crate::LineEditor::set_corrector) to catch
+//! mistyped commands before they reach the dispatcher. When it suggests a
+//! different line, the editor asks `did you mean ...? [y/n]` after the input and
+//! waits for a single key: `y` or Enter submits the suggestion, Ctrl+G goes
+//! back to editing the line, and any other key submits the line as typed.
+
+use alloc::string::String;
+
+/// Source of corrections for submitted lines.
+///
+/// Closures of the form `FnMut(&str) -> Option<String>` implement this trait.
+///
+/// # Examples
+///
+/// ```
+/// use editline::LineEditor;
+///
+/// const COMMANDS: &[&str] = &["status", "reset", "gpio"];
+///
+/// let mut editor = LineEditor::new(1024, 50);
+/// editor.set_corrector(|line: &str| {
+///     let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+///     if COMMANDS.contains(&command) {
+///         return None;
+///     }
+///     // Same letters in a different order
+///     let mut typed: Vec<char> = command.chars().collect();
+///     typed.sort_unstable();
+///     COMMANDS.iter().find(|known| {
+///         let mut letters: Vec<char> = known.chars().collect();
+///         letters.sort_unstable();
+///         letters == typed
+///     })
+///     .map(|known| format!("{} {}", known, rest).trim_end().to_string())
+/// });
+/// ```
+pub trait Corrector {
+    /// Returns a corrected version of `line` if it looks mistyped, e.g. because
+    /// its command is unknown.
+    ///
+    /// `line` is trimmed and not empty. Returning `None`, or `line` itself,
+    /// submits it without asking.
+    fn correct(&mut self, line: &str) -> Option<String>;
+}
+
+impl<F: FnMut(&str) -> Option<String>> Corrector for F {
+    fn correct(&mut self, line: &str) -> Option<String> {
+        self(line)
+    }
+}
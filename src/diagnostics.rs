@@ -0,0 +1,153 @@
+//! Tools for finding out what a terminal sends.
+//!
+//! Terminals disagree on the bytes for all but the simplest keys. When a key
+//! does nothing or inserts stray characters, [`key_probe`] shows what arrived
+//! and how the [ANSI decoder](crate::ansi::Decoder) read it, which is what a
+//! bug report about an unrecognized sequence needs.
+
+use alloc::format;
+
+use crate::ansi::Decoder;
+use crate::{write_newline, KeyEvent, Result, Terminal};
+
+/// Echoes each key pressed as the bytes received and the [`KeyEvent`] they
+/// decode to, one key per line, until Ctrl+D.
+///
+/// The terminal is put in raw mode for the duration. Lines look like
+/// `1b 5b 31 7e  Home`, with a sequence the decoder doesn't know showing as
+/// `Unknown(..)`. Applications can offer this as a `keytest` command.
+///
+/// Bytes are read with [`Terminal::read_byte`] and decoded here, so backends
+/// that produce key events without a byte stream, such as the Windows console,
+/// don't go through [`Terminal::parse_key_event`] as they otherwise would. A
+/// lone Escape shows once the next key arrives.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(unix)]
+/// # {
+/// use editline::{diagnostics, terminals::StdioTerminal};
+///
+/// let mut terminal = StdioTerminal::new();
+/// diagnostics::key_probe(&mut terminal)?;
+/// # }
+/// # Ok::<(), editline::Error>(())
+/// ```
+pub fn key_probe<T: Terminal + ?Sized>(terminal: &mut T) -> Result<()> {
+    terminal.enter_raw_mode()?;
+    let result = probe(terminal);
+    terminal.exit_raw_mode()?;
+    result
+}
+
+fn probe<T: Terminal + ?Sized>(terminal: &mut T) -> Result<()> {
+    terminal.write(b"Press keys to see what they send, Ctrl+D to stop")?;
+    write_newline(terminal)?;
+    terminal.flush()?;
+    let mut decoder = Decoder::new();
+    loop {
+        let mut line = alloc::string::String::new();
+        let event = loop {
+            let byte = if decoder.is_ambiguous() {
+                match terminal.try_read_byte()? {
+                    Some(byte) => byte,
+                    None => break KeyEvent::Escape,
+                }
+            } else {
+                terminal.read_byte()?
+            };
+            line.push_str(&format!("{:02x} ", byte));
+            if let Some(event) = decoder.push(byte) {
+                break event;
+            }
+        };
+        line.push_str(&format!(" {:?}", event));
+        terminal.write(line.as_bytes())?;
+        write_newline(terminal)?;
+        terminal.flush()?;
+        if event == KeyEvent::Ctrl('d') {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+    use alloc::vec::Vec;
+
+    struct Probe {
+        input: Vec<u8>,
+        output: Vec<u8>,
+        raw: bool,
+    }
+
+    impl Terminal for Probe {
+        fn read_byte(&mut self) -> Result<u8> {
+            if self.input.is_empty() {
+                Err(Error::Eof)
+            } else {
+                Ok(self.input.remove(0))
+            }
+        }
+        fn write(&mut self, data: &[u8]) -> Result<()> {
+            self.output.extend_from_slice(data);
+            Ok(())
+        }
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn enter_raw_mode(&mut self) -> Result<()> {
+            self.raw = true;
+            Ok(())
+        }
+        fn exit_raw_mode(&mut self) -> Result<()> {
+            self.raw = false;
+            Ok(())
+        }
+        fn cursor_left(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn cursor_right(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn clear_eol(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_key_probe() {
+        let mut term = Probe {
+            input: b"a\x1b[1~\x1b[99~\x04".to_vec(),
+            output: Vec::new(),
+            raw: false,
+        };
+        key_probe(&mut term).unwrap();
+        assert!(!term.raw);
+        let output = alloc::string::String::from_utf8(term.output).unwrap();
+        let lines: Vec<&str> = output.lines().skip(1).map(str::trim_end).collect();
+        assert_eq!(
+            lines,
+            [
+                "61  Normal('a')",
+                "1b 5b 31 7e  Home",
+                "1b 5b 39 39 7e  Unknown(UnknownSequence(\"\\x1b[99~\"))",
+                "04  Ctrl('d')"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_key_probe_restores_on_error() {
+        let mut term = Probe {
+            input: b"a".to_vec(),
+            output: Vec::new(),
+            raw: false,
+        };
+        assert!(key_probe(&mut term).is_err());
+        assert!(!term.raw);
+    }
+}
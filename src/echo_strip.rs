@@ -0,0 +1,258 @@
+//! Links that echo the device's own output.
+
+use alloc::collections::VecDeque;
+
+use crate::{Caps, Result, Terminal};
+
+/// Terminal removing the echo of its own output from the input.
+///
+/// Some links loop transmitted data back to the receiver: a half-duplex
+/// RS-485 transceiver hears itself on the bus, and some debug adapters
+/// connect TX to RX. Everything the editor writes then comes back as input,
+/// and typed characters would be inserted a second time. This wrapper
+/// remembers the last `window` bytes it wrote and drops them again as they
+/// come back.
+///
+/// Whether the link echoes is detected from the first byte read after
+/// anything was written: if it is the first byte written, the link echoes,
+/// otherwise it doesn't and input is passed through from then on. Use
+/// [`set_echoes`](Self::set_echoes) when it is known in advance.
+///
+/// An echo has to match the output byte for byte, so wrap the terminal that
+/// transmits unchanged, not one translating line endings. If echoed bytes
+/// get lost, stripping picks up again at the next byte that matches. Input
+/// typed while output is still on its way may be mistaken for its echo when
+/// it's the same byte; at typing speed, each echo is back before the next
+/// key.
+///
+/// Only [`Terminal::write`] output is remembered, so the wrapper sends cursor
+/// movement and screen clearing as ANSI escape sequences itself rather than
+/// leaving them to the wrapped terminal, and doesn't query the cursor
+/// position.
+///
+/// # Examples
+///
+/// ```ignore
+/// use editline::{EchoStripTerminal, LineEditor};
+///
+/// let mut terminal = EchoStripTerminal::new(rs485_terminal, 256);
+/// let mut editor = LineEditor::new(256, 20);
+/// let line = editor.read_line_with_prompt(&mut terminal, "> ")?;
+/// ```
+#[derive(Debug)]
+pub struct EchoStripTerminal<T> {
+    inner: T,
+    /// Bytes written whose echo hasn't come back yet
+    pending: VecDeque<u8>,
+    window: usize,
+    /// Whether the link echoes, once known
+    echoes: Option<bool>,
+}
+
+impl<T: Terminal> EchoStripTerminal<T> {
+    /// Wraps `inner`, expecting the echo of at most the last `window` bytes
+    /// written.
+    pub fn new(inner: T, window: usize) -> Self {
+        EchoStripTerminal {
+            inner,
+            pending: VecDeque::new(),
+            window,
+            echoes: None,
+        }
+    }
+
+    /// Returns whether the link echoes, or `None` if that isn't known yet.
+    pub fn echoes(&self) -> Option<bool> {
+        self.echoes
+    }
+
+    /// Sets whether the link echoes instead of detecting it.
+    pub fn set_echoes(&mut self, echoes: bool) {
+        self.echoes = Some(echoes);
+        if !echoes {
+            self.pending.clear();
+        }
+    }
+
+    /// Forgets the output whose echo is still expected, e.g. after the link
+    /// was reset.
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Returns the wrapped terminal.
+    pub fn inner(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Returns the wrapped terminal, dropping the wrapper's state.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Whether `byte` is the echo of pending output, consuming it if so.
+    fn is_echo(&mut self, byte: u8) -> bool {
+        if self.pending.is_empty() {
+            return false;
+        }
+        if self.echoes.is_none() {
+            let echoes = self.pending.front() == Some(&byte);
+            self.set_echoes(echoes);
+        }
+        // Bytes before the match were lost on the way back
+        match self.pending.iter().position(|&pending| pending == byte) {
+            Some(lost) => {
+                self.pending.drain(..=lost);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<T: Terminal> Terminal for EchoStripTerminal<T> {
+    fn read_byte(&mut self) -> Result<u8> {
+        loop {
+            let byte = self.inner.read_byte()?;
+            if !self.is_echo(byte) {
+                return Ok(byte);
+            }
+        }
+    }
+
+    fn try_read_byte(&mut self) -> Result<Option<u8>> {
+        while let Some(byte) = self.inner.try_read_byte()? {
+            if !self.is_echo(byte) {
+                return Ok(Some(byte));
+            }
+        }
+        Ok(None)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        if self.echoes != Some(false) {
+            self.pending.extend(data);
+            let excess = self.pending.len().saturating_sub(self.window);
+            self.pending.drain(..excess);
+        }
+        self.inner.write(data)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        self.inner.enter_raw_mode()
+    }
+
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        self.inner.exit_raw_mode()
+    }
+
+    fn cursor_left(&mut self) -> Result<()> {
+        self.write(b"\x1b[D")
+    }
+
+    fn cursor_right(&mut self) -> Result<()> {
+        self.write(b"\x1b[C")
+    }
+
+    fn clear_eol(&mut self) -> Result<()> {
+        self.write(b"\x1b[K")
+    }
+
+    fn size(&mut self) -> Option<(u16, u16)> {
+        self.inner.size()
+    }
+
+    fn capabilities(&self) -> Caps {
+        self.inner.capabilities()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, LineEditor};
+    use alloc::vec::Vec;
+
+    /// Link echoing output, except the bytes listed in `lose`, ahead of the
+    /// typed input.
+    struct Loopback {
+        echo: bool,
+        lose: Vec<u8>,
+        input: VecDeque<u8>,
+        typed: VecDeque<u8>,
+    }
+
+    impl Terminal for Loopback {
+        fn read_byte(&mut self) -> Result<u8> {
+            self.input
+                .pop_front()
+                .or_else(|| self.typed.pop_front())
+                .ok_or(Error::Eof)
+        }
+        fn write(&mut self, data: &[u8]) -> Result<()> {
+            if self.echo {
+                self.input
+                    .extend(data.iter().filter(|byte| !self.lose.contains(byte)));
+            }
+            Ok(())
+        }
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn enter_raw_mode(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn exit_raw_mode(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn cursor_left(&mut self) -> Result<()> {
+            unreachable!("sent as ANSI by the wrapper")
+        }
+        fn cursor_right(&mut self) -> Result<()> {
+            unreachable!("sent as ANSI by the wrapper")
+        }
+        fn clear_eol(&mut self) -> Result<()> {
+            unreachable!("sent as ANSI by the wrapper")
+        }
+    }
+
+    fn loopback(echo: bool, lose: &[u8], typed: &[u8]) -> EchoStripTerminal<Loopback> {
+        let link = Loopback {
+            echo,
+            lose: lose.to_vec(),
+            input: VecDeque::new(),
+            typed: typed.iter().copied().collect(),
+        };
+        EchoStripTerminal::new(link, 64)
+    }
+
+    #[test]
+    fn test_echo_stripped() {
+        let mut editor = LineEditor::new(64, 10);
+        let mut terminal = loopback(true, &[], b"ab\x1b[DX\r");
+        assert_eq!(
+            editor.read_line_with_prompt(&mut terminal, "> ").unwrap(),
+            "aXb"
+        );
+        assert_eq!(terminal.echoes(), Some(true));
+
+        // Lost echo bytes don't end up in the line
+        let mut terminal = loopback(true, b"[", b"ab\x1b[DX\r");
+        assert_eq!(
+            editor.read_line_with_prompt(&mut terminal, "> ").unwrap(),
+            "aXb"
+        );
+
+        let mut terminal = loopback(false, &[], b"ab\r");
+        assert_eq!(
+            editor.read_line_with_prompt(&mut terminal, "> ").unwrap(),
+            "ab"
+        );
+        assert_eq!(terminal.echoes(), Some(false));
+        assert!(terminal.pending.is_empty());
+    }
+}
@@ -0,0 +1,254 @@
+//! Raw key event streams.
+//!
+//! [`LineEditor::events`](crate::LineEditor::events) hands out decoded key events
+//! instead of lines, for applications that run their own full-screen modes but
+//! still want editline's raw-mode handling and key decoder. With the `async`
+//! feature, [`LineEditor::event_stream`](crate::LineEditor::event_stream) does the
+//! same for an `AsyncTerminal` as a `futures_core::Stream`.
+//!
+//! Raw mode is entered when the first event is requested and left when the
+//! iterator or stream is dropped. After an error, no further events are produced.
+
+use core::fmt;
+
+use crate::{KeyEvent, Result, Terminal};
+
+/// Iterator over key events from a [`Terminal`].
+///
+/// Created by [`LineEditor::events`](crate::LineEditor::events).
+pub struct Events<'a, T: Terminal> {
+    terminal: &'a mut T,
+    raw: bool,
+    done: bool,
+}
+
+impl<'a, T: Terminal> Events<'a, T> {
+    pub(crate) fn new(terminal: &'a mut T) -> Self {
+        Events {
+            terminal,
+            raw: false,
+            done: false,
+        }
+    }
+
+    /// Returns the terminal, e.g. to draw a screen between events.
+    pub fn terminal(&mut self) -> &mut T {
+        self.terminal
+    }
+}
+
+impl<T: Terminal> fmt::Debug for Events<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Events")
+            .field("raw", &self.raw)
+            .field("done", &self.done)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Terminal> Iterator for Events<'_, T> {
+    type Item = Result<KeyEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if !self.raw {
+            if let Err(e) = self.terminal.enter_raw_mode() {
+                self.done = true;
+                return Some(Err(e));
+            }
+            self.raw = true;
+        }
+
+        let event = self.terminal.parse_key_event();
+        self.done = event.is_err();
+        Some(event)
+    }
+}
+
+impl<T: Terminal> Drop for Events<'_, T> {
+    fn drop(&mut self) {
+        if self.raw {
+            let _ = self.terminal.exit_raw_mode();
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+pub use self::stream::EventStream;
+
+#[cfg(feature = "async")]
+mod stream {
+    use core::fmt;
+    use core::pin::Pin;
+    use core::task::{Context, Poll};
+
+    use futures_core::Stream;
+
+    use crate::ansi::Decoder;
+    use crate::asynch::AsyncTerminal;
+    use crate::{KeyEvent, Result};
+
+    /// Stream of key events from an [`AsyncTerminal`].
+    ///
+    /// Created by [`LineEditor::event_stream`](crate::LineEditor::event_stream).
+    pub struct EventStream<'a, T: AsyncTerminal + ?Sized> {
+        terminal: &'a mut T,
+        decoder: Decoder,
+        raw: bool,
+        done: bool,
+    }
+
+    impl<'a, T: AsyncTerminal + ?Sized> EventStream<'a, T> {
+        pub(crate) fn new(terminal: &'a mut T) -> Self {
+            EventStream {
+                terminal,
+                decoder: Decoder::new(),
+                raw: false,
+                done: false,
+            }
+        }
+
+        /// Returns the terminal, e.g. to draw a screen between events.
+        pub fn terminal(&mut self) -> &mut T {
+            self.terminal
+        }
+    }
+
+    impl<T: AsyncTerminal + ?Sized> fmt::Debug for EventStream<'_, T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("EventStream")
+                .field("decoder", &self.decoder)
+                .field("raw", &self.raw)
+                .field("done", &self.done)
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl<T: AsyncTerminal + ?Sized> Stream for EventStream<'_, T> {
+        type Item = Result<KeyEvent>;
+
+        fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            let this = self.get_mut();
+            if this.done {
+                return Poll::Ready(None);
+            }
+            if !this.raw {
+                if let Err(e) = this.terminal.enter_raw_mode() {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+                this.raw = true;
+            }
+
+            loop {
+                match this.terminal.poll_read_byte(cx) {
+                    Poll::Ready(Ok(byte)) => {
+                        if let Some(event) = this.decoder.push(byte) {
+                            return Poll::Ready(Some(Ok(event)));
+                        }
+                    }
+                    Poll::Ready(Err(e)) => {
+                        this.done = true;
+                        return Poll::Ready(Some(Err(e)));
+                    }
+                    // ESC ESC with nothing after it is Escape
+                    Poll::Pending => match this
+                        .decoder
+                        .is_ambiguous()
+                        .then(|| this.decoder.expire())
+                        .flatten()
+                    {
+                        Some(event) => return Poll::Ready(Some(Ok(event))),
+                        None => return Poll::Pending,
+                    },
+                }
+            }
+        }
+    }
+
+    impl<T: AsyncTerminal + ?Sized> Drop for EventStream<'_, T> {
+        fn drop(&mut self) {
+            if self.raw {
+                let _ = self.terminal.exit_raw_mode();
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "async"))]
+mod tests {
+    use super::*;
+    use crate::asynch::AsyncTerminal;
+    use crate::Error;
+    use alloc::vec::Vec;
+    use core::pin::Pin;
+    use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+    use futures_core::Stream;
+
+    /// Delivers its bytes one per poll, pending in between like a slow link.
+    struct Trickle {
+        input: Vec<u8>,
+        ready: bool,
+    }
+
+    impl AsyncTerminal for Trickle {
+        fn poll_read_byte(&mut self, cx: &mut Context<'_>) -> Poll<crate::Result<u8>> {
+            self.ready = !self.ready;
+            if !self.ready {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            if self.input.is_empty() {
+                Poll::Ready(Err(Error::Eof))
+            } else {
+                Poll::Ready(Ok(self.input.remove(0)))
+            }
+        }
+        fn write(&mut self, _: &[u8]) -> crate::Result<()> {
+            Ok(())
+        }
+        fn poll_flush(&mut self, _: &mut Context<'_>) -> Poll<crate::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn noop_waker() -> Waker {
+        fn clone(_: *const ()) -> RawWaker {
+            RawWaker::new(core::ptr::null(), &VTABLE)
+        }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        unsafe { Waker::from_raw(clone(core::ptr::null())) }
+    }
+
+    #[test]
+    fn test_event_stream_decodes_across_pending_polls() {
+        let mut term = Trickle {
+            input: b"a\x1b[Dq".to_vec(),
+            ready: true,
+        };
+        let mut stream = EventStream::new(&mut term);
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let mut events = Vec::new();
+        loop {
+            match Pin::new(&mut stream).poll_next(&mut cx) {
+                Poll::Ready(Some(Ok(event))) => events.push(event),
+                Poll::Ready(Some(Err(Error::Eof))) => break,
+                Poll::Ready(other) => panic!("unexpected {:?}", other.map(|r| r.is_ok())),
+                Poll::Pending => continue,
+            }
+        }
+        assert_eq!(
+            events,
+            [KeyEvent::Normal('a'), KeyEvent::Left, KeyEvent::Normal('q')]
+        );
+        assert!(matches!(
+            Pin::new(&mut stream).poll_next(&mut cx),
+            Poll::Ready(None)
+        ));
+    }
+}
@@ -0,0 +1,179 @@
+//! Editing the current line in an external program (Ctrl+X Ctrl+E).
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Editor used when neither `$VISUAL` nor `$EDITOR` is set.
+#[cfg(windows)]
+const DEFAULT_EDITOR: &str = "notepad";
+#[cfg(not(windows))]
+const DEFAULT_EDITOR: &str = "vi";
+
+/// Names tried for the temporary file before giving up.
+const TEMP_ATTEMPTS: u32 = 100;
+
+/// Writes `text` to a temporary file, runs the user's editor on it and returns
+/// the edited contents.
+///
+/// The editor command is taken from `$VISUAL`, then `$EDITOR`, and may include
+/// arguments (e.g. `code --wait`) and a quoted program path. Trailing newlines
+/// are removed and any remaining line breaks are joined with spaces, since the
+/// result is loaded back into a single line.
+pub(crate) fn edit(text: &str) -> io::Result<String> {
+    let (path, mut file) = create_temp_file()?;
+    let written = file.write_all(text.as_bytes());
+    drop(file);
+
+    let result = written
+        .and_then(|_| run_editor(&path))
+        .and_then(|_| fs::read_to_string(&path));
+    let _ = fs::remove_file(&path);
+
+    let edited = result?;
+    let edited = edited.trim_end_matches(&['\r', '\n'][..]);
+    Ok(edited.lines().collect::<Vec<_>>().join(" "))
+}
+
+fn run_editor(path: &Path) -> io::Result<()> {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_EDITOR.to_string());
+
+    let status = editor_command(&editor).arg(path).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "editor exited with an error",
+        ))
+    }
+}
+
+/// Builds the command that runs `editor`, to which the file is then added as
+/// the last argument.
+///
+/// As git does, the shell parses the command, so quoted paths and escapes work
+/// as they would typed at a prompt.
+#[cfg(not(windows))]
+fn editor_command(editor: &str) -> Command {
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(format!("{} \"$@\"", editor))
+        .arg(editor);
+    command
+}
+
+/// Builds the command that runs `editor`, to which the file is then added as
+/// the last argument.
+#[cfg(windows)]
+fn editor_command(editor: &str) -> Command {
+    let mut words = split_words(editor).into_iter();
+    let mut command = Command::new(words.next().unwrap_or_else(|| DEFAULT_EDITOR.to_string()));
+    command.args(words);
+    command
+}
+
+/// Splits `text` at whitespace outside double quotes, which are removed, the
+/// way Windows paths such as `"C:\Program Files\..."` are quoted.
+#[cfg(any(windows, test))]
+fn split_words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quoted = false;
+    for c in text.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_word = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    words
+}
+
+/// Creates a file of our own in the temporary directory.
+///
+/// The file must not exist yet, so another user of a shared `/tmp` can't have
+/// it, or a symlink by its name, waiting for us to write through.
+fn create_temp_file() -> io::Result<(PathBuf, File)> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    for attempt in 0..TEMP_ATTEMPTS {
+        let name = format!("editline-{}-{}-{}.txt", std::process::id(), nanos, attempt);
+        let path = std::env::temp_dir().join(name);
+        match options.open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        "no free temporary file name",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(split_words("code --wait"), ["code", "--wait"]);
+        assert_eq!(
+            split_words(r#""C:\Program Files\Notepad++\notepad++.exe" -multiInst"#),
+            [r"C:\Program Files\Notepad++\notepad++.exe", "-multiInst"]
+        );
+        assert_eq!(split_words("  vi  \"\" "), ["vi", ""]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_editor_command_keeps_quoted_arguments() {
+        let output = editor_command("printf '%s|' 'two words'")
+            .arg("file name")
+            .output()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "two words|file name|"
+        );
+    }
+
+    #[test]
+    fn test_temp_file_is_new() {
+        let (first, _file) = create_temp_file().unwrap();
+        let (second, _file) = create_temp_file().unwrap();
+        assert_ne!(first, second);
+        fs::remove_file(first).unwrap();
+        fs::remove_file(second).unwrap();
+    }
+}
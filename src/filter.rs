@@ -0,0 +1,56 @@
+//! Transforming typed characters before they are inserted.
+//!
+//! Install an [`InputFilter`] with
+//! [`LineEditor::set_input_filter`](crate::LineEditor::set_input_filter) to
+//! rewrite each printable character the user types: force upper case on a hex
+//! entry console, turn straight quotes into typographic ones, or insert digit
+//! separators. Only typed characters pass through the filter; text from history,
+//! completion, hints and Ctrl+V quoted insert is inserted as is.
+
+use alloc::string::String;
+
+use crate::Context;
+
+/// What an [`InputFilter`] makes of a typed character.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filtered {
+    /// Insert this character instead.
+    Insert(char),
+    /// Insert this text instead, e.g. a separator followed by the character.
+    InsertStr(String),
+    /// Insert nothing.
+    Discard,
+}
+
+/// Hook rewriting typed characters.
+///
+/// Closures of the form `FnMut(char, &Context) -> Filtered` implement this trait.
+///
+/// # Examples
+///
+/// A console that only accepts upper-case hex digits:
+///
+/// ```
+/// use editline::{Context, Filtered, LineEditor};
+///
+/// let mut editor = LineEditor::new(1024, 50);
+/// editor.set_input_filter(|c: char, _ctx: &Context| {
+///     if c.is_ascii_hexdigit() || c == ' ' {
+///         Filtered::Insert(c.to_ascii_uppercase())
+///     } else {
+///         Filtered::Discard
+///     }
+/// });
+/// ```
+pub trait InputFilter {
+    /// Returns what to insert for the typed character `c`.
+    ///
+    /// `ctx` describes the line and cursor before the insertion.
+    fn filter(&mut self, c: char, ctx: &Context) -> Filtered;
+}
+
+impl<F: FnMut(char, &Context) -> Filtered> InputFilter for F {
+    fn filter(&mut self, c: char, ctx: &Context) -> Filtered {
+        self(c, ctx)
+    }
+}
@@ -0,0 +1,365 @@
+//! Full-screen editing on the alternate screen.
+//!
+//! Used by [`LineEditor::read_line_fullscreen`](crate::LineEditor::read_line_fullscreen)
+//! for input too long or too structured for a single line. The text may contain
+//! line breaks; long lines wrap one column short of the margin, which keeps
+//! every row clear of the terminal's pending-wrap behavior. The bottom row holds
+//! the prompt and key help.
+//!
+//! With [auto-indent](crate::LineEditor::set_auto_indent), Enter starts the new
+//! line with the indentation of the one before.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::completion::DEFAULT_SIZE;
+use crate::theme::Theme;
+use crate::width::char_width;
+use crate::{
+    cursor_right_by, last_line, write_newline, write_visible, Error, KeyEvent, LineBuffer, Result,
+    Terminal,
+};
+
+const HELP: &[u8] = b"  Ctrl+D: done  Ctrl+C: cancel";
+
+/// How new lines are indented.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Indent<'a> {
+    /// Characters ending a line after which the next one is indented further
+    pub(crate) openers: &'a str,
+    /// Spaces per indentation level
+    pub(crate) width: usize,
+}
+
+/// A row of the display: byte range of the buffer it shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Row {
+    start: usize,
+    end: usize,
+}
+
+/// Splits `text` into display rows at line breaks and wherever a line exceeds
+/// `width` columns.
+fn layout(text: &str, width: usize) -> Vec<Row> {
+    let mut rows = Vec::new();
+    let mut offset = 0;
+    for line in text.split('\n') {
+        let mut start = offset;
+        let mut used = 0;
+        for (i, c) in line.char_indices() {
+            let w = char_width(c);
+            if used + w > width && used > 0 {
+                rows.push(Row {
+                    start,
+                    end: offset + i,
+                });
+                start = offset + i;
+                used = 0;
+            }
+            used += w;
+        }
+        rows.push(Row {
+            start,
+            end: offset + line.len(),
+        });
+        offset += line.len() + 1;
+    }
+    rows
+}
+
+/// Index of the row showing byte position `pos`.
+///
+/// A position where a wrapped line breaks belongs to the following row, the
+/// end of a line to its last row.
+fn row_of(rows: &[Row], pos: usize) -> usize {
+    rows.iter().rposition(|row| row.start <= pos).unwrap_or(0)
+}
+
+fn width_of(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+/// Byte position in `row` closest to display column `column`.
+fn position_at(text: &str, row: Row, column: usize) -> usize {
+    let mut used = 0;
+    for (i, c) in text[row.start..row.end].char_indices() {
+        let w = char_width(c);
+        if used + w > column {
+            return row.start + i;
+        }
+        used += w;
+    }
+    row.end
+}
+
+/// State of one full-screen editing session.
+pub(crate) struct Screen<'a> {
+    line: &'a mut LineBuffer,
+    prompt: &'a str,
+    theme: Theme,
+    /// First buffer row shown on screen
+    top: usize,
+    /// Screen row the terminal cursor is on
+    cursor_row: usize,
+    /// Column Up/Down try to keep, across shorter rows
+    goal: Option<usize>,
+    /// Drop an LF right after a CR
+    crlf_as_one: bool,
+    indent: Option<Indent<'a>>,
+}
+
+impl<'a> Screen<'a> {
+    pub(crate) fn new(
+        line: &'a mut LineBuffer,
+        prompt: &'a str,
+        theme: Theme,
+        crlf_as_one: bool,
+        indent: Option<Indent<'a>>,
+    ) -> Self {
+        Screen {
+            line,
+            prompt,
+            theme,
+            top: 0,
+            cursor_row: 0,
+            goal: None,
+            crlf_as_one,
+            indent,
+        }
+    }
+
+    /// Edits until Ctrl+D, returning `Ok(())`, or Ctrl+C, returning
+    /// [`Error::Interrupted`].
+    pub(crate) fn run<T: Terminal>(&mut self, terminal: &mut T) -> Result<()> {
+        terminal.clear_screen()?;
+        let mut after_cr = false;
+        loop {
+            self.render(terminal)?;
+            terminal.flush()?;
+
+            let event = terminal.parse_key_event()?;
+            let vertical = matches!(event, KeyEvent::Up | KeyEvent::Down);
+            let crlf = after_cr && self.crlf_as_one && event == KeyEvent::LineFeed;
+            after_cr = event == KeyEvent::Enter;
+            match event {
+                KeyEvent::Ctrl('d') => return Ok(()),
+                KeyEvent::Ctrl('c') => return Err(Error::Interrupted),
+                KeyEvent::Normal('\0') | KeyEvent::Unknown(_) => {}
+                // Alt+B/F move by word, as in the line editor's default keymap
+                KeyEvent::CtrlLeft | KeyEvent::Alt('b') => {
+                    self.line.move_cursor_word_left();
+                }
+                KeyEvent::CtrlRight | KeyEvent::Alt('f') => {
+                    self.line.move_cursor_word_right();
+                }
+                KeyEvent::Normal(c) | KeyEvent::Alt(c) => self.insert(terminal, c)?,
+                KeyEvent::LineFeed if crlf => {}
+                KeyEvent::Enter | KeyEvent::LineFeed => self.new_line(terminal)?,
+                KeyEvent::Left => {
+                    self.line.move_cursor_left();
+                }
+                KeyEvent::Right => {
+                    self.line.move_cursor_right();
+                }
+                KeyEvent::Up => self.move_vertically(terminal, false)?,
+                KeyEvent::Down => self.move_vertically(terminal, true)?,
+                KeyEvent::Home | KeyEvent::ShiftHome => self.line.cursor_pos = self.line_start(),
+                KeyEvent::End | KeyEvent::ShiftEnd => self.line.cursor_pos = self.line_end(),
+                KeyEvent::CtrlHome => self.line.cursor_pos = 0,
+                KeyEvent::CtrlEnd => self.line.cursor_pos = self.line.len(),
+                KeyEvent::Backspace => self.backspace(),
+                KeyEvent::Delete => {
+                    self.line.delete_at_cursor();
+                }
+                KeyEvent::AltBackspace => {
+                    self.line.delete_word_left();
+                }
+                KeyEvent::CtrlDelete => {
+                    self.line.delete_word_right();
+                }
+                KeyEvent::Tab
+                | KeyEvent::BackTab
+                | KeyEvent::Insert
+                | KeyEvent::PageUp
+                | KeyEvent::PageDown
+                | KeyEvent::F(_)
+                | KeyEvent::Escape
+                | KeyEvent::PasteStart
+                | KeyEvent::PasteEnd
+                | KeyEvent::Ctrl(_) => {}
+            }
+            if !vertical {
+                self.goal = None;
+            }
+        }
+    }
+
+    fn insert<T: Terminal>(&mut self, terminal: &mut T, c: char) -> Result<()> {
+        if self.line.insert_char(c) {
+            Ok(())
+        } else {
+            terminal.bell()
+        }
+    }
+
+    /// Breaks the line at the cursor, indenting the new line if auto-indent
+    /// is on.
+    fn new_line<T: Terminal>(&mut self, terminal: &mut T) -> Result<()> {
+        let indent = match self.indent {
+            Some(indent) => indent,
+            None => return self.insert(terminal, '\n'),
+        };
+        let before = &self.text()[self.line_start()..self.line.cursor_pos];
+        let mut text = String::from("\n");
+        text.push_str(&before[..before.len() - before.trim_start().len()]);
+        if before.trim_end().ends_with(|c| indent.openers.contains(c)) {
+            text.extend(core::iter::repeat(' ').take(indent.width));
+        }
+        if self.line.insert_str(&text) {
+            Ok(())
+        } else {
+            terminal.bell()
+        }
+    }
+
+    /// Deletes the character before the cursor, or with auto-indent on, back
+    /// to the previous indentation level if only spaces precede it.
+    fn backspace(&mut self) {
+        let before = &self.text()[self.line_start()..self.line.cursor_pos];
+        let count = match self.indent {
+            Some(Indent { width, .. })
+                if width > 0 && !before.is_empty() && before.bytes().all(|b| b == b' ') =>
+            {
+                before.len() - (before.len() - 1) / width * width
+            }
+            _ => 1,
+        };
+        for _ in 0..count {
+            self.line.delete_before_cursor();
+        }
+    }
+
+    fn text(&self) -> &str {
+        // Only whole characters are ever inserted
+        core::str::from_utf8(self.line.as_bytes()).unwrap_or("")
+    }
+
+    fn line_start(&self) -> usize {
+        let before = &self.line.as_bytes()[..self.line.cursor_pos];
+        before
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1)
+    }
+
+    fn line_end(&self) -> usize {
+        let pos = self.line.cursor_pos;
+        let after = &self.line.as_bytes()[pos..];
+        after
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(self.line.len(), |i| pos + i)
+    }
+
+    /// Returns the display width available for text and the number of text rows.
+    fn dimensions<T: Terminal>(terminal: &mut T) -> (usize, usize) {
+        let (columns, rows) = terminal.size().unwrap_or(DEFAULT_SIZE);
+        let width = usize::from(columns).saturating_sub(1).max(1);
+        let rows = usize::from(rows).saturating_sub(1).max(1);
+        (width, rows)
+    }
+
+    fn move_vertically<T: Terminal>(&mut self, terminal: &mut T, down: bool) -> Result<()> {
+        let (width, _) = Self::dimensions(terminal);
+        let text = self.text();
+        let rows = layout(text, width);
+        let current = row_of(&rows, self.line.cursor_pos);
+        let target = if down {
+            if current + 1 >= rows.len() {
+                return Ok(());
+            }
+            current + 1
+        } else {
+            match current.checked_sub(1) {
+                Some(target) => target,
+                None => return Ok(()),
+            }
+        };
+
+        let row = rows[current];
+        let column = match self.goal {
+            Some(goal) => goal,
+            None => width_of(&text[row.start..self.line.cursor_pos]),
+        };
+        let pos = position_at(text, rows[target], column);
+        self.goal = Some(column);
+        self.line.cursor_pos = pos;
+        Ok(())
+    }
+
+    /// Repaints the visible rows and the help row, then places the cursor.
+    fn render<T: Terminal>(&mut self, terminal: &mut T) -> Result<()> {
+        let (width, height) = Self::dimensions(terminal);
+        let text = core::str::from_utf8(self.line.as_bytes()).unwrap_or("");
+        let rows = layout(text, width);
+        let current = row_of(&rows, self.line.cursor_pos);
+
+        // Scroll just enough to keep the cursor row visible
+        if current < self.top {
+            self.top = current;
+        } else if current >= self.top + height {
+            self.top = current + 1 - height;
+        }
+
+        for _ in 0..self.cursor_row {
+            terminal.cursor_up()?;
+        }
+        terminal.write(b"\r")?;
+        for index in self.top..self.top + height {
+            if let Some(row) = rows.get(index) {
+                write_visible(terminal, &text.as_bytes()[row.start..row.end])?;
+            }
+            terminal.clear_eol()?;
+            write_newline(terminal)?;
+        }
+        self.theme
+            .prompt
+            .paint(terminal, last_line(self.prompt).as_bytes())?;
+        self.theme.hint.paint(terminal, HELP)?;
+        terminal.clear_eol()?;
+
+        // Back up from the help row to the cursor
+        self.cursor_row = current - self.top;
+        for _ in self.cursor_row..height {
+            terminal.cursor_up()?;
+        }
+        terminal.write(b"\r")?;
+        let row = rows[current];
+        cursor_right_by(terminal, width_of(&text[row.start..self.line.cursor_pos]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_wraps_and_splits_lines() {
+        let rows = layout("abcdef\n\nxy", 4);
+        let rows: Vec<(usize, usize)> = rows.iter().map(|r| (r.start, r.end)).collect();
+        assert_eq!(rows, [(0, 4), (4, 6), (7, 7), (8, 10)]);
+
+        let rows = layout("abcd\nxy", 4);
+        assert_eq!(row_of(&rows, 4), 0);
+        assert_eq!(row_of(&rows, 5), 1);
+        assert_eq!(position_at("abcd\nxy", rows[1], 5), 7);
+    }
+
+    #[test]
+    fn test_layout_wide_characters() {
+        // Three columns available: a second ideograph doesn't fit
+        let rows = layout("漢字", 3);
+        assert_eq!(rows, [Row { start: 0, end: 3 }, Row { start: 3, end: 6 }]);
+        assert_eq!(position_at("漢字", rows[0], 1), 0);
+    }
+}
@@ -0,0 +1,48 @@
+//! Inline hints shown after the cursor.
+//!
+//! Install a [`Hinter`] with [`LineEditor::set_hinter`](crate::LineEditor::set_hinter)
+//! to display a suggestion after the text whenever the cursor is at the end of
+//! the line, in the style of fish autosuggestions. Pressing Right or End at the
+//! end of the line accepts the hint; any other key dismisses it.
+
+use alloc::string::String;
+
+use crate::Context;
+
+/// Source of inline hints.
+///
+/// Closures of the form `FnMut(&Context) -> Option<String>` implement this trait.
+///
+/// # Examples
+///
+/// Suggest the most recent history entry that extends the current line:
+///
+/// ```
+/// use editline::{Context, LineEditor};
+///
+/// let mut editor = LineEditor::new(1024, 50);
+/// editor.set_hinter(|ctx: &Context| {
+///     let line = ctx.line();
+///     if line.is_empty() {
+///         return None;
+///     }
+///     ctx.history()
+///         .iter()
+///         .rev()
+///         .find(|entry| entry.starts_with(line) && entry.len() > line.len())
+///         .map(|entry| entry[line.len()..].to_string())
+/// });
+/// ```
+pub trait Hinter {
+    /// Returns the text to show after the end of the line, if any.
+    ///
+    /// Only called when the cursor is at the end of the line. The returned text
+    /// is what gets inserted if the user accepts the hint.
+    fn hint(&mut self, ctx: &Context) -> Option<String>;
+}
+
+impl<F: FnMut(&Context) -> Option<String>> Hinter for F {
+    fn hint(&mut self, ctx: &Context) -> Option<String> {
+        self(ctx)
+    }
+}
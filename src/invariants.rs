@@ -0,0 +1,381 @@
+//! Edit operations and invariant checks for property-based testing.
+//!
+//! [`LineBufferOp`] names every edit a [`LineBuffer`] supports, so a fuzzer or
+//! a proptest strategy can generate random edit sequences, run them with
+//! [`apply`], or in one batch with [`LineBuffer::apply_ops`], and compare the
+//! result with a simple model.
+//! [`check`] verifies the properties every buffer must keep, whatever the
+//! sequence: the cursor within the text and on a character boundary, valid
+//! UTF-8, and consistent lengths.
+//!
+//! # Examples
+//!
+//! ```
+//! use editline::invariants::{apply, check, LineBufferOp};
+//! use editline::LineBuffer;
+//!
+//! let mut buffer = LineBuffer::new(64);
+//! let ops = [LineBufferOp::InsertStr("naïve".into()), LineBufferOp::Left, LineBufferOp::Left, LineBufferOp::DeleteBefore];
+//! for op in &ops {
+//!     apply(&mut buffer, op);
+//!     check(&buffer).unwrap();
+//! }
+//! assert_eq!(buffer.as_str().unwrap(), "nave");
+//! ```
+
+use alloc::string::String;
+use core::fmt;
+
+use crate::{is_continuation, LineBuffer};
+
+/// An edit of a [`LineBuffer`], one per editing method.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LineBufferOp {
+    /// [`LineBuffer::insert_char`]
+    Insert(char),
+    /// [`LineBuffer::insert_str`]
+    InsertStr(String),
+    /// [`LineBuffer::delete_before_cursor`]
+    DeleteBefore,
+    /// [`LineBuffer::delete_at_cursor`]
+    DeleteAt,
+    /// [`LineBuffer::move_cursor_left`]
+    Left,
+    /// [`LineBuffer::move_cursor_right`]
+    Right,
+    /// [`LineBuffer::move_cursor_to_start`]
+    Start,
+    /// [`LineBuffer::move_cursor_to_end`]
+    End,
+    /// [`LineBuffer::move_cursor_word_left`]
+    WordLeft,
+    /// [`LineBuffer::move_cursor_word_right`]
+    WordRight,
+    /// [`LineBuffer::delete_word_left`]
+    DeleteWordLeft,
+    /// [`LineBuffer::delete_word_right`]
+    DeleteWordRight,
+    /// [`LineBuffer::set_cursor`]; invalid positions leave the cursor alone.
+    SetCursor(usize),
+    /// [`LineBuffer::load`]
+    Load(String),
+    /// [`LineBuffer::clear`]
+    Clear,
+}
+
+/// Performs `op` on `buffer`.
+///
+/// Returns `false` if an insertion or load was cut short by the
+/// [maximum length](LineBuffer::set_max_len), else `true`.
+pub fn apply(buffer: &mut LineBuffer, op: &LineBufferOp) -> bool {
+    match op {
+        LineBufferOp::Insert(c) => return buffer.insert_char(*c),
+        LineBufferOp::InsertStr(text) => return buffer.insert_str(text),
+        LineBufferOp::Load(text) => return buffer.load(text),
+        LineBufferOp::DeleteBefore => {
+            buffer.delete_before_cursor();
+        }
+        LineBufferOp::DeleteAt => {
+            buffer.delete_at_cursor();
+        }
+        LineBufferOp::Left => {
+            buffer.move_cursor_left();
+        }
+        LineBufferOp::Right => {
+            buffer.move_cursor_right();
+        }
+        LineBufferOp::Start => {
+            buffer.move_cursor_to_start();
+        }
+        LineBufferOp::End => {
+            buffer.move_cursor_to_end();
+        }
+        LineBufferOp::WordLeft => {
+            buffer.move_cursor_word_left();
+        }
+        LineBufferOp::WordRight => {
+            buffer.move_cursor_word_right();
+        }
+        LineBufferOp::DeleteWordLeft => {
+            buffer.delete_word_left();
+        }
+        LineBufferOp::DeleteWordRight => {
+            buffer.delete_word_right();
+        }
+        LineBufferOp::SetCursor(pos) => {
+            let _ = buffer.set_cursor(*pos);
+        }
+        LineBufferOp::Clear => buffer.clear(),
+    }
+    true
+}
+
+/// A broken [`LineBuffer`] invariant, as reported by [`check`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    /// The cursor is past the end of the text.
+    CursorPastEnd,
+    /// The cursor is inside a multi-byte character.
+    CursorInsideChar,
+    /// The text isn't valid UTF-8.
+    InvalidUtf8,
+    /// `len`, `is_empty` and the byte and string views disagree.
+    LengthMismatch,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Violation::CursorPastEnd => "cursor past the end of the line",
+            Violation::CursorInsideChar => "cursor inside a character",
+            Violation::InvalidUtf8 => "line is not valid UTF-8",
+            Violation::LengthMismatch => "inconsistent line length",
+        })
+    }
+}
+
+/// Checks the invariants every [`LineBuffer`] keeps.
+///
+/// The maximum length isn't one of them: text already in the buffer stays
+/// when a smaller limit is set.
+pub fn check(buffer: &LineBuffer) -> Result<(), Violation> {
+    let bytes = buffer.as_bytes();
+    let cursor = buffer.cursor_pos();
+    if cursor > bytes.len() {
+        return Err(Violation::CursorPastEnd);
+    }
+    if bytes.get(cursor).map_or(false, |&b| is_continuation(b)) {
+        return Err(Violation::CursorInsideChar);
+    }
+    let text = buffer.as_str().map_err(|_| Violation::InvalidUtf8)?;
+    if buffer.len() != bytes.len()
+        || text.len() != bytes.len()
+        || buffer.is_empty() != bytes.is_empty()
+    {
+        return Err(Violation::LengthMismatch);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    /// The buffer's behavior, written over characters instead of bytes.
+    struct Model {
+        chars: Vec<char>,
+        cursor: usize,
+        max_len: usize,
+    }
+
+    #[derive(PartialEq)]
+    enum Class {
+        Space,
+        Word,
+        Symbol,
+    }
+
+    fn class(c: char) -> Class {
+        match c {
+            ' ' | '\t' => Class::Space,
+            c if c.is_ascii_alphanumeric() || c == '_' => Class::Word,
+            _ => Class::Symbol,
+        }
+    }
+
+    impl Model {
+        fn text(&self) -> String {
+            self.chars.iter().collect()
+        }
+
+        fn byte_pos(&self, index: usize) -> usize {
+            self.chars[..index].iter().map(|c| c.len_utf8()).sum()
+        }
+
+        fn insert(&mut self, c: char) -> bool {
+            if self.text().len() + c.len_utf8() > self.max_len {
+                return false;
+            }
+            self.chars.insert(self.cursor, c);
+            self.cursor += 1;
+            true
+        }
+
+        fn word_left(&self) -> usize {
+            let mut i = self.cursor;
+            while i > 0 && class(self.chars[i - 1]) == Class::Space {
+                i -= 1;
+            }
+            if i == 0 {
+                return 0;
+            }
+            let kind = class(self.chars[i - 1]);
+            while i > 0 && class(self.chars[i - 1]) == kind {
+                i -= 1;
+            }
+            i
+        }
+
+        fn word_right(&self) -> usize {
+            let mut i = self.cursor;
+            if i < self.chars.len() {
+                let kind = class(self.chars[i]);
+                while i < self.chars.len() && kind != Class::Space && class(self.chars[i]) == kind {
+                    i += 1;
+                }
+            }
+            while i < self.chars.len() && class(self.chars[i]) == Class::Space {
+                i += 1;
+            }
+            i
+        }
+
+        fn apply(&mut self, op: &LineBufferOp) {
+            match op {
+                LineBufferOp::Insert(c) => {
+                    self.insert(*c);
+                }
+                LineBufferOp::InsertStr(text) => {
+                    let _ = text.chars().all(|c| self.insert(c));
+                }
+                LineBufferOp::DeleteBefore if self.cursor > 0 => {
+                    self.cursor -= 1;
+                    self.chars.remove(self.cursor);
+                }
+                LineBufferOp::DeleteAt if self.cursor < self.chars.len() => {
+                    self.chars.remove(self.cursor);
+                }
+                LineBufferOp::Left => self.cursor = self.cursor.saturating_sub(1),
+                LineBufferOp::Right => self.cursor = (self.cursor + 1).min(self.chars.len()),
+                LineBufferOp::Start => self.cursor = 0,
+                LineBufferOp::End => self.cursor = self.chars.len(),
+                LineBufferOp::WordLeft => self.cursor = self.word_left(),
+                LineBufferOp::WordRight => self.cursor = self.word_right(),
+                LineBufferOp::DeleteWordLeft => {
+                    let start = self.word_left();
+                    self.chars.drain(start..self.cursor);
+                    self.cursor = start;
+                }
+                LineBufferOp::DeleteWordRight => {
+                    let end = self.word_right();
+                    self.chars.drain(self.cursor..end);
+                }
+                LineBufferOp::SetCursor(pos) => {
+                    if let Some(index) = (0..=self.chars.len()).find(|&i| self.byte_pos(i) == *pos)
+                    {
+                        self.cursor = index;
+                    }
+                }
+                LineBufferOp::Load(text) => {
+                    self.chars.clear();
+                    self.cursor = 0;
+                    for c in text.chars() {
+                        if !self.insert(c) {
+                            break;
+                        }
+                    }
+                }
+                LineBufferOp::Clear => {
+                    self.chars.clear();
+                    self.cursor = 0;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// xorshift, to keep the sequences reproducible without dependencies
+    fn next(state: &mut u32) -> u32 {
+        *state ^= *state << 13;
+        *state ^= *state >> 17;
+        *state ^= *state << 5;
+        *state
+    }
+
+    fn random_op(state: &mut u32) -> LineBufferOp {
+        const CHARS: &[char] = &['a', 'Z', '_', '7', ' ', '\t', '+', 'é', '漢', '🦀'];
+        fn text(state: &mut u32, len: u32) -> String {
+            (0..len)
+                .map(|_| CHARS[next(state) as usize % CHARS.len()])
+                .collect()
+        }
+        match next(state) % 16 {
+            0..=3 => LineBufferOp::Insert(CHARS[next(state) as usize % CHARS.len()]),
+            4 => LineBufferOp::InsertStr(text(state, 4)),
+            5 => LineBufferOp::DeleteBefore,
+            6 => LineBufferOp::DeleteAt,
+            7 => LineBufferOp::Left,
+            8 => LineBufferOp::Right,
+            9 => [LineBufferOp::Start, LineBufferOp::End][next(state) as usize % 2].clone(),
+            10 => LineBufferOp::WordLeft,
+            11 => LineBufferOp::WordRight,
+            12 => LineBufferOp::DeleteWordLeft,
+            13 => LineBufferOp::DeleteWordRight,
+            14 => LineBufferOp::SetCursor(next(state) as usize % 40),
+            _ => [LineBufferOp::Load(text(state, 12)), LineBufferOp::Clear]
+                [next(state) as usize % 2]
+                .clone(),
+        }
+    }
+
+    #[test]
+    fn test_random_edits_match_model() {
+        let mut state = 0x2545_f491;
+        for _ in 0..200 {
+            let mut buffer = LineBuffer::new(32);
+            buffer.set_max_len(Some(32));
+            let mut model = Model {
+                chars: Vec::new(),
+                cursor: 0,
+                max_len: 32,
+            };
+            for _ in 0..60 {
+                let op = random_op(&mut state);
+                apply(&mut buffer, &op);
+                model.apply(&op);
+                check(&buffer).unwrap();
+                assert_eq!(buffer.as_str().unwrap(), model.text(), "after {:?}", op);
+                assert_eq!(
+                    buffer.cursor_pos(),
+                    model.byte_pos(model.cursor),
+                    "after {:?}",
+                    op
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_apply_ops_matches_apply() {
+        let mut state = 0x9e37_79b9;
+        for round in 0..200 {
+            let ops: Vec<LineBufferOp> = (0..round % 50).map(|_| random_op(&mut state)).collect();
+            let mut one_by_one = LineBuffer::new(32);
+            one_by_one.set_max_len(Some(32));
+            one_by_one.load("start é 漢");
+            one_by_one.set_cursor(6).unwrap();
+            let mut batched = one_by_one.clone();
+
+            let complete = ops
+                .iter()
+                .fold(true, |complete, op| apply(&mut one_by_one, op) & complete);
+            assert_eq!(batched.apply_ops(&ops), complete);
+            check(&batched).unwrap();
+            assert_eq!(batched, one_by_one, "after {:?}", ops);
+        }
+    }
+
+    #[test]
+    fn test_check_reports_violations() {
+        let mut buffer = LineBuffer::new(8);
+        buffer.load("é");
+        buffer.cursor_pos = 1;
+        assert_eq!(check(&buffer), Err(Violation::CursorInsideChar));
+        buffer.cursor_pos = 3;
+        assert_eq!(check(&buffer), Err(Violation::CursorPastEnd));
+        buffer.cursor_pos = 0;
+        buffer.buffer.truncate(1);
+        assert_eq!(check(&buffer), Err(Violation::InvalidUtf8));
+    }
+}
@@ -0,0 +1,212 @@
+//! Key bindings.
+//!
+//! Every key the editor receives is looked up to find the [`Action`] it performs.
+//! Lookups go through four layers, the first match winning:
+//!
+//! 1. bindings passed to a single [`LineEditor::read_line_with_bindings`](crate::LineEditor::read_line_with_bindings) call,
+//! 2. application commands bound with [`LineEditor::bind_command`](crate::LineEditor::bind_command),
+//! 3. bindings installed with [`LineEditor::bind`](crate::LineEditor::bind),
+//! 4. the built-in defaults ([`default_action`]).
+//!
+//! Printable characters that aren't bound insert themselves. The two-key
+//! Ctrl+X Ctrl+E sequence ([`Action::ExternalEditor`]) is fixed and checked
+//! before any layer; Ctrl+X itself can't be rebound.
+
+use alloc::vec::Vec;
+
+use crate::KeyEvent;
+
+/// Editing operation a key can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Insert a character at the cursor.
+    Insert(char),
+    /// Move the cursor one character left.
+    MoveLeft,
+    /// Move the cursor one character right, accepting a hint at the end of the line.
+    MoveRight,
+    /// Move the cursor to the start of the line.
+    MoveHome,
+    /// Move the cursor to the end of the line, accepting a hint if already there.
+    MoveEnd,
+    /// Move the cursor to the start of the buffer. The same as
+    /// [`MoveHome`](Self::MoveHome) on a single line.
+    MoveBufferStart,
+    /// Move the cursor to the end of the buffer. The same as
+    /// [`MoveEnd`](Self::MoveEnd) on a single line, without accepting hints.
+    MoveBufferEnd,
+    /// Extend the selection to the start of the line, starting one at the
+    /// cursor if none is active.
+    SelectHome,
+    /// Extend the selection to the end of the line, starting one at the
+    /// cursor if none is active.
+    SelectEnd,
+    /// Move the cursor to the start of the previous word.
+    MoveWordLeft,
+    /// Move the cursor to the start of the next word.
+    MoveWordRight,
+    /// Delete the character before the cursor.
+    DeleteBackward,
+    /// Delete the character under the cursor.
+    DeleteForward,
+    /// Delete the word before the cursor.
+    DeleteWordLeft,
+    /// Delete the word after the cursor.
+    DeleteWordRight,
+    /// Recall the previous (older) history entry.
+    HistoryPrevious,
+    /// Recall the next (newer) history entry.
+    HistoryNext,
+    /// Replace the line with a history entry: a positive number is an entry
+    /// ID, a negative one counts back from the newest entry, as in
+    /// [`LineEditor::recall`](crate::LineEditor::recall).
+    Recall(i64),
+    /// Complete the word before the cursor.
+    Complete,
+    /// Clear the screen and repaint the prompt and line at the top.
+    ClearScreen,
+    /// Accept the displayed hint, wherever the cursor is.
+    AcceptHint,
+    /// Start an incremental search towards older entries.
+    ReverseSearch,
+    /// Start an incremental search towards newer entries.
+    ForwardSearch,
+    /// Open a menu of the history entries starting with the line, to pick
+    /// one with the arrow keys and Enter.
+    HistoryMenu,
+    /// Insert the last whitespace-separated word of the previous history
+    /// entry; repeating it replaces that word with the one before it.
+    YankPreviousWord,
+    /// Insert the system clipboard's contents as if pasted, when enabled with
+    /// [`LineEditor::set_clipboard`](crate::LineEditor::set_clipboard).
+    PasteClipboard,
+    /// Insert the next key literally.
+    QuotedInsert,
+    /// Edit the line in `$VISUAL`/`$EDITOR` (does nothing without the `std` feature).
+    ExternalEditor,
+    /// Abort an incremental search, a "did you mean" prompt, a selection or
+    /// a Ctrl+X sequence, ringing the bell and keeping the line.
+    Abort,
+    /// Submit the line.
+    Submit,
+    /// Cancel the line with [`Error::Interrupted`](crate::Error::Interrupted).
+    Interrupt,
+    /// End input with [`Error::Eof`](crate::Error::Eof).
+    EndOfInput,
+    /// Do nothing. Binding a key to this disables its default action.
+    Ignore,
+}
+
+/// Set of key bindings.
+///
+/// # Examples
+///
+/// ```
+/// use editline::{Action, KeyEvent, Keymap};
+///
+/// let mut keymap = Keymap::new();
+/// keymap
+///     .bind(KeyEvent::Tab, Action::AcceptHint)
+///     .bind(KeyEvent::Ctrl('d'), Action::Ignore);
+///
+/// assert_eq!(keymap.get(KeyEvent::Tab), Some(Action::AcceptHint));
+/// assert_eq!(keymap.get(KeyEvent::Up), None);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Keymap {
+    bindings: Vec<(KeyEvent, Action)>,
+}
+
+impl Keymap {
+    /// Creates an empty keymap.
+    pub fn new() -> Self {
+        Self {
+            bindings: Vec::new(),
+        }
+    }
+
+    /// Binds `key` to `action`, replacing any previous binding of `key`.
+    pub fn bind(&mut self, key: KeyEvent, action: Action) -> &mut Self {
+        match self.bindings.iter_mut().find(|(k, _)| *k == key) {
+            Some(binding) => binding.1 = action,
+            None => self.bindings.push((key, action)),
+        }
+        self
+    }
+
+    /// Removes the binding of `key`, so lookups fall through to the next layer.
+    pub fn unbind(&mut self, key: KeyEvent) -> &mut Self {
+        self.bindings.retain(|(k, _)| *k != key);
+        self
+    }
+
+    /// Returns the action bound to `key` in this keymap.
+    pub fn get(&self, key: KeyEvent) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, action)| *action)
+    }
+
+    /// Returns `true` if the keymap has no bindings.
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty()
+    }
+
+    /// Removes all bindings.
+    pub fn clear(&mut self) {
+        self.bindings.clear();
+    }
+}
+
+/// Returns the built-in action for `key`.
+///
+/// Ctrl+S is only a search key when the editor isn't using software flow
+/// control; see [`LineEditor::set_flow_control`](crate::LineEditor::set_flow_control).
+pub fn default_action(key: KeyEvent) -> Option<Action> {
+    let action = match key {
+        // Custom backends may report ignored input as NUL
+        KeyEvent::Normal('\0') | KeyEvent::Unknown(_) => Action::Ignore,
+        KeyEvent::Normal(c) => Action::Insert(c),
+        KeyEvent::Left => Action::MoveLeft,
+        KeyEvent::Right => Action::MoveRight,
+        KeyEvent::Up => Action::HistoryPrevious,
+        KeyEvent::Down => Action::HistoryNext,
+        KeyEvent::Home => Action::MoveHome,
+        KeyEvent::End => Action::MoveEnd,
+        KeyEvent::Backspace => Action::DeleteBackward,
+        KeyEvent::Delete => Action::DeleteForward,
+        KeyEvent::Enter | KeyEvent::LineFeed => Action::Submit,
+        KeyEvent::PasteStart | KeyEvent::PasteEnd => Action::Ignore,
+        KeyEvent::Tab => Action::Complete,
+        // Left for applications to bind
+        KeyEvent::BackTab
+        | KeyEvent::Insert
+        | KeyEvent::PageUp
+        | KeyEvent::PageDown
+        | KeyEvent::F(_) => Action::Ignore,
+        KeyEvent::Escape | KeyEvent::Ctrl('g') => Action::Abort,
+        // Alt+B/F as in readline, and macOS Option+Left/Right
+        KeyEvent::CtrlLeft | KeyEvent::Alt('b') => Action::MoveWordLeft,
+        KeyEvent::CtrlRight | KeyEvent::Alt('f') => Action::MoveWordRight,
+        KeyEvent::CtrlDelete => Action::DeleteWordRight,
+        KeyEvent::CtrlHome => Action::MoveBufferStart,
+        KeyEvent::CtrlEnd => Action::MoveBufferEnd,
+        KeyEvent::ShiftHome => Action::SelectHome,
+        KeyEvent::ShiftEnd => Action::SelectEnd,
+        KeyEvent::AltBackspace => Action::DeleteWordLeft,
+        KeyEvent::Alt('_' | '.') => Action::YankPreviousWord,
+        // Other Alt keys type their character, as before they had an event
+        KeyEvent::Alt(c) => Action::Insert(c),
+        KeyEvent::Ctrl('c') => Action::Interrupt,
+        KeyEvent::Ctrl('d') => Action::EndOfInput,
+        KeyEvent::Ctrl('l') => Action::ClearScreen,
+        KeyEvent::Ctrl('r') => Action::ReverseSearch,
+        KeyEvent::Ctrl('s') => Action::ForwardSearch,
+        KeyEvent::Ctrl(' ') => Action::HistoryMenu,
+        KeyEvent::Ctrl('v') => Action::QuotedInsert,
+        KeyEvent::Ctrl('y') => Action::PasteClipboard,
+        KeyEvent::Ctrl(_) => return None,
+    };
+    Some(action)
+}
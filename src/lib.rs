@@ -105,7 +105,8 @@
 //!     }
 //!
 //!     fn parse_key_event(&mut self) -> Result<KeyEvent> {
-//!         // Parse input bytes into key events
+//!         // Parse input bytes into key events (optional: the default
+//!         // implementation decodes ANSI sequences from read_byte)
 //! #       Ok(KeyEvent::Enter)
 //!     }
 //! }
@@ -113,38 +114,151 @@
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(feature = "alloc")]
 extern crate alloc;
 
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+#[cfg(feature = "alloc")]
+use alloc::collections::VecDeque;
+#[cfg(feature = "alloc")]
 use alloc::string::{String, ToString};
+#[cfg(feature = "alloc")]
 use alloc::vec::Vec;
+use core::convert::From;
 use core::fmt;
+#[cfg(feature = "alloc")]
+use core::ops::{Bound, RangeBounds};
+use core::option::Option::{self, None, Some};
 use core::result::Result::Ok;
-use core::option::Option::{self, Some, None};
-use core::convert::From;
+#[cfg(feature = "alloc")]
+use core::task::Poll;
 
 /// Error type for editline operations
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Error {
     /// I/O error occurred
-    Io(&'static str),
+    Io(IoError),
     /// Invalid UTF-8 data
     InvalidUtf8,
     /// End of file
     Eof,
     /// Operation interrupted
     Interrupted,
+    /// Cursor position past the end of the line or inside a UTF-8 character
+    InvalidPosition,
+}
+
+/// Why an I/O operation failed, for handling failures programmatically.
+///
+/// # Examples
+///
+/// Retry while a USB endpoint is busy, but give up once the cable is pulled:
+///
+/// ```
+/// use editline::{Error, IoCause, LineEditor};
+///
+/// let mut editor = LineEditor::new(1024, 50);
+/// editor.set_retry_policy(|error: &Error, attempt: u32| match error {
+///     Error::Io(io) => io.cause() == IoCause::Busy && attempt <= 10,
+///     _ => false,
+/// });
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IoCause {
+    /// The device can't take data right now, e.g. a USB endpoint the host
+    /// hasn't drained yet
+    Busy,
+    /// A non-blocking operation would have had to wait
+    WouldBlock,
+    /// The other end is gone: a closed connection, an unconfigured USB device
+    Disconnected,
+    /// The output accepted no data at all
+    WriteZero,
+    /// The operation took too long
+    TimedOut,
+    /// Stored data, such as a saved history, is malformed
+    InvalidData,
+    /// The operation isn't available with this terminal or feature set
+    Unsupported,
+    /// Any other failure; see [`IoError::code`] for details the backend
+    /// provides
+    Other,
+}
+
+impl IoCause {
+    fn as_str(self) -> &'static str {
+        match self {
+            IoCause::Busy => "device busy",
+            IoCause::WouldBlock => "operation would block",
+            IoCause::Disconnected => "disconnected",
+            IoCause::WriteZero => "write accepted no data",
+            IoCause::TimedOut => "timed out",
+            IoCause::InvalidData => "invalid data",
+            IoCause::Unsupported => "unsupported operation",
+            IoCause::Other => "I/O failure",
+        }
+    }
+}
+
+/// Payload of [`Error::Io`]: a cause and an optional backend-specific code,
+/// such as an OS error number or a HAL status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoError {
+    cause: IoCause,
+    code: Option<i32>,
+}
+
+impl IoError {
+    /// Creates an error with the given cause and no code.
+    pub const fn new(cause: IoCause) -> Self {
+        IoError { cause, code: None }
+    }
+
+    /// Attaches a backend-specific code.
+    pub const fn with_code(self, code: i32) -> Self {
+        IoError {
+            cause: self.cause,
+            code: Some(code),
+        }
+    }
+
+    /// Returns why the operation failed.
+    pub fn cause(&self) -> IoCause {
+        self.cause
+    }
+
+    /// Returns the backend-specific code, if any.
+    pub fn code(&self) -> Option<i32> {
+        self.code
+    }
+}
+
+impl fmt::Display for IoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.cause.as_str())?;
+        match self.code {
+            Some(code) => write!(f, " (code {})", code),
+            None => Ok(()),
+        }
+    }
+}
+
+impl From<IoCause> for Error {
+    fn from(cause: IoCause) -> Self {
+        Error::Io(IoError::new(cause))
+    }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Error::Io(msg) => {
-                f.write_str("I/O error: ")?;
-                f.write_str(msg)
-            }
+            Error::Io(e) => write!(f, "I/O error: {}", e),
             Error::InvalidUtf8 => f.write_str("Invalid UTF-8"),
             Error::Eof => f.write_str("End of file"),
             Error::Interrupted => f.write_str("Interrupted"),
+            Error::InvalidPosition => f.write_str("Invalid cursor position"),
         }
     }
 }
@@ -153,23 +267,51 @@ impl fmt::Display for Error {
 impl From<std::io::Error> for Error {
     fn from(e: std::io::Error) -> Self {
         use std::io::ErrorKind;
-        match e.kind() {
-            ErrorKind::UnexpectedEof => Error::Eof,
-            ErrorKind::Interrupted => Error::Interrupted,
-            _ => Error::Io("I/O error"),
-        }
+        let cause = match e.kind() {
+            ErrorKind::UnexpectedEof => return Error::Eof,
+            ErrorKind::Interrupted => return Error::Interrupted,
+            ErrorKind::WouldBlock => IoCause::WouldBlock,
+            ErrorKind::BrokenPipe
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected => IoCause::Disconnected,
+            ErrorKind::WriteZero => IoCause::WriteZero,
+            ErrorKind::TimedOut => IoCause::TimedOut,
+            ErrorKind::InvalidData => IoCause::InvalidData,
+            ErrorKind::Unsupported => IoCause::Unsupported,
+            _ => IoCause::Other,
+        };
+        let error = IoError::new(cause);
+        Error::Io(match e.raw_os_error() {
+            Some(code) => error.with_code(code),
+            None => error,
+        })
     }
 }
 
 #[cfg(feature = "std")]
 impl From<Error> for std::io::Error {
     fn from(e: Error) -> Self {
-        use std::io::{Error as IoError, ErrorKind};
+        use std::io::{Error as StdError, ErrorKind};
         match e {
-            Error::Io(msg) => IoError::new(ErrorKind::Other, msg),
-            Error::InvalidUtf8 => IoError::new(ErrorKind::InvalidData, "Invalid UTF-8"),
-            Error::Eof => IoError::new(ErrorKind::UnexpectedEof, "End of file"),
-            Error::Interrupted => IoError::new(ErrorKind::Interrupted, "Interrupted"),
+            Error::Io(e) => {
+                let kind = match e.cause {
+                    IoCause::WouldBlock => ErrorKind::WouldBlock,
+                    IoCause::Disconnected => ErrorKind::NotConnected,
+                    IoCause::WriteZero => ErrorKind::WriteZero,
+                    IoCause::TimedOut => ErrorKind::TimedOut,
+                    IoCause::InvalidData => ErrorKind::InvalidData,
+                    IoCause::Unsupported => ErrorKind::Unsupported,
+                    IoCause::Busy | IoCause::Other => ErrorKind::Other,
+                };
+                StdError::new(kind, e.cause.as_str())
+            }
+            Error::InvalidUtf8 => StdError::new(ErrorKind::InvalidData, "Invalid UTF-8"),
+            Error::Eof => StdError::new(ErrorKind::UnexpectedEof, "End of file"),
+            Error::Interrupted => StdError::new(ErrorKind::Interrupted, "Interrupted"),
+            Error::InvalidPosition => {
+                StdError::new(ErrorKind::InvalidInput, "Invalid cursor position")
+            }
         }
     }
 }
@@ -183,8 +325,13 @@ impl From<core::str::Utf8Error> for Error {
 /// Result type for editline operations
 pub type Result<T> = core::result::Result<T, Error>;
 
+/// Handler installed with [`LineEditor::set_unknown_sequence_handler`].
+#[cfg(feature = "alloc")]
+type UnknownSequenceHandler = Box<dyn FnMut(&[u8])>;
+
 /// Key events that can be processed by the line editor
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum KeyEvent {
     /// Normal printable character
     Normal(char),
@@ -204,16 +351,54 @@ pub enum KeyEvent {
     Backspace,
     /// Delete
     Delete,
-    /// Enter/Return
+    /// Enter/Return (carriage return)
     Enter,
+    /// Line feed, sent for Enter by some hosts; see the [`newline`] module
+    LineFeed,
+    /// Start of bracketed pasted text
+    PasteStart,
+    /// End of bracketed pasted text
+    PasteEnd,
+    /// Tab (completion, or indentation without a completer)
+    Tab,
+    /// Shift+Tab
+    BackTab,
+    /// Insert key
+    Insert,
+    /// Page Up
+    PageUp,
+    /// Page Down
+    PageDown,
+    /// Function key by number, `F(1)` to `F(12)`
+    F(u8),
+    /// Escape key; ANSI terminals report it when ESC is pressed twice, or once
+    /// with an escape timeout set (see [`ansi::Decoder::expire`])
+    Escape,
     /// Ctrl+Left (word left)
     CtrlLeft,
     /// Ctrl+Right (word right)
     CtrlRight,
     /// Ctrl+Delete (delete word right)
     CtrlDelete,
+    /// Ctrl+Home (start of the buffer)
+    CtrlHome,
+    /// Ctrl+End (end of the buffer)
+    CtrlEnd,
+    /// Shift+Home (extend the selection to the start of the line)
+    ShiftHome,
+    /// Shift+End (extend the selection to the end of the line)
+    ShiftEnd,
     /// Alt+Backspace (delete word left)
     AltBackspace,
+    /// Alt with a printable character (`Alt('_')`); ANSI terminals send ESC
+    /// followed by the character
+    Alt(char),
+    /// Ctrl+letter without a dedicated event, as a lowercase letter (`Ctrl('x')`),
+    /// or `Ctrl(' ')` for Ctrl+Space
+    Ctrl(char),
+    /// Input that isn't a known key, such as an escape sequence from an
+    /// unusual terminal; the editor drops it
+    Unknown(ansi::UnknownSequence),
 }
 
 /// Terminal abstraction that enables platform-agnostic line editing.
@@ -263,9 +448,22 @@ pub trait Terminal {
     /// This is called repeatedly to fetch user input. Should block until a byte is available.
     fn read_byte(&mut self) -> Result<u8>;
 
+    /// Reads a byte if one is available, without waiting.
+    ///
+    /// Returns `Ok(None)` when no input is pending. Used where input has to be
+    /// checked without blocking, such as by [`LineEditor::poll_read_line`] and by
+    /// [`TeeTerminal`] watching two backends. The default implementation blocks in [`read_byte`](Self::read_byte);
+    /// backends that can tell whether input is pending should override it.
+    fn try_read_byte(&mut self) -> Result<Option<u8>> {
+        self.read_byte().map(Some)
+    }
+
     /// Writes raw bytes to the output.
     ///
     /// Used to display typed characters and redraw the line during editing.
+    /// Must write all of `data` before returning `Ok`; output that may accept
+    /// only part of it, as [`std::io::Write`] does, has to be written in a
+    /// loop. `terminals::WriteAllTerminal` does that for `std` writers.
     fn write(&mut self, data: &[u8]) -> Result<()>;
 
     /// Flushes any buffered output.
@@ -298,11 +496,133 @@ pub trait Terminal {
     /// Typically outputs an ANSI escape sequence like `\x1b[K` or calls a platform API.
     fn clear_eol(&mut self) -> Result<()>;
 
+    /// Moves the cursor up one row, keeping its column.
+    ///
+    /// Used to get back to the first row of a line that wrapped. The default
+    /// implementation writes the ANSI sequence `\x1b[A`.
+    fn cursor_up(&mut self) -> Result<()> {
+        self.write(b"\x1b[A")
+    }
+
+    /// Clears from the cursor position to the end of the screen.
+    ///
+    /// The default implementation writes the ANSI sequence `\x1b[J`.
+    fn clear_to_end_of_screen(&mut self) -> Result<()> {
+        self.write(b"\x1b[J")
+    }
+
+    /// Inserts a blank at the cursor, moving the rest of the row one column
+    /// right, and leaves the cursor on the blank.
+    ///
+    /// Only used when [`capabilities`](Self::capabilities) reports
+    /// [`insert_delete`](Caps::insert_delete). The default implementation
+    /// writes the ANSI sequence `\x1b[@` (ICH).
+    fn insert_blank(&mut self) -> Result<()> {
+        self.write(b"\x1b[@")
+    }
+
+    /// Deletes the character at the cursor, moving the rest of the row one
+    /// column left.
+    ///
+    /// Only used when [`capabilities`](Self::capabilities) reports
+    /// [`insert_delete`](Caps::insert_delete). The default implementation
+    /// writes the ANSI sequence `\x1b[P` (DCH).
+    fn delete_char(&mut self) -> Result<()> {
+        self.write(b"\x1b[P")
+    }
+
+    /// Clears the whole screen and moves the cursor to the top-left corner.
+    ///
+    /// The default implementation writes the ANSI sequences `\x1b[H\x1b[2J`.
+    fn clear_screen(&mut self) -> Result<()> {
+        self.write(b"\x1b[H\x1b[2J")
+    }
+
+    /// Alerts the user, e.g. when typing past the maximum line length.
+    ///
+    /// The default implementation writes BEL (`\x07`).
+    fn bell(&mut self) -> Result<()> {
+        self.write(b"\x07")
+    }
+
+    /// Switches to the alternate screen, leaving the main screen's contents to be
+    /// restored by [`exit_alternate_screen`](Self::exit_alternate_screen).
+    ///
+    /// The default implementation writes the ANSI sequence `\x1b[?1049h`.
+    fn enter_alternate_screen(&mut self) -> Result<()> {
+        self.write(b"\x1b[?1049h")
+    }
+
+    /// Returns to the main screen and restores its contents and cursor.
+    ///
+    /// The default implementation writes the ANSI sequence `\x1b[?1049l`.
+    fn exit_alternate_screen(&mut self) -> Result<()> {
+        self.write(b"\x1b[?1049l")
+    }
+
     /// Parses the next key event from input.
     ///
     /// Should handle multi-byte sequences (like ANSI escape codes) and return a single
     /// [`KeyEvent`]. Called once per key press by [`LineEditor::read_line`].
-    fn parse_key_event(&mut self) -> Result<KeyEvent>;
+    ///
+    /// The default implementation decodes ANSI/VT100 input from [`read_byte`](Self::read_byte)
+    /// using [`ansi::parse_key_event`], which suits any byte-stream backend.
+    fn parse_key_event(&mut self) -> Result<KeyEvent> {
+        ansi::parse_key_event(self)
+    }
+
+    /// Returns the terminal size as `(columns, rows)`, if known.
+    ///
+    /// Used to lay out multi-column output such as completion lists. The default
+    /// implementation returns `None`, in which case 80x24 is assumed.
+    fn size(&mut self) -> Option<(u16, u16)> {
+        None
+    }
+
+    /// Returns the cursor position as 0-based `(column, row)`, if the terminal
+    /// can report it.
+    ///
+    /// Called in raw mode at the start of [`LineEditor::read_line`] to find the
+    /// column the prompt starts at, so that a line following output of unknown
+    /// width (such as a prompt printed by the application itself) is redrawn in
+    /// place. The default implementation returns `None` and the prompt is assumed
+    /// to start at column 0. ANSI terminals can answer with
+    /// [`ansi::query_cursor_position`], given a read timeout in case nothing
+    /// replies.
+    fn get_cursor_position(&mut self) -> Option<(u16, u16)> {
+        None
+    }
+
+    /// Shows `text` on a status line below the input, leaving the cursor where it is.
+    ///
+    /// Used for transient information such as incremental search help and
+    /// application status set with [`LineEditor::set_status`]. The default
+    /// implementation uses ANSI sequences ([`ansi::show_status`]) if the
+    /// [capabilities](Self::capabilities) include
+    /// [`cursor_addressing`](Caps::cursor_addressing), and does nothing
+    /// otherwise, so a dumb serial backend gets no escape sequences.
+    fn show_status(&mut self, text: &[u8]) -> Result<()> {
+        if !self.capabilities().cursor_addressing {
+            return Ok(());
+        }
+        ansi::show_status(self, text)
+    }
+
+    /// Erases the status line shown by [`show_status`](Self::show_status).
+    fn clear_status(&mut self) -> Result<()> {
+        if !self.capabilities().cursor_addressing {
+            return Ok(());
+        }
+        ansi::clear_status(self)
+    }
+
+    /// Reports what the terminal can display.
+    ///
+    /// The default is [`Caps::NONE`], so features needing more, such as theme
+    /// colors, stay off until a backend says otherwise. See the [`caps`] module.
+    fn capabilities(&self) -> Caps {
+        Caps::NONE
+    }
 }
 
 /// Text buffer with cursor tracking for line editing operations.
@@ -312,11 +632,15 @@ pub trait Terminal {
 ///
 /// This struct is typically not used directly - instead use [`LineEditor`] which
 /// provides the high-level editing interface.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg(feature = "alloc")]
 pub struct LineBuffer {
     buffer: Vec<u8>,
     cursor_pos: usize,
+    max_len: Option<usize>,
 }
 
+#[cfg(feature = "alloc")]
 impl LineBuffer {
     /// Creates a new line buffer with the specified capacity.
     ///
@@ -336,7 +660,130 @@ impl LineBuffer {
         Self {
             buffer: Vec::with_capacity(capacity),
             cursor_pos: 0,
+            max_len: None,
+        }
+    }
+
+    /// Limits the line to `max_len` bytes, or lifts the limit with `None`.
+    ///
+    /// Inserts that would exceed the limit are refused and [`load`](Self::load)
+    /// truncates, so the buffer never grows past it; useful on small heaps where
+    /// a long pasted line or history entry must not exhaust memory. Text already
+    /// in the buffer is kept. There is no limit by default.
+    pub fn set_max_len(&mut self, max_len: Option<usize>) {
+        self.max_len = max_len;
+    }
+
+    /// Returns the maximum line length in bytes, if limited.
+    pub fn max_len(&self) -> Option<usize> {
+        self.max_len
+    }
+
+    /// Returns the number of bytes the buffer holds without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    /// Reserves room for at least `additional` more bytes, e.g. ahead of a
+    /// known paste, so inserting them doesn't reallocate. Never reserves past
+    /// the [maximum length](Self::set_max_len).
+    pub fn reserve(&mut self, additional: usize) {
+        let additional = match self.max_len {
+            Some(max) => additional.min(max.saturating_sub(self.buffer.len())),
+            None => additional,
+        };
+        self.buffer.reserve(additional);
+    }
+
+    /// Releases capacity beyond the current length.
+    pub fn shrink_to_fit(&mut self) {
+        self.buffer.shrink_to_fit();
+    }
+
+    /// Releases capacity beyond `capacity` bytes, keeping at least the
+    /// current length.
+    pub fn shrink_to(&mut self, capacity: usize) {
+        self.buffer.shrink_to(capacity);
+    }
+
+    /// Returns `true` if `extra` more bytes fit within the limit.
+    fn fits(&self, extra: usize) -> bool {
+        self.max_len
+            .map_or(true, |max| self.buffer.len() + extra <= max)
+    }
+
+    /// Performs a sequence of edits, with the same result as performing each
+    /// with [`invariants::apply`].
+    ///
+    /// Inserting a character normally shifts all text after the cursor. Here
+    /// that text is moved aside once for the whole batch, so pasting or
+    /// inserting a template stays linear in its length, which matters on slow
+    /// cores. Cursor movement by character, deletion and insertion take the
+    /// fast path; other operations are still correct but move the text back
+    /// and forth.
+    ///
+    /// Returns `false` if the [maximum length](Self::set_max_len) kept any
+    /// insertion or load from completing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use editline::{LineBuffer, LineBufferOp};
+    ///
+    /// let mut buffer = LineBuffer::new(64);
+    /// buffer.load("gpio 17");
+    /// let ops = [LineBufferOp::Start, LineBufferOp::WordRight, LineBufferOp::InsertStr("set ".into())];
+    /// assert!(buffer.apply_ops(&ops));
+    /// assert_eq!(buffer.as_str().unwrap(), "gpio set 17");
+    /// ```
+    pub fn apply_ops(&mut self, ops: &[LineBufferOp]) -> bool {
+        // Text after the cursor, reversed so that edits at the cursor only push
+        // and pop; the cursor stays at the end of `buffer` meanwhile
+        let mut after: Vec<u8> = self.buffer.drain(self.cursor_pos..).rev().collect();
+        let mut complete = true;
+        for op in ops {
+            match op {
+                LineBufferOp::Insert(c) => complete &= self.push_char(*c, after.len()),
+                LineBufferOp::InsertStr(text) => {
+                    complete &= text.chars().all(|c| self.push_char(c, after.len()))
+                }
+                LineBufferOp::DeleteBefore => {
+                    self.buffer.truncate(self.prev_char_start());
+                }
+                LineBufferOp::DeleteAt => {
+                    pop_char(&mut after, |_| {});
+                }
+                LineBufferOp::Left => {
+                    let start = self.prev_char_start();
+                    after.extend(self.buffer.drain(start..).rev());
+                }
+                LineBufferOp::Right => {
+                    let buffer = &mut self.buffer;
+                    pop_char(&mut after, |byte| buffer.push(byte));
+                }
+                LineBufferOp::Start => after.extend(self.buffer.drain(..).rev()),
+                LineBufferOp::End => self.buffer.extend(after.drain(..).rev()),
+                op => {
+                    self.buffer.extend(after.drain(..).rev());
+                    complete &= invariants::apply(self, op);
+                    after.extend(self.buffer.drain(self.cursor_pos..).rev());
+                }
+            }
+            self.cursor_pos = self.buffer.len();
+        }
+        self.buffer.extend(after.into_iter().rev());
+        complete
+    }
+
+    /// Appends `c` at the cursor while `after` bytes are set aside.
+    fn push_char(&mut self, c: char, after: usize) -> bool {
+        let mut buf = [0; 4];
+        let bytes = c.encode_utf8(&mut buf).as_bytes();
+        if !self.fits(after + bytes.len()) {
+            return false;
         }
+        self.buffer.extend_from_slice(bytes);
+        true
     }
 
     /// Clears the buffer and resets the cursor to the start.
@@ -362,6 +809,33 @@ impl LineBuffer {
         self.cursor_pos
     }
 
+    /// Moves the cursor to byte position `pos`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidPosition`] if `pos` is past the end of the buffer
+    /// or inside a multi-byte UTF-8 character; the cursor is left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use editline::LineBuffer;
+    ///
+    /// let mut buffer = LineBuffer::new(16);
+    /// buffer.load("né");
+    /// assert!(buffer.set_cursor(1).is_ok());
+    /// assert!(buffer.set_cursor(2).is_err()); // inside 'é'
+    /// assert_eq!(buffer.cursor_pos(), 1);
+    /// ```
+    pub fn set_cursor(&mut self, pos: usize) -> Result<()> {
+        if pos > self.buffer.len() || (pos < self.buffer.len() && is_continuation(self.buffer[pos]))
+        {
+            return Err(Error::InvalidPosition);
+        }
+        self.cursor_pos = pos;
+        Ok(())
+    }
+
     /// Returns the buffer contents as a UTF-8 string slice.
     ///
     /// # Errors
@@ -379,14 +853,28 @@ impl LineBuffer {
     /// Inserts a character at the cursor position, moving the cursor forward.
     ///
     /// Supports UTF-8 characters. The cursor advances by the byte length of the character.
-    pub fn insert_char(&mut self, c: char) {
+    /// Returns `false`, leaving the buffer unchanged, if the character would
+    /// exceed the [maximum length](Self::set_max_len).
+    pub fn insert_char(&mut self, c: char) -> bool {
         let mut buf = [0; 4];
         let bytes = c.encode_utf8(&mut buf).as_bytes();
+        if !self.fits(bytes.len()) {
+            return false;
+        }
 
         for &byte in bytes {
             self.buffer.insert(self.cursor_pos, byte);
             self.cursor_pos += 1;
         }
+        true
+    }
+
+    /// Inserts a string at the cursor position, moving the cursor past it.
+    ///
+    /// Returns `false` if the [maximum length](Self::set_max_len) was reached,
+    /// in which case only the characters that fit were inserted.
+    pub fn insert_str(&mut self, s: &str) -> bool {
+        s.chars().all(|c| self.insert_char(c))
     }
 
     /// Deletes the character before the cursor (backspace operation).
@@ -394,8 +882,9 @@ impl LineBuffer {
     /// Returns `true` if a character was deleted, `false` if the cursor is at the start.
     pub fn delete_before_cursor(&mut self) -> bool {
         if self.cursor_pos > 0 {
-            self.cursor_pos -= 1;
-            self.buffer.remove(self.cursor_pos);
+            let start = self.prev_char_start();
+            self.buffer.drain(start..self.cursor_pos);
+            self.cursor_pos = start;
             true
         } else {
             false
@@ -407,31 +896,32 @@ impl LineBuffer {
     /// Returns `true` if a character was deleted, `false` if the cursor is at the end.
     pub fn delete_at_cursor(&mut self) -> bool {
         if self.cursor_pos < self.buffer.len() {
-            self.buffer.remove(self.cursor_pos);
+            let end = self.next_char_end();
+            self.buffer.drain(self.cursor_pos..end);
             true
         } else {
             false
         }
     }
 
-    /// Moves the cursor one position to the left.
+    /// Moves the cursor one character to the left.
     ///
     /// Returns `true` if the cursor moved, `false` if already at the start.
     pub fn move_cursor_left(&mut self) -> bool {
         if self.cursor_pos > 0 {
-            self.cursor_pos -= 1;
+            self.cursor_pos = self.prev_char_start();
             true
         } else {
             false
         }
     }
 
-    /// Moves the cursor one position to the right.
+    /// Moves the cursor one character to the right.
     ///
     /// Returns `true` if the cursor moved, `false` if already at the end.
     pub fn move_cursor_right(&mut self) -> bool {
         if self.cursor_pos < self.buffer.len() {
-            self.cursor_pos += 1;
+            self.cursor_pos = self.next_char_end();
             true
         } else {
             false
@@ -456,6 +946,24 @@ impl LineBuffer {
         self.buffer.len() - old_pos
     }
 
+    /// Byte position where the character before the cursor starts.
+    fn prev_char_start(&self) -> usize {
+        let mut pos = self.cursor_pos.saturating_sub(1);
+        while pos > 0 && is_continuation(self.buffer[pos]) {
+            pos -= 1;
+        }
+        pos
+    }
+
+    /// Byte position just past the character at the cursor.
+    fn next_char_end(&self) -> usize {
+        let mut pos = (self.cursor_pos + 1).min(self.buffer.len());
+        while pos < self.buffer.len() && is_continuation(self.buffer[pos]) {
+            pos += 1;
+        }
+        pos
+    }
+
     /// Find start of word to the left
     fn find_word_start_left(&self) -> usize {
         if self.cursor_pos == 0 {
@@ -549,64 +1057,112 @@ impl LineBuffer {
 
     /// Deletes the word to the left of the cursor (Alt+Backspace operation).
     ///
-    /// Returns the number of bytes deleted.
-    pub fn delete_word_left(&mut self) -> usize {
-        let target = self.find_word_start_left();
-        let count = self.cursor_pos - target;
-
-        for _ in 0..count {
-            if self.cursor_pos > 0 {
-                self.cursor_pos -= 1;
-                self.buffer.remove(self.cursor_pos);
-            }
-        }
-
-        count
+    /// Returns the deleted text, which is empty if the cursor is at the start.
+    pub fn delete_word_left(&mut self) -> String {
+        let start = self.find_word_start_left();
+        let removed = self.remove(start, self.cursor_pos);
+        self.cursor_pos = start;
+        removed
     }
 
     /// Deletes the word to the right of the cursor (Ctrl+Delete operation).
     ///
-    /// Returns the number of bytes deleted.
-    pub fn delete_word_right(&mut self) -> usize {
-        let target = self.find_word_start_right();
-        let count = target - self.cursor_pos;
-
-        for _ in 0..count {
-            if self.cursor_pos < self.buffer.len() {
-                self.buffer.remove(self.cursor_pos);
-            }
-        }
+    /// Returns the deleted text, which is empty if the cursor is at the end.
+    pub fn delete_word_right(&mut self) -> String {
+        let end = self.find_word_start_right();
+        self.remove(self.cursor_pos, end)
+    }
 
-        count
+    /// Removes the bytes between `start` and `end`, returning them as text.
+    fn remove(&mut self, start: usize, end: usize) -> String {
+        let removed: Vec<u8> = self.buffer.drain(start..end).collect();
+        // Word boundaries never split a character
+        String::from_utf8(removed)
+            .unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
     }
 
     /// Loads text into the buffer, replacing existing content.
     ///
     /// The cursor is positioned at the end of the loaded text.
     /// Used internally for history navigation.
-    pub fn load(&mut self, text: &str) {
+    ///
+    /// Text longer than the [maximum length](Self::set_max_len) is truncated at
+    /// the last whole character that fits, and `false` is returned.
+    pub fn load(&mut self, text: &str) -> bool {
+        let mut len = text.len();
+        if let Some(max) = self.max_len {
+            len = len.min(max);
+            while !text.is_char_boundary(len) {
+                len -= 1;
+            }
+        }
+
         self.buffer.clear();
-        self.buffer.extend_from_slice(text.as_bytes());
+        self.buffer.extend_from_slice(&text.as_bytes()[..len]);
         self.cursor_pos = self.buffer.len();
+        len == text.len()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Debug for LineBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LineBuffer")
+            .field("line", &String::from_utf8_lossy(&self.buffer))
+            .field("cursor_pos", &self.cursor_pos)
+            .field("max_len", &self.max_len)
+            .finish()
     }
 }
 
 /// Check if a byte is a word character (alphanumeric or underscore).
+#[cfg(feature = "alloc")]
 fn is_word_char(c: u8) -> bool {
     c.is_ascii_alphanumeric() || c == b'_'
 }
 
+/// Pops the character at the top of a reversed byte stack, passing its bytes
+/// to `f` in text order.
+#[cfg(feature = "alloc")]
+fn pop_char(reversed: &mut Vec<u8>, mut f: impl FnMut(u8)) {
+    if let Some(lead) = reversed.pop() {
+        f(lead);
+        while reversed.last().map_or(false, |&b| is_continuation(b)) {
+            f(reversed.pop().unwrap_or_default());
+        }
+    }
+}
+
+/// Check if a byte continues a multi-byte UTF-8 character.
+#[cfg(feature = "alloc")]
+fn is_continuation(c: u8) -> bool {
+    c & 0xc0 == 0x80
+}
+
 /// Check if a byte is whitespace (space or tab).
+#[cfg(feature = "alloc")]
 fn is_whitespace(c: u8) -> bool {
     c == b' ' || c == b'\t'
 }
 
-/// Command history manager with circular buffer storage.
+/// Command history manager with bounded storage.
 ///
-/// Maintains a fixed-size history of entered commands with automatic
+/// Maintains a bounded history of entered commands with automatic
 /// duplicate and empty-line filtering. Supports bidirectional navigation
 /// and preserves the current line when browsing history.
 ///
+/// The history holds at most `capacity` entries and, optionally, at most a
+/// number of bytes set with [`set_max_bytes`](Self::set_max_bytes); the oldest
+/// entries make room for new ones.
+///
+/// Entries are stored back to back in one string rather than each in its own
+/// allocation, which keeps many short commands from fragmenting a small heap.
+///
+/// Each entry also has an ID, numbering all lines ever added from 1, which
+/// stays the same as older entries are dropped. IDs are what a `history`
+/// command should show, so that `!42` can find the entry again with
+/// [`get_by_id`](Self::get_by_id).
+///
 /// # Examples
 ///
 /// ```
@@ -620,14 +1176,24 @@ fn is_whitespace(c: u8) -> bool {
 /// assert_eq!(hist.previous(""), Some("second command"));
 /// assert_eq!(hist.previous(""), Some("first command"));
 /// ```
+#[derive(Clone)]
+#[cfg(feature = "alloc")]
 pub struct History {
-    entries: Vec<String>,
+    /// Text of the entries, oldest first, after `dead` bytes of dropped ones
+    arena: String,
+    dead: usize,
+    /// Byte ranges of the entries in `arena`, oldest first
+    spans: VecDeque<(usize, usize)>,
+    /// ID of the oldest entry
+    first_id: u64,
     capacity: usize,
-    current_entry: usize,
+    max_bytes: Option<usize>,
+    /// Index of the entry being viewed, in chronological order
     viewing_entry: Option<usize>,
     saved_line: Option<String>,
 }
 
+#[cfg(feature = "alloc")]
 impl History {
     /// Creates a new history buffer with the specified capacity.
     ///
@@ -638,18 +1204,100 @@ impl History {
     /// * `capacity` - Maximum number of history entries to store
     pub fn new(capacity: usize) -> Self {
         Self {
-            entries: Vec::with_capacity(capacity),
+            arena: String::new(),
+            dead: 0,
+            spans: VecDeque::with_capacity(capacity),
+            first_id: 1,
             capacity,
-            current_entry: 0,
+            max_bytes: None,
             viewing_entry: None,
             saved_line: None,
         }
     }
 
+    /// Limits the text stored to `max_bytes`, or lifts the limit with `None`.
+    ///
+    /// On devices where memory is counted in bytes rather than entries, this
+    /// keeps a few long lines from taking as much room as many short ones
+    /// would. The oldest entries are dropped until the rest fit, now and
+    /// whenever a line is added; a line longer than the whole budget isn't
+    /// stored, and the entries already there are kept. The entry count limit
+    /// still applies. There is no byte limit by
+    /// default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use editline::History;
+    ///
+    /// let mut hist = History::new(50);
+    /// hist.set_max_bytes(Some(16));
+    /// hist.add("status");
+    /// hist.add("reset");
+    /// hist.add("gpio 17");
+    /// assert_eq!(hist.iter().collect::<Vec<_>>(), ["reset", "gpio 17"]);
+    /// assert_eq!(hist.memory_usage(), 12);
+    /// ```
+    pub fn set_max_bytes(&mut self, max_bytes: Option<usize>) {
+        self.max_bytes = max_bytes;
+        if self.evict() {
+            self.viewing_entry = None;
+        }
+    }
+
+    /// Returns the byte limit, if any.
+    pub fn max_bytes(&self) -> Option<usize> {
+        self.max_bytes
+    }
+
+    /// Returns the number of bytes of text stored in the entries, which is
+    /// what [`set_max_bytes`](Self::set_max_bytes) limits.
+    pub fn memory_usage(&self) -> usize {
+        self.arena.len() - self.dead
+    }
+
+    /// Drops the oldest entries while over a limit, returning `true` if any
+    /// were dropped.
+    fn evict(&mut self) -> bool {
+        let mut evicted = false;
+        while self.spans.len() > self.capacity
+            || self
+                .max_bytes
+                .map_or(false, |max| self.memory_usage() > max)
+        {
+            if self.spans.pop_front().is_none() {
+                break;
+            }
+            self.dead = self
+                .spans
+                .front()
+                .map_or(self.arena.len(), |&(start, _)| start);
+            self.first_id += 1;
+            evicted = true;
+        }
+
+        // Reclaim dropped text once it outweighs the rest, which keeps the
+        // copying linear overall
+        if self.dead > self.memory_usage() {
+            self.arena.drain(..self.dead);
+            for span in &mut self.spans {
+                *span = (span.0 - self.dead, span.1 - self.dead);
+            }
+            self.dead = 0;
+        }
+        evicted
+    }
+
+    fn entry(&self, index: usize) -> &str {
+        let (start, end) = self.spans[index];
+        &self.arena[start..end]
+    }
+
     /// Adds a line to the history.
     ///
-    /// Empty lines (including whitespace-only) and consecutive duplicates are automatically skipped.
-    /// When the buffer is full, the oldest entry is overwritten.
+    /// Empty lines (including whitespace-only) and consecutive duplicates are automatically skipped,
+    /// as are lines longer than the [byte limit](Self::set_max_bytes).
+    /// When the history is full, the oldest entries are dropped.
     ///
     /// # Arguments
     ///
@@ -663,21 +1311,20 @@ impl History {
         }
 
         // Skip if same as most recent (after trimming)
-        if let Some(last) = self.entries.last() {
-            if last == trimmed {
-                return;
-            }
+        if !self.spans.is_empty() && self.entry(self.spans.len() - 1) == trimmed {
+            return;
         }
 
-        if self.entries.len() < self.capacity {
-            self.entries.push(trimmed.to_string());
-            self.current_entry = self.entries.len() - 1;
-        } else {
-            // Circular buffer - overwrite oldest
-            self.current_entry = (self.current_entry + 1) % self.capacity;
-            self.entries[self.current_entry] = trimmed.to_string();
+        // Skip lines that can't fit, rather than dropping every entry for them
+        if self.max_bytes.map_or(false, |max| trimmed.len() > max) {
+            return;
         }
 
+        let start = self.arena.len();
+        self.arena.push_str(trimmed);
+        self.spans.push_back((start, self.arena.len()));
+        self.evict();
+
         self.viewing_entry = None;
         self.saved_line = None;
     }
@@ -695,40 +1342,31 @@ impl History {
     ///
     /// `Some(&str)` with the previous history entry, or `None` if at the oldest entry.
     pub fn previous(&mut self, current_line: &str) -> Option<&str> {
-        if self.entries.is_empty() {
+        let index = self.previous_index(current_line)?;
+        Some(self.entry(index))
+    }
+
+    /// Moves to the previous entry like [`previous`](Self::previous), returning
+    /// its index so the editor can load it without a copy.
+    fn previous_index(&mut self, current_line: &str) -> Option<usize> {
+        if self.spans.is_empty() {
             return None;
         }
 
-        match self.viewing_entry {
+        let index = match self.viewing_entry {
             None => {
-                // First time - save current line and start at most recent
-                self.saved_line = Some(current_line.to_string());
-                self.viewing_entry = Some(self.current_entry);
-                Some(&self.entries[self.current_entry])
-            }
-            Some(idx) => {
-                // Go further back
-                if self.entries.len() < self.capacity {
-                    // Haven't filled buffer yet
-                    if idx > 0 {
-                        let prev = idx - 1;
-                        self.viewing_entry = Some(prev);
-                        Some(&self.entries[prev])
-                    } else {
-                        None
-                    }
-                } else {
-                    // Buffer is full
-                    let prev = (idx + self.capacity - 1) % self.capacity;
-                    if prev == self.current_entry {
-                        None
-                    } else {
-                        self.viewing_entry = Some(prev);
-                        Some(&self.entries[prev])
-                    }
-                }
+                // First time - save current line, reusing the last one's
+                // allocation, and start at most recent
+                let saved = self.saved_line.get_or_insert_with(String::new);
+                saved.clear();
+                saved.push_str(current_line);
+                self.spans.len() - 1
             }
-        }
+            Some(0) => return None,
+            Some(idx) => idx - 1,
+        };
+        self.viewing_entry = Some(index);
+        Some(index)
     }
 
     /// Navigates to the next (newer) history entry.
@@ -741,33 +1379,31 @@ impl History {
     /// `Some(&str)` with the next history entry or saved line, or `None` if
     /// not currently viewing history.
     pub fn next_entry(&mut self) -> Option<&str> {
-        match self.viewing_entry {
-            None => None,
-            Some(idx) => {
-                if self.entries.len() < self.capacity {
-                    // Haven't filled buffer yet
-                    if idx < self.entries.len() - 1 {
-                        let next = idx + 1;
-                        self.viewing_entry = Some(next);
-                        Some(&self.entries[next])
-                    } else {
-                        // Reached the end, return saved line
-                        self.viewing_entry = None;
-                        self.saved_line.as_deref()
-                    }
-                } else {
-                    // Buffer is full
-                    let next = (idx + 1) % self.capacity;
-                    if next == (self.current_entry + 1) % self.capacity {
-                        // Reached the end, return saved line
-                        self.viewing_entry = None;
-                        self.saved_line.as_deref()
-                    } else {
-                        self.viewing_entry = Some(next);
-                        Some(&self.entries[next])
-                    }
-                }
-            }
+        match self.next_index()? {
+            Some(index) => Some(self.entry(index)),
+            None => self.saved_line.as_deref(),
+        }
+    }
+
+    /// Moves to the next entry like [`next_entry`](Self::next_entry), returning
+    /// its index, or `Some(None)` for the saved line.
+    fn next_index(&mut self) -> Option<Option<usize>> {
+        let next = self.viewing_entry? + 1;
+        if next < self.spans.len() {
+            self.viewing_entry = Some(next);
+            Some(Some(next))
+        } else {
+            // Reached the end, back to the saved line
+            self.viewing_entry = None;
+            Some(None)
+        }
+    }
+
+    /// Returns entry `index`, or the saved line for `None`.
+    fn view(&self, index: Option<usize>) -> &str {
+        match index {
+            Some(index) => self.entry(index),
+            None => self.saved_line.as_deref().unwrap_or(""),
         }
     }
 
@@ -777,42 +1413,301 @@ impl History {
     pub fn reset_view(&mut self) {
         self.viewing_entry = None;
     }
-}
 
-/// Main line editor interface with full editing and history support.
-///
-/// Provides a high-level API for reading edited lines from any [`Terminal`]
-/// implementation. Handles all keyboard input, cursor movement, text editing,
-/// and history navigation.
-///
-/// # Examples
-///
-/// ```no_run
-/// use editline::{LineEditor, terminals::StdioTerminal};
-///
-/// let mut editor = LineEditor::new(1024, 50);
-/// let mut terminal = StdioTerminal::new();
-///
-/// match editor.read_line(&mut terminal) {
-///     Ok(line) => println!("Got: {}", line),
-///     Err(e) => eprintln!("Error: {}", e),
-/// }
-/// ```
-///
-/// # Key Bindings
-///
+    /// Returns the number of entries currently stored.
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    /// Returns `true` if the history contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// Returns the entry at `index` in chronological order (0 is the oldest).
+    pub fn get(&self, index: usize) -> Option<&str> {
+        if index >= self.spans.len() {
+            return None;
+        }
+        Some(self.entry(index))
+    }
+
+    /// Returns an iterator over the entries from oldest to newest.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use editline::History;
+    ///
+    /// let mut hist = History::new(2);
+    /// hist.add("one");
+    /// hist.add("two");
+    /// hist.add("three");
+    ///
+    /// let entries: Vec<&str> = hist.iter().collect();
+    /// assert_eq!(entries, ["two", "three"]);
+    /// ```
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &str> + ExactSizeIterator + '_ {
+        self.spans
+            .iter()
+            .map(move |&(start, end)| &self.arena[start..end])
+    }
+
+    /// Returns the entry with the given ID, or `None` if it was dropped or
+    /// doesn't exist yet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use editline::History;
+    ///
+    /// let mut hist = History::new(2);
+    /// hist.add("one");
+    /// hist.add("two");
+    /// hist.add("three");
+    ///
+    /// assert_eq!(hist.get_by_id(1), None);
+    /// assert_eq!(hist.get_by_id(3), Some("three"));
+    /// ```
+    pub fn get_by_id(&self, id: u64) -> Option<&str> {
+        let index = id.checked_sub(self.first_id)?;
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| self.get(index))
+    }
+
+    /// Returns the ID of the entry at `index` in chronological order.
+    pub fn id_of(&self, index: usize) -> Option<u64> {
+        if index >= self.spans.len() {
+            return None;
+        }
+        Some(self.first_id + index as u64)
+    }
+
+    /// Returns the ID the next line added will get.
+    pub fn next_id(&self) -> u64 {
+        self.first_id + self.spans.len() as u64
+    }
+
+    /// Returns an iterator over the entries and their IDs from oldest to
+    /// newest; call `rev()` on it to go from newest to oldest.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use editline::History;
+    ///
+    /// let mut hist = History::new(2);
+    /// hist.add("one");
+    /// hist.add("two");
+    /// hist.add("three");
+    ///
+    /// let newest_first: Vec<(u64, &str)> = hist.iter_with_ids().rev().collect();
+    /// assert_eq!(newest_first, [(3, "three"), (2, "two")]);
+    /// ```
+    pub fn iter_with_ids(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = (u64, &str)> + ExactSizeIterator + '_ {
+        let first_id = self.first_id;
+        self.iter()
+            .enumerate()
+            .map(move |(index, entry)| (first_id + index as u64, entry))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Debug for History {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("History")
+            .field("entries", &self.iter().collect::<Vec<_>>())
+            .field("capacity", &self.capacity)
+            .field("max_bytes", &self.max_bytes)
+            .field("viewing_entry", &self.viewing_entry)
+            .finish()
+    }
+}
+
+/// Main line editor interface with full editing and history support.
+///
+/// Provides a high-level API for reading edited lines from any [`Terminal`]
+/// implementation. Handles all keyboard input, cursor movement, text editing,
+/// and history navigation.
+///
+/// # Examples
+///
+/// ```no_run
+/// use editline::{LineEditor, terminals::StdioTerminal};
+///
+/// let mut editor = LineEditor::new(1024, 50);
+/// let mut terminal = StdioTerminal::new();
+///
+/// match editor.read_line(&mut terminal) {
+///     Ok(line) => println!("Got: {}", line),
+///     Err(e) => eprintln!("Error: {}", e),
+/// }
+/// ```
+///
+/// # Key Bindings
+///
 /// - **Arrow keys**: Move cursor left/right, navigate history up/down
-/// - **Home/End**: Jump to start/end of line
+/// - **Home/End**: Jump to start/end of line (Ctrl+Home/End: start/end of the buffer)
+/// - **Shift+Home/End**: Select to the start/end of the line; typing replaces
+///   the selection and Backspace/Delete remove it
 /// - **Backspace/Delete**: Delete characters
-/// - **Ctrl+Left/Right**: Move by word
+/// - **Ctrl+Left/Right**: Move by word (also Alt+Left/Right, Alt+B/F and Option+Left/Right on macOS)
 /// - **Alt+Backspace**: Delete word left
+/// - **Alt+_ / Alt+.**: Insert the last word of the previous line; repeat for
+///   the words before it
 /// - **Ctrl+Delete**: Delete word right
+/// - **Tab**: Complete the word before the cursor (see [`LineEditor::set_completer`]),
+///   or indent to the next tab stop without a completer
+/// - **Ctrl+L**: Clear the screen and redraw the line
+/// - **Ctrl+R / Ctrl+S**: Incremental history search backward / forward
+/// - **Ctrl+Space**: Pick from the history entries starting with the line, in a menu above the prompt
+/// - **Ctrl+V**: Insert the next key literally (control characters show as `^X`)
+/// - **Ctrl+Y**: Paste from the system clipboard, see [`set_clipboard`](Self::set_clipboard)
+/// - **Ctrl+X Ctrl+E**: Edit the line in `$VISUAL`/`$EDITOR` (std only)
+/// - **Ctrl+G, Esc Esc**: Abort a search, correction prompt or selection,
+///   keeping the line
+/// - **Ctrl+C**: Cancel the line ([`Error::Interrupted`])
+/// - **Ctrl+D**: End of input ([`Error::Eof`])
 /// - **Enter**: Submit line
+///
+/// Bindings can be changed with [`LineEditor::bind`]; see the [`keymap`] module.
+#[cfg(feature = "alloc")]
 pub struct LineEditor {
     line: LineBuffer,
     history: History,
+    ctrl_x_pending: bool,
+    /// Words back from the end of the previous entry yanked by the last key,
+    /// and where the yanked word starts
+    yanked_word: Option<(usize, usize)>,
+    /// How the read loop interprets the next key
+    mode: Mode,
+    /// Key decoder of the line being read with `poll_read_line`, if any
+    polling: Option<ansi::Decoder>,
+    /// Bytes that arrived ahead of a reply to a terminal query, read before
+    /// new input
+    typed_ahead: VecDeque<u8>,
+    /// Ticks to wait for the rest of an escape sequence while polling, and the
+    /// clock counting them
+    escape_timeout: Option<(u64, fn() -> u64)>,
+    /// Clock reading when the last byte was polled
+    last_input: u64,
+    flow_control: bool,
+    crlf_as_one: bool,
+    pasted_newlines: PastedNewline,
+    /// The last key was a CR, for collapsing CRLF
+    after_cr: bool,
+    /// Inside a bracketed paste
+    pasting: bool,
+    /// Deleted text goes to the clipboard and Ctrl+Y pastes from it
+    clipboard: bool,
+    /// Deleted text still to be sent to the clipboard
+    copied: Option<String>,
+    /// The key just handled asked to paste from the clipboard
+    paste_requested: bool,
+    /// Line, cursor and cursor column last shown, while a paste is edited off
+    /// screen to be shown in one go
+    paste_shown: Option<(String, usize, usize)>,
+    tab_width: usize,
+    auto_indent: bool,
+    /// Line endings after which auto-indent adds a level
+    indent_openers: String,
+    /// Columns of the single row a line scrolls in, if not wrapping
+    scroll_width: Option<usize>,
+    /// Output only moves forward, for screen readers
+    accessible: bool,
+    announcer: Option<Box<dyn Announcer>>,
+    echo: bool,
+    prompt: String,
+    /// Column the prompt starts at, when the application printed something
+    /// on the line before calling `read_line`
+    origin: usize,
+    /// Keep the line for the next `read_line`, set by `restore`
+    restored: bool,
+    /// The last read failed before its line was submitted or cancelled
+    unfinished: bool,
+    /// Byte offset where the selection started, if one is active
+    mark: Option<usize>,
+    completer: Option<Box<dyn Completer>>,
+    match_policy: MatchPolicy,
+    case_fold: completion::CaseFold,
+    /// Capacity the line buffer is shrunk to between lines
+    line_capacity_limit: Option<usize>,
+    hinter: Option<Box<dyn Hinter>>,
+    hint: Option<String>,
+    filter: Option<Box<dyn InputFilter>>,
+    corrector: Option<Box<dyn Corrector>>,
+    observer: Option<Box<dyn Observer>>,
+    submit_hook: Option<Box<dyn SubmitHook>>,
+    unknown_sequence: Option<UnknownSequenceHandler>,
+    retry: Option<Box<dyn RetryPolicy>>,
+    renderer: Option<Box<dyn Renderer>>,
+    theme: Theme,
+    #[cfg(feature = "metrics")]
+    metrics: core::cell::Cell<metrics::Metrics>,
+    #[cfg(feature = "metrics")]
+    metrics_clock: Option<fn() -> u64>,
+    status: String,
+    status_shown: bool,
+    keymap: Keymap,
+    overrides: Keymap,
+    commands: Vec<(KeyEvent, Box<dyn Command>)>,
+}
+
+/// What a key is bound to after resolving all layers.
+#[cfg(feature = "alloc")]
+enum Binding {
+    Action(Action),
+    /// Index into `LineEditor::commands`
+    Command(usize),
+}
+
+/// What the next key means to the read loop.
+#[cfg(feature = "alloc")]
+enum Mode {
+    /// Keys edit the line
+    Edit,
+    /// The next character is inserted as is (Ctrl+V), decoding a multi-byte
+    /// one in this
+    QuotedInsert(ansi::Decoder),
+    /// Keys edit an incremental history search
+    Search(Search),
+    /// Waiting for the answer to "did you mean ...?" after Enter
+    Correction { suggestion: String, width: usize },
+    /// Keys pick an entry from the history menu
+    HistoryMenu(HistoryMenu),
+}
+
+/// State of an incremental history search.
+#[cfg(feature = "alloc")]
+struct Search {
+    original: String,
+    query: String,
+    found: Option<usize>,
+    failed: bool,
+    forward: bool,
+    /// Display width of the search prompt and match
+    shown: usize,
+}
+
+/// State of the history menu.
+#[cfg(feature = "alloc")]
+struct HistoryMenu {
+    /// Indices of the entries starting with the line, newest first
+    matches: Vec<usize>,
+    /// Position of the selected entry in `matches`
+    selected: usize,
+    /// Rows the menu takes above the prompt
+    rows: usize,
 }
 
+/// Most entries the history menu shows at once.
+#[cfg(feature = "alloc")]
+const HISTORY_MENU_ROWS: usize = 10;
+
+#[cfg(feature = "alloc")]
 impl LineEditor {
     /// Creates a new line editor with the specified capacities.
     ///
@@ -833,6 +1728,57 @@ impl LineEditor {
         Self {
             line: LineBuffer::new(buffer_capacity),
             history: History::new(history_capacity),
+            ctrl_x_pending: false,
+            yanked_word: None,
+            mode: Mode::Edit,
+            polling: None,
+            typed_ahead: VecDeque::new(),
+            escape_timeout: None,
+            last_input: 0,
+            flow_control: false,
+            crlf_as_one: false,
+            pasted_newlines: PastedNewline::Submit,
+            after_cr: false,
+            pasting: false,
+            paste_shown: None,
+            clipboard: false,
+            copied: None,
+            paste_requested: false,
+            tab_width: 8,
+            auto_indent: false,
+            indent_openers: String::new(),
+            scroll_width: None,
+            accessible: false,
+            announcer: None,
+            echo: true,
+            prompt: String::new(),
+            origin: 0,
+            restored: false,
+            unfinished: false,
+            mark: None,
+            completer: None,
+            match_policy: MatchPolicy::Prefix,
+            case_fold: completion::unicode_fold,
+            line_capacity_limit: None,
+            hinter: None,
+            hint: None,
+            filter: None,
+            corrector: None,
+            observer: None,
+            submit_hook: None,
+            unknown_sequence: None,
+            retry: None,
+            renderer: None,
+            theme: Theme::new(),
+            #[cfg(feature = "metrics")]
+            metrics: core::cell::Cell::new(metrics::Metrics::default()),
+            #[cfg(feature = "metrics")]
+            metrics_clock: None,
+            status: String::new(),
+            status_shown: false,
+            keymap: Keymap::new(),
+            overrides: Keymap::new(),
+            commands: Vec::new(),
         }
     }
 
@@ -866,449 +1812,5258 @@ impl LineEditor {
     /// # Ok::<(), editline::Error>(())
     /// ```
     pub fn read_line<T: Terminal>(&mut self, terminal: &mut T) -> Result<String> {
-        self.line.clear();
-        terminal.enter_raw_mode()?;
+        self.read_line_with_prompt(terminal, "")
+    }
+
+    /// Writes `prompt` and reads a line, like [`read_line`](Self::read_line).
+    ///
+    /// Unlike printing the prompt yourself, the editor knows about it and can
+    /// repaint it when the line has to be redrawn from scratch, e.g. after
+    /// listing completions.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use editline::{LineEditor, terminals::StdioTerminal};
+    ///
+    /// let mut editor = LineEditor::new(1024, 50);
+    /// let mut terminal = StdioTerminal::new();
+    ///
+    /// let line = editor.read_line_with_prompt(&mut terminal, "> ")?;
+    /// # Ok::<(), editline::Error>(())
+    /// ```
+    pub fn read_line_with_prompt<T: Terminal>(
+        &mut self,
+        terminal: &mut T,
+        prompt: &str,
+    ) -> Result<String> {
+        self.read_line_with_bindings(terminal, prompt, &Keymap::new())
+    }
 
-        // Use a closure to ensure we always exit raw mode, even on error
+    fn read_line_inner<T: Terminal>(&mut self, terminal: &mut T) -> Result<String> {
+        let mut raw = RawModeGuard::new(terminal)?;
+        let mut policy = self.retry.take();
+        let terminal = &mut retry::Retrying::new(&mut *raw, policy.as_mut());
+        #[cfg(feature = "metrics")]
+        let terminal = &mut metrics::Counting::new(terminal);
+
+        // Use a closure so that errors still reach raw.exit() below; the guard
+        // covers panics
         let result = (|| {
+            self.begin(terminal)?;
+            self.render()?;
             loop {
-                let event = terminal.parse_key_event()?;
-
-                if event == KeyEvent::Enter {
-                    break;
+                let done = if let Mode::QuotedInsert(_) = self.mode {
+                    let byte = self.next_byte(terminal)?;
+                    self.timed(terminal, |editor, terminal| {
+                        editor.step_byte(terminal, byte)
+                    })?
+                } else {
+                    let event = self.read_key(terminal)?;
+                    self.timed(terminal, |editor, terminal| editor.step(terminal, event))?
+                };
+                if let Some(line) = done {
+                    return Ok(line);
                 }
-
-                self.handle_key_event(terminal, event)?;
+                self.render()?;
             }
+        })();
+        let off = self.bracket_paste(terminal, false);
+        let result = result.and_then(|line| off.map(|_| line));
+        self.retry = policy;
+        self.keep_line_on_error(&result);
 
-            // Platform-specific line ending
-            // Unix/Linux/macOS uses \n, but embedded serial terminals need \r\n
-            #[cfg(not(feature = "std"))]
-            terminal.write(b"\r\n")?;
-            #[cfg(feature = "std")]
-            terminal.write(b"\n")?;
-            terminal.flush()?;
+        // Always exit raw mode, even if an error occurred
+        raw.exit()?;
 
-            let result = self.line.as_str()?
-                .trim()
-                .to_string();
+        result
+    }
 
-            // Add to history (History::add will check if empty and skip duplicates)
-            self.history.add(&result);
-            self.history.reset_view();
+    /// Reads the next key, decoding bytes typed ahead of a terminal reply
+    /// before new input.
+    fn read_key<T: Terminal>(&mut self, terminal: &mut T) -> Result<KeyEvent> {
+        if self.typed_ahead.is_empty() {
+            return terminal.parse_key_event();
+        }
+        let mut decoder = ansi::Decoder::new();
+        loop {
+            if let Some(event) = decoder.push(self.next_byte(terminal)?) {
+                return Ok(event);
+            }
+        }
+    }
 
-            Ok(result)
-        })();
+    /// Reads the next byte, taking bytes typed ahead of a terminal reply first.
+    fn next_byte<T: Terminal>(&mut self, terminal: &mut T) -> Result<u8> {
+        match self.typed_ahead.pop_front() {
+            Some(byte) => Ok(byte),
+            None => terminal.read_byte(),
+        }
+    }
 
-        // Always exit raw mode, even if an error occurred
-        terminal.exit_raw_mode()?;
+    /// Keeps the line for the next read when the retry policy gave up on an
+    /// I/O error.
+    pub(crate) fn keep_line_on_error<R>(&mut self, result: &Result<R>) {
+        self.unfinished = matches!(result, Err(e) if *e != Error::Interrupted);
+        if self.retry.is_some() && matches!(result, Err(Error::Io(_))) {
+            self.restored = true;
+        }
+    }
 
+    /// Runs `step` for one key, recording it in the metrics.
+    #[cfg(feature = "metrics")]
+    fn timed<T: Terminal + ?Sized, R>(
+        &mut self,
+        terminal: &mut metrics::Counting<'_, T>,
+        step: impl FnOnce(&mut Self, &mut metrics::Counting<'_, T>) -> R,
+    ) -> R {
+        let watch = self.start_key(terminal);
+        let result = step(self, terminal);
+        self.end_key(watch, terminal);
         result
     }
 
-    fn handle_key_event<T: Terminal>(&mut self, terminal: &mut T, event: KeyEvent) -> Result<()> {
+    #[cfg(not(feature = "metrics"))]
+    fn timed<T: Terminal, R>(
+        &mut self,
+        terminal: &mut T,
+        step: impl FnOnce(&mut Self, &mut T) -> R,
+    ) -> R {
+        step(self, terminal)
+    }
+
+    /// Starts measuring one key, given the output counted so far.
+    #[cfg(feature = "metrics")]
+    pub(crate) fn start_key<T: ?Sized>(
+        &self,
+        terminal: &metrics::Counting<'_, T>,
+    ) -> metrics::Stopwatch {
+        metrics::Stopwatch::start(self.metrics_clock, terminal.written, terminal.writes)
+    }
+
+    /// Records a key measured since `watch` was started.
+    #[cfg(feature = "metrics")]
+    pub(crate) fn end_key<T: ?Sized>(
+        &self,
+        watch: metrics::Stopwatch,
+        terminal: &metrics::Counting<'_, T>,
+    ) {
+        let (time, bytes, writes) = watch.stop(terminal.written, terminal.writes);
+        let mut metrics = self.metrics.get();
+        metrics.record_key(time, bytes, writes);
+        self.metrics.set(metrics);
+    }
+
+    /// Counts a repaint in the metrics.
+    fn count_redraw(&self) {
+        #[cfg(feature = "metrics")]
+        {
+            let mut metrics = self.metrics.get();
+            metrics.redraws = metrics.redraws.saturating_add(1);
+            self.metrics.set(metrics);
+        }
+    }
+
+    /// Turns bracketed paste mode on or off, if pasted newlines are told apart
+    /// from typed ones.
+    pub(crate) fn bracket_paste<T: Terminal + ?Sized>(
+        &self,
+        terminal: &mut T,
+        on: bool,
+    ) -> Result<()> {
+        if self.pasted_newlines == PastedNewline::Submit || !terminal.capabilities().bracketed_paste
+        {
+            return Ok(());
+        }
+        if on {
+            terminal.write(newline::BRACKETED_PASTE_ON)
+        } else {
+            terminal.write(newline::BRACKETED_PASTE_OFF)?;
+            terminal.flush()
+        }
+    }
+
+    /// Applies the newline settings to a key, returning `None` for keys they
+    /// consume.
+    pub(crate) fn translate_newline(&mut self, event: KeyEvent) -> Option<KeyEvent> {
+        let after_cr = core::mem::replace(&mut self.after_cr, event == KeyEvent::Enter);
         match event {
-            KeyEvent::Normal(c) => {
-                self.history.reset_view();
-                self.line.insert_char(c);
-                terminal.write(c.to_string().as_bytes())?;
-                self.redraw_from_cursor(terminal)?;
+            KeyEvent::PasteStart | KeyEvent::PasteEnd => {
+                self.pasting = event == KeyEvent::PasteStart;
+                None
             }
-            KeyEvent::Left => {
-                if self.line.move_cursor_left() {
-                    terminal.cursor_left()?;
-                }
+            KeyEvent::LineFeed if after_cr && self.crlf_as_one => None,
+            KeyEvent::Enter | KeyEvent::LineFeed if self.pasting => match self.pasted_newlines {
+                PastedNewline::Submit => Some(event),
+                PastedNewline::Space => Some(KeyEvent::Normal(' ')),
+                PastedNewline::Literal => Some(KeyEvent::Normal('\n')),
+            },
+            _ => Some(event),
+        }
+    }
+
+    /// Resets the per-line state and draws the prompt, or the restored line.
+    fn begin<T: Terminal>(&mut self, terminal: &mut T) -> Result<()> {
+        if !self.restored {
+            self.line.clear();
+        }
+        if let Some(limit) = self.line_capacity_limit {
+            self.line.shrink_to(limit);
+        }
+        self.restored = false;
+        self.mark = None;
+        self.ctrl_x_pending = false;
+        self.yanked_word = None;
+        self.mode = Mode::Edit;
+        self.hint = None;
+        self.status_shown = false;
+        self.pasting = false;
+        self.paste_shown = None;
+
+        // The cursor position can only be queried in raw mode. Ask before
+        // drawing so the first paint goes out in one write on flush
+        self.origin = terminal
+            .get_cursor_position()
+            .map_or(0, |(column, _)| usize::from(column));
+        let terminal = &mut Batched::new(terminal);
+        self.bracket_paste(terminal, true)?;
+        if self.line.is_empty() {
+            self.write_prompt(terminal)?;
+        } else {
+            self.draw_line(terminal)?;
+        }
+        self.restore_status(terminal)?;
+        terminal.flush()
+    }
+
+    /// Processes one key, returning the line once it is submitted.
+    fn step<T: Terminal>(&mut self, terminal: &mut T, event: KeyEvent) -> Result<Option<String>> {
+        let mut done = self.step_key(terminal, event)?;
+        // Pasted keys go through step_key again, on the real terminal
+        if core::mem::replace(&mut self.paste_requested, false) && done.is_none() {
+            done = self.paste_clipboard(terminal)?;
+        }
+        self.send_copied(terminal)?;
+        Ok(done)
+    }
+
+    fn step_key<T: Terminal>(
+        &mut self,
+        terminal: &mut T,
+        event: KeyEvent,
+    ) -> Result<Option<String>> {
+        if self.unknown_sequence(&event) {
+            return Ok(None);
+        }
+        let event = match self.translate_newline(event) {
+            Some(event) => event,
+            None => return self.show_paste(terminal),
+        };
+        match core::mem::replace(&mut self.mode, Mode::Edit) {
+            Mode::Search(search) => return self.search_key(terminal, search, event),
+            Mode::HistoryMenu(menu) => return self.history_menu_key(terminal, menu, event),
+            Mode::Correction { suggestion, width } => {
+                return self.answer_correction(terminal, suggestion, width, event);
             }
-            KeyEvent::Right => {
-                if self.line.move_cursor_right() {
-                    terminal.cursor_right()?;
+            Mode::Edit | Mode::QuotedInsert(_) => {}
+        }
+
+        if !self.draws_after_step() && !self.wraps(terminal) && !self.pasting {
+            return match self.resolve(terminal, event)? {
+                Some(action) => self.step_action(terminal, action),
+                None => Ok(None),
+            };
+        }
+        let action = self.resolve(&mut NoEcho(terminal), event)?;
+        self.step_off_screen(terminal, action)
+    }
+
+    /// Whether edits are made off screen and shown afterwards, when scrolling
+    /// horizontally or in accessible mode.
+    fn draws_after_step(&self) -> bool {
+        self.scroll_width.is_some() || self.accessible
+    }
+
+    /// Whether the line reaches the last column, so that edits are repainted
+    /// from the prompt's first row instead of being made with relative cursor
+    /// moves.
+    ///
+    /// Terminals stop those moves at the margin rather than going to the row
+    /// above, so a deletion across a row boundary would leave the cursor out of
+    /// step with the line, and later edits would erase the prompt.
+    fn wraps<T: Terminal>(&self, terminal: &mut T) -> bool {
+        if self.draws_after_step() {
+            return false;
+        }
+        let columns = match terminal.size() {
+            Some((columns, _)) => usize::from(columns).max(1),
+            None => return false,
+        };
+        let end = self.line_origin()
+            + prompt_width(last_line(&self.prompt))
+            + display_width(self.line.as_bytes());
+        // Typing one more character reaches the margin
+        end + 1 >= columns
+    }
+
+    /// Carries out a resolved action off screen, then shows the result.
+    /// Actions drawing more than the line repaint through draw_line themselves.
+    fn step_off_screen<T: Terminal>(
+        &mut self,
+        terminal: &mut T,
+        action: Option<Action>,
+    ) -> Result<Option<String>> {
+        let deferred = self.paste_shown.is_some();
+        let (before, before_pos, before_column) = match self.paste_shown.take() {
+            Some(shown) => shown,
+            None => (
+                self.line.as_str()?.to_string(),
+                self.line.cursor_pos(),
+                self.cursor_column(),
+            ),
+        };
+        let done = match action {
+            Some(
+                action @ (Action::Complete
+                | Action::ClearScreen
+                | Action::ReverseSearch
+                | Action::ForwardSearch
+                | Action::HistoryMenu
+                | Action::ExternalEditor
+                | Action::Abort
+                | Action::Submit
+                | Action::Interrupt
+                | Action::EndOfInput),
+            ) => {
+                if deferred {
+                    self.show_change(terminal, &before, before_pos, before_column)?;
                 }
+                return self.step_action(terminal, action);
             }
-            KeyEvent::Up => {
-                let current = self.line.as_str().unwrap_or("").to_string();
-                if let Some(text) = self.history.previous(&current) {
-                    let text = text.to_string();
-                    self.load_history_into_line(terminal, &text)?;
-                }
+            Some(action) => self.step_action(&mut NoEcho(terminal), action)?,
+            None => None,
+        };
+        match self.mode {
+            // A paste is shown once it's complete, see show_paste
+            Mode::Edit if self.pasting && done.is_none() => {
+                self.paste_shown = Some((before, before_pos, before_column));
+                return Ok(None);
             }
-            KeyEvent::Down => {
-                if let Some(text) = self.history.next_entry() {
-                    let text = text.to_string();
-                    self.load_history_into_line(terminal, &text)?;
-                }
-                // If None, we're not viewing history, so do nothing
+            Mode::Edit => {
+                self.show_change(terminal, &before, before_pos, before_column)?;
+                self.show_hint(terminal)?;
             }
-            KeyEvent::Home => {
-                let count = self.line.move_cursor_to_start();
-                for _ in 0..count {
-                    terminal.cursor_left()?;
-                }
+            _ if deferred => self.show_change(terminal, &before, before_pos, before_column)?,
+            _ => {}
+        }
+        terminal.flush()?;
+        Ok(done)
+    }
+
+    /// Shows the edits a paste made off screen, with a single repaint, once the
+    /// paste has ended.
+    fn show_paste<T: Terminal>(&mut self, terminal: &mut T) -> Result<Option<String>> {
+        if self.pasting {
+            return Ok(None);
+        }
+        if let Some((before, before_pos, before_column)) = self.paste_shown.take() {
+            self.show_change(terminal, &before, before_pos, before_column)?;
+            self.show_hint(terminal)?;
+            terminal.flush()?;
+        }
+        Ok(None)
+    }
+
+    /// Shows an edit made off screen: repaints the window when scrolling, the
+    /// whole line when it [wraps](Self::wraps), or writes the change and
+    /// announces it in accessible mode. `before_column` is the
+    /// [`cursor_column`](Self::cursor_column) before the edit.
+    fn show_change<T: Terminal>(
+        &mut self,
+        terminal: &mut T,
+        before: &str,
+        before_pos: usize,
+        before_column: usize,
+    ) -> Result<()> {
+        if self.scroll_width.is_some() {
+            return self.draw_scrolled(terminal);
+        }
+        if !self.accessible {
+            // The repaint clears below the line, including the status
+            self.refresh_line(terminal, before_column)?;
+            self.status_shown = false;
+            return self.restore_status(terminal);
+        }
+        let (line, pos) = (self.line.as_str()?, self.line.cursor_pos());
+        let announcement = if line == before {
+            if pos == before_pos {
+                return Ok(());
             }
-            KeyEvent::End => {
-                let count = self.line.move_cursor_to_end();
-                for _ in 0..count {
-                    terminal.cursor_right()?;
-                }
+            accessibility::Announcement::Moved {
+                word: accessibility::word_at(line, pos),
             }
-            KeyEvent::Backspace => {
-                self.history.reset_view();
-                if self.line.delete_before_cursor() {
-                    terminal.cursor_left()?;
-                    self.redraw_from_cursor(terminal)?;
-                }
+        } else {
+            let (start, removed, inserted) = accessibility::difference(before, line);
+            let at_end = before_pos == before.len() && pos == line.len();
+            if at_end && removed == before.len() && removed == start {
+                // Typed at the end
+                return write_visible(terminal, &line.as_bytes()[start..]);
             }
-            KeyEvent::Delete => {
-                self.history.reset_view();
-                if self.line.delete_at_cursor() {
-                    self.redraw_from_cursor(terminal)?;
+            if at_end && inserted == line.len() && inserted == start {
+                for _ in 0..display_width(&before.as_bytes()[start..]) {
+                    terminal.write(b"\x08 \x08")?;
                 }
+            } else {
+                write_newline(terminal)?;
+                write_prompt(terminal, last_line(&self.prompt), self.theme.prompt)?;
+                write_visible(terminal, line.as_bytes())?;
             }
-            KeyEvent::CtrlLeft => {
-                let count = self.line.move_cursor_word_left();
-                for _ in 0..count {
-                    terminal.cursor_left()?;
+            if start == inserted {
+                accessibility::Announcement::Deleted(&before[start..removed])
+            } else {
+                accessibility::Announcement::Changed {
+                    line,
+                    word: accessibility::word_at(line, pos),
                 }
             }
-            KeyEvent::CtrlRight => {
-                let count = self.line.move_cursor_word_right();
-                for _ in 0..count {
-                    terminal.cursor_right()?;
+        };
+        if let Some(announcer) = self.announcer.as_mut() {
+            announcer.announce(&announcement);
+        }
+        Ok(())
+    }
+
+    /// Looks up what `event` is bound to, running a bound command right away.
+    ///
+    /// Returns the action left to carry out, if any.
+    fn resolve<T: Terminal>(
+        &mut self,
+        terminal: &mut T,
+        event: KeyEvent,
+    ) -> Result<Option<Action>> {
+        Ok(match self.key_binding(event) {
+            Some(Binding::Action(action)) => Some(action),
+            Some(Binding::Command(index)) => match self.run_command(terminal, index)? {
+                HandlerResult::Continue => None,
+                HandlerResult::Submit => Some(Action::Submit),
+                HandlerResult::Interrupt => Some(Action::Interrupt),
+            },
+            None => None,
+        })
+    }
+
+    /// Carries out a resolved action, returning the line once it is submitted.
+    fn step_action<T: Terminal>(
+        &mut self,
+        terminal: &mut T,
+        action: Action,
+    ) -> Result<Option<String>> {
+        match action {
+            Action::Submit => {
+                self.clear_hint(terminal)?;
+                self.drop_selection(terminal)?;
+                if self.offer_correction(terminal)? {
+                    return Ok(None);
                 }
+                self.finish(terminal).map(Some)
             }
-            KeyEvent::AltBackspace => {
-                self.history.reset_view();
-                let count = self.line.delete_word_left();
-                for _ in 0..count {
-                    terminal.cursor_left()?;
-                }
-                self.redraw_from_cursor(terminal)?;
+            Action::Interrupt => {
+                self.hide_status(terminal)?;
+                Err(Error::Interrupted)
             }
-            KeyEvent::CtrlDelete => {
-                self.history.reset_view();
-                self.line.delete_word_right();
-                self.redraw_from_cursor(terminal)?;
+            Action::EndOfInput => {
+                self.hide_status(terminal)?;
+                Err(Error::Eof)
+            }
+            Action::PasteClipboard => {
+                self.paste_requested = self.clipboard;
+                Ok(None)
+            }
+            _ => {
+                self.perform(terminal, action)?;
+                Ok(None)
             }
-            KeyEvent::Enter => {}
         }
-
-        terminal.flush()?;
-        Ok(())
     }
 
-    fn redraw_from_cursor<T: Terminal>(&self, terminal: &mut T) -> Result<()> {
-        terminal.clear_eol()?;
-
-        let cursor_pos = self.line.cursor_pos();
-        let remaining = &self.line.as_bytes()[cursor_pos..];
-        terminal.write(remaining)?;
-
-        // Move cursor back
-        for _ in 0..remaining.len() {
-            terminal.cursor_left()?;
+    /// Inserts the clipboard contents, read with an OSC 52 query, as a
+    /// bracketed paste would.
+    fn paste_clipboard<T: Terminal>(&mut self, terminal: &mut T) -> Result<Option<String>> {
+        let typed_ahead = &mut self.typed_ahead;
+        let text = match ansi::query_clipboard(terminal, |byte| typed_ahead.push_back(byte))? {
+            Some(text) => text,
+            None => return Ok(None),
+        };
+        let keys = text.chars().map(|c| match c {
+            '\r' => KeyEvent::Enter,
+            '\n' => KeyEvent::LineFeed,
+            c => KeyEvent::Normal(c),
+        });
+        let events = core::iter::once(KeyEvent::PasteStart)
+            .chain(keys)
+            .chain(core::iter::once(KeyEvent::PasteEnd));
+        for event in events {
+            if let Some(line) = self.step_key(terminal, event)? {
+                return Ok(Some(line));
+            }
         }
+        Ok(None)
+    }
 
-        Ok(())
+    /// Keeps deleted text for the clipboard, if enabled. It is sent once the
+    /// key has been handled, since edits may be made off screen.
+    fn copy(&mut self, text: &str) {
+        if self.clipboard && !text.is_empty() {
+            self.copied = Some(text.to_string());
+        }
     }
 
-    fn clear_line_display<T: Terminal>(&self, terminal: &mut T) -> Result<()> {
-        for _ in 0..self.line.cursor_pos() {
-            terminal.cursor_left()?;
+    /// Sends deleted text to the clipboard.
+    fn send_copied<T: Terminal>(&mut self, terminal: &mut T) -> Result<()> {
+        if let Some(text) = self.copied.take() {
+            ansi::copy_to_clipboard(terminal, &text)?;
+            terminal.flush()?;
         }
-        terminal.clear_eol()?;
         Ok(())
     }
 
-    fn load_history_into_line<T: Terminal>(&mut self, terminal: &mut T, text: &str) -> Result<()> {
-        self.clear_line_display(terminal)?;
-        self.line.load(text);
-        terminal.write(text.as_bytes())?;
-        Ok(())
+    /// Inserts the character following Ctrl+V, one byte at a time.
+    fn step_byte<T: Terminal>(&mut self, terminal: &mut T, byte: u8) -> Result<Option<String>> {
+        let mut decoder = match core::mem::replace(&mut self.mode, Mode::Edit) {
+            Mode::QuotedInsert(decoder) => decoder,
+            _ => ansi::Decoder::new(),
+        };
+        // Control bytes are taken as is, other characters once complete
+        let c = if byte.is_ascii() {
+            Some(byte as char)
+        } else {
+            match decoder.push(byte) {
+                Some(KeyEvent::Normal(c)) => Some(c),
+                Some(_) => None,
+                None => {
+                    self.mode = Mode::QuotedInsert(decoder);
+                    return Ok(None);
+                }
+            }
+        };
+        if let Some(c) = c {
+            if self.draws_after_step() || self.wraps(terminal) {
+                let (before, before_pos) =
+                    (self.line.as_str()?.to_string(), self.line.cursor_pos());
+                let before_column = self.cursor_column();
+                self.insert_char(&mut NoEcho(terminal), c)?;
+                self.show_change(terminal, &before, before_pos, before_column)?;
+            } else {
+                self.insert_char(terminal, c)?;
+            }
+        }
+        self.show_hint(terminal)?;
+        terminal.flush()?;
+        Ok(None)
     }
-}
 
-// Re-export terminal implementations
-#[cfg(any(feature = "std", feature = "microbit", feature = "rp_pico_usb", feature = "rp_pico2_usb"))]
-pub mod terminals;
+    /// Moves past the line and adds it to the history.
+    fn finish<T: Terminal>(&mut self, terminal: &mut T) -> Result<String> {
+        self.hide_status(terminal)?;
+        write_newline(terminal)?;
+        terminal.flush()?;
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        let mut result = self.line.as_str()?.trim().to_string();
+        if let Some(hook) = self.submit_hook.as_mut() {
+            hook.on_submit(&mut result);
+        }
 
-    // LineBuffer tests
-    #[test]
-    fn test_line_buffer_insert() {
-        let mut buf = LineBuffer::new(100);
-        buf.insert_char('h');
-        buf.insert_char('i');
-        assert_eq!(buf.as_str().unwrap(), "hi");
-        assert_eq!(buf.cursor_pos(), 2);
-        assert_eq!(buf.len(), 2);
+        // Add to history (History::add will check if empty and skip duplicates)
+        if self
+            .observer
+            .as_mut()
+            .map_or(true, |observer| observer.on_submit(&result))
+        {
+            self.history.add(&result);
+        }
+        self.history.reset_view();
+
+        if let Some(renderer) = self.renderer.as_mut().filter(|_| self.echo) {
+            renderer.finish(&result)?;
+        }
+
+        Ok(result)
     }
 
-    #[test]
-    fn test_line_buffer_backspace() {
-        let mut buf = LineBuffer::new(100);
-        buf.insert_char('h');
-        buf.insert_char('i');
-        assert!(buf.delete_before_cursor());
-        assert_eq!(buf.as_str().unwrap(), "h");
+    /// Reads a line with extra key bindings that apply to this call only.
+    ///
+    /// `bindings` take precedence over [`bind`](Self::bind) and the defaults and
+    /// are dropped when the call returns, e.g. to make Tab accept a suggestion
+    /// during one wizard prompt. See [`keymap`] for how keys are resolved.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use editline::{terminals::StdioTerminal, Action, KeyEvent, Keymap, LineEditor};
+    ///
+    /// let mut editor = LineEditor::new(1024, 50);
+    /// let mut terminal = StdioTerminal::new();
+    ///
+    /// let mut wizard = Keymap::new();
+    /// wizard.bind(KeyEvent::Tab, Action::AcceptHint);
+    /// let name = editor.read_line_with_bindings(&mut terminal, "Project name: ", &wizard)?;
+    /// # Ok::<(), editline::Error>(())
+    /// ```
+    pub fn read_line_with_bindings<T: Terminal>(
+        &mut self,
+        terminal: &mut T,
+        prompt: &str,
+        bindings: &Keymap,
+    ) -> Result<String> {
+        self.prompt.clear();
+        self.prompt.push_str(prompt);
+        self.overrides.clone_from(bindings);
+
+        let result = if self.echoes() {
+            self.read_line_inner(terminal)
+        } else {
+            self.read_line_inner(&mut NoEcho(terminal))
+        };
+        self.overrides.clear();
+        result
+    }
+
+    /// Reads a line without blocking, for firmware main loops without async.
+    ///
+    /// Each call handles the input that is available right now, read with
+    /// [`Terminal::try_read_byte`], and returns [`Poll::Pending`] when there is
+    /// no more, so the loop can get on with other work in between. The first call
+    /// enters raw mode and shows the prompt; once the line is submitted (or
+    /// reading fails) the result is returned as `Poll::Ready`, raw mode is left,
+    /// and the next call starts a new line.
+    ///
+    /// Input is decoded as ANSI/VT100 bytes, which suits serial and USB
+    /// backends and Unix terminals. The Windows console delivers key records
+    /// instead, so there the first call returns
+    /// [`IoCause::Unsupported`]; use [`read_line`](Self::read_line) on a
+    /// thread of its own.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use core::task::Poll;
+    /// use editline::{LineEditor, Result, Terminal};
+    ///
+    /// fn superloop<T: Terminal>(terminal: &mut T) -> Result<()> {
+    ///     let mut editor = LineEditor::new(1024, 50);
+    ///     loop {
+    ///         if let Poll::Ready(line) = editor.poll_read_line(terminal, "> ") {
+    ///             let line = line?;
+    ///             // run the command
+    ///         }
+    ///         // blink LEDs, service sensors, ...
+    ///     }
+    /// }
+    /// ```
+    pub fn poll_read_line<T: Terminal>(
+        &mut self,
+        terminal: &mut T,
+        prompt: &str,
+    ) -> Poll<Result<String>> {
+        let mut policy = self.retry.take();
+        let retrying = &mut retry::Retrying::new(&mut *terminal, policy.as_mut());
+        let step = if self.echoes() {
+            self.poll_line(retrying, prompt)
+        } else {
+            self.poll_line(&mut NoEcho(retrying), prompt)
+        };
+        self.retry = policy;
+        self.keep_line_on_error(&step);
+        let result = match step {
+            Ok(Some(line)) => Ok(line),
+            Ok(None) => return Poll::Pending,
+            Err(e) => Err(e),
+        };
+
+        self.polling = None;
+        let off = self.bracket_paste(terminal, false);
+        let exited = terminal.exit_raw_mode();
+        Poll::Ready(result.and_then(|line| off.and(exited).map(|_| line)))
+    }
+
+    /// Handles the available input of a polled line, returning it once submitted.
+    fn poll_line<T: Terminal>(&mut self, terminal: &mut T, prompt: &str) -> Result<Option<String>> {
+        #[cfg(feature = "metrics")]
+        let terminal = &mut metrics::Counting::new(terminal);
+        let mut decoder = match self.polling.take() {
+            Some(decoder) => decoder,
+            None => {
+                self.prompt.clear();
+                self.prompt.push_str(prompt);
+                self.overrides.clear();
+                terminal.enter_raw_mode()?;
+                self.begin(terminal)?;
+                self.render()?;
+                ansi::Decoder::new()
+            }
+        };
+
+        while let Some(byte) = self.poll_byte(terminal)? {
+            let done = if let Mode::QuotedInsert(_) = self.mode {
+                self.timed(terminal, |editor, terminal| {
+                    editor.step_byte(terminal, byte)
+                })?
+            } else {
+                match decoder.push(byte) {
+                    Some(event) => {
+                        self.timed(terminal, |editor, terminal| editor.step(terminal, event))?
+                    }
+                    None => None,
+                }
+            };
+            if done.is_some() {
+                return Ok(done);
+            }
+            self.render()?;
+            if let Some((_, clock)) = self.escape_timeout {
+                self.last_input = clock();
+            }
+        }
+
+        // No more input after ESC ESC means it was Escape
+        let expired = decoder.is_ambiguous()
+            || self.escape_timeout.map_or(false, |(timeout, clock)| {
+                decoder.is_pending() && clock().wrapping_sub(self.last_input) >= timeout
+            });
+        if expired {
+            if let Some(event) = decoder.expire() {
+                let done = self.timed(terminal, |editor, terminal| editor.step(terminal, event))?;
+                if done.is_some() {
+                    return Ok(done);
+                }
+                self.render()?;
+            }
+        }
+        self.polling = Some(decoder);
+        Ok(None)
+    }
+
+    /// Takes a byte typed ahead of a terminal reply, or polls for new input.
+    fn poll_byte<T: Terminal>(&mut self, terminal: &mut T) -> Result<Option<u8>> {
+        match self.typed_ahead.pop_front() {
+            Some(byte) => Ok(Some(byte)),
+            None => terminal.try_read_byte(),
+        }
+    }
+
+    /// Sets aside a line being read with [`poll_read_line`](Self::poll_read_line),
+    /// leaving it on the screen.
+    ///
+    /// The cursor moves to a new row and raw mode is left, so the terminal can
+    /// be handed to something else, such as another console of a
+    /// [`ConsoleMux`](mux::ConsoleMux). The line, its cursor and the history
+    /// position are kept: the next read draws the prompt and the line again
+    /// and editing carries on. Does nothing between lines.
+    pub fn suspend<T: Terminal>(&mut self, terminal: &mut T) -> Result<()> {
+        if self.polling.take().is_none() {
+            return Ok(());
+        }
+        self.restored = true;
+        if self.echoes() {
+            self.leave_line(terminal)
+        } else {
+            self.leave_line(&mut NoEcho(terminal))
+        }
+    }
+
+    /// Moves below a suspended line and leaves raw mode.
+    fn leave_line<T: Terminal>(&mut self, terminal: &mut T) -> Result<()> {
+        self.clear_hint(terminal)?;
+        self.hide_status(terminal)?;
+        write_newline(terminal)?;
+        let off = self.bracket_paste(terminal, false);
+        let exited = terminal.exit_raw_mode();
+        off.and(exited)?;
+        terminal.flush()
+    }
+
+    /// Lets go of a terminal whose transport went away, such as an unplugged
+    /// USB cable or a dropped connection, without writing to it.
+    ///
+    /// Call it after a read failed, or while a line is being polled. As with
+    /// [`suspend`](Self::suspend), the unfinished line, its cursor and the
+    /// history position are kept, and the next read, typically on the
+    /// reattached terminal, draws the prompt and the line again. A line that
+    /// was submitted, or cancelled with Ctrl+C, isn't brought back. See [`session`] for a wrapper that
+    /// detaches by itself.
+    pub fn detach(&mut self) {
+        if self.polling.take().is_some() || self.unfinished {
+            self.restored = true;
+        }
+    }
+
+    /// Gives up on an escape sequence whose next byte hasn't arrived within
+    /// `timeout` ticks of `clock`, in [`poll_read_line`](Self::poll_read_line).
+    ///
+    /// By default the editor waits for the rest of a sequence indefinitely, so
+    /// ESC alone only counts once a second ESC follows. With a timeout, a lone
+    /// ESC is reported as [`KeyEvent::Escape`] by the first poll after it
+    /// expires; see [`ansi::Decoder::expire`]. Blocking reads leave this to
+    /// the terminal's [`parse_key_event`](Terminal::parse_key_event), e.g.
+    /// `StdioTerminal::set_escape_timeout` on Unix.
+    pub fn set_escape_timeout(&mut self, timeout: u64, clock: fn() -> u64) {
+        self.escape_timeout = Some((timeout, clock));
+    }
+
+    /// Binds `key` to `action` for all subsequent reads.
+    ///
+    /// Replaces the default action of the key, and any command bound to it;
+    /// bind to [`Action::Ignore`] to disable a key.
+    pub fn bind(&mut self, key: KeyEvent, action: Action) {
+        self.commands.retain(|(k, _)| *k != key);
+        self.keymap.bind(key, action);
+    }
+
+    /// Binds `key` to an application [`Command`], replacing any previous binding.
+    ///
+    /// See the [`command`] module.
+    pub fn bind_command<C: Command + 'static>(&mut self, key: KeyEvent, command: C) {
+        self.keymap.unbind(key);
+        self.commands.retain(|(k, _)| *k != key);
+        self.commands.push((key, Box::new(command)));
+    }
+
+    /// Removes a binding made with [`bind`](Self::bind) or
+    /// [`bind_command`](Self::bind_command), restoring the default.
+    pub fn unbind(&mut self, key: KeyEvent) {
+        self.commands.retain(|(k, _)| *k != key);
+        self.keymap.unbind(key);
+    }
+
+    /// Edits multi-line text on the alternate screen.
+    ///
+    /// Meant for input that doesn't fit comfortably on one line, such as a
+    /// configuration blob. `initial` is loaded for editing; Enter inserts a line
+    /// break, the arrow keys move through the text, Ctrl+D finishes and Ctrl+C
+    /// cancels with [`Error::Interrupted`]. Afterwards the main screen is restored
+    /// and the prompt and final text are echoed there, so they stay in the
+    /// scrollback. The text is returned as typed, without trimming, and is not
+    /// added to the history.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use editline::{terminals::StdioTerminal, LineEditor};
+    ///
+    /// let mut editor = LineEditor::new(1024, 50);
+    /// let mut terminal = StdioTerminal::new();
+    ///
+    /// let config = editor.read_line_fullscreen(&mut terminal, "config", "baud=115200\n")?;
+    /// # Ok::<(), editline::Error>(())
+    /// ```
+    pub fn read_line_fullscreen<T: Terminal>(
+        &mut self,
+        terminal: &mut T,
+        prompt: &str,
+        initial: &str,
+    ) -> Result<String> {
+        self.line.load(initial);
+        let mut raw = RawModeGuard::new(terminal)?;
+        let terminal = &mut *raw;
+
+        let result = terminal.enter_alternate_screen().and_then(|_| {
+            let indent = Some(fullscreen::Indent {
+                openers: &self.indent_openers,
+                width: self.tab_width,
+            })
+            .filter(|_| self.auto_indent);
+            let edited = fullscreen::Screen::new(
+                &mut self.line,
+                prompt,
+                self.theme,
+                self.crlf_as_one,
+                indent,
+            )
+            .run(terminal);
+            terminal.exit_alternate_screen().and(edited)
+        });
+        let result = result.and_then(|_| {
+            let text = self.line.as_str()?.to_string();
+            write_prompt(terminal, prompt, self.theme.prompt)?;
+            for line in text.split('\n') {
+                write_visible(terminal, line.as_bytes())?;
+                write_newline(terminal)?;
+            }
+            terminal.flush()?;
+            Ok(text)
+        });
+
+        raw.exit()?;
+        result
+    }
+
+    /// Returns an iterator over raw key events instead of reading a line.
+    ///
+    /// Raw mode is entered on the first call to `next` and restored when the
+    /// iterator is dropped. See the [`events`] module.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use editline::{terminals::StdioTerminal, KeyEvent, LineEditor};
+    ///
+    /// let mut editor = LineEditor::new(1024, 50);
+    /// let mut terminal = StdioTerminal::new();
+    ///
+    /// for event in editor.events(&mut terminal) {
+    ///     match event? {
+    ///         KeyEvent::Normal('q') => break,
+    ///         other => println!("{:?}\r", other),
+    ///     }
+    /// }
+    /// # Ok::<(), editline::Error>(())
+    /// ```
+    pub fn events<'a, T: Terminal>(&'a mut self, terminal: &'a mut T) -> events::Events<'a, T> {
+        events::Events::new(terminal)
+    }
+
+    /// Returns a [`Stream`](futures_core::Stream) of raw key events from an
+    /// async terminal, like [`events`](Self::events).
+    #[cfg(feature = "async")]
+    pub fn event_stream<'a, T: asynch::AsyncTerminal + ?Sized>(
+        &'a mut self,
+        terminal: &'a mut T,
+    ) -> events::EventStream<'a, T> {
+        events::EventStream::new(terminal)
+    }
+
+    /// Treats Ctrl+S and Ctrl+Q as XOFF/XON software flow control.
+    ///
+    /// When enabled, the editor ignores these bytes instead of starting a forward
+    /// history search, for serial links where the peer uses them for flow control.
+    /// Disabled by default. On Unix, `StdioTerminal::set_flow_control` additionally
+    /// leaves flow control to the tty driver.
+    pub fn set_flow_control(&mut self, enabled: bool) {
+        self.flow_control = enabled;
+    }
+
+    /// Treats an LF right after a CR as part of the same Enter, for hosts
+    /// sending CRLF. Disabled by default, so each of them submits a line.
+    ///
+    /// See the [`newline`] module.
+    pub fn set_crlf_as_one_enter(&mut self, on: bool) {
+        self.crlf_as_one = on;
+    }
+
+    /// Sets what a newline inside pasted text does. The default,
+    /// [`PastedNewline::Submit`], treats it like Enter.
+    ///
+    /// See the [`newline`] module.
+    pub fn set_pasted_newlines(&mut self, policy: PastedNewline) {
+        self.pasted_newlines = policy;
+    }
+
+    /// Sets the distance between tab stops. The default is 8.
+    ///
+    /// Without a completer, Tab inserts spaces up to the next tab stop, counted
+    /// from the start of the line; a tab character's width would depend on the
+    /// terminal. A width of 0 makes Tab do nothing.
+    pub fn set_tab_width(&mut self, width: usize) {
+        self.tab_width = width;
+    }
+
+    /// Starts each new line of [`read_line_fullscreen`](Self::read_line_fullscreen)
+    /// text with the leading whitespace of the line before. Off by default.
+    ///
+    /// That is the multi-line mode, where Enter keeps editing. Backspace right
+    /// after leading spaces then removes them a level at a time, a level being
+    /// [`tab_width`](Self::set_tab_width) spaces; see
+    /// [`set_indent_openers`](Self::set_indent_openers) for adding levels.
+    pub fn set_auto_indent(&mut self, on: bool) {
+        self.auto_indent = on;
+    }
+
+    /// Sets the characters after which auto-indent indents the next line one
+    /// level further, such as `"{:"`. None by default.
+    ///
+    /// ```
+    /// use editline::LineEditor;
+    ///
+    /// let mut editor = LineEditor::new(1024, 50);
+    /// editor.set_auto_indent(true);
+    /// editor.set_tab_width(4);
+    /// editor.set_indent_openers("{:");
+    /// ```
+    pub fn set_indent_openers(&mut self, openers: &str) {
+        self.indent_openers.clear();
+        self.indent_openers.push_str(openers);
+    }
+
+    /// Keeps the line on one row of `width` columns, scrolling it sideways,
+    /// instead of wrapping long lines onto further rows. `None`, the default,
+    /// wraps.
+    ///
+    /// This suits character LCDs and narrow consoles, where a wrapped line
+    /// would push the rest of the display away. The part of the line around
+    /// the cursor is shown after the prompt, with `<` and `>` marking text cut
+    /// off to the left and right; the last column is left empty. Hints aren't
+    /// shown in this mode, and the incremental search status may still wrap.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use editline::{LineEditor, terminals::StdioTerminal};
+    ///
+    /// let mut editor = LineEditor::new(256, 10);
+    /// // A 20x4 character display
+    /// editor.set_horizontal_scroll(Some(20));
+    /// let line = editor.read_line_with_prompt(&mut StdioTerminal::new(), "> ")?;
+    /// # Ok::<(), editline::Error>(())
+    /// ```
+    pub fn set_horizontal_scroll(&mut self, width: Option<usize>) {
+        self.scroll_width = width;
+    }
+
+    /// Returns the key, output and redraw totals collected so far.
+    ///
+    /// See the [`metrics`] module.
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> metrics::Metrics {
+        self.metrics.get()
+    }
+
+    /// Sets all metrics back to zero.
+    #[cfg(feature = "metrics")]
+    pub fn reset_metrics(&mut self) {
+        self.metrics.set(metrics::Metrics::default());
+    }
+
+    /// Sets the clock key processing times are measured with, as a function
+    /// returning a free-running tick count, e.g. a hardware timer.
+    #[cfg(feature = "metrics")]
+    pub fn set_metrics_clock(&mut self, clock: fn() -> u64) {
+        self.metrics_clock = Some(clock);
+    }
+
+    /// Switches to output that never moves the cursor back, for screen readers
+    /// and braille displays, or back to normal editing.
+    ///
+    /// May be changed at any time, e.g. from a bound command. See the
+    /// [`accessibility`] module.
+    pub fn set_accessible(&mut self, enabled: bool) {
+        self.accessible = enabled;
+    }
+
+    /// Installs a receiver for announcements of edits in accessible mode.
+    ///
+    /// See the [`accessibility`] module.
+    pub fn set_announcer<A: Announcer + 'static>(&mut self, announcer: A) {
+        self.announcer = Some(Box::new(announcer));
+    }
+
+    /// Copies deleted words and selections to the system clipboard, and makes
+    /// Ctrl+Y ([`Action::PasteClipboard`]) paste from it. Off by default.
+    ///
+    /// Both go through OSC 52 escape sequences, which many terminal emulators
+    /// understand, also over SSH, without platform clipboard libraries.
+    /// Reading the clipboard is often disabled or needs the user's permission
+    /// in the terminal, and Ctrl+Y waits for its reply with
+    /// [`Terminal::read_byte`], so the paste key only suits terminals known to
+    /// answer and blocking reads; async editors ignore it.
+    pub fn set_clipboard(&mut self, enabled: bool) {
+        self.clipboard = enabled;
+    }
+
+    /// Limits input lines to `max` bytes, or lifts the limit with `None`.
+    ///
+    /// Keys that would make the line longer ring the terminal bell instead, and
+    /// recalled history entries or completions that don't fit are truncated,
+    /// also with a bell. See [`LineBuffer::set_max_len`].
+    pub fn set_max_line_length(&mut self, max: Option<usize>) {
+        self.line.set_max_len(max);
+    }
+
+    /// Gives back line buffer memory beyond `limit` bytes when the next line
+    /// starts, or keeps whatever the buffer grew to with `None`, the default.
+    ///
+    /// The buffer grows to fit the longest line typed or pasted and normally
+    /// keeps that capacity. With a limit, a single large paste only costs its
+    /// memory until the following read, which bounds what long-running firmware
+    /// holds between lines. Unlike [`set_max_line_length`](Self::set_max_line_length),
+    /// it doesn't restrict what can be typed.
+    pub fn set_line_capacity_limit(&mut self, limit: Option<usize>) {
+        self.line_capacity_limit = limit;
+    }
+
+    /// Limits the history to `max` bytes of text, or lifts the limit with
+    /// `None`. See [`History::set_max_bytes`].
+    pub fn set_history_max_bytes(&mut self, max: Option<usize>) {
+        self.history.set_max_bytes(max);
+    }
+
+    /// Enables or disables all output while editing.
+    ///
+    /// With echo disabled, keys are still edited into the buffer as usual but
+    /// nothing is written to the terminal: no typed characters, redraws, or the
+    /// final line ending. Use this when the remote side echoes input itself (some
+    /// modem/AT-command and telnet setups) to avoid doubled characters. Enabled
+    /// by default.
+    pub fn set_echo(&mut self, enabled: bool) {
+        self.echo = enabled;
+    }
+
+    /// Installs a completer invoked when Tab is pressed.
+    ///
+    /// See the [`completion`] module for how candidates are applied.
+    pub fn set_completer<C: Completer + 'static>(&mut self, completer: C) {
+        self.completer = Some(Box::new(completer));
+    }
+
+    /// Installs a hinter whose suggestion is shown after the end of the line.
+    ///
+    /// See the [`hint`] module for how hints are displayed and accepted.
+    pub fn set_hinter<H: Hinter + 'static>(&mut self, hinter: H) {
+        self.hinter = Some(Box::new(hinter));
+    }
+
+    /// Installs a filter that rewrites typed characters before insertion.
+    ///
+    /// See the [`filter`] module.
+    pub fn set_input_filter<F: InputFilter + 'static>(&mut self, filter: F) {
+        self.filter = Some(Box::new(filter));
+    }
+
+    /// Installs a corrector consulted when a line is submitted.
+    ///
+    /// See the [`correction`] module.
+    pub fn set_corrector<C: Corrector + 'static>(&mut self, corrector: C) {
+        self.corrector = Some(Box::new(corrector));
+    }
+
+    /// Installs an observer told about submitted lines, history recalls and
+    /// typed characters.
+    ///
+    /// See the [`observer`] module.
+    pub fn set_observer<O: Observer + 'static>(&mut self, observer: O) {
+        self.observer = Some(Box::new(observer));
+    }
+
+    /// Installs a hook rewriting each submitted line before it is added to the
+    /// history and returned.
+    ///
+    /// See the [`submit`] module.
+    pub fn set_submit_hook<H: SubmitHook + 'static>(&mut self, hook: H) {
+        self.submit_hook = Some(Box::new(hook));
+    }
+
+    /// Installs a handler given the bytes of each input sequence that isn't a
+    /// known key, e.g. to log escape sequences from an unusual terminal.
+    ///
+    /// Such input is dropped either way; without a handler it is dropped
+    /// silently. [`diagnostics::key_probe`] shows what a terminal sends
+    /// interactively.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use editline::LineEditor;
+    ///
+    /// let mut editor = LineEditor::new(1024, 50);
+    /// editor.set_unknown_sequence_handler(|sequence: &[u8]| {
+    ///     let _ = sequence; // e.g. log it
+    /// });
+    /// ```
+    pub fn set_unknown_sequence_handler<F: FnMut(&[u8]) + 'static>(&mut self, handler: F) {
+        self.unknown_sequence = Some(Box::new(handler));
+    }
+
+    /// Passes an unknown sequence to the handler, returning `true` if `event`
+    /// is one.
+    pub(crate) fn unknown_sequence(&mut self, event: &KeyEvent) -> bool {
+        let sequence = match event {
+            KeyEvent::Unknown(sequence) => sequence,
+            _ => return false,
+        };
+        if let Some(handler) = self.unknown_sequence.as_mut() {
+            handler(sequence.as_bytes());
+        }
+        true
+    }
+
+    /// Installs a policy retrying terminal operations that fail, e.g. on a
+    /// congested USB link.
+    ///
+    /// See the [`retry`] module.
+    pub fn set_retry_policy<P: RetryPolicy + 'static>(&mut self, policy: P) {
+        self.retry = Some(Box::new(policy));
+    }
+
+    /// Sets the colors used for the prompt, hints, search matches, completion
+    /// lists and error indicators.
+    ///
+    /// The default [`Theme`] writes no color escape sequences. Styles are
+    /// written as ANSI SGR sequences, and only to terminals whose
+    /// [`capabilities`](Terminal::capabilities) include colors.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    /// Returns the current theme.
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    /// Sets a status line shown below the input while a line is being read.
+    ///
+    /// Useful for mode indicators or short help. The text is written as given,
+    /// so it may contain color sequences but should fit on one row; see
+    /// [`ansi::strip_escapes`] for text that shouldn't. An empty
+    /// string removes the status line. Takes effect on the next read; features
+    /// that use the status line themselves (such as incremental search) restore
+    /// it when they finish. Terminals that can't address the row below show
+    /// nothing (see [`Terminal::show_status`]).
+    pub fn set_status(&mut self, text: &str) {
+        self.status.clear();
+        self.status.push_str(text);
+    }
+
+    /// Presents the line with `renderer` instead of drawing it on the terminal.
+    ///
+    /// Nothing is written to the terminal while a line is read; keys still come
+    /// from it. See the [`render`] module. Nothing is rendered while echo is
+    /// off.
+    pub fn set_renderer<R: Renderer + 'static>(&mut self, renderer: R) {
+        self.renderer = Some(Box::new(renderer));
+    }
+
+    /// Whether editing is drawn on the terminal.
+    fn echoes(&self) -> bool {
+        self.echo && self.renderer.is_none()
+    }
+
+    /// Hands the current state to the renderer, if one is installed.
+    fn render(&mut self) -> Result<()> {
+        let mut renderer = match self.renderer.take() {
+            Some(renderer) if self.echo => renderer,
+            renderer => {
+                self.renderer = renderer;
+                return Ok(());
+            }
+        };
+        let (line, cursor, search) = match &self.mode {
+            Mode::Search(search) => {
+                let found = search.found.and_then(|index| self.history.get(index));
+                let line = found.unwrap_or(&search.original);
+                let cursor = line
+                    .find(&search.query)
+                    .filter(|_| found.is_some())
+                    .unwrap_or(line.len());
+                (line, cursor, Some(search.query.as_str()))
+            }
+            _ => (self.line.as_str()?, self.line.cursor_pos(), None),
+        };
+        let view = render::View {
+            prompt: &self.prompt,
+            line,
+            cursor,
+            hint: self.hint.as_deref(),
+            search,
+            status: &self.status,
+        };
+        let result = renderer.render(&view);
+        self.renderer = Some(renderer);
+        result
+    }
+
+    /// Sets how completion candidates are matched against the word being completed.
+    ///
+    /// Defaults to [`MatchPolicy::Prefix`].
+    pub fn set_match_policy(&mut self, policy: MatchPolicy) {
+        self.match_policy = policy;
+    }
+
+    /// Sets how policies ignoring case compare characters.
+    ///
+    /// Defaults to [`completion::unicode_fold`]; [`completion::ascii_fold`]
+    /// only folds ASCII letters. See [`CaseFold`](completion::CaseFold) for a
+    /// language-specific mapping.
+    pub fn set_case_fold(&mut self, fold: completion::CaseFold) {
+        self.case_fold = fold;
+    }
+
+    /// Captures the line being edited, its cursor position and the position in
+    /// the history being browsed.
+    ///
+    /// The line is kept when `read_line` fails, so taking a snapshot after an
+    /// error such as a dropped connection preserves the unfinished command. See
+    /// the [`snapshot`] module.
+    pub fn snapshot(&self) -> EditorState {
+        let history_view = self
+            .history
+            .viewing_entry
+            .map(|index| (index, self.history.saved_line.clone().unwrap_or_default()));
+        EditorState {
+            line: String::from_utf8_lossy(self.line.as_bytes()).into_owned(),
+            cursor: self.line.cursor_pos(),
+            history_view,
+        }
+    }
+
+    /// Restores a state captured with [`snapshot`](Self::snapshot).
+    ///
+    /// The next `read_line` starts with the restored line and cursor, drawn
+    /// after its prompt, instead of an empty line. A history position that no
+    /// longer exists, because the history changed in between, is dropped.
+    pub fn restore(&mut self, state: EditorState) {
+        self.line.load(&state.line);
+        // load leaves the cursor at the end, which is kept if the position is bad
+        let _ = self.line.set_cursor(state.cursor);
+        match state.history_view {
+            Some((index, saved)) if index < self.history.len() => {
+                self.history.viewing_entry = Some(index);
+                self.history.saved_line = Some(saved);
+            }
+            _ => self.history.reset_view(),
+        }
+        self.restored = true;
+    }
+
+    /// Loads a history entry into the line, for the next `read_line` to start
+    /// with, and returns it.
+    ///
+    /// A positive `id_or_offset` is an entry [ID](History::get_by_id), as shown
+    /// by [`print_history`](Self::print_history); a negative one counts back
+    /// from the newest entry, so `-1` is the previous line. This is what a REPL
+    /// needs for `!42` and `!-1`. Returns `None`, leaving the line alone, if
+    /// there is no such entry.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use editline::{LineEditor, terminals::StdioTerminal};
+    ///
+    /// let mut editor = LineEditor::new(1024, 50);
+    /// let mut terminal = StdioTerminal::new();
+    ///
+    /// loop {
+    ///     let line = editor.read_line(&mut terminal)?;
+    ///     if let Some(n) = line.strip_prefix('!').and_then(|n| n.parse().ok()) {
+    ///         // Show the entry for editing instead of running "!42"
+    ///         if editor.recall(n).is_none() {
+    ///             println!("!{}: event not found", n);
+    ///         }
+    ///         continue;
+    ///     }
+    ///     // ... run the command
+    /// }
+    /// # Ok::<(), editline::Error>(())
+    /// ```
+    pub fn recall(&mut self, id_or_offset: i64) -> Option<String> {
+        let text = String::from(self.history_entry(id_or_offset)?);
+        self.line.load(&text);
+        self.history.reset_view();
+        self.restored = true;
+        Some(text)
+    }
+
+    fn history_entry(&self, id_or_offset: i64) -> Option<&str> {
+        if id_or_offset < 0 {
+            let back = usize::try_from(id_or_offset.unsigned_abs()).ok()?;
+            self.history
+                .len()
+                .checked_sub(back)
+                .and_then(|index| self.history.get(index))
+        } else {
+            self.history.get_by_id(id_or_offset as u64)
+        }
+    }
+
+    /// Clears the screen, e.g. to implement a `clear` command.
+    ///
+    /// Between lines this leaves the cursor at the top-left corner, ready for
+    /// the application's banner and the next prompt. While a line is being read
+    /// with [`poll_read_line`](Self::poll_read_line) the prompt, the line and
+    /// the status line are redrawn, as with Ctrl+L.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use editline::{LineEditor, terminals::StdioTerminal};
+    ///
+    /// let mut editor = LineEditor::new(1024, 50);
+    /// let mut terminal = StdioTerminal::new();
+    ///
+    /// if editor.read_line(&mut terminal)? == "clear" {
+    ///     editor.clear_screen(&mut terminal)?;
+    /// }
+    /// # Ok::<(), editline::Error>(())
+    /// ```
+    pub fn clear_screen<T: Terminal>(&mut self, terminal: &mut T) -> Result<()> {
+        if self.polling.is_some() {
+            self.repaint_cleared(terminal)?;
+        } else {
+            terminal.clear_screen()?;
+        }
+        terminal.flush()
+    }
+
+    /// Clears the screen and draws the prompt, line and status line at the top.
+    fn repaint_cleared<T: Terminal>(&mut self, terminal: &mut T) -> Result<()> {
+        terminal.clear_screen()?;
+        self.status_shown = false;
+        self.origin = 0;
+        self.draw_line(terminal)?;
+        self.restore_status(terminal)
+    }
+
+    /// Returns the editor's command history.
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Prints numbered history entries, one per line.
+    ///
+    /// `range` selects entries by chronological index (0 is the oldest); each
+    /// line shows the entry's [ID](History::get_by_id), which stays the same as
+    /// older entries are dropped. Lines end with the terminal's [line
+    /// ending](Caps::newline), so this can be used directly to implement a
+    /// `history` command. Control characters in entries, e.g. from a tampered
+    /// history file, are shown in caret notation (`^[`) rather than sent to the
+    /// terminal.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use editline::{LineEditor, terminals::StdioTerminal};
+    ///
+    /// let editor = LineEditor::new(1024, 50);
+    /// let mut terminal = StdioTerminal::new();
+    ///
+    /// // Show the whole history
+    /// editor.print_history(&mut terminal, ..)?;
+    /// # Ok::<(), editline::Error>(())
+    /// ```
+    pub fn print_history<T: Terminal, R: RangeBounds<usize>>(
+        &self,
+        terminal: &mut T,
+        range: R,
+    ) -> Result<()> {
+        let len = self.history.len();
+        let start = match range.start_bound() {
+            Bound::Included(&n) => n,
+            Bound::Excluded(&n) => n.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&n) => n.saturating_add(1),
+            Bound::Excluded(&n) => n,
+            Bound::Unbounded => len,
+        }
+        .min(len);
+
+        for index in start..end {
+            if let (Some(id), Some(entry)) = (self.history.id_of(index), self.history.get(index)) {
+                let number = alloc::format!("{:>5}  ", id);
+                terminal.write(number.as_bytes())?;
+                write_visible(terminal, entry.as_bytes())?;
+                write_newline(terminal)?;
+            }
+        }
+
+        terminal.flush()
+    }
+
+    /// Resolves what `event` is bound to, or `None` if the key starts a Ctrl+X
+    /// sequence.
+    fn key_binding(&mut self, event: KeyEvent) -> Option<Binding> {
+        // Ctrl+X starts a two-key sequence; any other key cancels it
+        if core::mem::take(&mut self.ctrl_x_pending) {
+            if event == KeyEvent::Ctrl('e') {
+                return Some(Binding::Action(Action::ExternalEditor));
+            }
+        } else if event == KeyEvent::Ctrl('x') {
+            self.ctrl_x_pending = true;
+            return None;
+        }
+
+        if let Some(action) = self.overrides.get(event) {
+            return Some(Binding::Action(action));
+        }
+        if let Some(index) = self.commands.iter().position(|(key, _)| *key == event) {
+            return Some(Binding::Command(index));
+        }
+
+        let action = self.keymap.get(event).or_else(|| match event {
+            // Ctrl+S / Ctrl+Q are XOFF/XON when flow control is enabled
+            KeyEvent::Ctrl('s') if self.flow_control => None,
+            _ => keymap::default_action(event),
+        });
+        Some(Binding::Action(action.unwrap_or(Action::Ignore)))
+    }
+
+    /// Runs a bound command and redraws the line if the command changed it.
+    fn run_command<T: Terminal>(
+        &mut self,
+        terminal: &mut T,
+        index: usize,
+    ) -> Result<HandlerResult> {
+        self.clear_hint(terminal)?;
+
+        let (before, before_pos) = (self.line.as_bytes().to_vec(), self.line.cursor_pos());
+        let result = self.commands[index].1.execute(&mut self.line);
+
+        if self.line.as_bytes() != &before[..] || self.line.cursor_pos() != before_pos {
+            self.history.reset_view();
+            cursor_left_by(terminal, display_width(&before[..before_pos]))?;
+            terminal.clear_eol()?;
+            write_visible(terminal, self.line.as_bytes())?;
+            cursor_left_by(
+                terminal,
+                self.width_between(self.line.cursor_pos(), self.line.len()),
+            )?;
+        }
+
+        if result == HandlerResult::Continue {
+            self.show_hint(terminal)?;
+        }
+        terminal.flush()?;
+        Ok(result)
+    }
+
+    /// Performs an editing action and refreshes the hint.
+    fn perform<T: Terminal>(&mut self, terminal: &mut T, action: Action) -> Result<()> {
+        // Typing replaces a selection and deleting removes it; anything else
+        // just drops it
+        let action = match (self.selection(), action) {
+            (_, Action::SelectHome | Action::SelectEnd) => action,
+            (
+                Some((start, end)),
+                Action::Insert(_) | Action::DeleteBackward | Action::DeleteForward,
+            ) => {
+                if let Action::Insert(_) = action {
+                    self.delete_selection(terminal, start, end)?;
+                    action
+                } else {
+                    let selected = self.line.as_str()?[start..end].to_string();
+                    self.delete_selection(terminal, start, end)?;
+                    self.copy(&selected);
+                    Action::Ignore
+                }
+            }
+            _ => {
+                self.drop_selection(terminal)?;
+                action
+            }
+        };
+
+        // Right or End at the end of the line accepts a displayed hint
+        if let (Some(hint), Action::MoveRight | Action::MoveEnd | Action::AcceptHint) =
+            (&self.hint, action)
+        {
+            let hint = hint.clone();
+            self.hint = None;
+            self.history.reset_view();
+            let start = self.line.cursor_pos();
+            let complete = self.line.insert_str(&hint);
+            write_visible(
+                terminal,
+                &self.line.as_bytes()[start..self.line.cursor_pos()],
+            )?;
+            if !complete {
+                terminal.bell()?;
+            }
+            self.show_hint(terminal)?;
+            return terminal.flush();
+        }
+        self.clear_hint(terminal)?;
+
+        self.dispatch_action(terminal, action)?;
+        // Search and quoted insert take over the following keys
+        if let Mode::Edit = self.mode {
+            self.show_hint(terminal)?;
+        }
+        terminal.flush()
+    }
+
+    fn dispatch_action<T: Terminal>(&mut self, terminal: &mut T, action: Action) -> Result<()> {
+        // Only a directly repeated yank continues with the word before
+        let yanked_word = self.yanked_word.take();
+        match action {
+            Action::Insert(c) => {
+                self.history.reset_view();
+                self.insert_typed(terminal, c)?;
+                if let Some(observer) = self.observer.as_mut() {
+                    observer.on_char(
+                        c,
+                        &Context::new(self.line.as_str()?, self.line.cursor_pos(), &self.history),
+                    );
+                }
+            }
+            Action::MoveLeft => {
+                let old = self.line.cursor_pos();
+                self.line.move_cursor_left();
+                self.move_cursor(terminal, old)?;
+            }
+            Action::MoveRight => {
+                let old = self.line.cursor_pos();
+                self.line.move_cursor_right();
+                self.move_cursor(terminal, old)?;
+            }
+            Action::HistoryPrevious => {
+                if let Some(index) = self
+                    .history
+                    .previous_index(self.line.as_str().unwrap_or(""))
+                {
+                    self.load_history(terminal, Some(index))?;
+                }
+            }
+            Action::HistoryNext => {
+                // Past the newest entry, the line being typed comes back;
+                // if None, we're not viewing history, so do nothing
+                if let Some(index) = self.history.next_index() {
+                    self.load_history(terminal, index)?;
+                }
+            }
+            Action::Recall(id_or_offset) => match self.history_entry(id_or_offset) {
+                Some(text) => {
+                    let text = text.to_string();
+                    self.history.reset_view();
+                    self.replace_line(terminal, &text)?;
+                    self.recalled(&text);
+                }
+                None => terminal.bell()?,
+            },
+            Action::MoveHome | Action::MoveBufferStart => {
+                let old = self.line.cursor_pos();
+                self.line.move_cursor_to_start();
+                self.move_cursor(terminal, old)?;
+            }
+            Action::MoveEnd | Action::MoveBufferEnd => {
+                let old = self.line.cursor_pos();
+                self.line.move_cursor_to_end();
+                self.move_cursor(terminal, old)?;
+            }
+            Action::SelectHome | Action::SelectEnd => {
+                let old_column = self.cursor_column();
+                self.mark.get_or_insert(self.line.cursor_pos());
+                if action == Action::SelectHome {
+                    self.line.move_cursor_to_start();
+                } else {
+                    self.line.move_cursor_to_end();
+                }
+                self.refresh_line(terminal, old_column)?;
+            }
+            Action::DeleteBackward => {
+                self.history.reset_view();
+                let old = self.line.cursor_pos();
+                let width = self.width_between(self.line.prev_char_start(), old);
+                if self.line.delete_before_cursor() {
+                    cursor_left_by(terminal, width)?;
+                    self.erase_at_cursor(terminal, width)?;
+                }
+            }
+            Action::DeleteForward => {
+                self.history.reset_view();
+                let width = self.width_between(self.line.cursor_pos(), self.line.next_char_end());
+                if self.line.delete_at_cursor() {
+                    self.erase_at_cursor(terminal, width)?;
+                }
+            }
+            Action::MoveWordLeft => {
+                let old = self.line.cursor_pos();
+                self.line.move_cursor_word_left();
+                self.move_cursor(terminal, old)?;
+            }
+            Action::MoveWordRight => {
+                let old = self.line.cursor_pos();
+                self.line.move_cursor_word_right();
+                self.move_cursor(terminal, old)?;
+            }
+            Action::DeleteWordLeft => {
+                self.history.reset_view();
+                let removed = self.line.delete_word_left();
+                let width = display_width(removed.as_bytes());
+                cursor_left_by(terminal, width)?;
+                self.erase_at_cursor(terminal, width)?;
+                self.copy(&removed);
+            }
+            Action::DeleteWordRight => {
+                self.history.reset_view();
+                let removed = self.line.delete_word_right();
+                self.erase_at_cursor(terminal, display_width(removed.as_bytes()))?;
+                self.copy(&removed);
+            }
+            Action::Complete if self.completer.is_none() => {
+                self.insert_tab(terminal)?;
+            }
+            Action::Complete => {
+                self.complete(terminal)?;
+            }
+            Action::ClearScreen => {
+                self.repaint_cleared(terminal)?;
+            }
+            Action::ReverseSearch => {
+                self.start_search(terminal, false)?;
+            }
+            Action::ForwardSearch => {
+                self.start_search(terminal, true)?;
+            }
+            Action::HistoryMenu => {
+                self.open_history_menu(terminal)?;
+            }
+            Action::YankPreviousWord => {
+                let (count, start) = match yanked_word {
+                    Some((count, start)) => (count + 1, start),
+                    None => (0, self.line.cursor_pos()),
+                };
+                let word = self
+                    .history
+                    .len()
+                    .checked_sub(1)
+                    .and_then(|newest| self.history.get(newest))
+                    .and_then(|entry| entry.split_whitespace().rev().nth(count))
+                    .map(ToString::to_string);
+                match word {
+                    Some(word) => {
+                        self.history.reset_view();
+                        self.replace_before_cursor(terminal, start, &word)?;
+                        self.yanked_word = Some((count, start));
+                    }
+                    None => {
+                        // Out of words; keep the last one yanked
+                        self.yanked_word = yanked_word;
+                        terminal.bell()?;
+                    }
+                }
+            }
+            Action::QuotedInsert => {
+                // Quoted insert: the next character goes into the line as-is
+                self.history.reset_view();
+                self.mode = Mode::QuotedInsert(ansi::Decoder::new());
+            }
+            #[cfg(feature = "std")]
+            Action::ExternalEditor => {
+                self.edit_in_external_editor(terminal)?;
+            }
+            #[cfg(not(feature = "std"))]
+            Action::ExternalEditor => {}
+            // A selection is dropped above
+            Action::Abort => terminal.bell()?,
+            // Handled by the read loop, or nothing to do (no hint to accept)
+            Action::AcceptHint
+            | Action::Submit
+            | Action::PasteClipboard
+            | Action::Interrupt
+            | Action::EndOfInput
+            | Action::Ignore => {}
+        }
+
+        Ok(())
+    }
+
+    /// Shows the hinter's suggestion if the cursor is at the end of the line.
+    fn show_hint<T: Terminal>(&mut self, terminal: &mut T) -> Result<()> {
+        if self.line.cursor_pos() != self.line.len()
+            || self.draws_after_step()
+            || self.mark.is_some()
+        {
+            return Ok(());
+        }
+        let hinter = match self.hinter.as_mut() {
+            Some(hinter) => hinter,
+            None => return Ok(()),
+        };
+
+        let ctx = Context::new(self.line.as_str()?, self.line.cursor_pos(), &self.history);
+        match hinter.hint(&ctx) {
+            Some(hint) => self.paint_hint(terminal, hint),
+            None => Ok(()),
+        }
+    }
+
+    /// Displays `hint` after the cursor, which is at the end of the line.
+    fn paint_hint<T: Terminal>(&mut self, terminal: &mut T, hint: String) -> Result<()> {
+        if !hint.is_empty() {
+            self.theme.hint.paint(terminal, hint.as_bytes())?;
+            cursor_left_by(terminal, display_width(hint.as_bytes()))?;
+            self.hint = Some(hint);
+        }
+        Ok(())
+    }
+
+    /// Shows `text` on the status line below the input.
+    fn show_status<T: Terminal>(&mut self, terminal: &mut T, text: &str) -> Result<()> {
+        terminal.show_status(text.as_bytes())?;
+        self.status_shown = true;
+        Ok(())
+    }
+
+    /// Erases the status line if one is shown.
+    fn hide_status<T: Terminal>(&mut self, terminal: &mut T) -> Result<()> {
+        if self.status_shown {
+            terminal.clear_status()?;
+            self.status_shown = false;
+        }
+        Ok(())
+    }
+
+    /// Puts the application's status line back, or erases a transient one.
+    fn restore_status<T: Terminal>(&mut self, terminal: &mut T) -> Result<()> {
+        if self.status.is_empty() {
+            self.hide_status(terminal)
+        } else {
+            terminal.show_status(self.status.as_bytes())?;
+            self.status_shown = true;
+            Ok(())
+        }
+    }
+
+    /// Erases a displayed hint (the cursor is always right before it).
+    fn clear_hint<T: Terminal>(&mut self, terminal: &mut T) -> Result<()> {
+        if self.hint.take().is_some() {
+            terminal.clear_eol()?;
+        }
+        Ok(())
+    }
+
+    /// Completes the word before the cursor using the installed completer.
+    fn complete<T: Terminal>(&mut self, terminal: &mut T) -> Result<()> {
+        let mut completer = match self.completer.take() {
+            Some(completer) => completer,
+            None => return Ok(()),
+        };
+
+        let ctx = Context::new(self.line.as_str()?, self.line.cursor_pos(), &self.history);
+        let candidates = completer.complete(&ctx);
+        self.completer = Some(completer);
+        self.apply_completion(terminal, candidates)
+    }
+
+    /// Inserts spaces up to the next tab stop.
+    fn insert_tab<T: Terminal>(&mut self, terminal: &mut T) -> Result<()> {
+        if self.tab_width == 0 {
+            return Ok(());
+        }
+        let pos = self.line.cursor_pos();
+        let column = display_width(&self.line.as_bytes()[..pos]);
+        let spaces = " ".repeat(self.tab_width - column % self.tab_width);
+        self.history.reset_view();
+        self.replace_before_cursor(terminal, pos, &spaces)
+    }
+
+    /// Completes the word before the cursor from a completer's `candidates`:
+    /// a single match is inserted, several are extended to their common prefix
+    /// or listed below the line.
+    fn apply_completion<T: Terminal>(
+        &mut self,
+        terminal: &mut T,
+        candidates: Vec<String>,
+    ) -> Result<()> {
+        let pos = self.line.cursor_pos();
+        let ctx = Context::new(self.line.as_str()?, pos, &self.history);
+        let start = ctx.word_start();
+        let word = ctx.word_before_cursor().to_string();
+        let mut candidates = self
+            .match_policy
+            .filter_with(&word, candidates, self.case_fold);
+
+        match candidates.len() {
+            0 => {}
+            1 => {
+                let mut text = candidates.remove(0);
+                text.push(' ');
+                self.history.reset_view();
+                self.replace_before_cursor(terminal, start, &text)?;
+            }
+            _ => {
+                let prefix =
+                    completion::common_prefix(&candidates, self.match_policy, self.case_fold);
+                if prefix.chars().count() > word.chars().count()
+                    && self
+                        .match_policy
+                        .matches_with(&word, prefix, self.case_fold)
+                {
+                    let prefix = prefix.to_string();
+                    self.history.reset_view();
+                    self.replace_before_cursor(terminal, start, &prefix)?;
+                } else {
+                    self.hide_status(terminal)?;
+                    cursor_right_by(terminal, self.width_between(pos, self.line.len()))?;
+                    write_newline(terminal)?;
+                    completion::print_candidates(
+                        terminal,
+                        &candidates,
+                        &word,
+                        self.match_policy,
+                        self.case_fold,
+                        self.theme.completion_highlight,
+                    )?;
+                    // The line is redrawn on a fresh row, without whatever
+                    // preceded the prompt
+                    self.origin = 0;
+                    self.draw_line(terminal)?;
+                    self.restore_status(terminal)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the buffer from byte position `start` up to the cursor with `text`.
+    fn replace_before_cursor<T: Terminal>(
+        &mut self,
+        terminal: &mut T,
+        start: usize,
+        text: &str,
+    ) -> Result<()> {
+        let old_column = self.cursor_column();
+        while self.line.cursor_pos() > start {
+            self.line.delete_before_cursor();
+        }
+        let complete = self.line.insert_str(text);
+        self.refresh_line(terminal, old_column)?;
+        if !complete {
+            terminal.bell()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the prompt in the theme's prompt style.
+    fn write_prompt<T: Terminal>(&self, terminal: &mut T) -> Result<()> {
+        write_prompt(terminal, &self.prompt, self.theme.prompt)
+    }
+
+    /// Column the last prompt line starts at; only a single-line prompt can
+    /// follow application output on the same row.
+    fn line_origin(&self) -> usize {
+        if self.prompt.contains('\n') {
+            0
+        } else {
+            self.origin
+        }
+    }
+
+    /// Display column of the cursor, counted from the start of the prompt's
+    /// last line.
+    fn cursor_column(&self) -> usize {
+        self.line_origin()
+            + prompt_width(last_line(&self.prompt))
+            + self.width_between(0, self.line.cursor_pos())
+    }
+
+    /// Byte range of the selection, if one is active and not empty.
+    fn selection(&self) -> Option<(usize, usize)> {
+        let mark = self.mark.filter(|&mark| mark <= self.line.len())?;
+        let cursor = self.line.cursor_pos();
+        Some((mark.min(cursor), mark.max(cursor))).filter(|(start, end)| start < end)
+    }
+
+    /// Ends the selection, repainting the line without it.
+    fn drop_selection<T: Terminal>(&mut self, terminal: &mut T) -> Result<()> {
+        let shown = self.selection().is_some();
+        self.mark = None;
+        if shown {
+            self.refresh_line(terminal, self.cursor_column())?;
+        }
+        Ok(())
+    }
+
+    /// Removes the selected text between `start` and `end`.
+    fn delete_selection<T: Terminal>(
+        &mut self,
+        terminal: &mut T,
+        start: usize,
+        end: usize,
+    ) -> Result<()> {
+        let old_column = self.cursor_column();
+        self.history.reset_view();
+        self.mark = None;
+        self.line.cursor_pos = end;
+        while self.line.cursor_pos() > start {
+            self.line.delete_before_cursor();
+        }
+        self.refresh_line(terminal, old_column)
+    }
+
+    /// Number of rows taken by the prompt lines above its last line.
+    fn prompt_rows_above(&self, columns: Option<usize>) -> usize {
+        let mut lines = self.prompt.split('\n');
+        lines.next_back();
+        let mut origin = self.origin;
+        lines
+            .map(|line| {
+                let width = origin + prompt_width(line);
+                origin = 0;
+                match columns {
+                    Some(columns) if width > 0 => (width + columns - 1) / columns,
+                    _ => 1,
+                }
+            })
+            .sum()
+    }
+
+    /// Prints `text` above the prompt, e.g. a log message arriving while a
+    /// line is edited, and redraws the prompt and line below it.
+    ///
+    /// The text is written as is, so it may contain color sequences; `\n`
+    /// starts a new line. Anything the application printed before the prompt on
+    /// its row is erased.
+    #[cfg(feature = "async")]
+    fn print_above<T: Terminal>(&mut self, terminal: &mut T, text: &str) -> Result<()> {
+        let columns = terminal
+            .size()
+            .map(|(columns, _)| usize::from(columns).max(1));
+        let mut rows = self.prompt_rows_above(columns);
+        if let (Some(columns), None) = (columns, self.scroll_width) {
+            rows += self.cursor_column() / columns;
+        }
+        for _ in 0..rows {
+            terminal.cursor_up()?;
+        }
+        terminal.write(b"\r")?;
+        terminal.clear_to_end_of_screen()?;
+        self.hint = None;
+        self.status_shown = false;
+
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        for line in text.split('\n') {
+            terminal.write(line.as_bytes())?;
+            write_newline(terminal)?;
+        }
+
+        self.origin = 0;
+        self.draw_line(terminal)?;
+        self.restore_status(terminal)?;
+        self.show_hint(terminal)
+    }
+
+    /// Repaints the prompt and the whole line in place.
+    ///
+    /// `old_column` is the [`cursor_column`](Self::cursor_column) at which the
+    /// line is currently displayed, before any buffer change, so that the
+    /// repaint starts on the prompt's first row even when the line wrapped or
+    /// the prompt spans several lines. Leaves the cursor at its position in the
+    /// buffer.
+    fn refresh_line<T: Terminal>(&self, terminal: &mut T, old_column: usize) -> Result<()> {
+        let columns = terminal
+            .size()
+            .map(|(columns, _)| usize::from(columns).max(1));
+
+        let mut rows = self.prompt_rows_above(columns);
+        if let (Some(columns), None) = (columns, self.scroll_width) {
+            rows += old_column / columns;
+        }
+        for _ in 0..rows {
+            terminal.cursor_up()?;
+        }
+        self.draw_line(terminal)
+    }
+
+    /// Paints the prompt and the whole line starting on the cursor's row, e.g.
+    /// on a fresh row after a completion list, and places the cursor.
+    fn draw_line<T: Terminal>(&self, terminal: &mut T) -> Result<()> {
+        self.count_redraw();
+        let columns = terminal
+            .size()
+            .map(|(columns, _)| usize::from(columns).max(1));
+
+        terminal.write(b"\r")?;
+        cursor_right_by(terminal, self.origin)?;
+        terminal.clear_to_end_of_screen()?;
+        self.write_prompt(terminal)?;
+        if let Some(width) = self.scroll_width {
+            return self.write_window(terminal, width);
+        }
+        match self.selection() {
+            Some((start, end)) => {
+                let bytes = self.line.as_bytes();
+                write_visible(terminal, &bytes[..start])?;
+                self.theme.selection.paint(terminal, &bytes[start..end])?;
+                write_visible(terminal, &bytes[end..])?;
+            }
+            None => write_visible(terminal, self.line.as_bytes())?,
+        }
+
+        let end = self.line_origin()
+            + prompt_width(last_line(&self.prompt))
+            + display_width(self.line.as_bytes());
+        let cursor = self.cursor_column();
+        let columns = match columns {
+            Some(columns) => columns,
+            None => return cursor_left_by(terminal, end - cursor),
+        };
+
+        // A line ending exactly at the margin leaves the cursor in the pending
+        // wrap state; force the wrap so row arithmetic holds
+        if end > 0 && end % columns == 0 {
+            write_newline(terminal)?;
+        }
+        if cursor == end {
+            return Ok(());
+        }
+        for _ in cursor / columns..end / columns {
+            terminal.cursor_up()?;
+        }
+        terminal.write(b"\r")?;
+        cursor_right_by(terminal, cursor % columns)
+    }
+
+    /// Writes the line after the prompt, leaving the cursor at its end, or
+    /// repaints the visible part of it when scrolling.
+    fn write_line<T: Terminal>(&self, terminal: &mut T) -> Result<()> {
+        match self.scroll_width {
+            Some(_) => self.draw_scrolled(terminal),
+            None => write_visible(terminal, self.line.as_bytes()),
+        }
+    }
+
+    /// Repaints the last prompt line and the visible part of the line in
+    /// horizontal scrolling mode.
+    fn draw_scrolled<T: Terminal>(&self, terminal: &mut T) -> Result<()> {
+        self.count_redraw();
+        let width = match self.scroll_width {
+            Some(width) => width,
+            None => return Ok(()),
+        };
+        terminal.write(b"\r")?;
+        cursor_right_by(terminal, self.line_origin())?;
+        write_prompt(terminal, last_line(&self.prompt), self.theme.prompt)?;
+        self.write_window(terminal, width)
+    }
+
+    /// Writes the part of the line around the cursor that fits in a row of
+    /// `width` columns after the prompt, and places the cursor.
+    fn write_window<T: Terminal>(&self, terminal: &mut T, width: usize) -> Result<()> {
+        let prompt = self.line_origin() + prompt_width(last_line(&self.prompt));
+        // The last column stays empty, so the cursor never waits to wrap
+        let room = width.saturating_sub(prompt + 1).max(1);
+        let text = self.line.as_str()?;
+        let total = display_width(text.as_bytes());
+        let cursor = self.width_between(0, self.line.cursor_pos());
+
+        // Text columns start..start + len are shown; the window moves by half
+        // its width when the cursor leaves it
+        let (start, len) = if total < room {
+            (0, room)
+        } else if cursor + 1 < room {
+            (0, room - 1)
+        } else {
+            let len = room.saturating_sub(2).max(1);
+            let step = (len / 2).max(1);
+            (((cursor - len) / step + 1) * step, len)
+        };
+
+        let mut drawn = 0;
+        if start > 0 {
+            terminal.write(b"<")?;
+            drawn += 1;
+        }
+        let mut column = 0;
+        for c in text.chars() {
+            let char_end = column + width::char_width(c);
+            if char_end > start + len {
+                break;
+            }
+            if column >= start {
+                let mut buf = [0; 4];
+                write_visible(terminal, c.encode_utf8(&mut buf).as_bytes())?;
+            } else {
+                // What remains of a wide character cut off by the left edge
+                for _ in start..char_end {
+                    terminal.write(b" ")?;
+                }
+            }
+            drawn += char_end.saturating_sub(start) - column.saturating_sub(start);
+            column = char_end;
+        }
+        if total > start + len {
+            let shown = drawn - usize::from(start > 0);
+            for _ in shown..len {
+                terminal.write(b" ")?;
+            }
+            terminal.write(b">")?;
+            drawn += len - shown + 1;
+        }
+        terminal.clear_eol()?;
+        cursor_left_by(terminal, drawn - usize::from(start > 0) - (cursor - start))
+    }
+
+    /// Starts an incremental history search; the following keys go to
+    /// [`search_key`](Self::search_key) until one ends it.
+    fn start_search<T: Terminal>(&mut self, terminal: &mut T, forward: bool) -> Result<()> {
+        let original = self.line.as_str()?.to_string();
+        self.clear_line_display(terminal)?;
+        let shown = self.render_search(terminal, forward, false, "", None)?;
+        let help = if self.flow_control {
+            "Ctrl+R: older  Ctrl+G: cancel"
+        } else {
+            "Ctrl+R: older  Ctrl+S: newer  Ctrl+G: cancel"
+        };
+        self.show_status(terminal, help)?;
+        self.mode = Mode::Search(Search {
+            original,
+            query: String::new(),
+            found: None,
+            failed: false,
+            forward,
+            shown,
+        });
+        Ok(())
+    }
+
+    /// Handles a key during incremental search.
+    ///
+    /// Typed characters extend the query, Backspace shortens it, and Ctrl+R / Ctrl+S
+    /// step to the next older / newer match. Ctrl+G restores the original line;
+    /// any other key accepts the current match and is then processed normally, so
+    /// Enter submits the match right away.
+    fn search_key<T: Terminal>(
+        &mut self,
+        terminal: &mut T,
+        mut search: Search,
+        event: KeyEvent,
+    ) -> Result<Option<String>> {
+        match event {
+            KeyEvent::Normal('\0') => {
+                self.mode = Mode::Search(search);
+                return Ok(None);
+            }
+            KeyEvent::Normal(c) => {
+                search.query.push(c);
+                // Extend the search from the current match, which may still match
+                let start = search.found.or_else(|| self.search_start(search.forward));
+                search.found =
+                    start.and_then(|i| self.search_history(&search.query, i, search.forward));
+            }
+            KeyEvent::Backspace => {
+                search.query.pop();
+                search.found = self
+                    .search_start(search.forward)
+                    .and_then(|i| self.search_history(&search.query, i, search.forward));
+            }
+            KeyEvent::Ctrl('r') | KeyEvent::Ctrl('s') => {
+                search.forward = event == KeyEvent::Ctrl('s');
+                let next = match search.found {
+                    Some(i) if search.forward => i.checked_add(1),
+                    Some(i) => i.checked_sub(1),
+                    None => self.search_start(search.forward),
+                };
+                if let Some(i) =
+                    next.and_then(|i| self.search_history(&search.query, i, search.forward))
+                {
+                    search.found = Some(i);
+                }
+            }
+            KeyEvent::Ctrl('g') | KeyEvent::Escape => {
+                cursor_left_by(terminal, search.shown)?;
+                terminal.clear_eol()?;
+                terminal.bell()?;
+                self.line.load(&search.original);
+                self.write_line(terminal)?;
+                self.restore_status(terminal)?;
+                self.show_hint(terminal)?;
+                terminal.flush()?;
+                return Ok(None);
+            }
+            _ => {
+                cursor_left_by(terminal, search.shown)?;
+                terminal.clear_eol()?;
+                let text = match search.found.and_then(|i| self.history.get(i)) {
+                    Some(entry) => {
+                        let entry = entry.to_string();
+                        self.recalled(&entry);
+                        entry
+                    }
+                    None => search.original,
+                };
+                self.history.reset_view();
+                if !self.line.load(&text) {
+                    terminal.bell()?;
+                }
+                self.write_line(terminal)?;
+                self.restore_status(terminal)?;
+                return self.step(terminal, event);
+            }
+        }
+
+        search.failed = !search.query.is_empty() && search.found.is_none();
+        cursor_left_by(terminal, search.shown)?;
+        terminal.clear_eol()?;
+        search.shown = self.render_search(
+            terminal,
+            search.forward,
+            search.failed,
+            &search.query,
+            search.found,
+        )?;
+        self.mode = Mode::Search(search);
+        terminal.flush()?;
+        Ok(None)
+    }
+
+    /// Index where a fresh search starts: the newest entry when searching
+    /// backward, the oldest when searching forward.
+    fn search_start(&self, forward: bool) -> Option<usize> {
+        match self.history.len() {
+            0 => None,
+            _ if forward => Some(0),
+            len => Some(len - 1),
+        }
+    }
+
+    /// Finds the first entry containing `query`, starting at `start` inclusive.
+    fn search_history(&self, query: &str, start: usize, forward: bool) -> Option<usize> {
+        let matches = |i: &usize| self.history.get(*i).map_or(false, |e| e.contains(query));
+        if forward {
+            (start..self.history.len()).find(matches)
+        } else {
+            (0..=start.min(self.history.len().checked_sub(1)?))
+                .rev()
+                .find(matches)
+        }
+    }
+
+    /// Shows the search status line and returns its display width.
+    fn render_search<T: Terminal>(
+        &self,
+        terminal: &mut T,
+        forward: bool,
+        failed: bool,
+        query: &str,
+        found: Option<usize>,
+    ) -> Result<usize> {
+        let label = match (failed, forward) {
+            (false, false) => "(reverse-i-search)`",
+            (false, true) => "(i-search)`",
+            (true, false) => "(failed reverse-i-search)`",
+            (true, true) => "(failed i-search)`",
+        };
+        let entry = found.and_then(|i| self.history.get(i)).unwrap_or("");
+
+        if failed {
+            self.theme.error.paint(terminal, label.as_bytes())?;
+        } else {
+            terminal.write(label.as_bytes())?;
+        }
+        write_visible(terminal, query.as_bytes())?;
+        terminal.write(b"': ")?;
+        match entry.find(query).filter(|_| !query.is_empty()) {
+            Some(at) => {
+                let (bytes, end) = (entry.as_bytes(), at + query.len());
+                write_visible(terminal, &bytes[..at])?;
+                self.theme.selection.paint(terminal, &bytes[at..end])?;
+                write_visible(terminal, &bytes[end..])?;
+            }
+            None => write_visible(terminal, entry.as_bytes())?,
+        }
+
+        Ok(label.len() + display_width(query.as_bytes()) + 3 + display_width(entry.as_bytes()))
+    }
+
+    /// Opens the history menu on the entries starting with the line, ringing
+    /// the bell if there are none.
+    fn open_history_menu<T: Terminal>(&mut self, terminal: &mut T) -> Result<()> {
+        let matches = self.history_menu_matches()?;
+        if matches.is_empty() {
+            return terminal.bell();
+        }
+        self.clear_hint(terminal)?;
+        self.hide_status(terminal)?;
+        let mut menu = HistoryMenu {
+            matches,
+            selected: 0,
+            rows: 0,
+        };
+        self.clear_history_menu(terminal, 0)?;
+        self.draw_history_menu(terminal, &mut menu)?;
+        self.mode = Mode::HistoryMenu(menu);
+        Ok(())
+    }
+
+    /// Handles a key while the history menu is open.
+    ///
+    /// Up and Down move the selection, typed characters and Backspace edit the
+    /// line and so narrow or widen the list, and Enter replaces the line with
+    /// the selected entry. Ctrl+G keeps the line as typed; any other key closes
+    /// the menu and is then processed normally.
+    fn history_menu_key<T: Terminal>(
+        &mut self,
+        terminal: &mut T,
+        mut menu: HistoryMenu,
+        event: KeyEvent,
+    ) -> Result<Option<String>> {
+        if event == KeyEvent::Normal('\0') {
+            self.mode = Mode::HistoryMenu(menu);
+            return Ok(None);
+        }
+        self.clear_history_menu(terminal, menu.rows)?;
+        match event {
+            KeyEvent::Up => {
+                menu.selected = (menu.selected + 1).min(menu.matches.len().saturating_sub(1))
+            }
+            KeyEvent::Down => menu.selected = menu.selected.saturating_sub(1),
+            KeyEvent::Normal(c) => {
+                self.history.reset_view();
+                if !self.line.insert_char(c) {
+                    terminal.bell()?;
+                }
+                menu.matches = self.history_menu_matches()?;
+                menu.selected = 0;
+            }
+            KeyEvent::Backspace => {
+                self.history.reset_view();
+                self.line.delete_before_cursor();
+                menu.matches = self.history_menu_matches()?;
+                menu.selected = 0;
+            }
+            KeyEvent::Enter | KeyEvent::LineFeed | KeyEvent::Ctrl('g') | KeyEvent::Escape => {
+                match menu
+                    .matches
+                    .get(menu.selected)
+                    .and_then(|&index| self.history.get(index))
+                {
+                    Some(entry) if matches!(event, KeyEvent::Enter | KeyEvent::LineFeed) => {
+                        let entry = entry.to_string();
+                        self.recalled(&entry);
+                        self.history.reset_view();
+                        if !self.line.load(&entry) {
+                            terminal.bell()?;
+                        }
+                    }
+                    _ => terminal.bell()?,
+                }
+                self.draw_line(terminal)?;
+                self.restore_status(terminal)?;
+                self.show_hint(terminal)?;
+                terminal.flush()?;
+                return Ok(None);
+            }
+            _ => {
+                self.draw_line(terminal)?;
+                self.restore_status(terminal)?;
+                return self.step(terminal, event);
+            }
+        }
+        self.draw_history_menu(terminal, &mut menu)?;
+        self.mode = Mode::HistoryMenu(menu);
+        terminal.flush()?;
+        Ok(None)
+    }
+
+    /// Indices of the distinct history entries starting with the line, newest
+    /// first.
+    fn history_menu_matches(&self) -> Result<Vec<usize>> {
+        let line = self.line.as_str()?;
+        let mut matches: Vec<usize> = Vec::new();
+        for index in (0..self.history.len()).rev() {
+            let entry = match self.history.get(index) {
+                Some(entry) if entry.starts_with(line) => entry,
+                _ => continue,
+            };
+            if !matches.iter().any(|&i| self.history.get(i) == Some(entry)) {
+                matches.push(index);
+            }
+        }
+        Ok(matches)
+    }
+
+    /// Erases the `rows` of menu above the prompt along with the prompt and
+    /// line, leaving the cursor where the menu started.
+    fn clear_history_menu<T: Terminal>(&mut self, terminal: &mut T, rows: usize) -> Result<()> {
+        let columns = terminal
+            .size()
+            .map(|(columns, _)| usize::from(columns).max(1));
+        let mut rows = rows + self.prompt_rows_above(columns);
+        if let (Some(columns), None) = (columns, self.scroll_width) {
+            rows += self.cursor_column() / columns;
+        }
+        for _ in 0..rows {
+            terminal.cursor_up()?;
+        }
+        terminal.write(b"\r")?;
+        terminal.clear_to_end_of_screen()?;
+        self.status_shown = false;
+        self.origin = 0;
+        Ok(())
+    }
+
+    /// Lists the matches on the rows from the cursor down, newest at the
+    /// bottom and the selection marked by `>`, then draws the prompt and line
+    /// below them.
+    fn draw_history_menu<T: Terminal>(
+        &mut self,
+        terminal: &mut T,
+        menu: &mut HistoryMenu,
+    ) -> Result<()> {
+        let size = terminal.size();
+        let height = size.map_or(HISTORY_MENU_ROWS, |(_, rows)| {
+            usize::from(rows).saturating_sub(2).max(1)
+        });
+        menu.rows = menu.matches.len().min(HISTORY_MENU_ROWS).min(height);
+        // The window of matches shown follows the selection
+        let first = (menu.selected + 1).saturating_sub(menu.rows.max(1));
+        for &index in menu.matches[first..first + menu.rows].iter().rev() {
+            let entry = self.history.get(index).unwrap_or("");
+            // Stay clear of the last column so no row wraps
+            let entry = match size {
+                Some((columns, _)) => {
+                    truncate_to_width(entry, usize::from(columns).saturating_sub(3))
+                }
+                None => entry,
+            };
+            if menu.matches.get(menu.selected) == Some(&index) {
+                terminal.write(b"> ")?;
+                self.theme.selection.paint(terminal, entry.as_bytes())?;
+            } else {
+                terminal.write(b"  ")?;
+                write_visible(terminal, entry.as_bytes())?;
+            }
+            write_newline(terminal)?;
+        }
+        self.draw_line(terminal)?;
+        self.show_status(terminal, "Up/Down: select  Enter: insert  Ctrl+G: cancel")
+    }
+
+    /// Hands the current line to the user's editor and loads the result back.
+    ///
+    /// Raw mode is suspended while the editor runs. If the editor can't be started
+    /// or exits with an error, the line is left unchanged.
+    #[cfg(feature = "std")]
+    fn edit_in_external_editor<T: Terminal>(&mut self, terminal: &mut T) -> Result<()> {
+        let text = self.line.as_str()?.to_string();
+
+        self.hide_status(terminal)?;
+        terminal.exit_raw_mode()?;
+        let edited = external_editor::edit(&text);
+        terminal.enter_raw_mode()?;
+        self.restore_status(terminal)?;
+
+        if let Ok(edited) = edited {
+            self.history.reset_view();
+            self.replace_line(terminal, &edited)?;
+        }
+
+        Ok(())
+    }
+
+    /// Asks whether to submit the corrector's suggestion instead of the line,
+    /// returning `true` if the question awaits an answer.
+    fn offer_correction<T: Terminal>(&mut self, terminal: &mut T) -> Result<bool> {
+        let corrector = match self.corrector.as_mut() {
+            Some(corrector) => corrector,
+            None => return Ok(false),
+        };
+        let line = self.line.as_str()?.trim();
+        if line.is_empty() {
+            return Ok(false);
+        }
+        let suggestion = match corrector.correct(line) {
+            Some(suggestion) if suggestion != line => suggestion,
+            _ => return Ok(false),
+        };
+
+        // Ask after the end of the line
+        let old = self.line.cursor_pos();
+        self.line.move_cursor_to_end();
+        self.move_cursor(terminal, old)?;
+        let question = alloc::format!("  did you mean `{}`? [y/n]", suggestion);
+        self.theme.hint.paint(terminal, question.as_bytes())?;
+        self.mode = Mode::Correction {
+            suggestion,
+            width: display_width(question.as_bytes()),
+        };
+        Ok(true)
+    }
+
+    /// Handles the single-key answer to a correction, then submits the line.
+    fn answer_correction<T: Terminal>(
+        &mut self,
+        terminal: &mut T,
+        suggestion: String,
+        width: usize,
+        event: KeyEvent,
+    ) -> Result<Option<String>> {
+        if event == KeyEvent::Normal('\0') {
+            self.mode = Mode::Correction { suggestion, width };
+            return Ok(None);
+        }
+        cursor_left_by(terminal, width)?;
+        terminal.clear_eol()?;
+        if let KeyEvent::Ctrl('g') | KeyEvent::Escape = event {
+            // Back to editing the line as typed
+            terminal.bell()?;
+            return terminal.flush().map(|_| None);
+        }
+        if let KeyEvent::Normal('y' | 'Y') | KeyEvent::Enter | KeyEvent::LineFeed = event {
+            self.replace_line(terminal, &suggestion)?;
+        }
+        self.finish(terminal).map(Some)
+    }
+
+    /// Inserts a typed character, passing it through the input filter.
+    fn insert_typed<T: Terminal>(&mut self, terminal: &mut T, c: char) -> Result<()> {
+        let filter = match self.filter.as_mut() {
+            Some(filter) => filter,
+            None => return self.insert_char(terminal, c),
+        };
+
+        let ctx = Context::new(self.line.as_str()?, self.line.cursor_pos(), &self.history);
+        match filter.filter(c, &ctx) {
+            Filtered::Insert(c) => self.insert_char(terminal, c),
+            Filtered::InsertStr(text) => {
+                let start = self.line.cursor_pos();
+                let complete = self.line.insert_str(&text);
+                write_visible(
+                    terminal,
+                    &self.line.as_bytes()[start..self.line.cursor_pos()],
+                )?;
+                self.redraw_from_cursor(terminal)?;
+                if !complete {
+                    terminal.bell()?;
+                }
+                Ok(())
+            }
+            Filtered::Discard => Ok(()),
+        }
+    }
+
+    /// Tells the observer a history entry replaced the line.
+    fn recalled(&mut self, entry: &str) {
+        if let Some(observer) = self.observer.as_mut() {
+            observer.on_history_recall(entry);
+        }
+    }
+
+    fn insert_char<T: Terminal>(&mut self, terminal: &mut T, c: char) -> Result<()> {
+        if !self.line.insert_char(c) {
+            return terminal.bell();
+        }
+        let mut buf = [0; 4];
+        let bytes = c.encode_utf8(&mut buf).as_bytes();
+        let width = display_width(bytes);
+        if width > 0 && self.edits_in_place(terminal) {
+            // Make room instead of rewriting the rest of the line
+            for _ in 0..width {
+                terminal.insert_blank()?;
+            }
+            return write_visible(terminal, bytes);
+        }
+        write_visible(terminal, bytes)?;
+        self.redraw_from_cursor(terminal)
+    }
+
+    /// Whether an edit before the end of the line can be shown by inserting or
+    /// deleting characters at the cursor, leaving the rest of the line alone.
+    fn edits_in_place<T: Terminal>(&self, terminal: &T) -> bool {
+        self.line.cursor_pos() < self.line.len() && terminal.capabilities().insert_delete
+    }
+
+    /// Shows the removal of `width` columns at the cursor.
+    fn erase_at_cursor<T: Terminal>(&self, terminal: &mut T, width: usize) -> Result<()> {
+        if !self.edits_in_place(terminal) {
+            return self.redraw_from_cursor(terminal);
+        }
+        for _ in 0..width {
+            terminal.delete_char()?;
+        }
+        Ok(())
+    }
+
+    /// Display width of the buffer bytes between two positions, in either order.
+    fn width_between(&self, a: usize, b: usize) -> usize {
+        display_width(&self.line.as_bytes()[a.min(b)..a.max(b)])
+    }
+
+    /// Moves the terminal cursor from the column of byte position `old` to the
+    /// column of the buffer's current cursor position.
+    fn move_cursor<T: Terminal>(&self, terminal: &mut T, old: usize) -> Result<()> {
+        let new = self.line.cursor_pos();
+        let width = self.width_between(old, new);
+        if new < old {
+            cursor_left_by(terminal, width)
+        } else {
+            cursor_right_by(terminal, width)
+        }
+    }
+
+    fn redraw_from_cursor<T: Terminal>(&self, terminal: &mut T) -> Result<()> {
+        terminal.clear_eol()?;
+
+        let cursor_pos = self.line.cursor_pos();
+        let remaining = &self.line.as_bytes()[cursor_pos..];
+        if !remaining.is_empty() {
+            self.count_redraw();
+        }
+        write_visible(terminal, remaining)?;
+
+        // Move cursor back
+        cursor_left_by(terminal, display_width(remaining))
+    }
+
+    fn clear_line_display<T: Terminal>(&self, terminal: &mut T) -> Result<()> {
+        if self.scroll_width.is_some() {
+            terminal.write(b"\r")?;
+            cursor_right_by(
+                terminal,
+                self.line_origin() + prompt_width(last_line(&self.prompt)),
+            )?;
+            return terminal.clear_eol();
+        }
+        cursor_left_by(terminal, self.width_between(0, self.line.cursor_pos()))?;
+        terminal.clear_eol()?;
+        Ok(())
+    }
+
+    /// Replaces the line with history entry `index`, or with the line saved
+    /// when browsing started, straight from the history's storage.
+    fn load_history<T: Terminal>(&mut self, terminal: &mut T, index: Option<usize>) -> Result<()> {
+        let old_column = self.cursor_column();
+        let complete = self.line.load(self.history.view(index));
+        self.refresh_line(terminal, old_column)?;
+        if !complete {
+            terminal.bell()?;
+        }
+        if let (Some(observer), Some(index)) = (self.observer.as_mut(), index) {
+            observer.on_history_recall(self.history.entry(index));
+        }
+        Ok(())
+    }
+
+    fn replace_line<T: Terminal>(&mut self, terminal: &mut T, text: &str) -> Result<()> {
+        let old_column = self.cursor_column();
+        let complete = self.line.load(text);
+        self.refresh_line(terminal, old_column)?;
+        if !complete {
+            terminal.bell()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Debug for LineEditor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LineEditor")
+            .field("line", &self.line)
+            .field("history", &self.history)
+            .field("prompt", &self.prompt)
+            .field("flow_control", &self.flow_control)
+            .field(
+                "escape_timeout",
+                &self.escape_timeout.map(|(timeout, _)| timeout),
+            )
+            .field("crlf_as_one", &self.crlf_as_one)
+            .field("pasted_newlines", &self.pasted_newlines)
+            .field("tab_width", &self.tab_width)
+            .field("auto_indent", &self.auto_indent)
+            .field("indent_openers", &self.indent_openers)
+            .field("scroll_width", &self.scroll_width)
+            .field("accessible", &self.accessible)
+            .field("announcer", &self.announcer.is_some())
+            .field("echo", &self.echo)
+            .field("completer", &self.completer.is_some())
+            .field("match_policy", &self.match_policy)
+            .field("clipboard", &self.clipboard)
+            .field("line_capacity_limit", &self.line_capacity_limit)
+            .field("hinter", &self.hinter.is_some())
+            .field("filter", &self.filter.is_some())
+            .field("corrector", &self.corrector.is_some())
+            .field("observer", &self.observer.is_some())
+            .field("submit_hook", &self.submit_hook.is_some())
+            .field("unknown_sequence", &self.unknown_sequence.is_some())
+            .field("retry", &self.retry.is_some())
+            .field("renderer", &self.renderer.is_some())
+            .field("theme", &self.theme)
+            .field("status", &self.status)
+            .field("keymap", &self.keymap)
+            .field(
+                "commands",
+                &self.commands.iter().map(|(key, _)| key).collect::<Vec<_>>(),
+            )
+            .finish_non_exhaustive()
+    }
+}
+
+/// Terminal adapter that passes input through and discards all output.
+#[cfg(feature = "alloc")]
+struct NoEcho<'a, T: Terminal>(&'a mut T);
+
+#[cfg(feature = "alloc")]
+impl<T: Terminal> Terminal for NoEcho<'_, T> {
+    fn read_byte(&mut self) -> Result<u8> {
+        self.0.read_byte()
+    }
+
+    fn try_read_byte(&mut self) -> Result<Option<u8>> {
+        self.0.try_read_byte()
+    }
+
+    fn write(&mut self, _data: &[u8]) -> Result<()> {
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        self.0.enter_raw_mode()
+    }
+
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        self.0.exit_raw_mode()
+    }
+
+    fn cursor_left(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn cursor_right(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn clear_eol(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn parse_key_event(&mut self) -> Result<KeyEvent> {
+        self.0.parse_key_event()
+    }
+}
+
+/// Terminal adapter that collects writes and sends them as one on flush, so a
+/// slow link shows the prompt at once rather than piece by piece.
+///
+/// Everything else is left to the wrapped terminal, which may not draw with
+/// writes at all, after sending what was collected so far to keep the order.
+#[cfg(feature = "alloc")]
+struct Batched<'a, T: Terminal> {
+    terminal: &'a mut T,
+    pending: Vec<u8>,
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, T: Terminal> Batched<'a, T> {
+    fn new(terminal: &'a mut T) -> Self {
+        Batched {
+            terminal,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Writes the collected output.
+    fn send(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let result = self.terminal.write(&self.pending);
+        self.pending.clear();
+        result
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Terminal> Terminal for Batched<'_, T> {
+    fn read_byte(&mut self) -> Result<u8> {
+        self.send()?;
+        self.terminal.read_byte()
+    }
+
+    fn try_read_byte(&mut self) -> Result<Option<u8>> {
+        self.send()?;
+        self.terminal.try_read_byte()
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.pending.extend_from_slice(data);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.send()?;
+        self.terminal.flush()
+    }
+
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        self.send()?;
+        self.terminal.enter_raw_mode()
+    }
+
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        self.send()?;
+        self.terminal.exit_raw_mode()
+    }
+
+    fn cursor_left(&mut self) -> Result<()> {
+        self.send()?;
+        self.terminal.cursor_left()
+    }
+
+    fn cursor_right(&mut self) -> Result<()> {
+        self.send()?;
+        self.terminal.cursor_right()
+    }
+
+    fn clear_eol(&mut self) -> Result<()> {
+        self.send()?;
+        self.terminal.clear_eol()
+    }
+
+    fn cursor_up(&mut self) -> Result<()> {
+        self.send()?;
+        self.terminal.cursor_up()
+    }
+
+    fn insert_blank(&mut self) -> Result<()> {
+        self.send()?;
+        self.terminal.insert_blank()
+    }
+
+    fn delete_char(&mut self) -> Result<()> {
+        self.send()?;
+        self.terminal.delete_char()
+    }
+
+    fn clear_to_end_of_screen(&mut self) -> Result<()> {
+        self.send()?;
+        self.terminal.clear_to_end_of_screen()
+    }
+
+    fn clear_screen(&mut self) -> Result<()> {
+        self.send()?;
+        self.terminal.clear_screen()
+    }
+
+    fn bell(&mut self) -> Result<()> {
+        self.send()?;
+        self.terminal.bell()
+    }
+
+    fn enter_alternate_screen(&mut self) -> Result<()> {
+        self.send()?;
+        self.terminal.enter_alternate_screen()
+    }
+
+    fn exit_alternate_screen(&mut self) -> Result<()> {
+        self.send()?;
+        self.terminal.exit_alternate_screen()
+    }
+
+    fn parse_key_event(&mut self) -> Result<KeyEvent> {
+        self.send()?;
+        self.terminal.parse_key_event()
+    }
+
+    fn size(&mut self) -> Option<(u16, u16)> {
+        self.terminal.size()
+    }
+
+    fn get_cursor_position(&mut self) -> Option<(u16, u16)> {
+        self.send().ok()?;
+        self.terminal.get_cursor_position()
+    }
+
+    fn show_status(&mut self, text: &[u8]) -> Result<()> {
+        self.send()?;
+        self.terminal.show_status(text)
+    }
+
+    fn clear_status(&mut self) -> Result<()> {
+        self.send()?;
+        self.terminal.clear_status()
+    }
+
+    fn capabilities(&self) -> Caps {
+        self.terminal.capabilities()
+    }
+}
+
+/// Returns `true` for bytes displayed in caret notation (`^A`, `^[`, `^?`).
+#[cfg(feature = "alloc")]
+fn is_control(byte: u8) -> bool {
+    byte < 0x20 || byte == 0x7f
+}
+
+/// Number of terminal columns needed to display `bytes`.
+///
+/// Control characters take two columns since they're shown in caret notation
+/// (four for C1 controls, see [`write_visible`]), wide characters such as CJK
+/// ideographs take two and combining marks none.
+#[cfg(feature = "alloc")]
+fn display_width(bytes: &[u8]) -> usize {
+    match core::str::from_utf8(bytes) {
+        Ok(text) => text.chars().map(width::char_width).sum(),
+        // Not text; the terminal shows one column per byte at best
+        Err(_) => bytes.len() + bytes.iter().filter(|&&b| is_control(b)).count(),
+    }
+}
+
+/// Longest prefix of `text` that fits in `columns` display columns.
+#[cfg(feature = "alloc")]
+fn truncate_to_width(text: &str, columns: usize) -> &str {
+    let mut width = 0;
+    for (index, c) in text.char_indices() {
+        width += width::char_width(c);
+        if width > columns {
+            return &text[..index];
+        }
+    }
+    text
+}
+
+/// Writes `prompt` in `style`, as given apart from line breaks, so it may carry
+/// its own escape sequences.
+#[cfg(feature = "alloc")]
+fn write_prompt<T: Terminal>(terminal: &mut T, prompt: &str, style: theme::Style) -> Result<()> {
+    if prompt.is_empty() {
+        return Ok(());
+    }
+    style.begin(terminal)?;
+    for (i, line) in prompt.split('\n').enumerate() {
+        if i > 0 {
+            write_newline(terminal)?;
+        }
+        terminal.write(line.as_bytes())?;
+    }
+    style.end(terminal)
+}
+
+/// Starts a new line with the terminal's [line ending](Caps::newline).
+fn write_newline<T: Terminal + ?Sized>(terminal: &mut T) -> Result<()> {
+    let newline = terminal.capabilities().newline();
+    terminal.write(newline)
+}
+
+/// Last line of a prompt that may span several lines.
+#[cfg(feature = "alloc")]
+fn last_line(prompt: &str) -> &str {
+    prompt.rsplit('\n').next().unwrap_or(prompt)
+}
+
+/// Display width of a prompt, skipping ANSI escape sequences such as colors.
+#[cfg(feature = "alloc")]
+fn prompt_width(prompt: &str) -> usize {
+    let mut width = 0;
+    let mut chars = prompt.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // CSI sequences end with a character in '@'..='~'
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+        } else if !c.is_control() {
+            width += width::char_width(c);
+        }
+    }
+    width
+}
+
+/// Writes `bytes`, showing control characters in caret notation so that what
+/// appears on screen matches [`display_width`].
+///
+/// C1 controls (U+0080 to U+009F), which some terminals act on like escape
+/// sequences, are shown as `M-^X`, as `cat -v` does.
+#[cfg(feature = "alloc")]
+fn write_visible<T: Terminal>(terminal: &mut T, bytes: &[u8]) -> Result<()> {
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let len = match bytes[i..] {
+            [b, ..] if is_control(b) => 1,
+            [0xc2, b, ..] if (0x80..0xa0).contains(&b) => 2,
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        if start < i {
+            terminal.write(&bytes[start..i])?;
+        }
+        if len == 2 {
+            terminal.write(b"M-")?;
+        }
+        terminal.write(&[b'^', (bytes[i + len - 1] & 0x7f) ^ 0x40])?;
+        i += len;
+        start = i;
+    }
+    if start < bytes.len() {
+        terminal.write(&bytes[start..])?;
+    }
+    Ok(())
+}
+
+fn cursor_left_by<T: Terminal + ?Sized>(terminal: &mut T, count: usize) -> Result<()> {
+    for _ in 0..count {
+        terminal.cursor_left()?;
+    }
+    Ok(())
+}
+
+fn cursor_right_by<T: Terminal + ?Sized>(terminal: &mut T, count: usize) -> Result<()> {
+    for _ in 0..count {
+        terminal.cursor_right()?;
+    }
+    Ok(())
+}
+
+#[macro_use]
+mod trace;
+
+#[cfg(feature = "alloc")]
+pub mod accessibility;
+pub mod ansi;
+#[cfg(feature = "async")]
+pub mod asynch;
+pub mod caps;
+#[cfg(feature = "alloc")]
+pub mod command;
+#[cfg(feature = "alloc")]
+pub mod completion;
+#[cfg(feature = "alloc")]
+mod context;
+#[cfg(feature = "alloc")]
+pub mod correction;
+#[cfg(feature = "alloc")]
+pub mod diagnostics;
+#[cfg(feature = "alloc")]
+mod echo_strip;
+#[cfg(feature = "alloc")]
+pub mod events;
+#[cfg(feature = "alloc")]
+pub mod filter;
+#[cfg(feature = "alloc")]
+mod fullscreen;
+#[cfg(feature = "alloc")]
+pub mod hint;
+#[cfg(feature = "alloc")]
+pub mod invariants;
+#[cfg(feature = "alloc")]
+pub mod keymap;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "alloc")]
+pub mod mux;
+#[cfg(feature = "alloc")]
+pub mod newline;
+#[cfg(feature = "alloc")]
+pub mod observer;
+#[cfg(feature = "alloc")]
+mod paced;
+#[cfg(feature = "alloc")]
+pub mod persist;
+#[cfg(feature = "alloc")]
+pub mod prompt;
+#[cfg(all(unix, feature = "pty-harness"))]
+pub mod pty;
+#[cfg(feature = "alloc")]
+mod raw_mode;
+#[cfg(feature = "std")]
+pub mod recording;
+#[cfg(feature = "alloc")]
+pub mod render;
+#[cfg(feature = "repl")]
+pub mod repl;
+#[cfg(feature = "alloc")]
+pub mod retry;
+#[cfg(feature = "std")]
+pub mod server;
+#[cfg(feature = "alloc")]
+pub mod session;
+#[cfg(feature = "alloc")]
+pub mod snapshot;
+pub mod static_editor;
+#[cfg(feature = "alloc")]
+pub mod submit;
+#[cfg(feature = "alloc")]
+mod tee;
+mod telnet;
+#[cfg(feature = "alloc")]
+pub mod theme;
+mod width;
+
+#[cfg(feature = "alloc")]
+pub use accessibility::Announcer;
+pub use caps::Caps;
+#[cfg(feature = "alloc")]
+pub use command::{Command, HandlerResult};
+#[cfg(feature = "alloc")]
+pub use completion::{Completer, MatchPolicy};
+#[cfg(feature = "alloc")]
+pub use context::Context;
+#[cfg(feature = "alloc")]
+pub use correction::Corrector;
+#[cfg(feature = "alloc")]
+pub use echo_strip::EchoStripTerminal;
+#[cfg(feature = "alloc")]
+pub use filter::{Filtered, InputFilter};
+#[cfg(feature = "alloc")]
+pub use hint::Hinter;
+#[cfg(feature = "alloc")]
+pub use invariants::LineBufferOp;
+#[cfg(feature = "alloc")]
+pub use keymap::{Action, Keymap};
+#[cfg(feature = "alloc")]
+pub use newline::PastedNewline;
+#[cfg(feature = "alloc")]
+pub use observer::Observer;
+#[cfg(feature = "alloc")]
+pub use paced::PacedTerminal;
+#[cfg(feature = "alloc")]
+pub use raw_mode::RawModeGuard;
+#[cfg(feature = "alloc")]
+pub use render::Renderer;
+#[cfg(feature = "alloc")]
+pub use retry::RetryPolicy;
+#[cfg(feature = "alloc")]
+pub use snapshot::EditorState;
+pub use static_editor::StaticLineEditor;
+#[cfg(feature = "alloc")]
+pub use submit::SubmitHook;
+#[cfg(feature = "alloc")]
+pub use tee::TeeTerminal;
+pub use telnet::TelnetTerminal;
+#[cfg(feature = "alloc")]
+pub use theme::Theme;
+
+#[cfg(feature = "std")]
+mod external_editor;
+
+// Re-export terminal implementations
+#[cfg(any(
+    feature = "std",
+    feature = "heapless",
+    feature = "microbit",
+    feature = "rp_pico_usb",
+    feature = "rp_pico2_usb",
+    feature = "nrf52840_usb",
+    feature = "usb-cdc",
+    feature = "nb-serial",
+    feature = "esp32c3",
+    feature = "embassy"
+))]
+pub mod terminals;
+
+#[cfg(all(test, feature = "alloc"))]
+mod testing;
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use alloc::{format, vec};
+
+    /// In-memory terminal that replays scripted key events and records output.
+    struct MockTerminal {
+        events: Vec<KeyEvent>,
+        output: Vec<u8>,
+        size: Option<(u16, u16)>,
+        position: Option<(u16, u16)>,
+        caps: Caps,
+    }
+
+    impl MockTerminal {
+        fn new(events: &[KeyEvent]) -> Self {
+            let mut events = events.to_vec();
+            events.reverse();
+            Self {
+                events,
+                output: Vec::new(),
+                size: None,
+                position: None,
+                caps: Caps::ANSI,
+            }
+        }
+
+        fn output(&self) -> &str {
+            core::str::from_utf8(&self.output).unwrap()
+        }
+    }
+
+    impl Terminal for MockTerminal {
+        fn read_byte(&mut self) -> Result<u8> {
+            Err(Error::Eof)
+        }
+        fn write(&mut self, data: &[u8]) -> Result<()> {
+            self.output.extend_from_slice(data);
+            Ok(())
+        }
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn enter_raw_mode(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn exit_raw_mode(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn cursor_left(&mut self) -> Result<()> {
+            self.write(b"\x1b[D")
+        }
+        fn cursor_right(&mut self) -> Result<()> {
+            self.write(b"\x1b[C")
+        }
+        fn clear_eol(&mut self) -> Result<()> {
+            self.write(b"\x1b[K")
+        }
+        fn parse_key_event(&mut self) -> Result<KeyEvent> {
+            self.events.pop().ok_or(Error::Eof)
+        }
+        fn size(&mut self) -> Option<(u16, u16)> {
+            self.size
+        }
+        fn get_cursor_position(&mut self) -> Option<(u16, u16)> {
+            self.position
+        }
+        fn capabilities(&self) -> Caps {
+            self.caps
+        }
+    }
+
+    #[test]
+    fn test_first_paint_is_one_write() {
+        /// Terminal recording each write separately.
+        struct Writes(Vec<Vec<u8>>);
+
+        impl Terminal for Writes {
+            fn read_byte(&mut self) -> Result<u8> {
+                Err(Error::Eof)
+            }
+            fn write(&mut self, data: &[u8]) -> Result<()> {
+                self.0.push(data.to_vec());
+                Ok(())
+            }
+            fn flush(&mut self) -> Result<()> {
+                Ok(())
+            }
+            fn enter_raw_mode(&mut self) -> Result<()> {
+                Ok(())
+            }
+            fn exit_raw_mode(&mut self) -> Result<()> {
+                Ok(())
+            }
+            fn cursor_left(&mut self) -> Result<()> {
+                self.write(b"\x1b[D")
+            }
+            fn cursor_right(&mut self) -> Result<()> {
+                self.write(b"\x1b[C")
+            }
+            fn clear_eol(&mut self) -> Result<()> {
+                self.write(b"\x1b[K")
+            }
+            fn parse_key_event(&mut self) -> Result<KeyEvent> {
+                Err(Error::Eof)
+            }
+            fn capabilities(&self) -> Caps {
+                Caps::ANSI
+            }
+        }
+
+        let mut editor = LineEditor::new(64, 10);
+        editor.set_theme(Theme {
+            prompt: theme::Style::new().bold(),
+            ..Theme::new()
+        });
+        let mut terminal = Writes(Vec::new());
+        assert!(editor
+            .read_line_with_prompt(&mut terminal, "device> ")
+            .is_err());
+        assert_eq!(terminal.0[0], b"\x1b[0;1mdevice> \x1b[0m");
+    }
+
+    fn type_line(text: &str) -> Vec<KeyEvent> {
+        let mut events: Vec<KeyEvent> = text.chars().map(KeyEvent::Normal).collect();
+        events.push(KeyEvent::Enter);
+        events
+    }
+
+    // LineBuffer tests
+    #[test]
+    fn test_line_buffer_insert() {
+        let mut buf = LineBuffer::new(100);
+        buf.insert_char('h');
+        buf.insert_char('i');
+        assert_eq!(buf.as_str().unwrap(), "hi");
+        assert_eq!(buf.cursor_pos(), 2);
+        assert_eq!(buf.len(), 2);
+    }
+
+    #[test]
+    fn test_line_buffer_backspace() {
+        let mut buf = LineBuffer::new(100);
+        buf.insert_char('h');
+        buf.insert_char('i');
+        assert!(buf.delete_before_cursor());
+        assert_eq!(buf.as_str().unwrap(), "h");
+        assert_eq!(buf.cursor_pos(), 1);
+    }
+
+    #[test]
+    fn test_line_buffer_delete() {
+        let mut buf = LineBuffer::new(100);
+        buf.insert_char('h');
+        buf.insert_char('i');
+        buf.move_cursor_left();
+        assert!(buf.delete_at_cursor());
+        assert_eq!(buf.as_str().unwrap(), "h");
         assert_eq!(buf.cursor_pos(), 1);
     }
 
     #[test]
-    fn test_line_buffer_delete() {
-        let mut buf = LineBuffer::new(100);
-        buf.insert_char('h');
-        buf.insert_char('i');
-        buf.move_cursor_left();
-        assert!(buf.delete_at_cursor());
-        assert_eq!(buf.as_str().unwrap(), "h");
-        assert_eq!(buf.cursor_pos(), 1);
+    fn test_line_buffer_cursor_movement() {
+        let mut buf = LineBuffer::new(100);
+        buf.insert_char('h');
+        buf.insert_char('e');
+        buf.insert_char('y');
+        assert_eq!(buf.cursor_pos(), 3);
+
+        assert!(buf.move_cursor_left());
+        assert_eq!(buf.cursor_pos(), 2);
+
+        assert!(buf.move_cursor_right());
+        assert_eq!(buf.cursor_pos(), 3);
+
+        assert!(!buf.move_cursor_right()); // at end
+    }
+
+    #[test]
+    fn test_line_buffer_home_end() {
+        let mut buf = LineBuffer::new(100);
+        buf.insert_char('h');
+        buf.insert_char('e');
+        buf.insert_char('y');
+
+        buf.move_cursor_to_start();
+        assert_eq!(buf.cursor_pos(), 0);
+
+        buf.move_cursor_to_end();
+        assert_eq!(buf.cursor_pos(), 3);
+    }
+
+    #[test]
+    fn test_line_buffer_word_navigation() {
+        let mut buf = LineBuffer::new(100);
+        for c in "hello world test".chars() {
+            buf.insert_char(c);
+        }
+
+        // At end: "hello world test|"
+        buf.move_cursor_word_left();
+        assert_eq!(buf.cursor_pos(), 12); // "hello world |test"
+
+        buf.move_cursor_word_left();
+        assert_eq!(buf.cursor_pos(), 6); // "hello |world test"
+
+        buf.move_cursor_word_right();
+        assert_eq!(buf.cursor_pos(), 12); // "hello world |test"
+    }
+
+    #[test]
+    fn test_line_buffer_delete_word() {
+        let mut buf = LineBuffer::new(100);
+        for c in "hello world".chars() {
+            buf.insert_char(c);
+        }
+
+        assert_eq!(buf.delete_word_left(), "world");
+        assert_eq!(buf.as_str().unwrap(), "hello ");
+
+        assert_eq!(buf.delete_word_left(), "hello ");
+        assert_eq!(buf.as_str().unwrap(), "");
+        assert_eq!(buf.delete_word_left(), "");
+    }
+
+    #[test]
+    fn test_line_buffer_delete_word_right() {
+        let mut buf = LineBuffer::new(100);
+        for c in "hello world".chars() {
+            buf.insert_char(c);
+        }
+        buf.move_cursor_to_start();
+
+        assert_eq!(buf.delete_word_right(), "hello ");
+        assert_eq!(buf.as_str().unwrap(), "world");
+    }
+
+    #[test]
+    fn test_line_buffer_insert_middle() {
+        let mut buf = LineBuffer::new(100);
+        buf.insert_char('h');
+        buf.insert_char('e');
+        buf.move_cursor_left();
+        buf.insert_char('x');
+        assert_eq!(buf.as_str().unwrap(), "hxe");
+        assert_eq!(buf.cursor_pos(), 2);
+    }
+
+    #[test]
+    fn test_word_navigation_with_symbols() {
+        let mut buf = LineBuffer::new(100);
+        for c in "3 + 5".chars() {
+            buf.insert_char(c);
+        }
+        // Cursor at end: "3 + 5|"
+
+        // Move left by word - should stop at '5'
+        buf.move_cursor_word_left();
+        assert_eq!(buf.cursor_pos(), 4); // Before '5'
+
+        // Move left by word - should stop at '+'
+        buf.move_cursor_word_left();
+        assert_eq!(buf.cursor_pos(), 2); // Before '+'
+
+        // Move left by word - should stop at '3'
+        buf.move_cursor_word_left();
+        assert_eq!(buf.cursor_pos(), 0); // Before '3'
+
+        // Move right by word - should stop after '3'
+        buf.move_cursor_word_right();
+        assert_eq!(buf.cursor_pos(), 2); // After '3 ', before '+'
+
+        // Move right by word - should stop after '+'
+        buf.move_cursor_word_right();
+        assert_eq!(buf.cursor_pos(), 4); // After '+ ', before '5'
+    }
+
+    #[test]
+    fn test_delete_word_with_symbols() {
+        let mut buf = LineBuffer::new(100);
+        for c in "3 + 5".chars() {
+            buf.insert_char(c);
+        }
+        // Cursor at end: "3 + 5|"
+
+        // Delete word left - should delete '5'
+        buf.delete_word_left();
+        assert_eq!(buf.as_str().unwrap(), "3 + ");
+
+        // Delete word left - should delete '+'
+        buf.delete_word_left();
+        assert_eq!(buf.as_str().unwrap(), "3 ");
+    }
+
+    // History tests
+    #[test]
+    fn test_history_add() {
+        let mut hist = History::new(10);
+        hist.add("first");
+        hist.add("second");
+
+        assert_eq!(hist.previous(""), Some("second"));
+        assert_eq!(hist.previous(""), Some("first"));
+        assert_eq!(hist.previous(""), None); // no more
+    }
+
+    #[test]
+    fn test_history_skip_empty() {
+        let mut hist = History::new(10);
+        hist.add("first");
+        hist.add("");
+        hist.add("second");
+
+        assert_eq!(hist.previous(""), Some("second"));
+        assert_eq!(hist.previous(""), Some("first"));
+        assert_eq!(hist.previous(""), None);
+    }
+
+    #[test]
+    fn test_history_skip_duplicates() {
+        let mut hist = History::new(10);
+        hist.add("test");
+        hist.add("test"); // should be skipped
+        hist.add("other");
+
+        assert_eq!(hist.previous(""), Some("other"));
+        assert_eq!(hist.previous(""), Some("test"));
+        assert_eq!(hist.previous(""), None);
+    }
+
+    #[test]
+    fn test_history_navigation() {
+        let mut hist = History::new(10);
+        hist.add("first");
+        hist.add("second");
+        hist.add("third");
+
+        // Go back through history
+        assert_eq!(hist.previous(""), Some("third"));
+        assert_eq!(hist.previous(""), Some("second"));
+
+        // Go forward
+        assert_eq!(hist.next_entry(), Some("third"));
+        assert_eq!(hist.next_entry(), Some("")); // returns saved line (empty string)
+    }
+
+    #[test]
+    fn test_history_saves_current_line() {
+        let mut hist = History::new(10);
+        hist.add("first");
+        hist.add("second");
+
+        // Start typing something
+        assert_eq!(hist.previous("hello"), Some("second"));
+        assert_eq!(hist.previous("hello"), Some("first"));
+
+        // Navigate back forward
+        assert_eq!(hist.next_entry(), Some("second"));
+        assert_eq!(hist.next_entry(), Some("hello")); // restored!
+    }
+
+    #[test]
+    fn test_history_down_without_up() {
+        let mut hist = History::new(10);
+        hist.add("first");
+
+        // Down without going up first should do nothing
+        assert_eq!(hist.next_entry(), None);
+    }
+
+    #[test]
+    fn test_history_circular_buffer() {
+        let mut hist = History::new(3);
+        hist.add("first");
+        hist.add("second");
+        hist.add("third");
+        hist.add("fourth"); // overwrites "first"
+
+        assert_eq!(hist.previous(""), Some("fourth"));
+        assert_eq!(hist.previous(""), Some("third"));
+        assert_eq!(hist.previous(""), Some("second"));
+        assert_eq!(hist.previous(""), None); // "first" was overwritten
+    }
+
+    #[test]
+    fn test_history_reset_view() {
+        let mut hist = History::new(10);
+        hist.add("first");
+        hist.add("second");
+
+        assert_eq!(hist.previous(""), Some("second"));
+        hist.reset_view();
+
+        // After reset, previous() should start from most recent again
+        assert_eq!(hist.previous(""), Some("second"));
+    }
+
+    #[test]
+    fn test_history_byte_budget() {
+        let mut hist = History::new(10);
+        hist.add("aaaa");
+        hist.add("bbbbbb");
+        hist.add("cc");
+        assert_eq!(hist.memory_usage(), 12);
+
+        hist.set_max_bytes(Some(9));
+        assert_eq!(hist.iter().collect::<Vec<_>>(), ["bbbbbb", "cc"]);
+        assert_eq!(hist.memory_usage(), 8);
+
+        // Too long to keep at all, and no reason to drop the others
+        hist.add("0123456789");
+        assert_eq!(hist.iter().collect::<Vec<_>>(), ["bbbbbb", "cc"]);
+        assert_eq!(hist.memory_usage(), 8);
+
+        hist.add("dd");
+        hist.add("eee");
+        assert_eq!(hist.iter().collect::<Vec<_>>(), ["cc", "dd", "eee"]);
+        assert_eq!(hist.previous(""), Some("eee"));
+        assert_eq!(hist.previous(""), Some("dd"));
+        assert_eq!(hist.previous(""), Some("cc"));
+        assert_eq!(hist.previous(""), None);
+        assert_eq!(hist.next_entry(), Some("dd"));
+        assert_eq!(hist.next_entry(), Some("eee"));
+        assert_eq!(hist.next_entry(), Some(""));
+    }
+
+    #[test]
+    fn test_history_arena_reclaims_dropped_entries() {
+        let mut hist = History::new(3);
+        for i in 0..1000 {
+            hist.add(&format!("command {}", i));
+        }
+        assert_eq!(
+            hist.iter().collect::<Vec<_>>(),
+            ["command 997", "command 998", "command 999"]
+        );
+        assert!(hist.arena.len() <= 2 * hist.memory_usage());
+        assert_eq!(hist.get(1), Some("command 998"));
+    }
+
+    #[test]
+    fn test_history_iter_after_wrap() {
+        let mut hist = History::new(3);
+        hist.add("first");
+        hist.add("second");
+        hist.add("third");
+        hist.add("fourth");
+
+        let entries: Vec<&str> = hist.iter().collect();
+        assert_eq!(entries, ["second", "third", "fourth"]);
+        assert_eq!(hist.get(0), Some("second"));
+        assert_eq!(hist.get(2), Some("fourth"));
+        assert_eq!(hist.get(3), None);
+    }
+
+    #[test]
+    fn test_history_ids() {
+        let mut hist = History::new(3);
+        for line in ["one", "two", "two", "three", "four", "five"] {
+            hist.add(line);
+        }
+        assert_eq!(
+            hist.iter_with_ids().collect::<Vec<_>>(),
+            [(3, "three"), (4, "four"), (5, "five")]
+        );
+        assert_eq!(hist.iter_with_ids().next_back(), Some((5, "five")));
+        assert_eq!(hist.get_by_id(2), None);
+        assert_eq!(hist.get_by_id(4), Some("four"));
+        assert_eq!(hist.get_by_id(6), None);
+        assert_eq!(hist.get_by_id(u64::MAX), None);
+        assert_eq!(hist.id_of(0), Some(3));
+        assert_eq!(hist.next_id(), 6);
+
+        let mut editor = LineEditor::new(100, 2);
+        for line in ["ls", "pwd", "date"] {
+            editor
+                .read_line(&mut MockTerminal::new(&type_line(line)))
+                .unwrap();
+        }
+        let mut out = MockTerminal::new(&[]);
+        editor.print_history(&mut out, ..).unwrap();
+        assert_eq!(out.output(), "    2  pwd\r\n    3  date\r\n");
+    }
+
+    #[test]
+    fn test_print_history() {
+        let mut editor = LineEditor::new(100, 10);
+        let mut events = type_line("ls");
+        events.extend(type_line("pwd"));
+        let mut term = MockTerminal::new(&events);
+        editor.read_line(&mut term).unwrap();
+        editor.read_line(&mut term).unwrap();
+
+        let mut out = MockTerminal::new(&[]);
+        editor.print_history(&mut out, ..).unwrap();
+        assert_eq!(out.output(), "    1  ls\r\n    2  pwd\r\n");
+
+        // A terminal whose output processing starts new lines gets `\n` alone
+        let mut out = MockTerminal::new(&[]);
+        out.caps = Caps::ANSI.with_crlf(false);
+        editor.print_history(&mut out, ..).unwrap();
+        assert_eq!(out.output(), "    1  ls\n    2  pwd\n");
+
+        let mut out = MockTerminal::new(&[]);
+        editor.print_history(&mut out, 1..).unwrap();
+        assert_eq!(out.output(), "    2  pwd\r\n");
+
+        // Escape sequences in entries aren't passed to the terminal
+        let mut events = vec![KeyEvent::Normal('\x1b')];
+        events.extend(type_line("[2J"));
+        editor.read_line(&mut MockTerminal::new(&events)).unwrap();
+        let mut out = MockTerminal::new(&[]);
+        editor.print_history(&mut out, 2..).unwrap();
+        assert_eq!(out.output(), "    3  ^[[2J\r\n");
+    }
+
+    #[test]
+    fn test_recall() {
+        let mut editor = LineEditor::new(100, 10);
+        for line in ["ls", "pwd", "date"] {
+            editor
+                .read_line(&mut MockTerminal::new(&type_line(line)))
+                .unwrap();
+        }
+        assert_eq!(editor.recall(0), None);
+        assert_eq!(editor.recall(4), None);
+        assert_eq!(editor.recall(-4), None);
+        assert_eq!(editor.recall(i64::MIN), None);
+        assert_eq!(editor.recall(-1).as_deref(), Some("date"));
+        assert_eq!(editor.recall(2).as_deref(), Some("pwd"));
+        let mut term = MockTerminal::new(&type_line(" -P"));
+        assert_eq!(editor.read_line(&mut term).unwrap(), "pwd -P");
+
+        editor.bind(KeyEvent::Ctrl('o'), Action::Recall(-2));
+        editor.bind(KeyEvent::Ctrl('p'), Action::Recall(99));
+        let mut term = MockTerminal::new(&[
+            KeyEvent::Normal('x'),
+            KeyEvent::Ctrl('p'),
+            KeyEvent::Ctrl('o'),
+            KeyEvent::Enter,
+        ]);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "date");
+    }
+
+    #[test]
+    fn test_ctrl_c_and_ctrl_d() {
+        let mut editor = LineEditor::new(100, 10);
+        let mut term = MockTerminal::new(&[KeyEvent::Normal('x'), KeyEvent::Ctrl('c')]);
+        assert!(matches!(
+            editor.read_line(&mut term),
+            Err(Error::Interrupted)
+        ));
+
+        let mut term = MockTerminal::new(&[KeyEvent::Ctrl('d')]);
+        assert!(matches!(editor.read_line(&mut term), Err(Error::Eof)));
+    }
+
+    /// Keys from `inner`, and the raw bytes read after Ctrl+V from `raw`,
+    /// last first.
+    struct QuotedTerminal {
+        inner: MockTerminal,
+        raw: Vec<u8>,
+    }
+
+    impl Terminal for QuotedTerminal {
+        fn read_byte(&mut self) -> Result<u8> {
+            self.raw.pop().ok_or(Error::Eof)
+        }
+        fn write(&mut self, data: &[u8]) -> Result<()> {
+            self.inner.write(data)
+        }
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn enter_raw_mode(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn exit_raw_mode(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn cursor_left(&mut self) -> Result<()> {
+            self.inner.cursor_left()
+        }
+        fn cursor_right(&mut self) -> Result<()> {
+            self.inner.cursor_right()
+        }
+        fn clear_eol(&mut self) -> Result<()> {
+            self.inner.clear_eol()
+        }
+        fn parse_key_event(&mut self) -> Result<KeyEvent> {
+            self.inner.parse_key_event()
+        }
+    }
+
+    #[test]
+    fn test_quoted_insert_renders_caret() {
+        let mut editor = LineEditor::new(100, 10);
+        let mut term = QuotedTerminal {
+            inner: MockTerminal::new(&[
+                KeyEvent::Normal('a'),
+                KeyEvent::Ctrl('v'),
+                KeyEvent::Left,
+                KeyEvent::Enter,
+            ]),
+            raw: vec![0x1b],
+        };
+        assert_eq!(editor.read_line(&mut term).unwrap(), "a\x1b");
+        // The escape is shown as "^[" and moving left over it takes two columns
+        assert!(term.inner.output().ends_with("^[\x1b[K\x1b[D\x1b[D\r\n"));
+    }
+
+    #[test]
+    fn test_quoted_insert_multibyte() {
+        let mut editor = LineEditor::new(100, 10);
+        let mut term = QuotedTerminal {
+            inner: MockTerminal::new(&[
+                KeyEvent::Normal('a'),
+                KeyEvent::Ctrl('v'),
+                KeyEvent::Enter,
+            ]),
+            raw: "é".bytes().rev().collect(),
+        };
+        assert_eq!(editor.read_line(&mut term).unwrap(), "aé");
+        assert!(term.inner.output().contains('é'));
+    }
+
+    #[test]
+    fn test_control_chars_from_history_render_visibly() {
+        let mut editor = editor_with_history(&["a\u{9b}b\x07c"]);
+        let events = [
+            KeyEvent::Up,
+            KeyEvent::Left,
+            KeyEvent::Left,
+            KeyEvent::Left,
+            KeyEvent::Enter,
+        ];
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "a\u{9b}b\x07c");
+        // Nothing the terminal would act on is written, and moving left over
+        // "^G" takes two columns
+        let output = term.output();
+        assert!(output.contains("aM-^[b^Gc"));
+        assert!(!output.contains('\u{9b}') && !output.contains('\x07'));
+        assert!(output.ends_with("\x1b[D\x1b[D\x1b[D\x1b[D\r\n"));
+        assert_eq!(display_width("a\u{9b}b\x07c".as_bytes()), 9);
+    }
+
+    fn editor_with_history(entries: &[&str]) -> LineEditor {
+        let mut editor = LineEditor::new(100, 10);
+        for entry in entries {
+            editor.history.add(entry);
+        }
+        editor
+    }
+
+    #[test]
+    fn test_reverse_search() {
+        let mut editor = editor_with_history(&["make test", "ls", "make run"]);
+        let mut events = vec![KeyEvent::Ctrl('r'), KeyEvent::Normal('m')];
+        events.extend(type_line(""));
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "make run");
+
+        let mut events = vec![
+            KeyEvent::Ctrl('r'),
+            KeyEvent::Normal('m'),
+            KeyEvent::Ctrl('r'),
+        ];
+        events.extend(type_line(""));
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "make test");
+    }
+
+    #[test]
+    fn test_forward_search_and_abort() {
+        let mut editor = editor_with_history(&["make test", "ls", "make run"]);
+        let mut events = vec![KeyEvent::Ctrl('s'), KeyEvent::Normal('m')];
+        events.extend(type_line(""));
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "make test");
+
+        let mut events = type_line("ab");
+        events.insert(2, KeyEvent::Ctrl('r'));
+        events.insert(3, KeyEvent::Normal('l'));
+        events.insert(4, KeyEvent::Ctrl('g'));
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "ab");
+    }
+
+    #[test]
+    fn test_history_menu() {
+        let mut editor = editor_with_history(&["make test", "ls", "make run", "make test"]);
+        let mut events = vec![
+            KeyEvent::Normal('m'),
+            KeyEvent::Ctrl(' '),
+            KeyEvent::Up,
+            KeyEvent::Enter,
+        ];
+        events.extend(type_line(""));
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "make run");
+        assert!(term.output().contains("> make run\r\n  make test\r\n"));
+
+        // Typing narrows the list; other keys close it and go on editing
+        let mut events = vec![KeyEvent::Ctrl(' ')];
+        events.extend(type_line("make t"));
+        events.insert(7, KeyEvent::Enter);
+        events.insert(8, KeyEvent::Normal('!'));
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "make test!");
+
+        let events = type_line("m");
+        let mut events = [
+            &events[..1],
+            &[KeyEvent::Ctrl(' '), KeyEvent::Down, KeyEvent::Ctrl('g')],
+            &events[1..],
+        ]
+        .concat();
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "m");
+
+        // Nothing to pick from
+        events[0] = KeyEvent::Normal('x');
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "x");
+        assert!(!term.output().contains("Enter: insert"));
+    }
+
+    #[test]
+    fn test_clipboard_copy() {
+        let mut events = type_line("hello world");
+        events.insert(11, KeyEvent::AltBackspace);
+        let mut editor = LineEditor::new(100, 10);
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "hello");
+        assert!(!term.output().contains("\x1b]52"));
+
+        editor.set_clipboard(true);
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "hello");
+        assert!(term.output().contains("\x1b]52;c;d29ybGQ=\x07"));
+    }
+
+    #[test]
+    fn test_clipboard_paste_keeps_typed_ahead() {
+        struct ByteTerminal {
+            input: Vec<u8>,
+        }
+
+        impl Terminal for ByteTerminal {
+            fn read_byte(&mut self) -> Result<u8> {
+                if self.input.is_empty() {
+                    Err(Error::Eof)
+                } else {
+                    Ok(self.input.remove(0))
+                }
+            }
+            fn write(&mut self, _: &[u8]) -> Result<()> {
+                Ok(())
+            }
+            fn flush(&mut self) -> Result<()> {
+                Ok(())
+            }
+            fn enter_raw_mode(&mut self) -> Result<()> {
+                Ok(())
+            }
+            fn exit_raw_mode(&mut self) -> Result<()> {
+                Ok(())
+            }
+            fn cursor_left(&mut self) -> Result<()> {
+                Ok(())
+            }
+            fn cursor_right(&mut self) -> Result<()> {
+                Ok(())
+            }
+            fn clear_eol(&mut self) -> Result<()> {
+                Ok(())
+            }
+        }
+
+        // Keys typed while the reply was on its way, an arrow among them
+        let mut editor = LineEditor::new(100, 10);
+        editor.set_clipboard(true);
+        let mut term = ByteTerminal {
+            input: b"ab\x19cd\x1b[De\x1b]52;c;aGk=\x07f\r".to_vec(),
+        };
+        assert_eq!(editor.read_line(&mut term).unwrap(), "abhicefd");
+    }
+
+    #[test]
+    fn test_unknown_sequence_handler() {
+        use alloc::rc::Rc;
+        use core::cell::RefCell;
+
+        let mut events = type_line("ab");
+        events.insert(
+            1,
+            KeyEvent::Unknown(ansi::UnknownSequence::new(b"\x1b[99~")),
+        );
+        let mut editor = LineEditor::new(100, 10);
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "ab");
+
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let log = seen.clone();
+        editor.set_unknown_sequence_handler(move |sequence: &[u8]| {
+            log.borrow_mut().push(sequence.to_vec())
+        });
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "ab");
+        assert_eq!(*seen.borrow(), [b"\x1b[99~".to_vec()]);
+    }
+
+    #[test]
+    fn test_yank_previous_word() {
+        let mut editor = editor_with_history(&["flash /dev/ttyACM0 0x08000000"]);
+        let mut events = type_line("read ");
+        events.insert(5, KeyEvent::Alt('_'));
+        events.insert(6, KeyEvent::Alt('_'));
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "read /dev/ttyACM0");
+
+        // Past the first word, the last one yanked stays
+        let mut editor = editor_with_history(&["flash /dev/ttyACM0 0x08000000"]);
+        let mut events = vec![KeyEvent::Alt('.'); 4];
+        events.push(KeyEvent::Enter);
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "flash");
+
+        // Any other key in between starts over at the last word
+        let mut editor = editor_with_history(&["erase 0x4000"]);
+        let events = [
+            KeyEvent::Alt('_'),
+            KeyEvent::Normal(' '),
+            KeyEvent::Alt('_'),
+            KeyEvent::Alt('x'),
+            KeyEvent::Enter,
+        ];
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "0x4000 0x4000x");
+    }
+
+    #[test]
+    fn test_flow_control_ignores_ctrl_s() {
+        let mut editor = editor_with_history(&["make"]);
+        editor.set_flow_control(true);
+        let mut events = vec![KeyEvent::Ctrl('s')];
+        events.extend(type_line("m"));
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "m");
+    }
+
+    #[test]
+    fn test_newline_policy() {
+        use KeyEvent::{Enter, LineFeed, Normal, PasteEnd, PasteStart};
+
+        // CRLF submits twice unless collapsed, also across reads
+        let mut editor = LineEditor::new(100, 10);
+        let mut term = MockTerminal::new(&[Normal('a'), Enter, LineFeed, Normal('b'), Enter]);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "a");
+        assert_eq!(editor.read_line(&mut term).unwrap(), "");
+        editor.set_crlf_as_one_enter(true);
+        let mut term = MockTerminal::new(&[Normal('a'), Enter, LineFeed, Normal('b'), LineFeed]);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "a");
+        assert_eq!(editor.read_line(&mut term).unwrap(), "b");
+
+        let paste = [
+            PasteStart,
+            Normal('a'),
+            Enter,
+            LineFeed,
+            Normal('b'),
+            PasteEnd,
+            Enter,
+        ];
+        editor.set_pasted_newlines(PastedNewline::Space);
+        let mut term = MockTerminal::new(&paste);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "a b");
+        assert!(term.output().starts_with("\x1b[?2004h"));
+        assert!(term.output().ends_with("\x1b[?2004l"));
+        editor.set_pasted_newlines(PastedNewline::Literal);
+        let mut term = MockTerminal::new(&paste);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "a\nb");
+    }
+
+    #[test]
+    fn test_paste_shown_once() {
+        use KeyEvent::{Enter, Normal, PasteEnd, PasteStart};
+
+        let mut editor = LineEditor::new(100, 10);
+        let mut term = MockTerminal::new(&[
+            Normal('x'),
+            PasteStart,
+            Normal('a'),
+            Normal('b'),
+            Normal('c'),
+            PasteEnd,
+            Enter,
+        ]);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "xabc");
+        // One repaint for the whole paste rather than an echo per character
+        assert_eq!(term.output(), "x\x1b[K\r\x1b[Jxabc\r\n");
+    }
+
+    #[test]
+    fn test_echo_disabled() {
+        let mut editor = LineEditor::new(100, 10);
+        editor.set_echo(false);
+        let mut events = type_line("hi");
+        events.insert(1, KeyEvent::Left);
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "ih");
+        assert_eq!(term.output(), "");
+    }
+
+    fn command_completer(_ctx: &Context) -> Vec<String> {
+        ["help", "hello", "history", "exit"]
+            .iter()
+            .map(|c| c.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_tab_completion() {
+        let mut editor = LineEditor::new(100, 10);
+        editor.set_completer(command_completer);
+
+        // Unique match is completed with a trailing space
+        let mut events = type_line("ex");
+        events.insert(2, KeyEvent::Tab);
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "exit");
+
+        // Several matches are extended to their common prefix
+        let mut events = type_line("he");
+        events.insert(2, KeyEvent::Tab);
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "hel");
+    }
+
+    #[test]
+    fn test_tab_without_completer_indents() {
+        let mut editor = LineEditor::new(100, 10);
+        editor.set_tab_width(4);
+        let mut events = type_line("ab漢x");
+        events.insert(0, KeyEvent::Tab);
+        events.insert(3, KeyEvent::Tab);
+        events.insert(5, KeyEvent::Tab);
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "ab  漢  x");
+
+        editor.set_tab_width(0);
+        let mut term = MockTerminal::new(&[KeyEvent::Tab, KeyEvent::Normal('a'), KeyEvent::Enter]);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "a");
+    }
+
+    #[test]
+    fn test_shift_home_end_selection() {
+        let mut editor = LineEditor::new(100, 10);
+        editor.set_theme(Theme {
+            selection: theme::Style::new().reverse(),
+            ..Theme::new()
+        });
+
+        // Typing replaces the selection
+        let mut events = type_line("set 1x");
+        events.insert(5, KeyEvent::ShiftHome);
+        events.insert(6, KeyEvent::Normal('r'));
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "rx");
+        assert!(
+            term.output().contains("\x1b[0;7mset 1\x1b[0m"),
+            "{:?}",
+            term.output()
+        );
+
+        // Backspace removes it, other keys drop it
+        let mut events = type_line("abcd");
+        events.splice(
+            2..2,
+            [
+                KeyEvent::Home,
+                KeyEvent::Right,
+                KeyEvent::ShiftEnd,
+                KeyEvent::Backspace,
+                KeyEvent::CtrlHome,
+            ],
+        );
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "cda");
+
+        let mut events = type_line("abc");
+        events.splice(
+            3..3,
+            [KeyEvent::ShiftHome, KeyEvent::CtrlEnd, KeyEvent::Backspace],
+        );
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "ab");
+    }
+
+    #[test]
+    fn test_abort() {
+        let mut editor = LineEditor::new(100, 10);
+        editor.history.add("status");
+        editor.set_corrector(|line: &str| {
+            if line == "stauts" {
+                Some("status".into())
+            } else {
+                None
+            }
+        });
+
+        // Search and the correction prompt go back to the line as typed
+        let mut events = type_line("stauts");
+        events.splice(
+            6..6,
+            [
+                KeyEvent::Ctrl('r'),
+                KeyEvent::Normal('s'),
+                KeyEvent::Escape,
+                KeyEvent::Enter,
+                KeyEvent::Ctrl('g'),
+            ],
+        );
+        events.splice(11..11, [KeyEvent::Backspace, KeyEvent::Normal('x')]);
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "stautx");
+        assert_eq!(term.output().matches('\x07').count(), 2);
+
+        // A selection is dropped, not replaced
+        let mut events = type_line("ab");
+        events.splice(1..1, [KeyEvent::ShiftHome, KeyEvent::Ctrl('g')]);
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "ba");
+    }
+
+    #[test]
+    fn test_fuzzy_completion() {
+        let mut editor = LineEditor::new(100, 10);
+        editor.set_completer(command_completer);
+        editor.set_match_policy(MatchPolicy::Fuzzy);
+
+        let mut events = type_line("hsy");
+        events.insert(3, KeyEvent::Tab);
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "history");
+    }
+
+    #[test]
+    fn test_completion_list_is_paged() {
+        let mut editor = LineEditor::new(100, 10);
+        editor.set_completer(|_: &Context| (0..10).map(|i| alloc::format!("item{}", i)).collect());
+
+        let mut events = type_line("item");
+        events.insert(4, KeyEvent::Tab);
+        events.insert(5, KeyEvent::Normal('q'));
+        let mut term = MockTerminal::new(&events);
+        term.size = Some((6, 4));
+        assert_eq!(
+            editor.read_line_with_prompt(&mut term, "> ").unwrap(),
+            "item"
+        );
+
+        // Three rows fit before the pager stops, then the prompt is repainted
+        let out = term.output();
+        assert!(out.contains("item0\r\nitem1\r\nitem2\r\n--More--\r\x1b[K\r\x1b[J> item"));
+        assert!(!out.contains("item3"));
+    }
+
+    #[test]
+    fn test_hint_accept_and_dismiss() {
+        let mut editor = editor_with_history(&["status --verbose"]);
+        editor.set_hinter(|ctx: &Context| {
+            let line = ctx.line();
+            ctx.history()
+                .iter()
+                .rev()
+                .find(|e| !line.is_empty() && e.starts_with(line) && e.len() > line.len())
+                .map(|e| e[line.len()..].to_string())
+        });
+
+        let mut events = type_line("st");
+        events.insert(2, KeyEvent::End);
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "status --verbose");
+
+        // Enter without accepting submits only what was typed and erases the hint
+        let mut term = MockTerminal::new(&type_line("st"));
+        assert_eq!(editor.read_line(&mut term).unwrap(), "st");
+        assert!(term.output().ends_with("atus --verbose\x1b[D\x1b[D\x1b[D\x1b[D\x1b[D\x1b[D\x1b[D\x1b[D\x1b[D\x1b[D\x1b[D\x1b[D\x1b[D\x1b[D\x1b[K\r\n"));
     }
 
     #[test]
-    fn test_line_buffer_cursor_movement() {
-        let mut buf = LineBuffer::new(100);
-        buf.insert_char('h');
-        buf.insert_char('e');
-        buf.insert_char('y');
-        assert_eq!(buf.cursor_pos(), 3);
+    fn test_theme_styles_prompt_and_hint() {
+        use crate::theme::{Color, Style};
+
+        let mut editor = LineEditor::new(64, 10);
+        editor.set_theme(Theme {
+            prompt: Style::new().fg(Color::Green),
+            hint: Style::new().dim(),
+            ..Theme::default()
+        });
+        editor.set_hinter(|_: &Context| Some("!".to_string()));
+
+        let mut term = MockTerminal::new(&type_line("a"));
+        editor.read_line_with_prompt(&mut term, "> ").unwrap();
+        assert_eq!(
+            term.output(),
+            "\x1b[0;32m> \x1b[0ma\x1b[K\x1b[0;2m!\x1b[0m\x1b[D\x1b[K\r\n"
+        );
+    }
 
-        assert!(buf.move_cursor_left());
-        assert_eq!(buf.cursor_pos(), 2);
+    #[test]
+    fn test_status_line() {
+        let mut editor = LineEditor::new(64, 10);
+        editor.set_status("[insert]");
+
+        let mut term = MockTerminal::new(&type_line("a"));
+        editor.read_line(&mut term).unwrap();
+        assert_eq!(
+            term.output(),
+            "\x1bD\x1b[A\x1b7\x1b[B\r[insert]\x1b[K\x1b8a\x1b[K\x1b7\x1b[B\r\x1b[K\x1b8\r\n"
+        );
+
+        // Search help replaces the status line and the application's comes back
+        let mut events = vec![KeyEvent::Ctrl('r'), KeyEvent::Ctrl('g')];
+        events.push(KeyEvent::Enter);
+        let mut term = MockTerminal::new(&events);
+        editor.read_line(&mut term).unwrap();
+        let out = term.output();
+        let help = out.find("Ctrl+G: cancel").unwrap();
+        assert!(out[help..].contains("[insert]"));
+    }
 
-        assert!(buf.move_cursor_right());
-        assert_eq!(buf.cursor_pos(), 3);
+    #[test]
+    fn test_no_status_line_without_cursor_addressing() {
+        let mut editor = LineEditor::new(64, 10);
+        editor.set_status("[insert]");
+        let mut term = MockTerminal::new(&type_line("a"));
+        term.caps = Caps::NONE;
+        editor.read_line(&mut term).unwrap();
+        assert!(
+            !term.output().contains("[insert]") && !term.output().contains("\x1b7"),
+            "{:?}",
+            term.output()
+        );
+    }
 
-        assert!(!buf.move_cursor_right()); // at end
+    #[test]
+    fn test_key_binding_layers() {
+        let mut editor = LineEditor::new(64, 10);
+        editor.set_hinter(|_: &Context| Some("ly".to_string()));
+        editor.bind(KeyEvent::Ctrl('d'), Action::Ignore);
+        editor.bind(KeyEvent::Ctrl('a'), Action::MoveHome);
+
+        // Per-call binding: Tab accepts the hint instead of completing
+        let mut wizard = Keymap::new();
+        wizard.bind(KeyEvent::Tab, Action::AcceptHint);
+        let mut events = type_line("on");
+        events.insert(2, KeyEvent::Tab);
+        events.insert(3, KeyEvent::Ctrl('d'));
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(
+            editor
+                .read_line_with_bindings(&mut term, "", &wizard)
+                .unwrap(),
+            "only"
+        );
+
+        // The override is gone afterwards, so Tab indents again; the editor's own bindings remain
+        let mut events = type_line("bc");
+        events.insert(2, KeyEvent::Ctrl('a'));
+        events.insert(3, KeyEvent::Normal('a'));
+        events.insert(4, KeyEvent::Tab);
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "a       bc");
     }
 
     #[test]
-    fn test_line_buffer_home_end() {
-        let mut buf = LineBuffer::new(100);
-        buf.insert_char('h');
-        buf.insert_char('e');
-        buf.insert_char('y');
+    fn test_alt_b_f_move_by_word_and_rebind() {
+        let mut editor = LineEditor::new(64, 10);
+        let mut events = type_line("one two");
+        events.splice(
+            7..7,
+            [
+                KeyEvent::Alt('b'),
+                KeyEvent::Alt('b'),
+                KeyEvent::Alt('f'),
+                KeyEvent::Normal('!'),
+            ],
+        );
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "one !two");
+
+        editor.bind(KeyEvent::Alt('f'), Action::MoveEnd);
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "one two!");
+    }
 
-        buf.move_cursor_to_start();
-        assert_eq!(buf.cursor_pos(), 0);
+    #[test]
+    fn test_bound_command() {
+        let mut editor = LineEditor::new(64, 10);
+        let mut toggles = 0;
+        editor.bind_command(KeyEvent::Ctrl('t'), |line: &mut LineBuffer| {
+            line.insert_str("set ");
+            HandlerResult::Continue
+        });
+        editor.bind_command(KeyEvent::Ctrl('o'), move |_: &mut LineBuffer| {
+            toggles += 1;
+            if toggles == 2 {
+                HandlerResult::Submit
+            } else {
+                HandlerResult::Continue
+            }
+        });
+
+        let mut term = MockTerminal::new(&[
+            KeyEvent::Normal('x'),
+            KeyEvent::Left,
+            KeyEvent::Ctrl('t'),
+            KeyEvent::Ctrl('o'),
+            KeyEvent::Ctrl('o'),
+        ]);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "set x");
+        assert!(term.output().ends_with("\x1b[D\x1b[Kset x\x1b[D\r\n"));
+
+        editor.unbind(KeyEvent::Ctrl('t'));
+        let mut term = MockTerminal::new(&[KeyEvent::Ctrl('t'), KeyEvent::Enter]);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "");
+    }
 
-        buf.move_cursor_to_end();
-        assert_eq!(buf.cursor_pos(), 3);
+    #[test]
+    fn test_events_iterator() {
+        let mut editor = LineEditor::new(64, 10);
+        let mut term = MockTerminal::new(&[KeyEvent::Up, KeyEvent::Normal('q')]);
+        let events: Vec<_> = editor.events(&mut term).collect();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].as_ref().ok(), Some(&KeyEvent::Up));
+        assert_eq!(events[1].as_ref().ok(), Some(&KeyEvent::Normal('q')));
+        assert!(matches!(events[2], Err(Error::Eof)));
     }
 
     #[test]
-    fn test_line_buffer_word_navigation() {
-        let mut buf = LineBuffer::new(100);
-        for c in "hello world test".chars() {
-            buf.insert_char(c);
-        }
+    fn test_history_recall_redraws_wrapped_line() {
+        let mut editor = editor_with_history(&["0123456789abc"]);
+        let mut events = type_line("x");
+        events.insert(1, KeyEvent::Up);
+        events.insert(2, KeyEvent::Down);
+        let mut term = MockTerminal::new(&events);
+        term.size = Some((10, 5));
+        assert_eq!(editor.read_line_with_prompt(&mut term, "> ").unwrap(), "x");
+
+        // The recalled entry wraps onto a second row; going back down starts
+        // the repaint from the prompt's row
+        let out = term.output();
+        assert!(out.contains("\r\x1b[J> 0123456789abc"));
+        assert!(out.ends_with("\x1b[A\r\x1b[J> x\r\n"));
+    }
 
-        // At end: "hello world test|"
-        buf.move_cursor_word_left();
-        assert_eq!(buf.cursor_pos(), 12); // "hello world |test"
+    #[test]
+    fn test_wrapped_line_keeps_prompt() {
+        let mut editor = LineEditor::new(64, 10);
+        let mut events: Vec<KeyEvent> = "abcd efghij".chars().map(KeyEvent::Normal).collect();
+        events.extend([
+            KeyEvent::AltBackspace,
+            KeyEvent::AltBackspace,
+            KeyEvent::Home,
+            KeyEvent::Enter,
+        ]);
+        let mut term = MockTerminal::new(&events);
+        term.size = Some((10, 5));
+        assert_eq!(editor.read_line_with_prompt(&mut term, "> ").unwrap(), "");
+
+        // Deleting back across the row boundary goes up a row and repaints
+        // the prompt instead of backing into it
+        let out = term.output();
+        assert!(out.contains("> abcd efg\r\n\x1b[A\r\x1b[J> abcd efgh"));
+        assert!(out.contains("> abcd efghij\x1b[A\r\x1b[J> abcd \x1b[D"));
+        assert!(!out.contains(&"\x1b[D".repeat(6)));
+    }
 
-        buf.move_cursor_word_left();
-        assert_eq!(buf.cursor_pos(), 6); // "hello |world test"
+    #[test]
+    fn test_prompt_after_application_output() {
+        let mut editor = editor_with_history(&["abc"]);
+        let mut events = type_line("");
+        events.insert(0, KeyEvent::Up);
+        let mut term = MockTerminal::new(&events);
+        term.size = Some((10, 5));
+        // The application already printed "hello " on the line
+        term.position = Some((6, 3));
+        assert_eq!(
+            editor.read_line_with_prompt(&mut term, "> ").unwrap(),
+            "abc"
+        );
+
+        // The repaint keeps the first six columns and wraps at the real margin
+        assert!(term
+            .output()
+            .contains("\r\x1b[C\x1b[C\x1b[C\x1b[C\x1b[C\x1b[C\x1b[J> abc"));
+        assert_eq!(editor.cursor_column(), 11);
+    }
 
-        buf.move_cursor_word_right();
-        assert_eq!(buf.cursor_pos(), 12); // "hello world |test"
+    #[test]
+    fn test_fullscreen_editing() {
+        let mut editor = LineEditor::new(64, 10);
+        let mut events = type_line("ab");
+        events.extend([
+            KeyEvent::Normal('c'),
+            KeyEvent::Up,
+            KeyEvent::Normal('X'),
+            KeyEvent::Ctrl('d'),
+        ]);
+        let mut term = MockTerminal::new(&events);
+        term.size = Some((20, 5));
+        assert_eq!(
+            editor.read_line_fullscreen(&mut term, "> ", "").unwrap(),
+            "aXb\nc"
+        );
+
+        let out = term.output();
+        assert!(out.starts_with("\x1b[?1049h\x1b[H\x1b[2J"));
+        assert!(out.contains(
+            "aXb\x1b[K\r\nc\x1b[K\r\n\x1b[K\r\n\x1b[K\r\n>   Ctrl+D: done  Ctrl+C: cancel\x1b[K"
+        ));
+        assert!(out.ends_with("\x1b[?1049l> aXb\r\nc\r\n"));
+        assert!(editor.history().is_empty());
     }
 
     #[test]
-    fn test_line_buffer_delete_word() {
-        let mut buf = LineBuffer::new(100);
-        for c in "hello world".chars() {
-            buf.insert_char(c);
-        }
+    fn test_fullscreen_auto_indent() {
+        let mut editor = LineEditor::new(64, 10);
+        editor.set_auto_indent(true);
+        editor.set_tab_width(2);
+        editor.set_indent_openers("{");
+        let mut events = type_line("if {");
+        events.extend(type_line("  x"));
+        events.extend([
+            KeyEvent::Backspace,
+            KeyEvent::Normal('}'),
+            KeyEvent::Ctrl('d'),
+        ]);
+        let mut term = MockTerminal::new(&events);
+        term.size = Some((20, 5));
+        assert_eq!(
+            editor.read_line_fullscreen(&mut term, "> ", "").unwrap(),
+            "if {\n    x\n  }"
+        );
+
+        editor.set_auto_indent(false);
+        let mut term = MockTerminal::new(&[KeyEvent::Enter, KeyEvent::Ctrl('d')]);
+        assert_eq!(
+            editor.read_line_fullscreen(&mut term, "> ", " {").unwrap(),
+            " {\n"
+        );
+    }
 
-        buf.delete_word_left();
-        assert_eq!(buf.as_str().unwrap(), "hello ");
+    #[test]
+    fn test_snapshot_restores_unfinished_line() {
+        let mut editor = editor_with_history(&["one", "two"]);
+        let mut term = MockTerminal::new(&[KeyEvent::Normal('x'), KeyEvent::Up, KeyEvent::Left]);
+        assert!(matches!(editor.read_line(&mut term), Err(Error::Eof)));
+
+        let state = editor.snapshot();
+        assert_eq!(state.line(), "two");
+        assert_eq!(state.cursor_pos(), 2);
+
+        // Picked up by a fresh editor with the same history
+        let mut editor = editor_with_history(&["one", "two"]);
+        editor.restore(state.clone());
+        let mut term = MockTerminal::new(&[KeyEvent::Down, KeyEvent::Enter]);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "x");
+
+        editor.restore(state);
+        let mut term = MockTerminal::new(&type_line("!"));
+        term.size = Some((80, 24));
+        assert_eq!(
+            editor.read_line_with_prompt(&mut term, "> ").unwrap(),
+            "tw!o"
+        );
+        assert!(term
+            .output()
+            .starts_with("\r\x1b[J> two\r\x1b[C\x1b[C\x1b[C\x1b[C\x1b[@!"));
+
+        // Only the next read_line is affected
+        let mut term = MockTerminal::new(&type_line("y"));
+        assert_eq!(editor.read_line(&mut term).unwrap(), "y");
+    }
 
-        buf.delete_word_left();
-        assert_eq!(buf.as_str().unwrap(), "");
+    #[test]
+    fn test_detach_keeps_unfinished_line() {
+        let mut editor = LineEditor::new(64, 10);
+        let mut term =
+            MockTerminal::new(&[KeyEvent::Normal('a'), KeyEvent::Normal('b'), KeyEvent::Left]);
+        assert!(matches!(editor.read_line(&mut term), Err(Error::Eof)));
+        editor.detach();
+        let mut term = MockTerminal::new(&type_line("x"));
+        assert_eq!(
+            editor.read_line_with_prompt(&mut term, "> ").unwrap(),
+            "axb"
+        );
+        assert!(
+            term.output().starts_with("\r\x1b[J> ab\x1b[D"),
+            "{:?}",
+            term.output()
+        );
+
+        // Submitted and cancelled lines stay gone
+        editor.detach();
+        let mut term = MockTerminal::new(&[KeyEvent::Normal('z'), KeyEvent::Ctrl('c')]);
+        assert!(matches!(
+            editor.read_line(&mut term),
+            Err(Error::Interrupted)
+        ));
+        editor.detach();
+        let mut term = MockTerminal::new(&type_line("y"));
+        assert_eq!(editor.read_line(&mut term).unwrap(), "y");
     }
 
     #[test]
-    fn test_line_buffer_delete_word_right() {
-        let mut buf = LineBuffer::new(100);
-        for c in "hello world".chars() {
-            buf.insert_char(c);
-        }
-        buf.move_cursor_to_start();
+    fn test_multi_line_prompt() {
+        let mut editor = editor_with_history(&["abc"]);
+        let mut events = type_line("");
+        events.insert(0, KeyEvent::Up);
+        events.insert(1, KeyEvent::Ctrl('l'));
+        let mut term = MockTerminal::new(&events);
+        term.size = Some((20, 5));
+        assert_eq!(
+            editor
+                .read_line_with_prompt(&mut term, "~/src\n> ")
+                .unwrap(),
+            "abc"
+        );
+
+        // Recall goes up over the first prompt line and reprints both
+        let out = term.output();
+        assert!(out.starts_with("~/src\r\n> \x1b[A\r\x1b[J~/src\r\n> abc"));
+        assert!(out.contains("\x1b[H\x1b[2J\r\x1b[J~/src\r\n> abc"));
+        assert_eq!(editor.cursor_column(), 5);
+    }
 
-        buf.delete_word_right();
-        assert_eq!(buf.as_str().unwrap(), "world");
+    #[test]
+    fn test_input_filter() {
+        let mut editor = LineEditor::new(64, 10);
+        // Group digits in threes, drop anything else
+        editor.set_input_filter(|c: char, ctx: &Context| {
+            if !c.is_ascii_digit() {
+                return Filtered::Discard;
+            }
+            let digits = ctx.line().chars().filter(|c| c.is_ascii_digit()).count();
+            if digits > 0 && digits % 3 == 0 {
+                Filtered::InsertStr(format!("_{}", c))
+            } else {
+                Filtered::Insert(c)
+            }
+        });
+        let mut term = MockTerminal::new(&type_line("1234x567"));
+        assert_eq!(editor.read_line(&mut term).unwrap(), "123_456_7");
+        assert!(term.output().contains("3\x1b[K_4\x1b[K"));
     }
 
     #[test]
-    fn test_line_buffer_insert_middle() {
-        let mut buf = LineBuffer::new(100);
-        buf.insert_char('h');
-        buf.insert_char('e');
-        buf.move_cursor_left();
-        buf.insert_char('x');
-        assert_eq!(buf.as_str().unwrap(), "hxe");
-        assert_eq!(buf.cursor_pos(), 2);
+    fn test_corrector_offers_suggestion() {
+        let mut editor = LineEditor::new(64, 10);
+        editor.set_corrector(|line: &str| {
+            if line == "stauts" {
+                Some("status".to_string())
+            } else {
+                None
+            }
+        });
+
+        let mut events = type_line("stauts");
+        events.push(KeyEvent::Normal('y'));
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "status");
+        assert!(term.output().contains("did you mean `status`? [y/n]"));
+
+        let mut events = type_line("stauts");
+        events.push(KeyEvent::Normal('n'));
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "stauts");
+
+        let mut term = MockTerminal::new(&type_line("reset"));
+        assert_eq!(editor.read_line(&mut term).unwrap(), "reset");
+        assert!(!term.output().contains("did you mean"));
     }
 
     #[test]
-    fn test_word_navigation_with_symbols() {
-        let mut buf = LineBuffer::new(100);
-        for c in "3 + 5".chars() {
-            buf.insert_char(c);
+    fn test_poll_read_line() {
+        /// Serial input in bursts; `None` ends a burst
+        struct Bursts {
+            input: Vec<Option<u8>>,
+            output: Vec<u8>,
+            raw: bool,
         }
-        // Cursor at end: "3 + 5|"
-
-        // Move left by word - should stop at '5'
-        buf.move_cursor_word_left();
-        assert_eq!(buf.cursor_pos(), 4); // Before '5'
-
-        // Move left by word - should stop at '+'
-        buf.move_cursor_word_left();
-        assert_eq!(buf.cursor_pos(), 2); // Before '+'
 
-        // Move left by word - should stop at '3'
-        buf.move_cursor_word_left();
-        assert_eq!(buf.cursor_pos(), 0); // Before '3'
+        impl Terminal for Bursts {
+            fn read_byte(&mut self) -> Result<u8> {
+                unreachable!()
+            }
+            fn try_read_byte(&mut self) -> Result<Option<u8>> {
+                if self.input.is_empty() {
+                    return Ok(None);
+                }
+                Ok(self.input.remove(0))
+            }
+            fn write(&mut self, data: &[u8]) -> Result<()> {
+                self.output.extend_from_slice(data);
+                Ok(())
+            }
+            fn flush(&mut self) -> Result<()> {
+                Ok(())
+            }
+            fn enter_raw_mode(&mut self) -> Result<()> {
+                self.raw = true;
+                Ok(())
+            }
+            fn exit_raw_mode(&mut self) -> Result<()> {
+                self.raw = false;
+                Ok(())
+            }
+            fn cursor_left(&mut self) -> Result<()> {
+                Ok(())
+            }
+            fn cursor_right(&mut self) -> Result<()> {
+                Ok(())
+            }
+            fn clear_eol(&mut self) -> Result<()> {
+                Ok(())
+            }
+        }
 
-        // Move right by word - should stop after '3'
-        buf.move_cursor_word_right();
-        assert_eq!(buf.cursor_pos(), 2); // After '3 ', before '+'
+        // An arrow key split across bursts, then a quoted Ctrl+A
+        let bytes: &[Option<u8>] = &[
+            Some(b'a'),
+            Some(b'c'),
+            Some(0x1b),
+            None,
+            Some(b'['),
+            Some(b'D'),
+            Some(b'b'),
+            None,
+            None,
+            Some(0x16),
+            None,
+            Some(0x01),
+            Some(b'\r'),
+            Some(b'x'),
+        ];
+        let mut term = Bursts {
+            input: bytes.to_vec(),
+            output: Vec::new(),
+            raw: false,
+        };
+        let mut editor = LineEditor::new(64, 10);
+
+        let mut pending = 0;
+        let line = loop {
+            match editor.poll_read_line(&mut term, "> ") {
+                Poll::Ready(line) => break line.unwrap(),
+                Poll::Pending => {
+                    assert!(term.raw);
+                    pending += 1;
+                }
+            }
+        };
+        assert_eq!(line, "ab\u{1}c");
+        assert_eq!(pending, 4);
+        assert!(!term.raw);
+        assert!(term.output.starts_with(b"> a"));
+        // The byte after Enter is left for the next line
+        assert_eq!(term.input, [Some(b'x')]);
+
+        // A sequence that stops arriving is given up on
+        static TICKS: core::sync::atomic::AtomicU64 = core::sync::atomic::AtomicU64::new(0);
+        editor.set_escape_timeout(1, || {
+            TICKS.fetch_add(1, core::sync::atomic::Ordering::Relaxed)
+        });
+        term.input = vec![
+            Some(b'a'),
+            Some(0x1b),
+            None,
+            Some(b'['),
+            Some(b'D'),
+            Some(b'\r'),
+        ];
+        let line = loop {
+            if let Poll::Ready(line) = editor.poll_read_line(&mut term, "> ") {
+                break line.unwrap();
+            }
+        };
+        assert_eq!(line, "a[D");
+    }
 
-        // Move right by word - should stop after '+'
-        buf.move_cursor_word_right();
-        assert_eq!(buf.cursor_pos(), 4); // After '+ ', before '5'
+    #[test]
+    fn test_ctrl_l_clears_screen() {
+        let mut editor = LineEditor::new(64, 10);
+        let mut events = type_line("ab");
+        events.insert(1, KeyEvent::Ctrl('l'));
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(
+            editor
+                .read_line_with_prompt(&mut term, "\x1b[1m>\x1b[0m ")
+                .unwrap(),
+            "ab"
+        );
+        assert!(term
+            .output()
+            .contains("\x1b[H\x1b[2J\r\x1b[J\x1b[1m>\x1b[0m a"));
+        assert_eq!(prompt_width("\x1b[1m>\x1b[0m "), 2);
     }
 
     #[test]
-    fn test_delete_word_with_symbols() {
+    fn test_line_buffer_utf8() {
         let mut buf = LineBuffer::new(100);
-        for c in "3 + 5".chars() {
-            buf.insert_char(c);
-        }
-        // Cursor at end: "3 + 5|"
+        buf.insert_char('ä');
+        buf.insert_char('ö');
+        buf.insert_char('ü');
+        assert_eq!(buf.as_str().unwrap(), "äöü");
+        assert_eq!(buf.len(), 6); // UTF-8 bytes
 
-        // Delete word left - should delete '5'
-        buf.delete_word_left();
-        assert_eq!(buf.as_str().unwrap(), "3 + ");
+        assert!(buf.move_cursor_left());
+        assert_eq!(buf.cursor_pos(), 4);
+        assert!(buf.delete_before_cursor());
+        assert_eq!(buf.as_str().unwrap(), "äü");
+        assert_eq!(buf.cursor_pos(), 2);
+        assert!(buf.delete_at_cursor());
+        assert_eq!(buf.as_str().unwrap(), "ä");
+        assert!(!buf.move_cursor_right());
+    }
 
-        // Delete word left - should delete '+'
-        buf.delete_word_left();
-        assert_eq!(buf.as_str().unwrap(), "3 ");
+    #[test]
+    fn test_horizontal_scroll() {
+        let mut editor = LineEditor::new(64, 10);
+        editor.set_horizontal_scroll(Some(10));
+        let mut events = type_line("abcdefghij");
+        events.insert(10, KeyEvent::Home);
+        let mut term = MockTerminal::new(&events[..11]);
+        let _ = editor.read_line_with_prompt(&mut term, "> ");
+        let output = term.output();
+        // Seven columns are left after the prompt and the unused last one; the
+        // window moves by half its width when the cursor reaches the edge
+        assert!(output.contains(
+            "\r> abcdef\x1b[K\r> <efg\x1b[K\r> <efgh\x1b[K\r> <ghi\x1b[K\r> <ghij\x1b[K"
+        ));
+        let home = "\r> abcdef>\x1b[K".to_string() + &"\x1b[D".repeat(7);
+        assert!(output.ends_with(&home));
+
+        let mut term = MockTerminal::new(&[KeyEvent::Right, KeyEvent::Right, KeyEvent::Enter]);
+        editor.restore(editor.snapshot());
+        assert_eq!(
+            editor.read_line_with_prompt(&mut term, "> ").unwrap(),
+            "abcdefghij"
+        );
+        assert!(!term.output().contains("abcdefghij"));
     }
 
-    // History tests
     #[test]
-    fn test_history_add() {
-        let mut hist = History::new(10);
-        hist.add("first");
-        hist.add("second");
+    fn test_observer() {
+        use alloc::rc::Rc;
+        use core::cell::RefCell;
 
-        assert_eq!(hist.previous(""), Some("second"));
-        assert_eq!(hist.previous(""), Some("first"));
-        assert_eq!(hist.previous(""), None); // no more
+        struct Log(Rc<RefCell<Vec<String>>>);
+
+        impl Observer for Log {
+            fn on_submit(&mut self, line: &str) -> bool {
+                self.0.borrow_mut().push(alloc::format!("submit {}", line));
+                !line.starts_with("secret")
+            }
+            fn on_history_recall(&mut self, entry: &str) {
+                self.0.borrow_mut().push(alloc::format!("recall {}", entry));
+            }
+            fn on_char(&mut self, c: char, ctx: &Context) {
+                self.0
+                    .borrow_mut()
+                    .push(alloc::format!("{} {}", c, ctx.line()));
+            }
+        }
+
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut editor = editor_with_history(&["status"]);
+        editor.set_observer(Log(log.clone()));
+
+        let mut term = MockTerminal::new(&[
+            KeyEvent::Up,
+            KeyEvent::Down,
+            KeyEvent::Normal('o'),
+            KeyEvent::Normal('k'),
+            KeyEvent::Enter,
+        ]);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "ok");
+        assert_eq!(*log.borrow(), ["recall status", "o o", "k ok", "submit ok"]);
+
+        let mut term = MockTerminal::new(&type_line("secret 1"));
+        assert_eq!(editor.read_line(&mut term).unwrap(), "secret 1");
+        assert_eq!(
+            editor.history().iter().collect::<Vec<_>>(),
+            ["status", "ok"]
+        );
     }
 
     #[test]
-    fn test_history_skip_empty() {
-        let mut hist = History::new(10);
-        hist.add("first");
-        hist.add("");
-        hist.add("second");
+    fn test_submit_hook() {
+        let mut editor = LineEditor::new(100, 10);
+        editor.set_submit_hook(|line: &mut String| {
+            if let Some(at) = line.find('#') {
+                line.truncate(at);
+            }
+            *line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        });
+        let mut term = MockTerminal::new(&type_line("gpio   set 17  # led"));
+        assert_eq!(editor.read_line(&mut term).unwrap(), "gpio set 17");
+        let mut term = MockTerminal::new(&type_line("# note"));
+        assert_eq!(editor.read_line(&mut term).unwrap(), "");
+        assert_eq!(editor.history().iter().collect::<Vec<_>>(), ["gpio set 17"]);
+    }
 
-        assert_eq!(hist.previous(""), Some("second"));
-        assert_eq!(hist.previous(""), Some("first"));
-        assert_eq!(hist.previous(""), None);
+    #[test]
+    fn test_accessible_output() {
+        use accessibility::Announcement;
+        use alloc::rc::Rc;
+        use core::cell::RefCell;
+
+        let heard = Rc::new(RefCell::new(Vec::new()));
+        let mut editor = LineEditor::new(100, 10);
+        editor.set_accessible(true);
+        let log = heard.clone();
+        editor.set_announcer(move |announcement: &Announcement| {
+            log.borrow_mut().push(alloc::format!("{:?}", announcement))
+        });
+
+        let mut events = type_line("gpio");
+        events.splice(
+            4..4,
+            [KeyEvent::Backspace, KeyEvent::Home, KeyEvent::Normal('x')],
+        );
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(
+            editor.read_line_with_prompt(&mut term, "> ").unwrap(),
+            "xgpi"
+        );
+        let newline = "\r\n";
+        assert_eq!(
+            term.output(),
+            alloc::format!("> gpio\x08 \x08{}> xgpi{}", newline, newline)
+        );
+        assert_eq!(
+            *heard.borrow(),
+            [
+                "Deleted(\"o\")",
+                "Moved { word: \"gpi\" }",
+                "Changed { line: \"xgpi\", word: \"xgpi\" }"
+            ]
+        );
     }
 
+    #[cfg(feature = "std")]
     #[test]
-    fn test_history_skip_duplicates() {
-        let mut hist = History::new(10);
-        hist.add("test");
-        hist.add("test"); // should be skipped
-        hist.add("other");
+    fn test_io_error_causes() {
+        use std::io::ErrorKind;
 
-        assert_eq!(hist.previous(""), Some("other"));
-        assert_eq!(hist.previous(""), Some("test"));
-        assert_eq!(hist.previous(""), None);
+        match Error::from(std::io::Error::from_raw_os_error(1)) {
+            Error::Io(e) => assert_eq!(e.code(), Some(1)),
+            error => panic!("unexpected {:?}", error),
+        }
+        assert_eq!(
+            Error::from(std::io::Error::from(ErrorKind::WouldBlock)),
+            IoCause::WouldBlock.into()
+        );
+        assert_eq!(
+            Error::from(std::io::Error::from(ErrorKind::BrokenPipe)),
+            IoCause::Disconnected.into()
+        );
+        assert_eq!(
+            Error::from(std::io::Error::from(ErrorKind::UnexpectedEof)),
+            Error::Eof
+        );
+        assert_eq!(
+            std::io::Error::from(Error::from(IoCause::TimedOut)).kind(),
+            ErrorKind::TimedOut
+        );
+        assert_eq!(
+            Error::Io(IoError::new(IoCause::Busy).with_code(3)).to_string(),
+            "I/O error: device busy (code 3)"
+        );
     }
 
+    #[cfg(feature = "metrics")]
     #[test]
-    fn test_history_navigation() {
-        let mut hist = History::new(10);
-        hist.add("first");
-        hist.add("second");
-        hist.add("third");
+    fn test_metrics() {
+        let mut editor = LineEditor::new(100, 10);
+        editor.set_metrics_clock(|| 0);
+        let mut events = type_line("ab");
+        events.splice(2..2, [KeyEvent::Left, KeyEvent::Normal('x')]);
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "axb");
+
+        let metrics = editor.metrics();
+        assert_eq!(metrics.keys, 5);
+        assert_eq!(metrics.key_time, 0);
+        assert_eq!(metrics.bytes_written, term.output().len() as u64);
+        assert_eq!(metrics.max_bytes_per_key, 4);
+        // "x" is inserted in place, without repainting "b"
+        assert_eq!(metrics.redraws, 0);
+        assert_eq!((metrics.writes, metrics.max_writes_per_key), (8, 2));
+        editor.reset_metrics();
+        assert_eq!(editor.metrics(), metrics::Metrics::default());
+    }
 
-        // Go back through history
-        assert_eq!(hist.previous(""), Some("third"));
-        assert_eq!(hist.previous(""), Some("second"));
+    #[test]
+    fn test_renderer() {
+        use alloc::rc::Rc;
+        use core::cell::RefCell;
+
+        let views = Rc::new(RefCell::new(Vec::new()));
+        let mut editor = editor_with_history(&["status"]);
+        editor.set_hinter(|ctx: &Context| {
+            Some(alloc::format!("{}!", ctx.line().len())).filter(|_| !ctx.line().is_empty())
+        });
+        let seen = Rc::clone(&views);
+        editor.set_renderer(move |view: &render::View| {
+            let hint = view.hint.unwrap_or("");
+            let search = view
+                .search
+                .map(|query| alloc::format!(" [{}]", query))
+                .unwrap_or_default();
+            seen.borrow_mut().push(alloc::format!(
+                "{}{}|{}{}",
+                &view.line[..view.cursor],
+                hint,
+                &view.line[view.cursor..],
+                search
+            ));
+            Ok(())
+        });
+
+        let mut events = type_line("ab");
+        events.insert(1, KeyEvent::Left);
+        events.insert(3, KeyEvent::Ctrl('r'));
+        events.insert(4, KeyEvent::Normal('t'));
+        events.insert(5, KeyEvent::Ctrl('g'));
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line_with_prompt(&mut term, "> ").unwrap(), "ba");
+        assert_eq!(term.output(), "");
+        assert_eq!(
+            *views.borrow(),
+            ["|", "a1!|", "|a", "b|a", "ba| []", "s|tatus [t]", "ba2!|"]
+        );
+    }
 
-        // Go forward
-        assert_eq!(hist.next_entry(), Some("third"));
-        assert_eq!(hist.next_entry(), Some("")); // returns saved line (empty string)
+    #[test]
+    fn test_wide_char_redraw() {
+        let mut editor = LineEditor::new(64, 10);
+        let mut events = type_line("漢字x");
+        events.insert(3, KeyEvent::Left);
+        events.insert(4, KeyEvent::Backspace);
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "漢x");
+        // Left over "x", then back two columns over "字" and delete both
+        assert!(term
+            .output()
+            .contains("x\x1b[K\x1b[D\x1b[D\x1b[D\x1b[P\x1b[P\r\n"));
+        assert_eq!(display_width("漢字x".as_bytes()), 5);
+        assert_eq!(display_width("e\u{301}".as_bytes()), 1);
     }
 
     #[test]
-    fn test_history_saves_current_line() {
-        let mut hist = History::new(10);
-        hist.add("first");
-        hist.add("second");
+    fn test_insert_delete_in_place() {
+        struct Plain(MockTerminal);
 
-        // Start typing something
-        assert_eq!(hist.previous("hello"), Some("second"));
-        assert_eq!(hist.previous("hello"), Some("first"));
+        impl Terminal for Plain {
+            fn read_byte(&mut self) -> Result<u8> {
+                self.0.read_byte()
+            }
+            fn write(&mut self, data: &[u8]) -> Result<()> {
+                self.0.write(data)
+            }
+            fn flush(&mut self) -> Result<()> {
+                Ok(())
+            }
+            fn enter_raw_mode(&mut self) -> Result<()> {
+                Ok(())
+            }
+            fn exit_raw_mode(&mut self) -> Result<()> {
+                Ok(())
+            }
+            fn cursor_left(&mut self) -> Result<()> {
+                self.0.cursor_left()
+            }
+            fn cursor_right(&mut self) -> Result<()> {
+                self.0.cursor_right()
+            }
+            fn clear_eol(&mut self) -> Result<()> {
+                self.0.clear_eol()
+            }
+            fn parse_key_event(&mut self) -> Result<KeyEvent> {
+                self.0.parse_key_event()
+            }
+        }
 
-        // Navigate back forward
-        assert_eq!(hist.next_entry(), Some("second"));
-        assert_eq!(hist.next_entry(), Some("hello")); // restored!
+        let mut events = type_line("abc");
+        events.splice(
+            3..3,
+            [
+                KeyEvent::Left,
+                KeyEvent::Left,
+                KeyEvent::Normal('x'),
+                KeyEvent::Delete,
+            ],
+        );
+
+        // Typed and deleted in place, the rest of the line untouched
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(LineEditor::new(64, 10).read_line(&mut term).unwrap(), "axc");
+        assert!(term
+            .output()
+            .ends_with("c\x1b[K\x1b[D\x1b[D\x1b[@x\x1b[P\r\n"));
+
+        // Rewritten without the capability
+        let mut term = Plain(MockTerminal::new(&events));
+        assert_eq!(LineEditor::new(64, 10).read_line(&mut term).unwrap(), "axc");
+        assert!(term
+            .0
+            .output()
+            .ends_with("\x1b[D\x1b[Dx\x1b[Kbc\x1b[D\x1b[D\x1b[Kc\x1b[D\r\n"));
     }
 
     #[test]
-    fn test_history_down_without_up() {
-        let mut hist = History::new(10);
-        hist.add("first");
-
-        // Down without going up first should do nothing
-        assert_eq!(hist.next_entry(), None);
+    fn test_line_buffer_max_len() {
+        let mut buf = LineBuffer::new(4);
+        buf.set_max_len(Some(4));
+        assert!(!buf.load("abé漢"));
+        assert_eq!(buf.as_str().unwrap(), "abé");
+        assert!(!buf.insert_char('x'));
+        assert!(buf.load("ab"));
+        assert!(!buf.insert_str("xyz"));
+        assert_eq!(buf.as_str().unwrap(), "abxy");
     }
 
     #[test]
-    fn test_history_circular_buffer() {
-        let mut hist = History::new(3);
-        hist.add("first");
-        hist.add("second");
-        hist.add("third");
-        hist.add("fourth"); // overwrites "first"
-
-        assert_eq!(hist.previous(""), Some("fourth"));
-        assert_eq!(hist.previous(""), Some("third"));
-        assert_eq!(hist.previous(""), Some("second"));
-        assert_eq!(hist.previous(""), None); // "first" was overwritten
+    fn test_max_line_length_rings_bell() {
+        let mut editor = editor_with_history(&["0123456789"]);
+        editor.set_max_line_length(Some(4));
+        let mut events = type_line("x");
+        events.insert(0, KeyEvent::Up);
+        let mut term = MockTerminal::new(&events);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "0123");
+        assert_eq!(term.output().matches('\x07').count(), 2);
+        // The full entry is still in the history
+        assert_eq!(editor.history().get(0), Some("0123456789"));
     }
 
     #[test]
-    fn test_history_reset_view() {
-        let mut hist = History::new(10);
-        hist.add("first");
-        hist.add("second");
-
-        assert_eq!(hist.previous(""), Some("second"));
-        hist.reset_view();
+    fn test_line_buffer_capacity() {
+        let mut buf = LineBuffer::new(4);
+        buf.reserve(100);
+        assert!(buf.capacity() >= 100);
+        buf.insert_str("hello");
+        buf.shrink_to_fit();
+        assert_eq!(buf.capacity(), 5);
+
+        // Reserving stops at the maximum length
+        let mut buf = LineBuffer::new(0);
+        buf.set_max_len(Some(8));
+        buf.reserve(1000);
+        assert!(buf.capacity() < 1000);
+
+        let mut editor = LineEditor::new(16, 10);
+        editor.set_line_capacity_limit(Some(16));
+        editor
+            .read_line(&mut MockTerminal::new(&type_line(&"x".repeat(200))))
+            .unwrap();
+        assert!(editor.line.capacity() >= 200);
+        editor
+            .read_line(&mut MockTerminal::new(&type_line("")))
+            .unwrap();
+        assert!(editor.line.capacity() <= 16);
+    }
 
-        // After reset, previous() should start from most recent again
-        assert_eq!(hist.previous(""), Some("second"));
+    #[test]
+    fn test_line_buffer_set_cursor() {
+        let mut buf = LineBuffer::new(16);
+        buf.load("a漢b");
+        assert!(buf.set_cursor(4).is_ok());
+        assert_eq!(buf.cursor_pos(), 4);
+        assert!(matches!(buf.set_cursor(2), Err(Error::InvalidPosition)));
+        assert!(matches!(buf.set_cursor(6), Err(Error::InvalidPosition)));
+        assert_eq!(buf.cursor_pos(), 4);
+        assert!(buf.set_cursor(5).is_ok());
+        assert!(buf.set_cursor(0).is_ok());
     }
 
     #[test]
-    fn test_line_buffer_utf8() {
-        let mut buf = LineBuffer::new(100);
-        buf.insert_char('ä');
-        buf.insert_char('ö');
-        buf.insert_char('ü');
-        assert_eq!(buf.as_str().unwrap(), "äöü");
-        assert_eq!(buf.len(), 6); // UTF-8 bytes
+    fn test_clone_and_debug() {
+        let mut buf = LineBuffer::new(16);
+        buf.load("hé");
+        let copy = buf.clone();
+        buf.delete_before_cursor();
+        assert_eq!(copy.as_str().unwrap(), "hé");
+        assert_eq!(
+            format!("{:?}", copy),
+            r#"LineBuffer { line: "hé", cursor_pos: 3, max_len: None }"#
+        );
+
+        let mut history = History::new(2);
+        history.add("one");
+        history.add("two");
+        history.add("three");
+        let copy = history.clone();
+        history.add("four");
+        assert_eq!(copy.iter().collect::<Vec<_>>(), ["two", "three"]);
+        assert_eq!(
+            format!("{:?}", copy),
+            r#"History { entries: ["two", "three"], capacity: 2, max_bytes: None, viewing_entry: None }"#
+        );
+
+        let editor = LineEditor::new(16, 2);
+        assert!(format!("{:?}", editor).starts_with("LineEditor { line: LineBuffer"));
     }
 
     #[test]
@@ -0,0 +1,260 @@
+//! Measuring the editor's overhead.
+//!
+//! With the `metrics` feature, the editor counts the keys it processes, the
+//! time spent on each, the bytes written in response and how often it
+//! repaints the line. Read the totals with
+//! [`LineEditor::metrics`](crate::LineEditor::metrics), e.g. to see what a
+//! console costs over a slow UART or whether a change reduced redraws.
+//!
+//! Times are measured with the clock set by
+//! [`LineEditor::set_metrics_clock`](crate::LineEditor::set_metrics_clock), in
+//! its ticks. Without one, they are in microseconds with the `std` feature and
+//! not measured otherwise.
+
+#[cfg(feature = "async")]
+use core::task::{Context, Poll};
+
+#[cfg(feature = "async")]
+use crate::asynch::AsyncTerminal;
+use crate::{Caps, KeyEvent, Result, Terminal};
+
+/// Totals collected since the editor was created or the metrics were reset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Metrics {
+    /// Keys processed.
+    pub keys: u32,
+    /// Time spent processing keys, in clock ticks.
+    pub key_time: u64,
+    /// Longest time spent on one key, in clock ticks.
+    pub max_key_time: u64,
+    /// Bytes written to the terminal in response to keys. Cursor movement,
+    /// erasing and the bell count as their ANSI sequences, whichever way the
+    /// terminal carries them out; the status line isn't counted.
+    pub bytes_written: u64,
+    /// Most bytes written in response to one key.
+    pub max_bytes_per_key: u64,
+    /// Output calls made to the terminal in response to keys: writes, cursor
+    /// movement, erasing and the bell. Each can cost a system call or console
+    /// API call on a backend that doesn't buffer its output.
+    pub writes: u64,
+    /// Most output calls made in response to one key.
+    pub max_writes_per_key: u64,
+    /// Repaints of the whole line or of the text after the cursor.
+    pub redraws: u32,
+}
+
+impl Metrics {
+    /// Mean time spent per key, in clock ticks.
+    pub fn mean_key_time(&self) -> u64 {
+        self.key_time / u64::from(self.keys.max(1))
+    }
+
+    /// Mean bytes written per key.
+    pub fn mean_bytes_per_key(&self) -> u64 {
+        self.bytes_written / u64::from(self.keys.max(1))
+    }
+
+    /// Adds one processed key.
+    pub(crate) fn record_key(&mut self, time: u64, bytes: u64, writes: u64) {
+        self.keys = self.keys.saturating_add(1);
+        self.key_time = self.key_time.saturating_add(time);
+        self.max_key_time = self.max_key_time.max(time);
+        self.bytes_written = self.bytes_written.saturating_add(bytes);
+        self.max_bytes_per_key = self.max_bytes_per_key.max(bytes);
+        self.writes = self.writes.saturating_add(writes);
+        self.max_writes_per_key = self.max_writes_per_key.max(writes);
+    }
+}
+
+/// Start of the processing of one key.
+pub(crate) struct Stopwatch {
+    clock: Option<fn() -> u64>,
+    start: u64,
+    #[cfg(feature = "std")]
+    instant: std::time::Instant,
+    written: u64,
+    writes: u64,
+}
+
+impl Stopwatch {
+    pub(crate) fn start(clock: Option<fn() -> u64>, written: u64, writes: u64) -> Self {
+        Stopwatch {
+            clock,
+            start: clock.map_or(0, |clock| clock()),
+            #[cfg(feature = "std")]
+            instant: std::time::Instant::now(),
+            written,
+            writes,
+        }
+    }
+
+    /// Returns the time passed, the bytes written and the output calls made
+    /// since the start.
+    pub(crate) fn stop(&self, written: u64, writes: u64) -> (u64, u64, u64) {
+        let time = match self.clock {
+            Some(clock) => clock().wrapping_sub(self.start),
+            #[cfg(feature = "std")]
+            None => self.instant.elapsed().as_micros() as u64,
+            #[cfg(not(feature = "std"))]
+            None => 0,
+        };
+        (time, written - self.written, writes - self.writes)
+    }
+}
+
+/// Terminal adapter counting the bytes written and the output calls made
+/// through it.
+pub(crate) struct Counting<'a, T: ?Sized> {
+    inner: &'a mut T,
+    pub(crate) written: u64,
+    pub(crate) writes: u64,
+}
+
+impl<'a, T: ?Sized> Counting<'a, T> {
+    pub(crate) fn new(inner: &'a mut T) -> Self {
+        Counting {
+            inner,
+            written: 0,
+            writes: 0,
+        }
+    }
+
+    /// Counts one output call of `bytes` bytes.
+    fn count(&mut self, bytes: u64) {
+        self.written += bytes;
+        self.writes += 1;
+    }
+}
+
+impl<T: Terminal + ?Sized> Terminal for Counting<'_, T> {
+    fn read_byte(&mut self) -> Result<u8> {
+        self.inner.read_byte()
+    }
+
+    fn try_read_byte(&mut self) -> Result<Option<u8>> {
+        self.inner.try_read_byte()
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.count(data.len() as u64);
+        self.inner.write(data)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        self.inner.enter_raw_mode()
+    }
+
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        self.inner.exit_raw_mode()
+    }
+
+    fn cursor_left(&mut self) -> Result<()> {
+        self.count(3);
+        self.inner.cursor_left()
+    }
+
+    fn cursor_right(&mut self) -> Result<()> {
+        self.count(3);
+        self.inner.cursor_right()
+    }
+
+    fn clear_eol(&mut self) -> Result<()> {
+        self.count(3);
+        self.inner.clear_eol()
+    }
+
+    fn cursor_up(&mut self) -> Result<()> {
+        self.count(3);
+        self.inner.cursor_up()
+    }
+
+    fn insert_blank(&mut self) -> Result<()> {
+        self.count(3);
+        self.inner.insert_blank()
+    }
+
+    fn delete_char(&mut self) -> Result<()> {
+        self.count(3);
+        self.inner.delete_char()
+    }
+
+    fn clear_to_end_of_screen(&mut self) -> Result<()> {
+        self.count(3);
+        self.inner.clear_to_end_of_screen()
+    }
+
+    fn clear_screen(&mut self) -> Result<()> {
+        self.count(7);
+        self.inner.clear_screen()
+    }
+
+    fn bell(&mut self) -> Result<()> {
+        self.count(1);
+        self.inner.bell()
+    }
+
+    fn enter_alternate_screen(&mut self) -> Result<()> {
+        self.inner.enter_alternate_screen()
+    }
+
+    fn exit_alternate_screen(&mut self) -> Result<()> {
+        self.inner.exit_alternate_screen()
+    }
+
+    fn parse_key_event(&mut self) -> Result<KeyEvent> {
+        self.inner.parse_key_event()
+    }
+
+    fn size(&mut self) -> Option<(u16, u16)> {
+        self.inner.size()
+    }
+
+    fn get_cursor_position(&mut self) -> Option<(u16, u16)> {
+        self.inner.get_cursor_position()
+    }
+
+    fn show_status(&mut self, text: &[u8]) -> Result<()> {
+        self.inner.show_status(text)
+    }
+
+    fn clear_status(&mut self) -> Result<()> {
+        self.inner.clear_status()
+    }
+
+    fn capabilities(&self) -> Caps {
+        self.inner.capabilities()
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T: AsyncTerminal + ?Sized> AsyncTerminal for Counting<'_, T> {
+    fn poll_read_byte(&mut self, cx: &mut Context<'_>) -> Poll<Result<u8>> {
+        self.inner.poll_read_byte(cx)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.count(data.len() as u64);
+        self.inner.write(data)
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.inner.poll_flush(cx)
+    }
+
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        self.inner.enter_raw_mode()
+    }
+
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        self.inner.exit_raw_mode()
+    }
+
+    fn capabilities(&self) -> Caps {
+        self.inner.capabilities()
+    }
+}
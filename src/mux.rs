@@ -0,0 +1,438 @@
+//! Several consoles sharing one terminal.
+//!
+//! A device with a single serial port may still have more than one thing to
+//! talk to: a bootloader monitor and the application, or the application and
+//! a debug shell. [`ConsoleMux`] gives each its own [`LineEditor`], with its
+//! own prompt, line and history, and lets the user switch between them with
+//! an escape key, much like `screen` or the `telnet` escape character.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::task::Poll;
+
+use crate::{write_newline, Caps, LineEditor, Result, Terminal};
+
+/// Ctrl+], the default escape key, as in `telnet`.
+const DEFAULT_ESCAPE: u8 = 0x1d;
+
+/// Console chosen with an escape command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Target {
+    Index(usize),
+    Next,
+}
+
+/// The shared terminal, with escape commands taken out of its input.
+#[derive(Debug)]
+struct Switch<T> {
+    inner: T,
+    escape: u8,
+    /// The escape key was pressed and the command key is next
+    escaped: bool,
+    /// Input read ahead while waiting in [`ConsoleMux::read_line`]
+    peeked: Option<Result<u8>>,
+    /// Switch requested by the user, which stops input to the active editor
+    request: Option<Target>,
+}
+
+impl<T: Terminal> Switch<T> {
+    /// Runs `byte` through the escape handling, returning it if it is input.
+    fn filter(&mut self, byte: u8) -> Option<u8> {
+        if !self.escaped {
+            if byte == self.escape {
+                self.escaped = true;
+                return None;
+            }
+            return Some(byte);
+        }
+        self.escaped = false;
+        match byte {
+            // Pressed twice, sent as is
+            _ if byte == self.escape => return Some(byte),
+            b'1'..=b'9' => self.request = Some(Target::Index(usize::from(byte - b'1'))),
+            b'n' | b' ' => self.request = Some(Target::Next),
+            _ => {}
+        }
+        None
+    }
+
+    fn next_byte(&mut self) -> Result<Option<u8>> {
+        match self.peeked.take() {
+            Some(byte) => byte.map(Some),
+            None => self.inner.try_read_byte(),
+        }
+    }
+}
+
+impl<T: Terminal> Terminal for Switch<T> {
+    fn read_byte(&mut self) -> Result<u8> {
+        loop {
+            let byte = match self.peeked.take() {
+                Some(byte) => byte?,
+                None => self.inner.read_byte()?,
+            };
+            if let Some(byte) = self.filter(byte) {
+                return Ok(byte);
+            }
+        }
+    }
+
+    fn try_read_byte(&mut self) -> Result<Option<u8>> {
+        while self.request.is_none() {
+            match self.next_byte()? {
+                Some(byte) => {
+                    if let Some(byte) = self.filter(byte) {
+                        return Ok(Some(byte));
+                    }
+                }
+                None => break,
+            }
+        }
+        Ok(None)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.inner.write(data)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        self.inner.enter_raw_mode()
+    }
+
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        self.inner.exit_raw_mode()
+    }
+
+    fn cursor_left(&mut self) -> Result<()> {
+        self.inner.cursor_left()
+    }
+
+    fn cursor_right(&mut self) -> Result<()> {
+        self.inner.cursor_right()
+    }
+
+    fn clear_eol(&mut self) -> Result<()> {
+        self.inner.clear_eol()
+    }
+
+    fn cursor_up(&mut self) -> Result<()> {
+        self.inner.cursor_up()
+    }
+
+    fn insert_blank(&mut self) -> Result<()> {
+        self.inner.insert_blank()
+    }
+
+    fn delete_char(&mut self) -> Result<()> {
+        self.inner.delete_char()
+    }
+
+    fn clear_to_end_of_screen(&mut self) -> Result<()> {
+        self.inner.clear_to_end_of_screen()
+    }
+
+    fn clear_screen(&mut self) -> Result<()> {
+        self.inner.clear_screen()
+    }
+
+    fn bell(&mut self) -> Result<()> {
+        self.inner.bell()
+    }
+
+    fn enter_alternate_screen(&mut self) -> Result<()> {
+        self.inner.enter_alternate_screen()
+    }
+
+    fn exit_alternate_screen(&mut self) -> Result<()> {
+        self.inner.exit_alternate_screen()
+    }
+
+    fn size(&mut self) -> Option<(u16, u16)> {
+        self.inner.size()
+    }
+
+    fn get_cursor_position(&mut self) -> Option<(u16, u16)> {
+        self.inner.get_cursor_position()
+    }
+
+    fn show_status(&mut self, text: &[u8]) -> Result<()> {
+        self.inner.show_status(text)
+    }
+
+    fn clear_status(&mut self) -> Result<()> {
+        self.inner.clear_status()
+    }
+
+    fn capabilities(&self) -> Caps {
+        self.inner.capabilities()
+    }
+}
+
+/// One of the consoles of a [`ConsoleMux`].
+#[derive(Debug)]
+struct Console {
+    name: String,
+    prompt: String,
+    editor: LineEditor,
+}
+
+/// Consoles taking turns on one terminal.
+///
+/// Each console added with [`add`](Self::add) has its own editor, so the line
+/// being typed and the history stay with it. Only the active console gets
+/// input. Pressing the escape key, Ctrl+] by default, followed by:
+///
+/// - `1` to `9` switches to that console
+/// - `n` or Space switches to the next one
+/// - the escape key again types it into the line
+///
+/// Anything else cancels the command. On a switch, the line being edited is
+/// set aside with [`LineEditor::suspend`] and left on screen, the new
+/// console's name is shown in brackets, and its prompt and unfinished line
+/// are drawn again below.
+///
+/// Lines are read without blocking with [`poll_read_line`](Self::poll_read_line),
+/// for main loops that service the hardware in between, or with
+/// [`read_line`](Self::read_line). Both return which console the line was
+/// typed into.
+///
+/// # Examples
+///
+/// ```
+/// use editline::mux::ConsoleMux;
+/// use editline::LineEditor;
+/// # use editline::{Error, Result, Terminal};
+/// # struct Uart(Vec<u8>);
+/// # impl Terminal for Uart {
+/// #     fn read_byte(&mut self) -> Result<u8> { if self.0.is_empty() { Err(Error::Eof) } else { Ok(self.0.remove(0)) } }
+/// #     fn write(&mut self, _: &[u8]) -> Result<()> { Ok(()) }
+/// #     fn flush(&mut self) -> Result<()> { Ok(()) }
+/// #     fn enter_raw_mode(&mut self) -> Result<()> { Ok(()) }
+/// #     fn exit_raw_mode(&mut self) -> Result<()> { Ok(()) }
+/// #     fn cursor_left(&mut self) -> Result<()> { Ok(()) }
+/// #     fn cursor_right(&mut self) -> Result<()> { Ok(()) }
+/// #     fn clear_eol(&mut self) -> Result<()> { Ok(()) }
+/// # }
+/// # let uart = Uart(b"\x1d2run\r".to_vec());
+///
+/// let mut mux = ConsoleMux::new(uart);
+/// let boot = mux.add("boot", "boot> ", LineEditor::new(128, 10));
+/// let app = mux.add("app", "app> ", LineEditor::new(256, 50));
+///
+/// // The user switched to the application console with Ctrl+] 2
+/// let (console, line) = mux.read_line()?;
+/// assert_eq!((console, line.as_str()), (app, "run"));
+/// # let _ = boot;
+/// # Ok::<(), editline::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct ConsoleMux<T> {
+    terminal: Switch<T>,
+    consoles: Vec<Console>,
+    active: usize,
+}
+
+impl<T: Terminal> ConsoleMux<T> {
+    /// Shares `terminal`, starting without consoles.
+    pub fn new(terminal: T) -> Self {
+        let terminal = Switch {
+            inner: terminal,
+            escape: DEFAULT_ESCAPE,
+            escaped: false,
+            peeked: None,
+            request: None,
+        };
+        ConsoleMux {
+            terminal,
+            consoles: Vec::new(),
+            active: 0,
+        }
+    }
+
+    /// Adds a console shown as `name`, reading lines with `editor` after
+    /// `prompt`, and returns its index.
+    ///
+    /// The first console added is the active one.
+    pub fn add(&mut self, name: &str, prompt: &str, editor: LineEditor) -> usize {
+        self.consoles.push(Console {
+            name: name.into(),
+            prompt: prompt.into(),
+            editor,
+        });
+        self.consoles.len() - 1
+    }
+
+    /// Sets the byte that starts an escape command, e.g. `0x01` for Ctrl+A as
+    /// in `screen`.
+    pub fn set_escape(&mut self, escape: u8) {
+        self.terminal.escape = escape;
+    }
+
+    /// Returns the index of the console that gets input.
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    /// Returns the editor of console `index`, e.g. to set its completer.
+    pub fn editor(&mut self, index: usize) -> Option<&mut LineEditor> {
+        self.consoles
+            .get_mut(index)
+            .map(|console| &mut console.editor)
+    }
+
+    /// Returns the shared terminal, e.g. to print a command's output.
+    pub fn terminal(&mut self) -> &mut T {
+        &mut self.terminal.inner
+    }
+
+    /// Returns the shared terminal.
+    pub fn into_inner(self) -> T {
+        self.terminal.inner
+    }
+
+    /// Makes console `index` the active one, as if the user had switched to it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no console `index`.
+    pub fn switch_to(&mut self, index: usize) -> Result<()> {
+        assert!(index < self.consoles.len(), "no console {}", index);
+        if index == self.active {
+            return Ok(());
+        }
+        self.consoles[self.active]
+            .editor
+            .suspend(&mut self.terminal)?;
+        self.active = index;
+        let terminal = &mut self.terminal.inner;
+        terminal.write(b"[")?;
+        terminal.write(self.consoles[index].name.as_bytes())?;
+        terminal.write(b"]")?;
+        write_newline(terminal)?;
+        terminal.flush()
+    }
+
+    /// Carries out a switch the user asked for.
+    fn switch(&mut self, target: Target) -> Result<()> {
+        let index = match target {
+            Target::Index(index) => index,
+            Target::Next => (self.active + 1) % self.consoles.len(),
+        };
+        if index < self.consoles.len() {
+            self.switch_to(index)
+        } else {
+            self.terminal.bell()?;
+            self.terminal.flush()
+        }
+    }
+
+    /// Reads a line on the active console without blocking, like
+    /// [`LineEditor::poll_read_line`], returning the console's index with it.
+    ///
+    /// Stays pending while there are no consoles.
+    pub fn poll_read_line(&mut self) -> Poll<Result<(usize, String)>> {
+        loop {
+            let index = self.active;
+            let console = match self.consoles.get_mut(index) {
+                Some(console) => console,
+                None => return Poll::Pending,
+            };
+            let poll = console
+                .editor
+                .poll_read_line(&mut self.terminal, &console.prompt);
+            if poll.is_pending() {
+                if let Some(target) = self.terminal.request.take() {
+                    if let Err(e) = self.switch(target) {
+                        return Poll::Ready(Err(e));
+                    }
+                    // Show the new console's prompt
+                    continue;
+                }
+            }
+            return poll.map(|line| line.map(|line| (index, line)));
+        }
+    }
+
+    /// Reads a line on whichever console the user submits one, blocking until
+    /// then.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no consoles.
+    pub fn read_line(&mut self) -> Result<(usize, String)> {
+        assert!(!self.consoles.is_empty(), "no consoles");
+        loop {
+            if let Poll::Ready(result) = self.poll_read_line() {
+                return result;
+            }
+            // Wait for input, which the editor picks up on the next poll
+            self.terminal.peeked = Some(self.terminal.inner.read_byte());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ScriptedTerminal;
+    use crate::{ansi, Error};
+
+    #[test]
+    fn test_switching_keeps_lines_and_history() {
+        // A link that stays open, with nothing waiting between inputs
+        let mut mux = ConsoleMux::new(ScriptedTerminal {
+            end: None,
+            ..ScriptedTerminal::new(b"")
+        });
+        let boot = mux.add("boot", "boot> ", LineEditor::new(64, 10));
+        let app = mux.add("app", "app> ", LineEditor::new(64, 10));
+        let input =
+            |mux: &mut ConsoleMux<ScriptedTerminal>, bytes: &[u8]| mux.terminal().feed(bytes);
+
+        input(&mut mux, b"flash\r");
+        assert_eq!(
+            mux.poll_read_line(),
+            Poll::Ready(Ok((boot, "flash".into())))
+        );
+        // Half a line, then over to the application and back
+        input(&mut mux, b"era\x1dnstat\x1d\x1dus\r");
+        assert_eq!(
+            mux.poll_read_line(),
+            Poll::Ready(Ok((app, "status".into())))
+        );
+        input(&mut mux, b"\x1d1se\r");
+        assert_eq!(
+            mux.poll_read_line(),
+            Poll::Ready(Ok((boot, "erase".into())))
+        );
+        assert_eq!(mux.active(), boot);
+
+        // Histories are separate
+        assert_eq!(mux.editor(boot).unwrap().history().len(), 2);
+        assert_eq!(mux.editor(app).unwrap().history().len(), 1);
+
+        let output = String::from_utf8(mux.terminal().output.clone()).unwrap();
+        let shown = ansi::strip_escapes(&output).replace("\r\n", "\n");
+        assert!(shown.contains("boot> era\n[app]\napp> stat"), "{:?}", shown);
+        assert!(shown.contains("[boot]\nboot> erase\n"), "{:?}", shown);
+
+        // An unknown console rings the bell; a lone escape key waits
+        input(&mut mux, b"\x1d7x\x1d");
+        assert_eq!(mux.poll_read_line(), Poll::Pending);
+        assert!(mux.terminal().output.ends_with(b"boot> \x07x\x1b[K"));
+        assert!(mux.terminal().input.is_empty());
+    }
+
+    #[test]
+    fn test_read_line_blocks_for_input() {
+        let mut mux = ConsoleMux::new(ScriptedTerminal::new(b"\x1d2ok\r"));
+        mux.add("one", "1> ", LineEditor::new(64, 10));
+        mux.add("two", "2> ", LineEditor::new(64, 10));
+        assert_eq!(mux.read_line().unwrap(), (1, "ok".into()));
+        assert_eq!(mux.read_line(), Err(Error::Eof));
+    }
+}
@@ -0,0 +1,45 @@
+//! Line breaks in the input.
+//!
+//! Hosts disagree on what Enter sends: terminals send CR (`\r`), some serial
+//! tools LF (`\n`), and others both. The decoder reports CR as
+//! [`KeyEvent::Enter`](crate::KeyEvent::Enter) and LF as
+//! [`KeyEvent::LineFeed`](crate::KeyEvent::LineFeed), and both submit the line
+//! by default. With
+//! [`LineEditor::set_crlf_as_one_enter`](crate::LineEditor::set_crlf_as_one_enter)
+//! an LF right after a CR is dropped, so CRLF hosts don't submit an extra empty
+//! line.
+//!
+//! Pasted text is another matter: a newline inside it would submit whatever
+//! came before. [`LineEditor::set_pasted_newlines`](crate::LineEditor::set_pasted_newlines)
+//! chooses what happens to it instead. The editor then turns on the terminal's
+//! bracketed paste mode while reading, if its
+//! [`capabilities`](crate::Terminal::capabilities) include it, so pasted text
+//! arrives between [`KeyEvent::PasteStart`](crate::KeyEvent::PasteStart) and
+//! [`KeyEvent::PasteEnd`](crate::KeyEvent::PasteEnd); elsewhere pastes look
+//! like typing.
+//!
+//! [Full-screen editing](crate::LineEditor::read_line_fullscreen) keeps every
+//! line break, typed or pasted, and only honors the CRLF setting.
+
+/// What a newline inside pasted text does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PastedNewline {
+    /// Submits the line, like Enter; the rest of the paste goes to the next
+    /// read. Bracketed paste mode stays off.
+    Submit,
+    /// Becomes a space, so a multi-line paste ends up on one line.
+    Space,
+    /// Is kept as a line break: shown as `^J` and returned as `\n`.
+    Literal,
+}
+
+impl Default for PastedNewline {
+    fn default() -> Self {
+        PastedNewline::Submit
+    }
+}
+
+/// Turns on bracketed paste mode.
+pub(crate) const BRACKETED_PASTE_ON: &[u8] = b"\x1b[?2004h";
+/// Turns off bracketed paste mode.
+pub(crate) const BRACKETED_PASTE_OFF: &[u8] = b"\x1b[?2004l";
@@ -0,0 +1,53 @@
+//! Watching what happens while lines are edited.
+//!
+//! Install an [`Observer`] with
+//! [`LineEditor::set_observer`](crate::LineEditor::set_observer) to hear about
+//! submitted lines, history recalls and typed characters without wrapping every
+//! call to `read_line`: log commands with timestamps, keep secrets out of the
+//! history, or feed a parser as the user types. Every method has an empty
+//! default, so implement only the ones you need.
+
+use crate::Context;
+
+/// Callbacks for editor events.
+///
+/// # Examples
+///
+/// Keep lines containing passwords out of the history:
+///
+/// ```
+/// use editline::observer::Observer;
+/// use editline::LineEditor;
+///
+/// struct NoSecrets;
+///
+/// impl Observer for NoSecrets {
+///     fn on_submit(&mut self, line: &str) -> bool {
+///         !line.contains("password")
+///     }
+/// }
+///
+/// let mut editor = LineEditor::new(1024, 50);
+/// editor.set_observer(NoSecrets);
+/// ```
+pub trait Observer {
+    /// Called with each submitted line, trimmed and rewritten by any
+    /// [`SubmitHook`](crate::SubmitHook), before it is added to the history and
+    /// returned. Return `false` to keep it out of the history.
+    fn on_submit(&mut self, line: &str) -> bool {
+        let _ = line;
+        true
+    }
+
+    /// Called when a history entry replaces the line, through Up/Down, a
+    /// [`Recall`](crate::Action::Recall) binding or an incremental search.
+    fn on_history_recall(&mut self, entry: &str) {
+        let _ = entry;
+    }
+
+    /// Called after a typed character is inserted; `ctx` describes the line
+    /// and cursor after the insertion.
+    fn on_char(&mut self, c: char, ctx: &Context) {
+        let _ = (c, ctx);
+    }
+}
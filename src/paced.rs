@@ -0,0 +1,158 @@
+//! Pacing output for slow links.
+
+use crate::{Caps, Result, Terminal};
+
+/// Terminal pausing after every few bytes of output.
+///
+/// A UART without hardware flow control sends as fast as the baud rate
+/// allows, and a peer with a tiny receive buffer, such as a cheap USB serial
+/// adapter, drops what it can't take in time. Typed characters are no
+/// problem, but a redraw, e.g. recalling a long line from the history, can
+/// overflow it. This wrapper flushes and calls a delay hook after each
+/// `chunk` bytes written, counted across writes, giving the peer time to
+/// catch up.
+///
+/// Only [`Terminal::write`] output is counted, so the wrapper sends cursor
+/// movement and screen clearing as ANSI escape sequences itself rather than
+/// leaving them to the wrapped terminal.
+///
+/// # Examples
+///
+/// ```ignore
+/// use editline::{LineEditor, PacedTerminal};
+///
+/// // 16 bytes, then a millisecond for the adapter to drain them
+/// let mut terminal = PacedTerminal::new(uart_terminal, 16, || delay.delay_ms(1));
+/// let mut editor = LineEditor::new(256, 20);
+/// let line = editor.read_line_with_prompt(&mut terminal, "> ")?;
+/// ```
+#[derive(Debug)]
+pub struct PacedTerminal<T, D> {
+    inner: T,
+    chunk: usize,
+    delay: D,
+    /// Bytes written since the last pause
+    sent: usize,
+}
+
+impl<T: Terminal, D: FnMut()> PacedTerminal<T, D> {
+    /// Wraps `inner`, calling `delay` after each `chunk` bytes written. A
+    /// `chunk` of 0 is taken as 1.
+    pub fn new(inner: T, chunk: usize, delay: D) -> Self {
+        PacedTerminal {
+            inner,
+            chunk: chunk.max(1),
+            delay,
+            sent: 0,
+        }
+    }
+
+    /// Returns the wrapped terminal.
+    pub fn inner(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Returns the wrapped terminal and the delay hook.
+    pub fn into_inner(self) -> (T, D) {
+        (self.inner, self.delay)
+    }
+}
+
+impl<T: Terminal, D: FnMut()> Terminal for PacedTerminal<T, D> {
+    fn read_byte(&mut self) -> Result<u8> {
+        self.inner.read_byte()
+    }
+
+    fn try_read_byte(&mut self) -> Result<Option<u8>> {
+        self.inner.try_read_byte()
+    }
+
+    fn write(&mut self, mut data: &[u8]) -> Result<()> {
+        while !data.is_empty() {
+            let n = data.len().min(self.chunk - self.sent);
+            self.inner.write(&data[..n])?;
+            data = &data[n..];
+            self.sent += n;
+            if self.sent == self.chunk {
+                self.inner.flush()?;
+                (self.delay)();
+                self.sent = 0;
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        self.inner.enter_raw_mode()
+    }
+
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        self.inner.exit_raw_mode()
+    }
+
+    fn cursor_left(&mut self) -> Result<()> {
+        self.write(b"\x1b[D")
+    }
+
+    fn cursor_right(&mut self) -> Result<()> {
+        self.write(b"\x1b[C")
+    }
+
+    fn clear_eol(&mut self) -> Result<()> {
+        self.write(b"\x1b[K")
+    }
+
+    fn size(&mut self) -> Option<(u16, u16)> {
+        self.inner.size()
+    }
+
+    fn get_cursor_position(&mut self) -> Option<(u16, u16)> {
+        self.inner.get_cursor_position()
+    }
+
+    fn capabilities(&self) -> Caps {
+        self.inner.capabilities()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ScriptedTerminal;
+    use crate::LineEditor;
+    use alloc::rc::Rc;
+    use alloc::vec::Vec;
+    use core::cell::RefCell;
+
+    #[test]
+    fn test_output_paced() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        // Output logged with `|` where the writer paused
+        let uart = ScriptedTerminal {
+            log: Some(log.clone()),
+            ..ScriptedTerminal::new(b"hello\x1b[H\r")
+        };
+        let pauses = log.clone();
+        let mut terminal = PacedTerminal::new(uart, 4, move || pauses.borrow_mut().push(b'|'));
+        let mut editor = LineEditor::new(64, 10);
+        assert_eq!(
+            editor.read_line_with_prompt(&mut terminal, "> ").unwrap(),
+            "hello"
+        );
+
+        let log = log.borrow();
+        assert!(log
+            .split(|&byte| byte == b'|')
+            .all(|chunk| chunk.len() <= 4));
+        let output: Vec<u8> = log.iter().copied().filter(|&byte| byte != b'|').collect();
+        assert_eq!(log.len() - output.len(), output.len() / 4);
+        assert!(
+            output.starts_with(b"> h\x1b[Ke")
+                && output.ends_with(b"\x1b[D\x1b[D\x1b[D\x1b[D\x1b[D\r\n")
+        );
+    }
+}
@@ -0,0 +1,400 @@
+//! Saving history to flash and loading it back.
+//!
+//! Flash is erased in sectors and wears out with every erase, so writing the
+//! whole history after each command is the worst thing a device can do. A
+//! [`HistoryWriter`] instead collects submitted lines and hands them to a
+//! [`HistoryStore`] in blocks of a configured size, which the store appends to
+//! a log; a sector is only erased once the log fills it. With the
+//! `history-compress` feature, blocks are compressed with a small LZSS coder
+//! that needs no tables or separate window, which suits command lines
+//! that mostly repeat the same few words.
+//!
+//! On startup, pass each stored block, oldest first, to [`load_block`].
+//!
+//! # Examples
+//!
+//! ```
+//! use editline::persist::{load_block, HistoryWriter};
+//! use editline::History;
+//!
+//! // A log of blocks, standing in for a flash region
+//! let mut log: Vec<Vec<u8>> = Vec::new();
+//!
+//! let mut writer = HistoryWriter::new(|block: &[u8]| {
+//!     log.push(block.to_vec());
+//!     Ok(())
+//! }, 64);
+//! for line in ["status", "gpio set 17 1", "gpio set 17 0", "reset"] {
+//!     writer.record(line)?;
+//! }
+//! writer.flush()?;
+//! drop(writer);
+//!
+//! let mut history = History::new(50);
+//! for block in &log {
+//!     load_block(&mut history, block)?;
+//! }
+//! assert_eq!(history.len(), 4);
+//! # Ok::<(), editline::Error>(())
+//! ```
+
+use alloc::vec::Vec;
+
+use crate::{Error, History, IoCause, Result};
+
+/// Flag in a block's first byte: the records are compressed.
+const COMPRESSED: u8 = 1;
+
+/// Destination of history blocks, typically an append-only log in flash.
+///
+/// Closures of the form `FnMut(&[u8]) -> Result<()>` implement this trait.
+pub trait HistoryStore {
+    /// Appends one block. Blocks must be kept intact and returned to
+    /// [`load_block`] in the order they were appended.
+    fn append(&mut self, block: &[u8]) -> Result<()>;
+}
+
+impl<F: FnMut(&[u8]) -> Result<()>> HistoryStore for F {
+    fn append(&mut self, block: &[u8]) -> Result<()> {
+        self(block)
+    }
+}
+
+/// Batches submitted lines into blocks for a [`HistoryStore`].
+#[derive(Debug)]
+pub struct HistoryWriter<S> {
+    store: S,
+    /// Encoded records not yet written
+    pending: Vec<u8>,
+    batch: usize,
+    #[cfg(feature = "history-compress")]
+    compress: bool,
+}
+
+impl<S: HistoryStore> HistoryWriter<S> {
+    /// Creates a writer appending a block to `store` whenever `batch` bytes of
+    /// lines have been recorded.
+    ///
+    /// Choose `batch` to match the flash write granularity or a reasonable
+    /// fraction of a sector; lines recorded since the last block are lost on
+    /// power failure unless [`flush`](Self::flush) is called.
+    pub fn new(store: S, batch: usize) -> Self {
+        HistoryWriter {
+            store,
+            pending: Vec::new(),
+            batch,
+            #[cfg(feature = "history-compress")]
+            compress: false,
+        }
+    }
+
+    /// Compresses blocks before storing them.
+    #[cfg(feature = "history-compress")]
+    pub fn set_compression(&mut self, enabled: bool) {
+        self.compress = enabled;
+    }
+
+    /// Records a submitted line, appending a block if the batch is full.
+    ///
+    /// Empty lines are skipped, as [`History::add`] does.
+    pub fn record(&mut self, line: &str) -> Result<()> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(());
+        }
+        encode_record(&mut self.pending, line.as_bytes());
+        if self.pending.len() >= self.batch {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Appends a block with the lines recorded so far, if any.
+    pub fn flush(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let mut block = Vec::with_capacity(self.pending.len() + 1);
+        #[cfg(feature = "history-compress")]
+        if self.compress {
+            block.push(COMPRESSED);
+            lzss::compress(&self.pending, &mut block);
+        }
+        if block.is_empty() {
+            block.push(0);
+            block.extend_from_slice(&self.pending);
+        }
+        self.store.append(&block)?;
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// Returns the store.
+    pub fn store(&mut self) -> &mut S {
+        &mut self.store
+    }
+}
+
+/// Encodes all entries of `history` as one block, e.g. to rewrite a log that
+/// has filled its flash region.
+pub fn encode(history: &History) -> Vec<u8> {
+    let mut block = Vec::from([0]);
+    for entry in history.iter() {
+        encode_record(&mut block, entry.as_bytes());
+    }
+    block
+}
+
+/// Adds the lines stored in `block` to `history`.
+///
+/// # Errors
+///
+/// Returns [`Error::Io`] with [`IoCause::InvalidData`] if the block is
+/// corrupt, or [`IoCause::Unsupported`] if it is compressed and the
+/// `history-compress` feature is off. Lines before the damage are still added.
+pub fn load_block(history: &mut History, block: &[u8]) -> Result<()> {
+    let (&flags, data) = block
+        .split_first()
+        .ok_or(Error::from(IoCause::InvalidData))?;
+    let decompressed;
+    let mut records = if flags & COMPRESSED != 0 {
+        decompressed = lzss::decompress(data)?;
+        &decompressed[..]
+    } else {
+        data
+    };
+    while !records.is_empty() {
+        let (len, rest) = decode_varint(records).ok_or(Error::from(IoCause::InvalidData))?;
+        if len > rest.len() {
+            return Err(IoCause::InvalidData.into());
+        }
+        let line = core::str::from_utf8(&rest[..len]).map_err(|_| Error::InvalidUtf8)?;
+        history.add(line);
+        records = &rest[len..];
+    }
+    Ok(())
+}
+
+/// Appends a length-prefixed record.
+fn encode_record(out: &mut Vec<u8>, data: &[u8]) {
+    let mut len = data.len();
+    while len >= 0x80 {
+        out.push(len as u8 | 0x80);
+        len >>= 7;
+    }
+    out.push(len as u8);
+    out.extend_from_slice(data);
+}
+
+/// Reads a length written by [`encode_record`], which can be any `usize`;
+/// lengths that overflow one are corrupt.
+fn decode_varint(data: &[u8]) -> Option<(usize, &[u8])> {
+    let mut value = 0usize;
+    for (i, &byte) in data.iter().enumerate() {
+        let shift = 7 * i as u32;
+        let group = usize::from(byte & 0x7f);
+        if shift >= usize::BITS || (group << shift) >> shift != group {
+            return None;
+        }
+        value |= group << shift;
+        if byte & 0x80 == 0 {
+            return Some((value, &data[i + 1..]));
+        }
+    }
+    None
+}
+
+#[cfg(feature = "history-compress")]
+mod lzss {
+    //! LZSS in the style of heatshrink: a flag byte precedes each group of
+    //! eight items, a set bit marking a back-reference of two bytes (12-bit
+    //! distance, 4-bit length) and a clear bit a literal byte.
+
+    use alloc::vec::Vec;
+
+    use crate::{Error, IoCause, Result};
+
+    const WINDOW: usize = 4096;
+    const MIN_MATCH: usize = 3;
+    const MAX_MATCH: usize = MIN_MATCH + 15;
+
+    /// Longest earlier match for the text at `pos`, as `(distance, length)`.
+    fn longest_match(input: &[u8], pos: usize) -> (usize, usize) {
+        let max = (input.len() - pos).min(MAX_MATCH);
+        let mut best = (0, 0);
+        for start in pos.saturating_sub(WINDOW)..pos {
+            let len = (0..max)
+                .take_while(|&i| input[start + i] == input[pos + i])
+                .count();
+            if len > best.1 {
+                best = (pos - start, len);
+            }
+        }
+        best
+    }
+
+    pub(super) fn compress(input: &[u8], out: &mut Vec<u8>) {
+        let mut pos = 0;
+        while pos < input.len() {
+            let flags = out.len();
+            out.push(0);
+            for bit in 0..8 {
+                if pos >= input.len() {
+                    break;
+                }
+                let (distance, len) = longest_match(input, pos);
+                if len >= MIN_MATCH {
+                    let distance = distance - 1;
+                    out[flags] |= 1 << bit;
+                    out.push((distance >> 4) as u8);
+                    out.push(((distance & 0xf) << 4 | (len - MIN_MATCH)) as u8);
+                    pos += len;
+                } else {
+                    out.push(input[pos]);
+                    pos += 1;
+                }
+            }
+        }
+    }
+
+    pub(super) fn decompress(mut input: &[u8]) -> Result<Vec<u8>> {
+        let corrupt = Error::from(IoCause::InvalidData);
+        let mut out = Vec::with_capacity(input.len() * 2);
+        while let Some((&flags, rest)) = input.split_first() {
+            input = rest;
+            for bit in 0..8 {
+                match input {
+                    [] => break,
+                    [high, low, rest @ ..] if flags & (1 << bit) != 0 => {
+                        let distance = (usize::from(*high) << 4 | usize::from(low >> 4)) + 1;
+                        let len = usize::from(low & 0xf) + MIN_MATCH;
+                        if distance > out.len() {
+                            return Err(corrupt);
+                        }
+                        for _ in 0..len {
+                            out.push(out[out.len() - distance]);
+                        }
+                        input = rest;
+                    }
+                    [literal, rest @ ..] if flags & (1 << bit) == 0 => {
+                        out.push(*literal);
+                        input = rest;
+                    }
+                    _ => return Err(corrupt),
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(not(feature = "history-compress"))]
+mod lzss {
+    use alloc::vec::Vec;
+
+    use crate::{IoCause, Result};
+
+    pub(super) fn decompress(_input: &[u8]) -> Result<Vec<u8>> {
+        Err(IoCause::Unsupported.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+
+    fn lines() -> Vec<String> {
+        (0..40)
+            .map(|i| alloc::format!("gpio set {} {}", i % 8, i % 2))
+            .chain(Some("é".into()))
+            .collect()
+    }
+
+    #[test]
+    fn test_batched_blocks_round_trip() {
+        let mut blocks: Vec<Vec<u8>> = Vec::new();
+        let mut writer = HistoryWriter::new(
+            |block: &[u8]| {
+                blocks.push(block.to_vec());
+                Ok(())
+            },
+            100,
+        );
+        for line in lines() {
+            writer.record(&line).unwrap();
+        }
+        writer.record("  ").unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+        // Five blocks of eight 13-byte records, then the rest on flush
+        assert_eq!(blocks.len(), 6);
+        assert_eq!(blocks[0].len(), 1 + 8 * 13);
+
+        let mut history = History::new(100);
+        for block in &blocks {
+            load_block(&mut history, block).unwrap();
+        }
+        assert!(history.iter().eq(lines().iter().map(String::as_str)));
+
+        let mut copy = History::new(100);
+        load_block(&mut copy, &encode(&history)).unwrap();
+        assert!(copy.iter().eq(history.iter()));
+
+        assert!(load_block(&mut copy, &[0, 5, b'a']).is_err());
+    }
+
+    #[test]
+    fn test_record_lengths() {
+        // Whatever encode_record writes, decode_varint reads back
+        let mut record = Vec::new();
+        encode_record(&mut record, &[b'x'; 20_000]);
+        assert_eq!(&record[..3], [0xa0, 0x9c, 0x01]);
+        assert_eq!(
+            decode_varint(&record).map(|(len, rest)| (len, rest.len())),
+            Some((20_000, 20_000))
+        );
+        assert_eq!(
+            decode_varint(&[0x80, 0x80, 0x80, 0x80, 0x01]).map(|(len, _)| len),
+            Some(1 << 28)
+        );
+
+        // Lengths past usize::MAX are corrupt, not truncated
+        let mut huge = [0xff; 11];
+        huge[10] = 0x7f;
+        assert_eq!(decode_varint(&huge), None);
+        assert_eq!(decode_varint(&[0x80]), None);
+    }
+
+    #[cfg(feature = "history-compress")]
+    #[test]
+    fn test_compressed_blocks() {
+        let mut blocks: Vec<Vec<u8>> = Vec::new();
+        let mut writer = HistoryWriter::new(
+            |block: &[u8]| {
+                blocks.push(block.to_vec());
+                Ok(())
+            },
+            4096,
+        );
+        writer.set_compression(true);
+        for line in lines() {
+            writer.record(&line).unwrap();
+        }
+        writer.flush().unwrap();
+        drop(writer);
+
+        let raw: usize = lines().iter().map(|line| line.len() + 1).sum();
+        assert_eq!(blocks.len(), 1);
+        assert!(
+            blocks[0].len() * 3 < raw,
+            "{} of {} bytes",
+            blocks[0].len(),
+            raw
+        );
+
+        let mut history = History::new(100);
+        load_block(&mut history, &blocks[0]).unwrap();
+        assert!(history.iter().eq(lines().iter().map(String::as_str)));
+    }
+}
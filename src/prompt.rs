@@ -0,0 +1,623 @@
+//! One-key questions for provisioning and setup consoles.
+//!
+//! [`confirm`] asks a yes/no question and [`select`] lets the user pick one of
+//! several items with the arrow keys. Both read keys through the terminal's
+//! [`parse_key_event`](crate::Terminal::parse_key_event) and handle raw mode
+//! themselves, so they can be used between calls to
+//! [`LineEditor::read_line`](crate::LineEditor::read_line). With the `async`
+//! feature, [`confirm_async`] and [`select_async`] do the same on an
+//! [`AsyncTerminal`](crate::asynch::AsyncTerminal).
+//!
+//! [`read_u32`] and [`read_hex`] read a number with the line editor, accepting
+//! only digits and asking again until the value is valid. [`read_template`]
+//! fills in a fixed-format field such as an IP or MAC address.
+//!
+//! Ctrl+C cancels with [`Error::Interrupted`] and Ctrl+D with [`Error::Eof`].
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+
+use crate::{
+    cursor_left_by, cursor_right_by, write_newline, write_visible, Context, Error, Filtered,
+    KeyEvent, LineEditor, RawModeGuard, Result, Terminal,
+};
+
+/// Asks a yes/no question, returning `true` for yes.
+///
+/// Shows `question` followed by `[y/N]` and waits for `y` or `n`; Enter
+/// answers no. The answer is printed after the question.
+///
+/// # Examples
+///
+/// ```no_run
+/// use editline::prompt::confirm;
+/// use editline::terminals::StdioTerminal;
+///
+/// let mut terminal = StdioTerminal::new();
+/// if confirm(&mut terminal, "Erase flash?")? {
+///     // erase
+/// }
+/// # Ok::<(), editline::Error>(())
+/// ```
+pub fn confirm<T: Terminal>(terminal: &mut T, question: &str) -> Result<bool> {
+    let mut raw = RawModeGuard::new(terminal)?;
+    let terminal = &mut *raw;
+    ask(terminal, question)?;
+    let answer = loop {
+        let event = terminal.parse_key_event()?;
+        if let Some(answer) = confirm_key(event) {
+            break answer;
+        }
+    };
+    let result = answer.and_then(|yes| answered(terminal, yes));
+    raw.exit()?;
+    result
+}
+
+/// Lets the user pick one of `items` with Up/Down and Enter, returning its
+/// index.
+///
+/// The items are listed one per line with the selection marked by `>`; Home
+/// and End jump to the first and last item, and digits 1 to 9 to the item with
+/// that number. The list stays on screen afterwards.
+///
+/// # Panics
+///
+/// Panics if `items` is empty.
+///
+/// # Examples
+///
+/// ```no_run
+/// use editline::prompt::select;
+/// use editline::terminals::StdioTerminal;
+///
+/// let mut terminal = StdioTerminal::new();
+/// let band = select(&mut terminal, &["868 MHz", "915 MHz", "2.4 GHz"])?;
+/// # Ok::<(), editline::Error>(())
+/// ```
+pub fn select<T: Terminal>(terminal: &mut T, items: &[&str]) -> Result<usize> {
+    let mut menu = Menu::new(items);
+    let mut raw = RawModeGuard::new(terminal)?;
+    let terminal = &mut *raw;
+    let result = (|| {
+        menu.draw(terminal)?;
+        loop {
+            let event = terminal.parse_key_event()?;
+            if let Some(choice) = menu.key(event) {
+                return choice;
+            }
+            menu.redraw(terminal)?;
+        }
+    })();
+    raw.exit()?;
+    result
+}
+
+/// Reads a decimal number in `range`.
+///
+/// Only digits can be typed. If the number entered on Enter is outside
+/// `range`, an error naming the range is shown below it and `prompt` is shown
+/// again.
+///
+/// # Examples
+///
+/// ```no_run
+/// use editline::prompt::read_u32;
+/// use editline::terminals::StdioTerminal;
+///
+/// let mut terminal = StdioTerminal::new();
+/// let channel = read_u32(&mut terminal, "Channel (1-13): ", 1..=13)?;
+/// # Ok::<(), editline::Error>(())
+/// ```
+pub fn read_u32<T: Terminal>(
+    terminal: &mut T,
+    prompt: &str,
+    range: RangeInclusive<u32>,
+) -> Result<u32> {
+    let mut editor = LineEditor::new(16, 0);
+    // u32::MAX has ten digits
+    editor.set_max_line_length(Some(10));
+    editor.set_input_filter(|c: char, _ctx: &Context| {
+        if c.is_ascii_digit() {
+            Filtered::Insert(c)
+        } else {
+            Filtered::Discard
+        }
+    });
+    let error = format!("enter a number from {} to {}", range.start(), range.end());
+    read_field(terminal, &mut editor, prompt, &error, |line| {
+        line.parse().ok().filter(|value| range.contains(value))
+    })
+}
+
+/// Reads a hexadecimal number of up to `width` digits.
+///
+/// Only hex digits can be typed, shown in upper case, and no more than
+/// `width` of them. An empty entry shows an error and `prompt` again.
+///
+/// # Panics
+///
+/// Panics if `width` is 0 or more than 16.
+///
+/// # Examples
+///
+/// ```no_run
+/// use editline::prompt::read_hex;
+/// use editline::terminals::StdioTerminal;
+///
+/// let mut terminal = StdioTerminal::new();
+/// let address = read_hex(&mut terminal, "I2C address: 0x", 2)?;
+/// # Ok::<(), editline::Error>(())
+/// ```
+pub fn read_hex<T: Terminal>(terminal: &mut T, prompt: &str, width: usize) -> Result<u64> {
+    assert!((1..=16).contains(&width), "hex fields hold 1 to 16 digits");
+    let mut editor = LineEditor::new(width, 0);
+    editor.set_max_line_length(Some(width));
+    editor.set_input_filter(|c: char, _ctx: &Context| {
+        if c.is_ascii_hexdigit() {
+            Filtered::Insert(c.to_ascii_uppercase())
+        } else {
+            Filtered::Discard
+        }
+    });
+    let error = format!("enter 1 to {} hex digits", width);
+    read_field(terminal, &mut editor, prompt, &error, |line| {
+        u64::from_str_radix(line, 16).ok()
+    })
+}
+
+/// Placeholder for an unfilled position in a [`read_template`] mask.
+pub const PLACEHOLDER: char = '_';
+
+/// Reads a fixed-format field such as `___.___.___.___` for an IP address or
+/// `__:__:__:__:__:__` for a MAC address.
+///
+/// The mask is shown after `prompt` and each [`PLACEHOLDER`] in it is a
+/// position to fill; the cursor skips the other characters. Characters for
+/// which `accept` returns `true` fill the position under the cursor. Typing a
+/// separator from the mask moves to the group after it, so `1.2.3.4` can be
+/// entered as typed. Backspace and Delete clear a position, Left/Right, Home
+/// and End move between positions.
+///
+/// Enter returns the mask with the entered characters and the unfilled
+/// positions removed, e.g. `192.168.1.1` for `192.168.1__.1__`.
+///
+/// # Panics
+///
+/// Panics if `mask` isn't ASCII or has no placeholder.
+///
+/// # Examples
+///
+/// ```no_run
+/// use editline::prompt::read_template;
+/// use editline::terminals::StdioTerminal;
+///
+/// let mut terminal = StdioTerminal::new();
+/// let ip = read_template(&mut terminal, "IP: ", "___.___.___.___", |c| c.is_ascii_digit())?;
+/// let mac = read_template(&mut terminal, "MAC: ", "__:__:__:__:__:__", |c| c.is_ascii_hexdigit())?;
+/// # Ok::<(), editline::Error>(())
+/// ```
+pub fn read_template<T: Terminal>(
+    terminal: &mut T,
+    prompt: &str,
+    mask: &str,
+    mut accept: impl FnMut(char) -> bool,
+) -> Result<String> {
+    let mut field = Template::new(mask);
+    let mut raw = RawModeGuard::new(terminal)?;
+    let terminal = &mut *raw;
+    let result = (|| {
+        terminal.write(prompt.as_bytes())?;
+        terminal.write(mask.as_bytes())?;
+        cursor_left_by(terminal, mask.len() - field.cursor)?;
+        terminal.flush()?;
+        let mut column = field.cursor;
+        loop {
+            let event = terminal.parse_key_event()?;
+            if let Some(done) = field.key(event, &mut accept) {
+                cursor_right_by(terminal, field.cells.len() - column)?;
+                write_newline(terminal)?;
+                terminal.flush()?;
+                return done;
+            }
+            // Rewrite from the first changed position
+            if let Some(changed) = field.changed.take() {
+                move_to(terminal, &mut column, changed)?;
+                terminal.write(&field.cells[changed..])?;
+                column = field.cells.len();
+            }
+            move_to(terminal, &mut column, field.cursor)?;
+            terminal.flush()?;
+        }
+    })();
+    raw.exit()?;
+    result
+}
+
+/// Moves the cursor within a template field.
+fn move_to<T: Terminal>(terminal: &mut T, column: &mut usize, target: usize) -> Result<()> {
+    if target < *column {
+        cursor_left_by(terminal, *column - target)?;
+    } else {
+        cursor_right_by(terminal, target - *column)?;
+    }
+    *column = target;
+    Ok(())
+}
+
+/// State of a [`read_template`] field.
+struct Template {
+    cells: Vec<u8>,
+    /// Offsets of the placeholders in `cells`
+    slots: Vec<usize>,
+    /// Offset of the cursor: a slot, or the end once the last slot is filled
+    cursor: usize,
+    /// Lowest offset changed since the last redraw
+    changed: Option<usize>,
+}
+
+impl Template {
+    fn new(mask: &str) -> Self {
+        assert!(mask.is_ascii(), "template masks must be ASCII");
+        let cells = mask.as_bytes().to_vec();
+        let slots: Vec<usize> = (0..cells.len())
+            .filter(|&i| cells[i] == PLACEHOLDER as u8)
+            .collect();
+        assert!(!slots.is_empty(), "template mask has no placeholder");
+        Template {
+            cursor: slots[0],
+            cells,
+            slots,
+            changed: None,
+        }
+    }
+
+    /// The slot after the cursor, or the end of the field.
+    fn next(&self) -> usize {
+        self.slots
+            .iter()
+            .copied()
+            .find(|&slot| slot > self.cursor)
+            .unwrap_or(self.cells.len())
+    }
+
+    /// The slot before the cursor, if any.
+    fn previous(&self) -> Option<usize> {
+        self.slots
+            .iter()
+            .copied()
+            .rev()
+            .find(|&slot| slot < self.cursor)
+    }
+
+    fn set(&mut self, slot: usize, c: u8) {
+        self.cells[slot] = c;
+        self.changed = Some(self.changed.map_or(slot, |changed| changed.min(slot)));
+    }
+
+    /// Edits the field for `event`, returning the outcome once it is done with.
+    fn key(
+        &mut self,
+        event: KeyEvent,
+        accept: &mut impl FnMut(char) -> bool,
+    ) -> Option<Result<String>> {
+        let at_slot = self.cursor < self.cells.len();
+        match event {
+            KeyEvent::Normal(c) if c.is_ascii() && at_slot && accept(c) => {
+                self.set(self.cursor, c as u8);
+                self.cursor = self.next();
+            }
+            KeyEvent::Normal(c)
+                if c.is_ascii() && c != PLACEHOLDER && self.cells.contains(&(c as u8)) =>
+            {
+                // Jump past the next such separator, unless it was just passed
+                let passed = self.cursor > 0 && self.cells[self.cursor - 1] == c as u8;
+                let next = (self.cursor..self.cells.len()).find(|&i| self.cells[i] == c as u8);
+                if let Some(separator) = next.filter(|_| !passed) {
+                    self.cursor = separator;
+                    self.cursor = self.next();
+                }
+            }
+            KeyEvent::Backspace => {
+                if let Some(slot) = self.previous() {
+                    self.set(slot, PLACEHOLDER as u8);
+                    self.cursor = slot;
+                }
+            }
+            KeyEvent::Delete if at_slot => self.set(self.cursor, PLACEHOLDER as u8),
+            KeyEvent::Left => self.cursor = self.previous().unwrap_or(self.cursor),
+            KeyEvent::Right if at_slot => self.cursor = self.next(),
+            KeyEvent::Home => self.cursor = self.slots[0],
+            KeyEvent::End => self.cursor = self.cells.len(),
+            KeyEvent::Enter | KeyEvent::LineFeed => {
+                let text = self
+                    .cells
+                    .iter()
+                    .filter(|&&c| c != PLACEHOLDER as u8)
+                    .map(|&c| c as char)
+                    .collect();
+                return Some(Ok(text));
+            }
+            KeyEvent::Ctrl('c') => return Some(Err(Error::Interrupted)),
+            KeyEvent::Ctrl('d') => return Some(Err(Error::Eof)),
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Reads lines with `editor` until `parse` accepts one, showing `error` after
+/// each it rejects.
+fn read_field<T: Terminal, V>(
+    terminal: &mut T,
+    editor: &mut LineEditor,
+    prompt: &str,
+    error: &str,
+    mut parse: impl FnMut(&str) -> Option<V>,
+) -> Result<V> {
+    loop {
+        let line = editor.read_line_with_prompt(terminal, prompt)?;
+        if let Some(value) = parse(&line) {
+            return Ok(value);
+        }
+        terminal.write(b"  ")?;
+        terminal.write(error.as_bytes())?;
+        write_newline(terminal)?;
+    }
+}
+
+/// Writes the question and the answer choices.
+fn ask<T: Terminal>(terminal: &mut T, question: &str) -> Result<()> {
+    terminal.write(question.as_bytes())?;
+    terminal.write(b" [y/N] ")?;
+    terminal.flush()
+}
+
+/// Interprets a key pressed in answer to [`confirm`].
+fn confirm_key(event: KeyEvent) -> Option<Result<bool>> {
+    match event {
+        KeyEvent::Normal('y' | 'Y') => Some(Ok(true)),
+        KeyEvent::Normal('n' | 'N') | KeyEvent::Enter | KeyEvent::LineFeed => Some(Ok(false)),
+        KeyEvent::Ctrl('c') => Some(Err(Error::Interrupted)),
+        KeyEvent::Ctrl('d') => Some(Err(Error::Eof)),
+        _ => None,
+    }
+}
+
+/// Echoes the answer and ends the line.
+fn answered<T: Terminal>(terminal: &mut T, yes: bool) -> Result<bool> {
+    terminal.write(if yes { b"yes" } else { b"no" })?;
+    write_newline(terminal)?;
+    terminal.flush()?;
+    Ok(yes)
+}
+
+/// State of a [`select`] list.
+struct Menu<'a> {
+    items: &'a [&'a str],
+    selected: usize,
+}
+
+impl<'a> Menu<'a> {
+    fn new(items: &'a [&'a str]) -> Self {
+        assert!(!items.is_empty(), "nothing to select from");
+        Menu { items, selected: 0 }
+    }
+
+    /// Moves the selection for `event`, returning the outcome once the list
+    /// is done with.
+    fn key(&mut self, event: KeyEvent) -> Option<Result<usize>> {
+        let last = self.items.len() - 1;
+        match event {
+            KeyEvent::Up => self.selected = self.selected.saturating_sub(1),
+            KeyEvent::Down => self.selected = (self.selected + 1).min(last),
+            KeyEvent::Home => self.selected = 0,
+            KeyEvent::End => self.selected = last,
+            KeyEvent::Normal(c @ '1'..='9') => {
+                let index = c as usize - '1' as usize;
+                if index <= last {
+                    self.selected = index;
+                }
+            }
+            KeyEvent::Enter | KeyEvent::LineFeed => return Some(Ok(self.selected)),
+            KeyEvent::Ctrl('c') => return Some(Err(Error::Interrupted)),
+            KeyEvent::Ctrl('d') => return Some(Err(Error::Eof)),
+            _ => {}
+        }
+        None
+    }
+
+    /// Writes the list, leaving the cursor on the line below it.
+    fn draw<T: Terminal>(&self, terminal: &mut T) -> Result<()> {
+        for (index, item) in self.items.iter().enumerate() {
+            terminal.write(if index == self.selected { b"> " } else { b"  " })?;
+            write_visible(terminal, item.as_bytes())?;
+            terminal.clear_eol()?;
+            write_newline(terminal)?;
+        }
+        terminal.flush()
+    }
+
+    /// Draws the list again over the previous drawing.
+    fn redraw<T: Terminal>(&self, terminal: &mut T) -> Result<()> {
+        for _ in self.items {
+            terminal.cursor_up()?;
+        }
+        terminal.write(b"\r")?;
+        self.draw(terminal)
+    }
+}
+
+/// Asks a yes/no question on an async terminal, like [`confirm`].
+#[cfg(feature = "async")]
+pub async fn confirm_async<T: crate::asynch::AsyncTerminal + ?Sized>(
+    terminal: &mut T,
+    question: &str,
+) -> Result<bool> {
+    use crate::asynch::{flush, read_key, Bridge};
+
+    terminal.enter_raw_mode()?;
+    let result = async {
+        ask(
+            &mut Bridge {
+                terminal: &mut *terminal,
+                echo: true,
+            },
+            question,
+        )?;
+        flush(terminal).await?;
+        let mut decoder = crate::ansi::Decoder::new();
+        let answer = loop {
+            if let Some(answer) = confirm_key(read_key(terminal, &mut decoder).await?) {
+                break answer?;
+            }
+        };
+        answered(
+            &mut Bridge {
+                terminal: &mut *terminal,
+                echo: true,
+            },
+            answer,
+        )?;
+        flush(terminal).await?;
+        Ok(answer)
+    }
+    .await;
+    let exited = terminal.exit_raw_mode();
+    result.and_then(|answer| exited.map(|_| answer))
+}
+
+/// Lets the user pick one of `items` on an async terminal, like [`select`].
+///
+/// # Panics
+///
+/// Panics if `items` is empty.
+#[cfg(feature = "async")]
+pub async fn select_async<T: crate::asynch::AsyncTerminal + ?Sized>(
+    terminal: &mut T,
+    items: &[&str],
+) -> Result<usize> {
+    use crate::asynch::{flush, read_key, Bridge};
+
+    let mut menu = Menu::new(items);
+    terminal.enter_raw_mode()?;
+    let result = async {
+        menu.draw(&mut Bridge {
+            terminal: &mut *terminal,
+            echo: true,
+        })?;
+        flush(terminal).await?;
+        let mut decoder = crate::ansi::Decoder::new();
+        loop {
+            if let Some(choice) = menu.key(read_key(terminal, &mut decoder).await?) {
+                return choice;
+            }
+            menu.redraw(&mut Bridge {
+                terminal: &mut *terminal,
+                echo: true,
+            })?;
+            flush(terminal).await?;
+        }
+    }
+    .await;
+    let exited = terminal.exit_raw_mode();
+    result.and_then(|index| exited.map(|_| index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ScriptedTerminal;
+
+    #[test]
+    fn test_confirm() {
+        let mut terminal = ScriptedTerminal::new(b"xY");
+        assert!(confirm(&mut terminal, "Erase flash?").unwrap());
+        assert!(terminal.output().starts_with("Erase flash? [y/N] yes"));
+
+        assert!(!confirm(&mut ScriptedTerminal::new(b"\r"), "Erase flash?").unwrap());
+        assert!(matches!(
+            confirm(&mut ScriptedTerminal::new(b"\x03"), "Erase flash?"),
+            Err(Error::Interrupted)
+        ));
+    }
+
+    #[test]
+    fn test_select() {
+        let items = ["868 MHz", "915 MHz", "2.4 GHz"];
+        let mut terminal = ScriptedTerminal::new(b"\x1b[B\x1b[B\x1b[B\x1b[A\r");
+        assert_eq!(select(&mut terminal, &items).unwrap(), 1);
+        let newline = "\r\n";
+        let last = [
+            "\r  868 MHz\x1b[K",
+            "> 915 MHz\x1b[K",
+            "  2.4 GHz\x1b[K",
+            "",
+        ]
+        .join(newline);
+        assert!(
+            terminal.output().ends_with(&last),
+            "{:?}",
+            terminal.output()
+        );
+
+        assert_eq!(
+            select(&mut ScriptedTerminal::new(b"3\r"), &items).unwrap(),
+            2
+        );
+        assert_eq!(
+            select(&mut ScriptedTerminal::new(b"9\x1b[F\x1b[H\r"), &items).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_read_template() {
+        let digits = |c: char| c.is_ascii_digit();
+        let mut terminal = ScriptedTerminal::new(b"192.168x.1.1\r");
+        assert_eq!(
+            read_template(&mut terminal, "IP: ", "___.___.___.___", digits).unwrap(),
+            "192.168.1.1"
+        );
+        assert!(terminal.output().starts_with("IP: ___.___.___.___"));
+
+        // Backspace clears the digit before the separator
+        let mut terminal = ScriptedTerminal::new(b"12\x7f\x7f\x7f3\x1b[F\x7f4\r");
+        assert_eq!(
+            read_template(&mut terminal, "", "__:__", |c: char| c.is_ascii_hexdigit()).unwrap(),
+            "3:4"
+        );
+    }
+
+    #[test]
+    fn test_read_u32() {
+        let mut terminal = ScriptedTerminal::new(b"1x4\r\r7\r");
+        assert_eq!(read_u32(&mut terminal, "Channel: ", 1..=13).unwrap(), 7);
+        let output = terminal.output();
+        assert_eq!(output.matches("enter a number from 1 to 13").count(), 2);
+        assert_eq!(output.matches("Channel: ").count(), 3);
+        assert!(!output.contains('x'));
+
+        let mut terminal = ScriptedTerminal::new(b"99999999999\r");
+        assert!(matches!(
+            read_u32(&mut terminal, "> ", 0..=u32::MAX),
+            Err(Error::Eof)
+        ));
+    }
+
+    #[test]
+    fn test_read_hex() {
+        let mut terminal = ScriptedTerminal::new(b"\rg3fa\r");
+        assert_eq!(read_hex(&mut terminal, "0x", 2).unwrap(), 0x3f);
+        let output = terminal.output();
+        assert!(output.contains("enter 1 to 2 hex digits"));
+        // The third digit doesn't fit
+        assert!(
+            output.ends_with("F\x1b[K\x07\n") || output.ends_with("F\x1b[K\x07\r\n"),
+            "{:?}",
+            output
+        );
+    }
+}
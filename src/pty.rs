@@ -0,0 +1,561 @@
+//! Pseudo-terminal test harness (`pty-harness` feature, Unix only).
+//!
+//! [`Pty`] opens a pseudo-terminal with `openpty` and hands its slave side to
+//! the editor as a [`PtyTerminal`], usually on a second thread. The test types
+//! keys into the master side and reads back what the editor drew, which a
+//! small emulator turns into a [`Screen`] of rows and a cursor. Assertions on
+//! the screen catch rendering regressions such as cursor drift on wrapped
+//! lines or leftovers of a missing clear-to-end-of-line, which byte-level
+//! tests against a mock terminal let through.
+//!
+//! The emulator understands the sequences the editor sends: cursor movement,
+//! line and screen clearing, save and restore cursor, and cursor position
+//! queries, which it answers. Colors and other attributes are ignored.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::thread;
+//! use editline::pty::Pty;
+//! use editline::LineEditor;
+//!
+//! let mut pty = Pty::open(20, 5)?;
+//! let mut terminal = pty.terminal()?;
+//! let editor = thread::spawn(move || LineEditor::new(256, 10).read_line_with_prompt(&mut terminal, "> "));
+//!
+//! pty.settle()?;
+//! pty.send(b"hello\x1b[D\x1b[DXY")?;
+//! assert_eq!(pty.screen().row(0), "> helXYlo");
+//! assert_eq!(pty.screen().cursor(), (7, 0));
+//!
+//! pty.send(b"\r")?;
+//! assert_eq!(editor.join().unwrap().unwrap(), "helXYlo");
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::string::String;
+use std::vec::Vec;
+
+use crate::width::char_width;
+use crate::{Error, Result, Terminal};
+
+/// How long the editor may stay silent before its output counts as complete,
+/// in milliseconds.
+const SETTLE_MS: i32 = 100;
+
+/// A pseudo-terminal with an emulated screen on its master side.
+#[derive(Debug)]
+pub struct Pty {
+    master: File,
+    slave: File,
+    screen: Screen,
+}
+
+impl Pty {
+    /// Opens a pseudo-terminal of `columns` by `rows` cells.
+    pub fn open(columns: u16, rows: u16) -> io::Result<Self> {
+        let mut master = 0;
+        let mut slave = 0;
+        let size = libc::winsize {
+            ws_row: rows,
+            ws_col: columns,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        let opened = unsafe {
+            libc::openpty(
+                &mut master,
+                &mut slave,
+                std::ptr::null_mut(),
+                std::ptr::null(),
+                &size,
+            )
+        };
+        if opened != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let (master, slave) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+        Ok(Pty {
+            master,
+            slave,
+            screen: Screen::new(usize::from(columns), usize::from(rows)),
+        })
+    }
+
+    /// Returns a terminal on the slave side, for the editor under test.
+    pub fn terminal(&self) -> io::Result<PtyTerminal> {
+        Ok(PtyTerminal {
+            file: self.slave.try_clone()?,
+            original: None,
+            typed_ahead: Vec::new(),
+        })
+    }
+
+    /// Types `keys` on the terminal and waits for the editor to redraw.
+    pub fn send(&mut self, keys: &[u8]) -> io::Result<()> {
+        self.master.write_all(keys)?;
+        self.settle()
+    }
+
+    /// Processes the editor's output until it has been quiet for a moment,
+    /// answering cursor position queries on the way.
+    pub fn settle(&mut self) -> io::Result<()> {
+        let mut buf = [0u8; 1024];
+        loop {
+            let mut poll = libc::pollfd {
+                fd: self.master.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            match unsafe { libc::poll(&mut poll, 1, SETTLE_MS) } {
+                0 => return Ok(()),
+                n if n < 0 => return Err(io::Error::last_os_error()),
+                _ => {}
+            }
+            let count = match self.master.read(&mut buf) {
+                Ok(count) => count,
+                // The slave side was closed
+                Err(e) if e.raw_os_error() == Some(libc::EIO) => return Ok(()),
+                Err(e) => return Err(e),
+            };
+            if count == 0 {
+                return Ok(());
+            }
+            let replies = self.screen.feed(&buf[..count]);
+            self.master.write_all(&replies)?;
+        }
+    }
+
+    /// Returns the screen as drawn so far.
+    pub fn screen(&self) -> &Screen {
+        &self.screen
+    }
+}
+
+/// The slave side of a [`Pty`], in the role of the user's terminal.
+///
+/// Behaves like [`StdioTerminal`](crate::terminals::StdioTerminal): raw mode
+/// turns off echo, line buffering and signals, and output processing stays on,
+/// so `\n` reaches the screen as `\r\n`.
+#[derive(Debug)]
+pub struct PtyTerminal {
+    file: File,
+    original: Option<Termios>,
+    /// Bytes that arrived ahead of a cursor position reply, in reverse order
+    typed_ahead: Vec<u8>,
+}
+
+/// `libc::termios`, which only implements `Debug` with libc's extra traits.
+#[derive(Clone, Copy)]
+struct Termios(libc::termios);
+
+impl core::fmt::Debug for Termios {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("Termios")
+    }
+}
+
+impl PtyTerminal {
+    fn attributes(&self) -> Result<libc::termios> {
+        let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(self.file.as_raw_fd(), &mut termios) } != 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(termios)
+    }
+
+    fn set_attributes(&self, termios: &libc::termios) -> Result<()> {
+        if unsafe { libc::tcsetattr(self.file.as_raw_fd(), libc::TCSANOW, termios) } != 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+}
+
+impl Terminal for PtyTerminal {
+    fn read_byte(&mut self) -> Result<u8> {
+        if let Some(byte) = self.typed_ahead.pop() {
+            return Ok(byte);
+        }
+        let mut buf = [0u8; 1];
+        match self.file.read(&mut buf)? {
+            0 => Err(Error::Eof),
+            _ => Ok(buf[0]),
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.file.write_all(data).map_err(Error::from)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.file.flush().map_err(Error::from)
+    }
+
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        let mut termios = self.attributes()?;
+        self.original = Some(Termios(termios));
+        termios.c_lflag &= !(libc::ECHO | libc::ICANON | libc::ISIG);
+        termios.c_iflag &= !libc::IXON;
+        termios.c_cc[libc::VMIN] = 1;
+        termios.c_cc[libc::VTIME] = 0;
+        self.set_attributes(&termios)
+    }
+
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        match self.original.take() {
+            Some(Termios(original)) => self.set_attributes(&original),
+            None => Ok(()),
+        }
+    }
+
+    fn cursor_left(&mut self) -> Result<()> {
+        self.write(b"\x1b[D")
+    }
+
+    fn cursor_right(&mut self) -> Result<()> {
+        self.write(b"\x1b[C")
+    }
+
+    fn clear_eol(&mut self) -> Result<()> {
+        self.write(b"\x1b[K")
+    }
+
+    fn size(&mut self) -> Option<(u16, u16)> {
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        if unsafe { libc::ioctl(self.file.as_raw_fd(), libc::TIOCGWINSZ, &mut size) } != 0
+            || size.ws_col == 0
+        {
+            return None;
+        }
+        Some((size.ws_col, size.ws_row))
+    }
+
+    fn get_cursor_position(&mut self) -> Option<(u16, u16)> {
+        // Only in raw mode, where the reply isn't echoed
+        self.original.as_ref()?;
+        // Give up after 200ms, as StdioTerminal does
+        let saved = self.attributes().ok()?;
+        let mut termios = saved;
+        termios.c_cc[libc::VMIN] = 0;
+        termios.c_cc[libc::VTIME] = 2;
+        self.set_attributes(&termios).ok()?;
+        let mut typed_ahead = Vec::new();
+        let position = crate::ansi::query_cursor_position(self, |byte| typed_ahead.push(byte));
+        self.typed_ahead.extend(typed_ahead.iter().rev());
+        let _ = self.set_attributes(&saved);
+        position.ok()
+    }
+
+    fn capabilities(&self) -> crate::Caps {
+        crate::Caps::ANSI.with_size(true).with_crlf(false)
+    }
+}
+
+/// Placeholder in the cell right of a double-width character.
+const WIDE_TAIL: char = '\0';
+
+/// The emulated screen of a [`Pty`].
+#[derive(Debug, Clone)]
+pub struct Screen {
+    columns: usize,
+    cells: Vec<Vec<char>>,
+    /// Cursor column, equal to `columns` after writing the last column: the
+    /// next character goes on the next row
+    column: usize,
+    row: usize,
+    saved: (usize, usize),
+    /// Escape sequence or UTF-8 character being received
+    pending: Vec<u8>,
+    bells: usize,
+}
+
+impl Screen {
+    fn new(columns: usize, rows: usize) -> Self {
+        Screen {
+            columns,
+            cells: vec![vec![' '; columns]; rows],
+            column: 0,
+            row: 0,
+            saved: (0, 0),
+            pending: Vec::new(),
+            bells: 0,
+        }
+    }
+
+    /// Returns the text of row `index`, without trailing blanks.
+    pub fn row(&self, index: usize) -> String {
+        let row = self.cells[index].iter().filter(|&&c| c != WIDE_TAIL);
+        row.collect::<String>().trim_end().into()
+    }
+
+    /// Returns all rows, without trailing blanks.
+    pub fn rows(&self) -> Vec<String> {
+        (0..self.cells.len()).map(|index| self.row(index)).collect()
+    }
+
+    /// Returns the cursor position as `(column, row)`, counting from 0.
+    ///
+    /// After a character is written in the last column, the column equals the
+    /// screen width until the next character wraps to the next row. Relative
+    /// movement and erasing count from there, as in tmux and VTE; xterm would
+    /// count from the last column instead.
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.column, self.row)
+    }
+
+    /// Returns how often the bell rang.
+    pub fn bells(&self) -> usize {
+        self.bells
+    }
+
+    /// Interprets output from the editor, returning replies to its queries.
+    fn feed(&mut self, bytes: &[u8]) -> Vec<u8> {
+        let mut replies = Vec::new();
+        for &byte in bytes {
+            if self.pending.first() == Some(&0x1b) {
+                self.pending.push(byte);
+                self.escape(&mut replies);
+                continue;
+            }
+            if !self.pending.is_empty() || byte >= 0x80 {
+                self.pending.push(byte);
+                if let Ok(text) = std::str::from_utf8(&self.pending) {
+                    let c = text.chars().next().unwrap_or(' ');
+                    self.pending.clear();
+                    self.put(c);
+                } else if self.pending.len() >= 4 {
+                    self.pending.clear();
+                }
+                continue;
+            }
+            match byte {
+                0x1b => self.pending.push(byte),
+                b'\r' => self.move_to(0, self.row),
+                b'\n' => self.line_feed(),
+                0x08 => self.move_to(self.column.saturating_sub(1), self.row),
+                0x07 => self.bells += 1,
+                0x20..=0x7e => self.put(byte as char),
+                _ => {}
+            }
+        }
+        replies
+    }
+
+    /// Acts on the escape sequence in `pending` once it is complete.
+    fn escape(&mut self, replies: &mut Vec<u8>) {
+        let incomplete = match self.pending[1..] {
+            [b'['] => true,
+            [b'[', .., last] => !(0x40..=0x7e).contains(&last),
+            _ => false,
+        };
+        if incomplete {
+            return;
+        }
+        let sequence = core::mem::take(&mut self.pending);
+        match sequence[1..] {
+            [b'[', ref body @ .., last] => self.control(body, last, replies),
+            [b'7'] => self.saved = (self.column, self.row),
+            [b'8'] => self.move_to(self.saved.0, self.saved.1),
+            [b'D'] => self.line_feed(),
+            _ => {}
+        }
+    }
+
+    /// Carries out a control sequence `ESC [ body last`.
+    fn control(&mut self, body: &[u8], last: u8, replies: &mut Vec<u8>) {
+        if body.first() == Some(&b'?') {
+            // Private modes, e.g. the alternate screen
+            return;
+        }
+        let params: Vec<usize> = body
+            .split(|&b| b == b';')
+            .map(|p| {
+                std::str::from_utf8(p)
+                    .ok()
+                    .and_then(|p| p.parse().ok())
+                    .unwrap_or(0)
+            })
+            .collect();
+        let param = |index: usize, default: usize| {
+            params
+                .get(index)
+                .copied()
+                .filter(|&n| n > 0)
+                .unwrap_or(default)
+        };
+        let (column, row) = (self.column, self.row);
+        match last {
+            b'A' => self.move_to(column, row.saturating_sub(param(0, 1))),
+            b'B' => self.move_to(column, row + param(0, 1)),
+            b'C' => self.move_to(column + param(0, 1), row),
+            b'D' => self.move_to(column.saturating_sub(param(0, 1)), row),
+            b'G' => self.move_to(param(0, 1) - 1, row),
+            b'H' | b'f' => self.move_to(param(1, 1) - 1, param(0, 1) - 1),
+            b'K' => {
+                let range = match params[0] {
+                    1 => 0..column + 1,
+                    2 => 0..self.columns,
+                    _ => column..self.columns,
+                };
+                self.clear(row, range);
+            }
+            b'@' | b'P' if column < self.columns => {
+                let cells = &mut self.cells[row];
+                for _ in 0..param(0, 1).min(self.columns - column) {
+                    if last == b'@' {
+                        cells.pop();
+                        cells.insert(column, ' ');
+                    } else {
+                        cells.remove(column);
+                        cells.push(' ');
+                    }
+                }
+            }
+            b'J' => {
+                if params[0] == 2 {
+                    (0..self.cells.len()).for_each(|r| self.clear(r, 0..self.columns));
+                } else {
+                    self.clear(row, column..self.columns);
+                    (row + 1..self.cells.len()).for_each(|r| self.clear(r, 0..self.columns));
+                }
+            }
+            b'n' if params[0] == 6 => {
+                replies.extend_from_slice(
+                    format!("\x1b[{};{}R", row + 1, column.min(self.columns - 1) + 1).as_bytes(),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    fn move_to(&mut self, column: usize, row: usize) {
+        self.column = column.min(self.columns - 1);
+        self.row = row.min(self.cells.len() - 1);
+    }
+
+    fn clear(&mut self, row: usize, columns: core::ops::Range<usize>) {
+        let end = columns.end.min(self.columns);
+        self.cells[row][columns.start.min(end)..end]
+            .iter_mut()
+            .for_each(|cell| *cell = ' ');
+    }
+
+    /// Moves to the next row, scrolling at the bottom.
+    fn line_feed(&mut self) {
+        if self.row + 1 < self.cells.len() {
+            self.row += 1;
+        } else {
+            self.cells.remove(0);
+            self.cells.push(vec![' '; self.columns]);
+        }
+    }
+
+    fn put(&mut self, c: char) {
+        let width = char_width(c);
+        if width == 0 || c.is_control() {
+            return;
+        }
+        if self.column + width > self.columns {
+            self.column = 0;
+            self.line_feed();
+        }
+        self.cells[self.row][self.column] = c;
+        if width == 2 {
+            self.cells[self.row][self.column + 1] = WIDE_TAIL;
+        }
+        self.column += width;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LineEditor;
+    use std::thread;
+
+    /// Starts an editor on a new pseudo-terminal and waits for its prompt.
+    fn start(columns: u16) -> (Pty, thread::JoinHandle<Result<String>>) {
+        let mut pty = Pty::open(columns, 6).unwrap();
+        let mut terminal = pty.terminal().unwrap();
+        let editor = thread::spawn(move || {
+            LineEditor::new(256, 10).read_line_with_prompt(&mut terminal, "> ")
+        });
+        pty.settle().unwrap();
+        (pty, editor)
+    }
+
+    #[test]
+    fn test_screen_emulation() {
+        let mut screen = Screen::new(4, 2);
+        let replies = screen.feed(b"abcd\x1b[6nef\x1b[2D\x1b[K\x1b[1;1H\x1b[1m\xe6\xbc\xa2\x1b[0m");
+        assert_eq!(replies, b"\x1b[1;4R");
+        assert_eq!(screen.rows(), ["漢cd", ""]);
+        screen.feed(b"\x1b[@x");
+        assert_eq!(screen.rows(), ["漢xc", ""]);
+        screen.feed(b"\x1b[D\x1b[P");
+        assert_eq!(screen.rows(), ["漢c", ""]);
+        assert_eq!(screen.cursor(), (2, 0));
+    }
+
+    #[test]
+    fn test_editing_in_place() {
+        let (mut pty, editor) = start(20);
+        pty.send(b"set led 1").unwrap();
+        pty.send(b"\x1b[D\x1b[D\x7f").unwrap();
+        assert_eq!(pty.screen().rows()[..2], ["> set le 1", ""]);
+        assert_eq!(pty.screen().cursor(), (8, 0));
+
+        // The shortened line must not leave characters behind
+        pty.send(b"\x1b\x7f").unwrap();
+        assert_eq!(pty.screen().row(0), "> set  1");
+        assert_eq!(pty.screen().cursor(), (6, 0));
+
+        pty.send(b"\r").unwrap();
+        assert_eq!(editor.join().unwrap().unwrap(), "set  1");
+        assert_eq!(pty.screen().cursor(), (0, 1));
+    }
+
+    #[test]
+    fn test_typed_ahead_of_cursor_query() {
+        let mut pty = Pty::open(20, 6).unwrap();
+        let mut terminal = pty.terminal().unwrap();
+        let editor = thread::spawn(move || {
+            let mut editor = LineEditor::new(256, 10);
+            (0..3)
+                .map(|_| editor.read_line_with_prompt(&mut terminal, "> "))
+                .collect::<Result<Vec<_>>>()
+        });
+        pty.settle().unwrap();
+
+        // Each new line asks for the cursor position with the rest still unread
+        pty.send(b"one\rtwo\rthree\r").unwrap();
+        assert_eq!(editor.join().unwrap().unwrap(), ["one", "two", "three"]);
+        assert_eq!(pty.screen().rows()[..4], ["> one", "> two", "> three", ""]);
+    }
+
+    #[test]
+    fn test_wrapping_at_margin() {
+        let (mut pty, editor) = start(8);
+        pty.send(b"abcdef").unwrap();
+        assert_eq!(pty.screen().rows()[..2], ["> abcdef", ""]);
+        // Reaching the margin repaints the line and moves on to the next row
+        assert_eq!(pty.screen().cursor(), (0, 1));
+
+        pty.send(b"\x7f").unwrap();
+        assert_eq!(pty.screen().row(0), "> abcde");
+        assert_eq!(pty.screen().cursor(), (7, 0));
+
+        pty.send(b"fgh").unwrap();
+        assert_eq!(pty.screen().rows()[..2], ["> abcdef", "gh"]);
+        pty.send(b"\x7f\x7f").unwrap();
+        assert_eq!(pty.screen().rows()[..2], ["> abcdef", ""]);
+        assert_eq!(pty.screen().cursor(), (0, 1));
+
+        pty.send(b"\r").unwrap();
+        assert_eq!(editor.join().unwrap().unwrap(), "abcdef");
+    }
+}
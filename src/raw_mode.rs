@@ -0,0 +1,140 @@
+//! Scoped raw mode.
+
+use core::ops::{Deref, DerefMut};
+
+use crate::{Result, Terminal};
+
+/// Keeps a terminal in raw mode for as long as the guard lives.
+///
+/// Created with [`RawModeGuard::new`], which enters raw mode; the mode is left
+/// again when the guard is dropped, including while unwinding from a panic, so
+/// the user's shell is never left without echo. [`exit`](Self::exit) leaves raw
+/// mode explicitly and reports any error, which dropping can't.
+///
+/// The guard dereferences to the terminal, so it can be used in its place. The
+/// line editor uses the same guard around every `read_line`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use editline::{terminals::StdioTerminal, RawModeGuard, Terminal};
+///
+/// let mut terminal = StdioTerminal::new();
+///
+/// // Wait for a single key press, e.g. in a "press any key" pager
+/// let mut raw = RawModeGuard::new(&mut terminal)?;
+/// let key = raw.parse_key_event()?;
+/// raw.exit()?;
+/// # Ok::<(), editline::Error>(())
+/// ```
+pub struct RawModeGuard<'a, T: Terminal + ?Sized> {
+    terminal: &'a mut T,
+    active: bool,
+}
+
+impl<'a, T: Terminal + ?Sized> RawModeGuard<'a, T> {
+    /// Enters raw mode on `terminal`.
+    pub fn new(terminal: &'a mut T) -> Result<Self> {
+        terminal.enter_raw_mode()?;
+        Ok(RawModeGuard {
+            terminal,
+            active: true,
+        })
+    }
+
+    /// Leaves raw mode, returning any error from the terminal.
+    pub fn exit(mut self) -> Result<()> {
+        self.active = false;
+        self.terminal.exit_raw_mode()
+    }
+}
+
+impl<T: Terminal + ?Sized> Deref for RawModeGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.terminal
+    }
+}
+
+impl<T: Terminal + ?Sized> DerefMut for RawModeGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.terminal
+    }
+}
+
+impl<T: Terminal + ?Sized> Drop for RawModeGuard<'_, T> {
+    fn drop(&mut self) {
+        if self.active {
+            let _ = self.terminal.exit_raw_mode();
+        }
+    }
+}
+
+impl<T: Terminal + ?Sized> core::fmt::Debug for RawModeGuard<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("RawModeGuard")
+            .field("active", &self.active)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Mode {
+        raw: bool,
+    }
+
+    impl Terminal for Mode {
+        fn read_byte(&mut self) -> Result<u8> {
+            Ok(0)
+        }
+        fn write(&mut self, _: &[u8]) -> Result<()> {
+            Ok(())
+        }
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn enter_raw_mode(&mut self) -> Result<()> {
+            self.raw = true;
+            Ok(())
+        }
+        fn exit_raw_mode(&mut self) -> Result<()> {
+            self.raw = false;
+            Ok(())
+        }
+        fn cursor_left(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn cursor_right(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn clear_eol(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_guard_restores_mode() {
+        let mut term = Mode::default();
+        let guard = RawModeGuard::new(&mut term).unwrap();
+        assert!(guard.raw);
+        guard.exit().unwrap();
+        assert!(!term.raw);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_guard_restores_mode_on_panic() {
+        let mut term = Mode::default();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _guard = RawModeGuard::new(&mut term).unwrap();
+            panic!("while in raw mode");
+        }));
+        assert!(result.is_err());
+        assert!(!term.raw);
+    }
+}
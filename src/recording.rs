@@ -0,0 +1,467 @@
+//! Recording terminal sessions and playing them back.
+//!
+//! Wrap a terminal in a [`RecordingTerminal`] to log every byte the editor reads
+//! and writes, with the time it happened. The resulting [`Recording`] converts
+//! to a plain text format that can be attached to a bug report, and a
+//! [`ReplayTerminal`] feeds it back to a [`LineEditor`](crate::LineEditor),
+//! which reproduces problems such as an emulator sending an unusual escape
+//! sequence without needing that emulator.
+//!
+//! The text format has one event per line: the time in microseconds since
+//! recording started, `<` for input or `>` for output, and the bytes, with
+//! anything but printable ASCII written as `\xNN`:
+//!
+//! ```text
+//! 0 > >
+//! 1843021 < \x1b[1;5D
+//! 1843107 > \x1b[D
+//! ```
+//!
+//! Only [`Terminal::write`] output is recorded, so the wrapper sends cursor
+//! movement and screen clearing as ANSI escape sequences itself rather than
+//! leaving them to the wrapped terminal.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use std::vec::Vec;
+
+use crate::{Caps, Error, Result, Terminal};
+
+/// Input bytes arriving within this time of each other are kept in one event.
+const BURST: Duration = Duration::from_millis(1);
+
+/// Which way an [`Event`]'s bytes went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Read from the terminal.
+    Input,
+    /// Written to the terminal.
+    Output,
+}
+
+/// Bytes read or written at one time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    /// Time since recording started.
+    pub at: Duration,
+    /// Whether the bytes were read or written.
+    pub direction: Direction,
+    /// The bytes.
+    pub bytes: Vec<u8>,
+}
+
+/// A recorded session: input and output events in order.
+///
+/// `Display` and `FromStr` convert it to and from the text format described
+/// in the [module documentation](self).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Recording {
+    /// The events, oldest first.
+    pub events: Vec<Event>,
+}
+
+impl Recording {
+    /// All bytes read during the session.
+    pub fn input(&self) -> Vec<u8> {
+        self.bytes(Direction::Input)
+    }
+
+    /// All bytes written during the session.
+    pub fn output(&self) -> Vec<u8> {
+        self.bytes(Direction::Output)
+    }
+
+    fn bytes(&self, direction: Direction) -> Vec<u8> {
+        let events = self
+            .events
+            .iter()
+            .filter(|event| event.direction == direction);
+        events
+            .flat_map(|event| event.bytes.iter().copied())
+            .collect()
+    }
+
+    fn push(&mut self, at: Duration, direction: Direction, bytes: &[u8]) {
+        // Writes are merged while nothing is read in between, reads while they
+        // arrive in one burst
+        if let Some(last) = self.events.last_mut() {
+            if last.direction == direction
+                && (direction == Direction::Output || at - last.at < BURST)
+            {
+                last.bytes.extend_from_slice(bytes);
+                return;
+            }
+        }
+        self.events.push(Event {
+            at,
+            direction,
+            bytes: bytes.to_vec(),
+        });
+    }
+}
+
+impl fmt::Display for Recording {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for event in &self.events {
+            let arrow = match event.direction {
+                Direction::Input => '<',
+                Direction::Output => '>',
+            };
+            write!(f, "{} {} ", event.at.as_micros(), arrow)?;
+            for &byte in &event.bytes {
+                match byte {
+                    b'\\' => f.write_str("\\\\")?,
+                    b' '..=b'~' => write!(f, "{}", byte as char)?,
+                    _ => write!(f, "\\x{:02x}", byte)?,
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Error parsing a [`Recording`] from text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseRecordingError {
+    line: usize,
+}
+
+impl ParseRecordingError {
+    /// The line that couldn't be parsed, counting from 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl fmt::Display for ParseRecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "malformed event on line {} of recording", self.line)
+    }
+}
+
+impl std::error::Error for ParseRecordingError {}
+
+impl FromStr for Recording {
+    type Err = ParseRecordingError;
+
+    fn from_str(text: &str) -> core::result::Result<Self, ParseRecordingError> {
+        let invalid = |number: usize| ParseRecordingError { line: number + 1 };
+        let mut events = Vec::new();
+        for (number, line) in text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty())
+        {
+            let mut parts = line.splitn(3, ' ');
+            let at = parts
+                .next()
+                .and_then(|micros| micros.parse::<u64>().ok())
+                .ok_or_else(|| invalid(number))?;
+            let direction = match parts.next() {
+                Some("<") => Direction::Input,
+                Some(">") => Direction::Output,
+                _ => return Err(invalid(number)),
+            };
+            let bytes = unescape(parts.next().unwrap_or("")).ok_or_else(|| invalid(number))?;
+            events.push(Event {
+                at: Duration::from_micros(at),
+                direction,
+                bytes,
+            });
+        }
+        Ok(Recording { events })
+    }
+}
+
+/// Reverses the escaping done by `Recording`'s `Display`.
+fn unescape(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        if byte != b'\\' {
+            bytes.push(byte);
+            continue;
+        }
+        match rest {
+            [b'\\', tail @ ..] => {
+                bytes.push(b'\\');
+                rest = tail;
+            }
+            [b'x', hex @ ..] if hex.len() >= 2 => {
+                let hex = std::str::from_utf8(&hex[..2]).ok()?;
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+                rest = &rest[3..];
+            }
+            _ => return None,
+        }
+    }
+    Some(bytes)
+}
+
+/// Terminal wrapper logging every byte read and written.
+///
+/// Cursor position queries aren't passed on to the wrapped terminal, so the
+/// editor lays out lines the same way when the recording is replayed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use editline::recording::RecordingTerminal;
+/// use editline::terminals::StdioTerminal;
+/// use editline::LineEditor;
+///
+/// let mut terminal = RecordingTerminal::new(StdioTerminal::new());
+/// let mut editor = LineEditor::new(1024, 50);
+/// let _ = editor.read_line_with_prompt(&mut terminal, "> ");
+/// std::fs::write("session.txt", terminal.recording().to_string())?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct RecordingTerminal<T> {
+    inner: T,
+    start: Instant,
+    recording: Recording,
+}
+
+impl<T: Terminal> RecordingTerminal<T> {
+    /// Starts recording the session on `inner`.
+    pub fn new(inner: T) -> Self {
+        RecordingTerminal {
+            inner,
+            start: Instant::now(),
+            recording: Recording::default(),
+        }
+    }
+
+    /// Returns what has been recorded so far.
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    /// Returns the wrapped terminal.
+    pub fn inner(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Stops recording, returning the wrapped terminal and the recording.
+    pub fn into_inner(self) -> (T, Recording) {
+        (self.inner, self.recording)
+    }
+
+    fn record(&mut self, direction: Direction, bytes: &[u8]) {
+        // Whole microseconds, as in the text format
+        let at = Duration::from_micros(self.start.elapsed().as_micros() as u64);
+        self.recording.push(at, direction, bytes);
+    }
+}
+
+impl<T: Terminal> Terminal for RecordingTerminal<T> {
+    fn read_byte(&mut self) -> Result<u8> {
+        let byte = self.inner.read_byte()?;
+        self.record(Direction::Input, &[byte]);
+        Ok(byte)
+    }
+
+    fn try_read_byte(&mut self) -> Result<Option<u8>> {
+        let byte = self.inner.try_read_byte()?;
+        if let Some(byte) = byte {
+            self.record(Direction::Input, &[byte]);
+        }
+        Ok(byte)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.record(Direction::Output, data);
+        self.inner.write(data)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        self.inner.enter_raw_mode()
+    }
+
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        self.inner.exit_raw_mode()
+    }
+
+    fn cursor_left(&mut self) -> Result<()> {
+        self.write(b"\x1b[D")
+    }
+
+    fn cursor_right(&mut self) -> Result<()> {
+        self.write(b"\x1b[C")
+    }
+
+    fn clear_eol(&mut self) -> Result<()> {
+        self.write(b"\x1b[K")
+    }
+
+    fn size(&mut self) -> Option<(u16, u16)> {
+        self.inner.size()
+    }
+
+    fn capabilities(&self) -> Caps {
+        self.inner.capabilities()
+    }
+}
+
+/// Terminal playing back the input of a [`Recording`].
+///
+/// Input events are returned byte by byte, without waiting for their recorded
+/// time, so a replay is deterministic. [`try_read_byte`](Terminal::try_read_byte)
+/// reports no input once at the end of each event, keeping the original bursts
+/// apart. After the last byte, reads fail with [`Error::Eof`].
+///
+/// Output is collected and can be compared with the recorded output.
+///
+/// # Examples
+///
+/// ```
+/// use editline::recording::{Recording, ReplayTerminal};
+/// use editline::LineEditor;
+///
+/// let recording: Recording = "0 < ls\n210000 < \\x0d\n".parse().unwrap();
+/// let mut terminal = ReplayTerminal::new(&recording);
+/// let mut editor = LineEditor::new(1024, 50);
+/// assert_eq!(editor.read_line(&mut terminal)?, "ls");
+/// # Ok::<(), editline::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReplayTerminal {
+    /// Remaining input events, each followed by `None` marking its end
+    input: std::vec::IntoIter<Option<u8>>,
+    output: Vec<u8>,
+    size: Option<(u16, u16)>,
+}
+
+impl ReplayTerminal {
+    /// Creates a terminal replaying the input of `recording`.
+    pub fn new(recording: &Recording) -> Self {
+        let input = recording
+            .events
+            .iter()
+            .filter(|event| event.direction == Direction::Input);
+        let input: Vec<Option<u8>> = input
+            .flat_map(|event| event.bytes.iter().copied().map(Some).chain(Some(None)))
+            .collect();
+        ReplayTerminal {
+            input: input.into_iter(),
+            output: Vec::new(),
+            size: None,
+        }
+    }
+
+    /// Sets the size reported by [`Terminal::size`], which the recording
+    /// doesn't contain. The default is none, as for a serial line.
+    pub fn set_size(&mut self, size: Option<(u16, u16)>) {
+        self.size = size;
+    }
+
+    /// Returns the output written so far.
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+}
+
+impl Terminal for ReplayTerminal {
+    fn read_byte(&mut self) -> Result<u8> {
+        self.input.by_ref().flatten().next().ok_or(Error::Eof)
+    }
+
+    fn try_read_byte(&mut self) -> Result<Option<u8>> {
+        match self.input.next() {
+            Some(byte) => Ok(byte),
+            None => Err(Error::Eof),
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.output.extend_from_slice(data);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn cursor_left(&mut self) -> Result<()> {
+        self.write(b"\x1b[D")
+    }
+
+    fn cursor_right(&mut self) -> Result<()> {
+        self.write(b"\x1b[C")
+    }
+
+    fn clear_eol(&mut self) -> Result<()> {
+        self.write(b"\x1b[K")
+    }
+
+    fn size(&mut self) -> Option<(u16, u16)> {
+        self.size
+    }
+
+    fn capabilities(&self) -> Caps {
+        Caps::ANSI.with_size(self.size.is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ScriptedTerminal;
+    use crate::LineEditor;
+
+    #[test]
+    fn test_record_and_replay() {
+        let mut terminal = RecordingTerminal::new(ScriptedTerminal {
+            caps: Caps::ANSI,
+            ..ScriptedTerminal::new(b"ab\x1b[1;5Dx\\\r")
+        });
+        let mut editor = LineEditor::new(64, 10);
+        assert_eq!(
+            editor.read_line_with_prompt(&mut terminal, "> ").unwrap(),
+            "x\\ab"
+        );
+        let (_, recording) = terminal.into_inner();
+        assert_eq!(recording.input(), b"ab\x1b[1;5Dx\\\r");
+
+        let text = recording.to_string();
+        assert!(text.contains(r"< \x1b[1;5D") && text.contains(r"< \\"));
+        let parsed: Recording = text.parse().unwrap();
+        assert_eq!(parsed, recording);
+
+        let mut replay = ReplayTerminal::new(&parsed);
+        let mut editor = LineEditor::new(64, 10);
+        assert_eq!(
+            editor.read_line_with_prompt(&mut replay, "> ").unwrap(),
+            "x\\ab"
+        );
+        assert_eq!(replay.output(), &recording.output()[..]);
+        assert!(matches!(replay.read_byte(), Err(Error::Eof)));
+    }
+
+    #[test]
+    fn test_replay_keeps_bursts() {
+        let recording: Recording = "0 < a\\x1b\n5000 < [D\n".parse().unwrap();
+        let mut replay = ReplayTerminal::new(&recording);
+        let read: Vec<Option<u8>> = (0..5).map(|_| replay.try_read_byte().unwrap()).collect();
+        assert_eq!(read, [Some(b'a'), Some(0x1b), None, Some(b'['), Some(b'D')]);
+
+        assert_eq!("0 < a\n0 ? a".parse::<Recording>().unwrap_err().line(), 2);
+        assert!("0 < \\x1".parse::<Recording>().is_err());
+    }
+}
@@ -0,0 +1,75 @@
+//! Presenting the line somewhere other than the terminal.
+//!
+//! By default the editor draws on the [`Terminal`](crate::Terminal) it reads
+//! from, using cursor movement and erase sequences. Displays that aren't a
+//! character terminal, such as an OLED or LCD driven through
+//! `embedded-graphics` or a panel taking key-value updates, can't follow
+//! those. Install a [`Renderer`] with
+//! [`LineEditor::set_renderer`](crate::LineEditor::set_renderer) and the
+//! editor stops writing to the terminal altogether; after every key it hands
+//! the renderer a [`View`] of the prompt, line and cursor to present however
+//! it likes. Keys are still read from the terminal, or the
+//! [`AsyncTerminal`](crate::asynch::AsyncTerminal) with the `async` feature.
+
+use crate::{display_width, Result};
+
+/// What the editor would show, passed to a [`Renderer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct View<'a> {
+    /// The prompt, which may contain `\n` and escape sequences.
+    pub prompt: &'a str,
+    /// The line being edited, or the match during an incremental search.
+    pub line: &'a str,
+    /// Byte offset of the cursor in `line`.
+    pub cursor: usize,
+    /// Suggestion from the [`Hinter`](crate::Hinter) to show after the line.
+    pub hint: Option<&'a str>,
+    /// The query while an incremental history search is active.
+    pub search: Option<&'a str>,
+    /// The application's status text, see
+    /// [`LineEditor::set_status`](crate::LineEditor::set_status).
+    pub status: &'a str,
+}
+
+impl View<'_> {
+    /// Number of display columns before the cursor, counting wide characters
+    /// as two and control characters in caret notation.
+    pub fn cursor_column(&self) -> usize {
+        display_width(&self.line.as_bytes()[..self.cursor])
+    }
+}
+
+/// Presents the edited line on a display of its own.
+///
+/// Closures of the form `FnMut(&View) -> Result<()>` implement this trait.
+///
+/// # Examples
+///
+/// ```
+/// use editline::render::View;
+/// use editline::LineEditor;
+///
+/// let mut editor = LineEditor::new(256, 10);
+/// editor.set_renderer(|view: &View| {
+///     // e.g. lcd.clear(); lcd.print(view.prompt); lcd.print(view.line); ...
+///     let _ = (view.line, view.cursor_column());
+///     Ok(())
+/// });
+/// ```
+pub trait Renderer {
+    /// Shows `view`. Called once the prompt is up and after every key.
+    fn render(&mut self, view: &View<'_>) -> Result<()>;
+
+    /// Called with the submitted line, e.g. to scroll it up on the display.
+    fn finish(&mut self, line: &str) -> Result<()> {
+        let _ = line;
+        Ok(())
+    }
+}
+
+impl<F: FnMut(&View<'_>) -> Result<()>> Renderer for F {
+    fn render(&mut self, view: &View<'_>) -> Result<()> {
+        self(view)
+    }
+}
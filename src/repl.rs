@@ -0,0 +1,424 @@
+//! Ready-made command loop (`repl` feature).
+//!
+//! [`Repl`] owns a [`LineEditor`] and a terminal and runs the loop every
+//! interactive console ends up writing: show the prompt, read a line, split it
+//! into words, look up the command and run it. `help`, `history`, `clear` and
+//! `exit` are built in; Ctrl+C cancels the current line and Ctrl+D leaves the loop.
+//!
+//! Command output goes through [`Output`], which implements [`core::fmt::Write`]
+//! and turns `\n` into the terminal's line ending, so the same handlers work on
+//! a desktop terminal and a raw serial link.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+
+use crate::{write_newline, Error, LineEditor, Result, Terminal};
+
+/// Commands every [`Repl`] understands, with their help text.
+const BUILTINS: &[(&str, &str)] = &[
+    ("help", "List the available commands"),
+    ("history", "Show previous lines"),
+    ("clear", "Clear the screen"),
+    ("exit", "Leave the REPL"),
+];
+
+type Handler<T> = Box<dyn FnMut(&[&str], &mut Output<'_, T>) -> fmt::Result>;
+
+struct Entry<T: Terminal> {
+    name: String,
+    help: String,
+    handler: Handler<T>,
+}
+
+/// Line-oriented command interpreter.
+///
+/// # Examples
+///
+/// ```no_run
+/// use core::fmt::Write;
+/// use editline::repl::Repl;
+/// use editline::terminals::StdioTerminal;
+///
+/// let mut repl = Repl::new(StdioTerminal::new());
+/// repl.set_prompt("device> ");
+/// repl.add_command("echo", "Print the arguments", |args, out| writeln!(out, "{}", args.join(" ")));
+/// repl.add_command("add", "Add two numbers", |args, out| match args {
+///     [a, b] => match (a.parse::<i64>(), b.parse::<i64>()) {
+///         (Ok(a), Ok(b)) => writeln!(out, "{}", a + b),
+///         _ => writeln!(out, "add: not a number"),
+///     },
+///     _ => writeln!(out, "usage: add <a> <b>"),
+/// });
+/// repl.run()?;
+/// # Ok::<(), editline::Error>(())
+/// ```
+pub struct Repl<T: Terminal> {
+    editor: LineEditor,
+    terminal: T,
+    prompt: String,
+    banner: String,
+    commands: Vec<Entry<T>>,
+}
+
+impl<T: Terminal> Repl<T> {
+    /// Creates a REPL on `terminal` with a 1024-byte line and 50 history entries.
+    pub fn new(terminal: T) -> Self {
+        Self::with_editor(LineEditor::new(1024, 50), terminal)
+    }
+
+    /// Creates a REPL using an editor configured by the caller, e.g. with a
+    /// completer or theme.
+    pub fn with_editor(editor: LineEditor, terminal: T) -> Self {
+        Repl {
+            editor,
+            terminal,
+            prompt: String::from("> "),
+            banner: String::new(),
+            commands: Vec::new(),
+        }
+    }
+
+    /// Sets the prompt shown before each line. The default is `"> "`.
+    pub fn set_prompt(&mut self, prompt: &str) {
+        self.prompt = String::from(prompt);
+    }
+
+    /// Sets text shown when [`run`](Self::run) starts and after `clear`, e.g.
+    /// the application name and how to get help. `\n` is turned into the
+    /// terminal's line ending. There is no banner by default.
+    pub fn set_banner(&mut self, banner: &str) {
+        self.banner = String::from(banner);
+    }
+
+    /// Registers a command.
+    ///
+    /// `handler` receives the words following the command name; words may be
+    /// quoted to include spaces, see [`split_args`]. A command with the name of
+    /// an earlier one replaces it, which also works for the built-in commands.
+    pub fn add_command<F>(&mut self, name: &str, help: &str, handler: F)
+    where
+        F: FnMut(&[&str], &mut Output<'_, T>) -> fmt::Result + 'static,
+    {
+        self.commands.retain(|entry| entry.name != name);
+        self.commands.push(Entry {
+            name: String::from(name),
+            help: String::from(help),
+            handler: Box::new(handler),
+        });
+    }
+
+    /// Returns the line editor, e.g. to change its settings between commands.
+    pub fn editor(&mut self) -> &mut LineEditor {
+        &mut self.editor
+    }
+
+    /// Returns the terminal.
+    pub fn terminal(&mut self) -> &mut T {
+        &mut self.terminal
+    }
+
+    /// Runs commands until `exit` or Ctrl+D.
+    ///
+    /// Ctrl+C abandons the current line and shows a fresh prompt. Terminal
+    /// errors end the loop and are returned.
+    pub fn run(&mut self) -> Result<()> {
+        self.write_banner()?;
+        loop {
+            let line = match self
+                .editor
+                .read_line_with_prompt(&mut self.terminal, &self.prompt)
+            {
+                Ok(line) => line,
+                Err(Error::Interrupted) => {
+                    write_newline(&mut self.terminal)?;
+                    continue;
+                }
+                Err(Error::Eof) => {
+                    write_newline(&mut self.terminal)?;
+                    return self.terminal.flush();
+                }
+                Err(e) => return Err(e),
+            };
+
+            let words = split_args(&line);
+            let words: Vec<&str> = words.iter().map(String::as_str).collect();
+            let (name, args) = match words.split_first() {
+                Some((name, args)) => (*name, args),
+                None => continue,
+            };
+            if !self.execute(name, args)? {
+                return self.terminal.flush();
+            }
+        }
+    }
+
+    /// Runs one command, returning `false` if it was `exit`.
+    fn execute(&mut self, name: &str, args: &[&str]) -> Result<bool> {
+        let mut out = Output {
+            terminal: &mut self.terminal,
+            error: None,
+        };
+        if let Some(entry) = self.commands.iter_mut().find(|entry| entry.name == name) {
+            let _ = (entry.handler)(args, &mut out);
+            return out.finish().map(|_| true);
+        }
+
+        let _ = match name {
+            "help" => write_help(&mut out, &self.commands),
+            "history" => {
+                self.editor.print_history(&mut *out.terminal, ..)?;
+                Ok(())
+            }
+            "clear" => {
+                self.editor.clear_screen(&mut *out.terminal)?;
+                out.finish()?;
+                self.write_banner()?;
+                return Ok(true);
+            }
+            "exit" => return Ok(false),
+            _ => writeln!(out, "{}: unknown command, type `help` for a list", name),
+        };
+        out.finish().map(|_| true)
+    }
+
+    /// Writes the banner, if any.
+    fn write_banner(&mut self) -> Result<()> {
+        if self.banner.is_empty() {
+            return Ok(());
+        }
+        let mut out = Output {
+            terminal: &mut self.terminal,
+            error: None,
+        };
+        let _ = out.write_str(&self.banner);
+        out.finish()
+    }
+}
+
+impl<T: Terminal> fmt::Debug for Repl<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = self
+            .commands
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect();
+        f.debug_struct("Repl")
+            .field("editor", &self.editor)
+            .field("prompt", &self.prompt)
+            .field("banner", &self.banner)
+            .field("commands", &names)
+            .finish_non_exhaustive()
+    }
+}
+
+fn write_help<T: Terminal>(out: &mut Output<'_, T>, commands: &[Entry<T>]) -> fmt::Result {
+    let builtins = BUILTINS
+        .iter()
+        .filter(|(name, _)| !commands.iter().any(|entry| entry.name == *name));
+    let all: Vec<(&str, &str)> = commands
+        .iter()
+        .map(|entry| (entry.name.as_str(), entry.help.as_str()))
+        .chain(builtins.copied())
+        .collect();
+    let width = all
+        .iter()
+        .map(|(name, _)| name.chars().count())
+        .max()
+        .unwrap_or(0);
+    for (name, help) in all {
+        writeln!(out, "  {:width$}  {}", name, help, width = width)?;
+    }
+    Ok(())
+}
+
+/// Command output on the REPL's terminal.
+///
+/// Write to it with `write!` and `writeln!`; each `\n` is sent as the
+/// terminal's line ending. Terminal errors end the [`Repl::run`] loop.
+pub struct Output<'a, T: Terminal> {
+    terminal: &'a mut T,
+    error: Option<Error>,
+}
+
+impl<T: Terminal> Output<'_, T> {
+    /// Returns the terminal, for output that `fmt::Write` can't express, such
+    /// as raw escape sequences.
+    pub fn terminal(&mut self) -> &mut T {
+        self.terminal
+    }
+
+    fn finish(self) -> Result<()> {
+        match self.error {
+            Some(e) => Err(e),
+            None => self.terminal.flush(),
+        }
+    }
+}
+
+impl<T: Terminal> fmt::Write for Output<'_, T> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut parts = s.split('\n');
+        let mut result = parts
+            .next()
+            .map_or(Ok(()), |first| self.terminal.write(first.as_bytes()));
+        for part in parts {
+            result = result
+                .and_then(|_| write_newline(&mut *self.terminal))
+                .and_then(|_| self.terminal.write(part.as_bytes()));
+        }
+        result.map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
+impl<T: Terminal> fmt::Debug for Output<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Output")
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Splits a command line into words.
+///
+/// Words are separated by whitespace. Single or double quotes group text
+/// containing spaces into one word, and a backslash takes the next character
+/// literally, so `say "hi there" it\'s` gives `say`, `hi there` and `it's`.
+pub fn split_args(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (c, quote) {
+            ('\\', _) => {
+                word.extend(chars.next());
+                in_word = true;
+            }
+            (c, Some(q)) if c == q => quote = None,
+            (c, Some(_)) => word.push(c),
+            ('"' | '\'', None) => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (c, None) if c.is_whitespace() => {
+                if in_word {
+                    words.push(core::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (c, None) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    /// Terminal fed with whole lines, recording the output.
+    struct Script {
+        input: Vec<u8>,
+        output: Vec<u8>,
+    }
+
+    impl Terminal for Script {
+        fn read_byte(&mut self) -> Result<u8> {
+            if self.input.is_empty() {
+                Err(Error::Eof)
+            } else {
+                Ok(self.input.remove(0))
+            }
+        }
+        fn write(&mut self, data: &[u8]) -> Result<()> {
+            self.output.extend_from_slice(data);
+            Ok(())
+        }
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn enter_raw_mode(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn exit_raw_mode(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn cursor_left(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn cursor_right(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn clear_eol(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_split_args() {
+        assert_eq!(split_args("  set  led 1 "), ["set", "led", "1"]);
+        assert_eq!(
+            split_args(r#"say "hi there" it\'s ''"#),
+            ["say", "hi there", "it's", ""]
+        );
+        assert!(split_args("   ").is_empty());
+    }
+
+    #[test]
+    fn test_run_commands() {
+        let input = b"add 2 3\rfrob\rhelp\rexit\rnot reached\r".to_vec();
+        let mut repl = Repl::new(Script {
+            input,
+            output: Vec::new(),
+        });
+        repl.add_command("add", "Add two numbers", |args, out| {
+            let sum: i32 = args.iter().filter_map(|a| a.parse::<i32>().ok()).sum();
+            writeln!(out, "sum {}", sum)
+        });
+        repl.run().unwrap();
+
+        let output = String::from_utf8_lossy(&repl.terminal().output).to_string();
+        let newline = "\r\n";
+        assert!(output.contains(&["sum 5", ""].join(newline)));
+        assert!(output.contains("frob: unknown command"));
+        assert!(output.contains(
+            &[
+                "  add      Add two numbers",
+                "  help     List the available commands"
+            ]
+            .join(newline)
+        ));
+        assert!(!output.contains("not reached"));
+        assert_eq!(repl.editor().history().len(), 4);
+    }
+
+    #[test]
+    fn test_clear_repaints_banner() {
+        let input = b"clear\rexit\r".to_vec();
+        let mut repl = Repl::new(Script {
+            input,
+            output: Vec::new(),
+        });
+        repl.set_banner("Device console\n");
+        repl.run().unwrap();
+
+        let output = String::from_utf8_lossy(&repl.terminal().output).to_string();
+        let newline = "\r\n";
+        let banner = ["Device console", ""].join(newline);
+        assert!(output.starts_with(&banner));
+        assert!(output.contains(&["\x1b[H\x1b[2J", &banner, "> exit"].concat()));
+    }
+}
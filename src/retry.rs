@@ -0,0 +1,340 @@
+//! Riding out transient terminal errors.
+//!
+//! USB CDC and UART backends can fail a write or read now and then, e.g. while
+//! the host isn't draining the endpoint. Normally such an error ends
+//! `read_line` and the half-typed line is gone. Install a [`RetryPolicy`] with
+//! [`LineEditor::set_retry_policy`](crate::LineEditor::set_retry_policy) and
+//! failed terminal operations are retried while the policy agrees. When it
+//! gives up on an I/O error, the error is returned as before, but the line is
+//! kept and drawn again by the next `read_line`.
+//!
+//! The policy covers reads and writes of [`LineEditor`](crate::LineEditor),
+//! including [`poll_read_line`](crate::LineEditor::poll_read_line), and of
+//! [`AsyncLineEditor`](crate::asynch::AsyncLineEditor). Entering and leaving raw
+//! mode are not retried.
+
+use alloc::boxed::Box;
+#[cfg(feature = "async")]
+use core::task::{Context, Poll};
+
+#[cfg(feature = "async")]
+use crate::asynch::AsyncTerminal;
+use crate::{Caps, Error, IoCause, KeyEvent, Result, Terminal};
+
+/// Decides whether a failed terminal operation is tried again.
+///
+/// Closures of the form `FnMut(&Error, u32) -> bool` implement this trait.
+pub trait RetryPolicy {
+    /// Called when an operation fails with `error`, for the `attempt`th time in
+    /// a row, starting at 1. Return `true` to try it again; any waiting before
+    /// the retry is done here.
+    fn retry(&mut self, error: &Error, attempt: u32) -> bool;
+}
+
+impl<F: FnMut(&Error, u32) -> bool> RetryPolicy for F {
+    fn retry(&mut self, error: &Error, attempt: u32) -> bool {
+        self(error, attempt)
+    }
+}
+
+/// Policy retrying [`Error::Io`] up to a fixed number of times, calling a
+/// backoff hook with the attempt number before each retry.
+///
+/// Causes that won't go away by waiting, [`Disconnected`](IoCause::Disconnected),
+/// [`InvalidData`](IoCause::InvalidData) and
+/// [`Unsupported`](IoCause::Unsupported), are not retried.
+///
+/// # Examples
+///
+/// ```
+/// use editline::retry::Attempts;
+/// use editline::LineEditor;
+///
+/// let mut editor = LineEditor::new(1024, 50);
+/// editor.set_retry_policy(Attempts::new(3, |attempt| {
+///     std::thread::sleep(std::time::Duration::from_millis(10 << attempt));
+/// }));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Attempts<B> {
+    max: u32,
+    backoff: B,
+}
+
+impl<B: FnMut(u32)> Attempts<B> {
+    /// Creates a policy retrying an operation at most `max` times.
+    pub fn new(max: u32, backoff: B) -> Self {
+        Attempts { max, backoff }
+    }
+}
+
+impl<B: FnMut(u32)> RetryPolicy for Attempts<B> {
+    fn retry(&mut self, error: &Error, attempt: u32) -> bool {
+        let transient = match error {
+            Error::Io(e) => !matches!(
+                e.cause(),
+                IoCause::Disconnected | IoCause::InvalidData | IoCause::Unsupported
+            ),
+            _ => false,
+        };
+        if !transient || attempt > self.max {
+            return false;
+        }
+        (self.backoff)(attempt);
+        true
+    }
+}
+
+/// Terminal adapter retrying failed operations as the policy decides.
+pub(crate) struct Retrying<'a, T: ?Sized> {
+    inner: &'a mut T,
+    policy: Option<&'a mut dyn RetryPolicy>,
+}
+
+impl<'a, T: ?Sized> Retrying<'a, T> {
+    pub(crate) fn new(inner: &'a mut T, policy: Option<&'a mut Box<dyn RetryPolicy>>) -> Self {
+        Retrying {
+            inner,
+            policy: policy.map(|policy| &mut **policy as &mut dyn RetryPolicy),
+        }
+    }
+
+    /// Runs `op` until it succeeds or the policy gives up.
+    fn retry<R>(&mut self, mut op: impl FnMut(&mut T) -> Result<R>) -> Result<R> {
+        let mut attempt = 0;
+        loop {
+            match op(self.inner) {
+                Err(error) => {
+                    attempt += 1;
+                    let retry = match self.policy.as_mut() {
+                        Some(policy) => policy.retry(&error, attempt),
+                        None => false,
+                    };
+                    if !retry {
+                        return Err(error);
+                    }
+                }
+                done => return done,
+            }
+        }
+    }
+}
+
+impl<T: Terminal + ?Sized> Terminal for Retrying<'_, T> {
+    fn read_byte(&mut self) -> Result<u8> {
+        self.retry(|terminal| terminal.read_byte())
+    }
+
+    fn try_read_byte(&mut self) -> Result<Option<u8>> {
+        self.retry(|terminal| terminal.try_read_byte())
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.retry(|terminal| terminal.write(data))
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.retry(|terminal| terminal.flush())
+    }
+
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        self.inner.enter_raw_mode()
+    }
+
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        self.inner.exit_raw_mode()
+    }
+
+    fn cursor_left(&mut self) -> Result<()> {
+        self.retry(|terminal| terminal.cursor_left())
+    }
+
+    fn cursor_right(&mut self) -> Result<()> {
+        self.retry(|terminal| terminal.cursor_right())
+    }
+
+    fn clear_eol(&mut self) -> Result<()> {
+        self.retry(|terminal| terminal.clear_eol())
+    }
+
+    fn cursor_up(&mut self) -> Result<()> {
+        self.retry(|terminal| terminal.cursor_up())
+    }
+
+    fn insert_blank(&mut self) -> Result<()> {
+        self.retry(|terminal| terminal.insert_blank())
+    }
+
+    fn delete_char(&mut self) -> Result<()> {
+        self.retry(|terminal| terminal.delete_char())
+    }
+
+    fn clear_to_end_of_screen(&mut self) -> Result<()> {
+        self.retry(|terminal| terminal.clear_to_end_of_screen())
+    }
+
+    fn clear_screen(&mut self) -> Result<()> {
+        self.retry(|terminal| terminal.clear_screen())
+    }
+
+    fn bell(&mut self) -> Result<()> {
+        self.retry(|terminal| terminal.bell())
+    }
+
+    fn enter_alternate_screen(&mut self) -> Result<()> {
+        self.retry(|terminal| terminal.enter_alternate_screen())
+    }
+
+    fn exit_alternate_screen(&mut self) -> Result<()> {
+        self.retry(|terminal| terminal.exit_alternate_screen())
+    }
+
+    fn parse_key_event(&mut self) -> Result<KeyEvent> {
+        self.retry(|terminal| terminal.parse_key_event())
+    }
+
+    fn size(&mut self) -> Option<(u16, u16)> {
+        self.inner.size()
+    }
+
+    fn get_cursor_position(&mut self) -> Option<(u16, u16)> {
+        self.inner.get_cursor_position()
+    }
+
+    fn show_status(&mut self, text: &[u8]) -> Result<()> {
+        self.retry(|terminal| terminal.show_status(text))
+    }
+
+    fn clear_status(&mut self) -> Result<()> {
+        self.retry(|terminal| terminal.clear_status())
+    }
+
+    fn capabilities(&self) -> Caps {
+        self.inner.capabilities()
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T: AsyncTerminal + ?Sized> AsyncTerminal for Retrying<'_, T> {
+    fn poll_read_byte(&mut self, cx: &mut Context<'_>) -> Poll<Result<u8>> {
+        self.retry(|terminal| match terminal.poll_read_byte(cx) {
+            Poll::Ready(Err(error)) => Err(error),
+            poll => Ok(poll),
+        })
+        .unwrap_or_else(|error| Poll::Ready(Err(error)))
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.retry(|terminal| terminal.write(data))
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.retry(|terminal| match terminal.poll_flush(cx) {
+            Poll::Ready(Err(error)) => Err(error),
+            poll => Ok(poll),
+        })
+        .unwrap_or_else(|error| Poll::Ready(Err(error)))
+    }
+
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        self.inner.enter_raw_mode()
+    }
+
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        self.inner.exit_raw_mode()
+    }
+
+    fn capabilities(&self) -> Caps {
+        self.inner.capabilities()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LineEditor;
+    use alloc::vec::Vec;
+
+    /// Terminal failing the next `failures` writes that contain `fail_on`.
+    struct Flaky {
+        input: Vec<u8>,
+        fail_on: u8,
+        failures: u32,
+    }
+
+    impl Terminal for Flaky {
+        fn read_byte(&mut self) -> Result<u8> {
+            if self.input.is_empty() {
+                return Err(Error::Eof);
+            }
+            Ok(self.input.remove(0))
+        }
+        fn write(&mut self, data: &[u8]) -> Result<()> {
+            if self.failures > 0 && data.contains(&self.fail_on) {
+                self.failures -= 1;
+                return Err(IoCause::Busy.into());
+            }
+            Ok(())
+        }
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn enter_raw_mode(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn exit_raw_mode(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn cursor_left(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn cursor_right(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn clear_eol(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_retry_policy() {
+        use alloc::rc::Rc;
+        use core::cell::RefCell;
+
+        let mut editor = LineEditor::new(100, 10);
+        let mut term = Flaky {
+            input: b"ab\r".to_vec(),
+            fail_on: b'b',
+            failures: 2,
+        };
+        assert!(
+            matches!(editor.read_line(&mut term), Err(Error::Io(e)) if e.cause() == IoCause::Busy)
+        );
+
+        let backoffs = Rc::new(RefCell::new(Vec::new()));
+        let log = backoffs.clone();
+        editor.set_retry_policy(Attempts::new(2, move |attempt| {
+            log.borrow_mut().push(attempt)
+        }));
+        let mut term = Flaky {
+            input: b"ab\r".to_vec(),
+            fail_on: b'b',
+            failures: 2,
+        };
+        assert_eq!(editor.read_line(&mut term).unwrap(), "ab");
+        assert_eq!(*backoffs.borrow(), [1, 2]);
+
+        // Once the policy gives up, the line is kept for the next read
+        editor.set_retry_policy(Attempts::new(1, |_| {}));
+        let mut term = Flaky {
+            input: b"cd\r".to_vec(),
+            fail_on: b'd',
+            failures: 2,
+        };
+        assert!(
+            matches!(editor.read_line(&mut term), Err(Error::Io(e)) if e.cause() == IoCause::Busy)
+        );
+        term.failures = 0;
+        assert_eq!(editor.read_line(&mut term).unwrap(), "cd");
+    }
+}
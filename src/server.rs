@@ -0,0 +1,573 @@
+//! Network consoles (`std` feature).
+//!
+//! [`TcpConsole`] accepts TCP connections and runs a handler for each on its
+//! own thread, with a fresh [`LineEditor`] and a terminal on the connection,
+//! so a debug console on a port takes a few lines:
+//!
+//! ```no_run
+//! use editline::server::TcpConsole;
+//! use editline::Terminal;
+//!
+//! TcpConsole::bind("127.0.0.1:2323")?.serve_telnet(|mut session| {
+//!     while let Ok(line) = session.editor.read_line_with_prompt(&mut session.terminal, "debug> ") {
+//!         session.terminal.write(format!("got {}\n", line).as_bytes())?;
+//!     }
+//!     Ok(())
+//! })?;
+//! # Ok::<(), editline::Error>(())
+//! ```
+//!
+//! A [`Session`] owns its editor and terminal, so it can also be handed to a
+//! [`Repl`](crate::repl::Repl) with `Repl::with_editor`.
+//!
+//! On Unix, `UnixConsole` does the same on a Unix domain socket, the usual
+//! home of a daemon's local admin console, and tells the handler which user
+//! connected.
+//!
+//! By default every connection gets a thread for as long as its client stays
+//! connected, idle or not. `set_max_sessions` caps how many run at once and
+//! `set_read_timeout` ends sessions whose client goes quiet.
+
+use std::boxed::Box;
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::terminals::WriteAllTerminal;
+use crate::{LineEditor, Result, TelnetTerminal};
+
+/// Terminal on a TCP connection.
+pub type TcpTerminal = WriteAllTerminal<TcpStream, TcpStream>;
+
+type EditorFactory = Box<dyn Fn() -> LineEditor + Send + Sync>;
+
+/// Resource limits shared by the consoles.
+#[derive(Debug, Clone, Copy, Default)]
+struct Limits {
+    max_sessions: Option<usize>,
+    read_timeout: Option<Duration>,
+}
+
+/// One client connection, handed to the handler.
+#[derive(Debug)]
+pub struct Session<T, P = SocketAddr> {
+    /// Editor for this connection, with its own history
+    pub editor: LineEditor,
+    /// Terminal on the connection
+    pub terminal: T,
+    /// Who connected
+    pub peer: P,
+}
+
+/// TCP listener serving a console on every connection.
+///
+/// Handlers run on one thread per connection. A connection ends when its
+/// handler returns, typically when reading a line fails with
+/// [`Error::Eof`](crate::Error::Eof) as the client disconnects; errors a
+/// handler returns are only logged (see the `log`/`tracing` features), and a
+/// failed accept doesn't stop the server.
+///
+/// Nothing limits the number of sessions or how long they last unless
+/// [`set_max_sessions`](Self::set_max_sessions) and
+/// [`set_read_timeout`](Self::set_read_timeout) are used: every client holds a
+/// thread until it disconnects.
+///
+/// The listener binds wherever it is told to: a console on anything but a
+/// loopback address is open to the network, without authentication.
+pub struct TcpConsole {
+    listener: TcpListener,
+    editor: EditorFactory,
+    limits: Limits,
+}
+
+impl TcpConsole {
+    /// Listens on `addr`, e.g. `"127.0.0.1:2323"` or `"127.0.0.1:0"` for any
+    /// free port.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        Ok(Self::from_listener(TcpListener::bind(addr)?))
+    }
+
+    /// Serves connections on a listener set up by the caller.
+    pub fn from_listener(listener: TcpListener) -> Self {
+        TcpConsole {
+            listener,
+            editor: Box::new(|| LineEditor::new(1024, 50)),
+            limits: Limits::default(),
+        }
+    }
+
+    /// Returns the address listened on, e.g. to find the port picked for `:0`.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Sets how each connection's editor is created, e.g. with a completer
+    /// installed. The default is `LineEditor::new(1024, 50)`.
+    pub fn set_editor<F: Fn() -> LineEditor + Send + Sync + 'static>(&mut self, factory: F) {
+        self.editor = Box::new(factory);
+    }
+
+    /// Caps the number of sessions running at once; connections arriving
+    /// while `max` are running are closed straight away. Unlimited by default.
+    pub fn set_max_sessions(&mut self, max: usize) {
+        self.limits.max_sessions = Some(max);
+    }
+
+    /// Ends a session when its client sends nothing for `timeout`: reading a
+    /// line fails with an I/O error, which ends the usual read loop. `None`,
+    /// the default, waits forever.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.limits.read_timeout = timeout;
+    }
+
+    /// Accepts connections forever, running `handler` for each on a new
+    /// thread with a plain terminal on the stream, for clients such as
+    /// `nc` or `socat` with the local terminal in raw mode.
+    pub fn serve<F>(self, handler: F) -> Result<()>
+    where
+        F: Fn(Session<TcpTerminal>) -> Result<()> + Send + Sync + 'static,
+    {
+        let timeout = self.limits.read_timeout;
+        serve(
+            self.listener.incoming(),
+            self.editor,
+            self.limits,
+            move |stream: TcpStream| {
+                stream.set_read_timeout(timeout)?;
+                let peer = stream.peer_addr()?;
+                Ok((WriteAllTerminal::new(stream.try_clone()?, stream), peer))
+            },
+            handler,
+        )
+    }
+
+    /// Like [`serve`](Self::serve), for `telnet` and other Telnet clients:
+    /// the terminal is wrapped in a [`TelnetTerminal`].
+    pub fn serve_telnet<F>(self, handler: F) -> Result<()>
+    where
+        F: Fn(Session<TelnetTerminal<TcpTerminal>>) -> Result<()> + Send + Sync + 'static,
+    {
+        let timeout = self.limits.read_timeout;
+        serve(
+            self.listener.incoming(),
+            self.editor,
+            self.limits,
+            move |stream: TcpStream| {
+                stream.set_read_timeout(timeout)?;
+                let peer = stream.peer_addr()?;
+                Ok((
+                    TelnetTerminal::new(WriteAllTerminal::new(stream.try_clone()?, stream)),
+                    peer,
+                ))
+            },
+            handler,
+        )
+    }
+}
+
+impl core::fmt::Debug for TcpConsole {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TcpConsole")
+            .field("listener", &self.listener)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(unix)]
+pub use self::unix::{PeerCredentials, UnixConsole, UnixTerminal};
+
+#[cfg(unix)]
+mod unix {
+    use std::boxed::Box;
+    use std::io;
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::Path;
+    use std::time::Duration;
+
+    use super::{serve, EditorFactory, Limits, Session};
+    use crate::terminals::WriteAllTerminal;
+    use crate::{LineEditor, Result};
+
+    /// Terminal on a Unix domain socket connection.
+    pub type UnixTerminal = WriteAllTerminal<UnixStream, UnixStream>;
+
+    type Authorizer = Box<dyn Fn(&PeerCredentials) -> bool + Send + Sync>;
+
+    /// Identity of the process at the other end of a Unix domain socket, as
+    /// vouched for by the kernel.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct PeerCredentials {
+        /// Effective user ID
+        pub uid: u32,
+        /// Effective group ID
+        pub gid: u32,
+        /// Process ID, where the platform reports it (Linux and Android)
+        pub pid: Option<i32>,
+    }
+
+    impl PeerCredentials {
+        /// Reads the credentials of the peer of `stream`.
+        pub fn of(stream: &UnixStream) -> io::Result<Self> {
+            peer_credentials(stream.as_raw_fd())
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn peer_credentials(fd: i32) -> io::Result<PeerCredentials> {
+        let mut cred = libc::ucred {
+            pid: 0,
+            uid: 0,
+            gid: 0,
+        };
+        let mut len = core::mem::size_of::<libc::ucred>() as libc::socklen_t;
+        let result = unsafe {
+            libc::getsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                libc::SO_PEERCRED,
+                &mut cred as *mut libc::ucred as *mut libc::c_void,
+                &mut len,
+            )
+        };
+        if result != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(PeerCredentials {
+            uid: cred.uid,
+            gid: cred.gid,
+            pid: Some(cred.pid),
+        })
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn peer_credentials(fd: i32) -> io::Result<PeerCredentials> {
+        let (mut uid, mut gid) = (0, 0);
+        if unsafe { libc::getpeereid(fd, &mut uid, &mut gid) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(PeerCredentials {
+            uid,
+            gid,
+            pid: None,
+        })
+    }
+
+    /// Unix domain socket listener serving a console on every connection.
+    ///
+    /// Works like [`TcpConsole`](super::TcpConsole), with the peer of each
+    /// [`Session`] being its [`PeerCredentials`]. Access is first limited by
+    /// the socket file's permissions; [`set_authorizer`](Self::set_authorizer)
+    /// can turn users away before a session starts, and handlers can check
+    /// the credentials again per command. Sessions are unlimited in number
+    /// and length unless [`set_max_sessions`](Self::set_max_sessions) and
+    /// [`set_read_timeout`](Self::set_read_timeout) say otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use editline::server::UnixConsole;
+    ///
+    /// let _ = std::fs::remove_file("/run/mydaemon/console.sock");
+    /// let mut console = UnixConsole::bind("/run/mydaemon/console.sock")?;
+    /// // Only root and the daemon's own user
+    /// let owner = 1000;
+    /// console.set_authorizer(move |peer| peer.uid == 0 || peer.uid == owner);
+    /// console.serve(|mut session| {
+    ///     while let Ok(line) = session.editor.read_line_with_prompt(&mut session.terminal, "admin> ") {
+    ///         // run `line` on behalf of session.peer.uid
+    ///     }
+    ///     Ok(())
+    /// })?;
+    /// # Ok::<(), editline::Error>(())
+    /// ```
+    pub struct UnixConsole {
+        listener: UnixListener,
+        editor: EditorFactory,
+        authorizer: Option<Authorizer>,
+        limits: Limits,
+    }
+
+    impl UnixConsole {
+        /// Listens on a new socket file at `path`.
+        ///
+        /// Fails if the file exists, e.g. left behind by an earlier run; remove
+        /// it first.
+        pub fn bind<P: AsRef<Path>>(path: P) -> Result<Self> {
+            Ok(Self::from_listener(UnixListener::bind(path)?))
+        }
+
+        /// Serves connections on a listener set up by the caller, e.g. one
+        /// passed in by a service manager.
+        pub fn from_listener(listener: UnixListener) -> Self {
+            UnixConsole {
+                listener,
+                editor: Box::new(|| LineEditor::new(1024, 50)),
+                authorizer: None,
+                limits: Limits::default(),
+            }
+        }
+
+        /// Sets how each connection's editor is created. The default is
+        /// `LineEditor::new(1024, 50)`.
+        pub fn set_editor<F: Fn() -> LineEditor + Send + Sync + 'static>(&mut self, factory: F) {
+            self.editor = Box::new(factory);
+        }
+
+        /// Sets a check run on each connection's credentials before its
+        /// session starts; connections it returns `false` for are closed.
+        pub fn set_authorizer<F: Fn(&PeerCredentials) -> bool + Send + Sync + 'static>(
+            &mut self,
+            authorizer: F,
+        ) {
+            self.authorizer = Some(Box::new(authorizer));
+        }
+
+        /// Caps the number of sessions running at once, like
+        /// [`TcpConsole::set_max_sessions`](super::TcpConsole::set_max_sessions).
+        pub fn set_max_sessions(&mut self, max: usize) {
+            self.limits.max_sessions = Some(max);
+        }
+
+        /// Ends quiet sessions, like
+        /// [`TcpConsole::set_read_timeout`](super::TcpConsole::set_read_timeout).
+        pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+            self.limits.read_timeout = timeout;
+        }
+
+        /// Accepts connections forever, running `handler` for each on a new
+        /// thread, for clients such as `socat` with the local terminal in raw
+        /// mode.
+        pub fn serve<F>(self, handler: F) -> Result<()>
+        where
+            F: Fn(Session<UnixTerminal, PeerCredentials>) -> Result<()> + Send + Sync + 'static,
+        {
+            let authorizer = self.authorizer;
+            let timeout = self.limits.read_timeout;
+            serve(
+                self.listener.incoming(),
+                self.editor,
+                self.limits,
+                move |stream: UnixStream| {
+                    stream.set_read_timeout(timeout)?;
+                    let peer = PeerCredentials::of(&stream)?;
+                    if !authorizer
+                        .as_ref()
+                        .map_or(true, |authorize| authorize(&peer))
+                    {
+                        return Err(io::ErrorKind::PermissionDenied.into());
+                    }
+                    Ok((WriteAllTerminal::new(stream.try_clone()?, stream), peer))
+                },
+                handler,
+            )
+        }
+    }
+
+    impl core::fmt::Debug for UnixConsole {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            f.debug_struct("UnixConsole")
+                .field("listener", &self.listener)
+                .field("authorizer", &self.authorizer.is_some())
+                .finish_non_exhaustive()
+        }
+    }
+}
+
+/// Releases a session's place in the count when its thread ends.
+struct SessionSlot(Arc<AtomicUsize>);
+
+impl Drop for SessionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Accept loop shared by the consoles: `open` turns each stream into a
+/// terminal and peer, on the connection's thread.
+fn serve<S, T, P, O, F>(
+    incoming: impl Iterator<Item = io::Result<S>>,
+    editor: EditorFactory,
+    limits: Limits,
+    open: O,
+    handler: F,
+) -> Result<()>
+where
+    S: Send + 'static,
+    O: Fn(S) -> io::Result<(T, P)> + Send + Sync + 'static,
+    F: Fn(Session<T, P>) -> Result<()> + Send + Sync + 'static,
+{
+    let shared = Arc::new((editor, open, handler));
+    let active = Arc::new(AtomicUsize::new(0));
+    for stream in incoming {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                debug!("console accept failed: {}", e);
+                continue;
+            }
+        };
+        // Only this loop adds sessions, so the count can't grow past the check
+        if limits
+            .max_sessions
+            .map_or(false, |max| active.load(Ordering::SeqCst) >= max)
+        {
+            debug!("console full, closing connection");
+            continue;
+        }
+        active.fetch_add(1, Ordering::SeqCst);
+        let slot = SessionSlot(Arc::clone(&active));
+        let shared = Arc::clone(&shared);
+        thread::spawn(move || {
+            let _slot = slot;
+            let (editor, open, handler) = &*shared;
+            let result = open(stream)
+                .map_err(crate::Error::from)
+                .and_then(|(terminal, peer)| {
+                    handler(Session {
+                        editor: editor(),
+                        terminal,
+                        peer,
+                    })
+                });
+            if let Err(e) = result {
+                debug!("console session ended: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Terminal;
+    use std::io::{Read, Write};
+    use std::sync::{mpsc, Mutex};
+
+    #[test]
+    fn test_tcp_console() {
+        let console = TcpConsole::bind("127.0.0.1:0").unwrap();
+        let addr = console.local_addr().unwrap();
+        let (sender, lines) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        thread::spawn(move || {
+            console.serve(move |mut session| {
+                assert!(session.peer.ip().is_loopback());
+                while let Ok(line) = session.editor.read_line(&mut session.terminal) {
+                    sender.lock().unwrap().send(line).unwrap();
+                    session.terminal.write(b"ok\n")?;
+                }
+                Ok(())
+            })
+        });
+
+        // Connections are served one after another
+        for text in ["first", "second"] {
+            let mut client = TcpStream::connect(addr).unwrap();
+            client.write_all(format!("{}\r", text).as_bytes()).unwrap();
+            let mut reply = Vec::new();
+            while !reply.ends_with(b"ok\n") {
+                let mut buf = [0; 64];
+                let n = client.read(&mut buf).unwrap();
+                assert!(n > 0);
+                reply.extend_from_slice(&buf[..n]);
+            }
+            assert_eq!(
+                crate::ansi::strip_escapes(&String::from_utf8(reply).unwrap()),
+                format!("{}\nok\n", text)
+            );
+            assert_eq!(lines.recv().unwrap(), text);
+        }
+    }
+
+    #[test]
+    fn test_tcp_console_limits() {
+        let mut console = TcpConsole::bind("127.0.0.1:0").unwrap();
+        let addr = console.local_addr().unwrap();
+        console.set_max_sessions(1);
+        console.set_read_timeout(Some(Duration::from_millis(200)));
+        thread::spawn(move || {
+            console.serve(|mut session| {
+                while let Ok(line) = session.editor.read_line(&mut session.terminal) {
+                    session.terminal.write(format!("{}!\n", line).as_bytes())?;
+                }
+                Ok(())
+            })
+        });
+        let reply = |client: &mut TcpStream| {
+            let mut reply = Vec::new();
+            let mut buf = [0; 64];
+            while !reply.ends_with(b"!\n") {
+                match client.read(&mut buf) {
+                    Ok(0) | Err(_) => return None,
+                    Ok(n) => reply.extend_from_slice(&buf[..n]),
+                }
+            }
+            Some(reply)
+        };
+
+        let mut first = TcpStream::connect(addr).unwrap();
+        first.write_all(b"hi\r").unwrap();
+        assert!(reply(&mut first).is_some());
+
+        // The one session is taken: a second client is closed at once
+        let mut second = TcpStream::connect(addr).unwrap();
+        assert_eq!(second.read(&mut [0; 16]).unwrap(), 0);
+
+        // The first client goes quiet and its session times out, freeing
+        // the place
+        assert!(matches!(first.read(&mut [0; 16]), Ok(0) | Err(_)));
+        let mut third = loop {
+            let mut client = TcpStream::connect(addr).unwrap();
+            client.write_all(b"back\r").unwrap();
+            if reply(&mut client).is_some() {
+                break client;
+            }
+            thread::sleep(Duration::from_millis(10));
+        };
+        third.write_all(b"again\r").unwrap();
+        assert!(reply(&mut third).is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unix_console() {
+        use std::os::unix::net::UnixStream;
+
+        let path =
+            std::env::temp_dir().join(format!("editline-console-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut console = UnixConsole::bind(&path).unwrap();
+        let uid = unsafe { libc::getuid() };
+        let (sender, peers) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        let allowed = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let allow = Arc::clone(&allowed);
+        console.set_authorizer(move |_| allow.load(std::sync::atomic::Ordering::SeqCst));
+        thread::spawn(move || {
+            console.serve(move |mut session| {
+                let line = session.editor.read_line(&mut session.terminal)?;
+                sender.lock().unwrap().send((session.peer, line)).unwrap();
+                Ok(())
+            })
+        });
+
+        // Turned away: closed without a session
+        let mut client = UnixStream::connect(&path).unwrap();
+        assert_eq!(client.read(&mut [0; 16]).unwrap(), 0);
+
+        allowed.store(true, std::sync::atomic::Ordering::SeqCst);
+        let mut client = UnixStream::connect(&path).unwrap();
+        client.write_all(b"status\r").unwrap();
+        let (peer, line) = peers.recv().unwrap();
+        assert_eq!(line, "status");
+        assert_eq!(peer.uid, uid);
+        #[cfg(target_os = "linux")]
+        assert_eq!(peer.pid, Some(std::process::id() as i32));
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,168 @@
+//! Editing sessions that outlive their terminal.
+//!
+//! A USB CDC port disappears when the cable is pulled, and a network console
+//! when the connection drops. With [`PersistentSession`], the line being typed
+//! stays in the device meanwhile, like a detached `screen` session: once a
+//! terminal is attached again, the prompt and the unfinished line are drawn on
+//! it and editing carries on where it stopped.
+
+use alloc::string::String;
+use core::task::Poll;
+
+use crate::{Error, IoCause, LineEditor, Result, Terminal};
+
+/// A [`LineEditor`] and prompt that terminals attach to and detach from.
+///
+/// Reads fail with [`IoCause::Disconnected`] while no terminal is attached;
+/// polled reads stay pending instead. When a read fails with an I/O error or
+/// end of input, the terminal is dropped as gone and the session detaches by
+/// itself, keeping the line, its cursor and the history position (see
+/// [`LineEditor::detach`]). The next read on a newly
+/// [`attach`](Self::attach)ed terminal draws the prompt and the line again.
+///
+/// # Examples
+///
+/// A network console that survives reconnects:
+///
+/// ```no_run
+/// use editline::session::PersistentSession;
+/// use editline::terminals::WriteAllTerminal;
+/// use editline::LineEditor;
+/// use std::net::TcpListener;
+///
+/// let listener = TcpListener::bind("127.0.0.1:2323")?;
+/// let mut session = PersistentSession::new(LineEditor::new(1024, 50), "> ");
+/// for stream in listener.incoming() {
+///     let stream = stream?;
+///     session.attach(WriteAllTerminal::new(stream.try_clone()?, stream));
+///     while let Ok(line) = session.read_line() {
+///         // run the command
+///     }
+///     // Dropped: the unfinished line waits for the next connection
+/// }
+/// # Ok::<(), editline::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct PersistentSession<T> {
+    editor: LineEditor,
+    prompt: String,
+    terminal: Option<T>,
+}
+
+impl<T: Terminal> PersistentSession<T> {
+    /// Creates a detached session reading lines with `editor` after `prompt`.
+    pub fn new(editor: LineEditor, prompt: &str) -> Self {
+        PersistentSession {
+            editor,
+            prompt: prompt.into(),
+            terminal: None,
+        }
+    }
+
+    /// Attaches `terminal`, returning the one attached before, if any.
+    ///
+    /// The next read shows the prompt and any unfinished line on it.
+    pub fn attach(&mut self, terminal: T) -> Option<T> {
+        let previous = self.detach();
+        self.terminal = Some(terminal);
+        previous
+    }
+
+    /// Detaches the terminal without writing to it, and returns it.
+    pub fn detach(&mut self) -> Option<T> {
+        self.editor.detach();
+        self.terminal.take()
+    }
+
+    /// Returns whether a terminal is attached.
+    pub fn is_attached(&self) -> bool {
+        self.terminal.is_some()
+    }
+
+    /// Returns the attached terminal, e.g. to print a command's output.
+    pub fn terminal(&mut self) -> Option<&mut T> {
+        self.terminal.as_mut()
+    }
+
+    /// Returns the editor.
+    pub fn editor(&mut self) -> &mut LineEditor {
+        &mut self.editor
+    }
+
+    /// Reads a line on the attached terminal, like
+    /// [`LineEditor::read_line_with_prompt`].
+    pub fn read_line(&mut self) -> Result<String> {
+        let terminal = self
+            .terminal
+            .as_mut()
+            .ok_or(Error::from(IoCause::Disconnected))?;
+        let result = self.editor.read_line_with_prompt(terminal, &self.prompt);
+        self.detach_on_error(&result);
+        result
+    }
+
+    /// Reads a line on the attached terminal without blocking, like
+    /// [`LineEditor::poll_read_line`]; pending while detached.
+    pub fn poll_read_line(&mut self) -> Poll<Result<String>> {
+        let terminal = match self.terminal.as_mut() {
+            Some(terminal) => terminal,
+            None => return Poll::Pending,
+        };
+        let poll = self.editor.poll_read_line(terminal, &self.prompt);
+        if let Poll::Ready(result) = &poll {
+            self.detach_on_error(result);
+        }
+        poll
+    }
+
+    /// Drops the terminal if `result` says it is gone.
+    fn detach_on_error(&mut self, result: &Result<String>) {
+        if let Err(Error::Io(_) | Error::Eof) = result {
+            self.detach();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ScriptedTerminal;
+
+    /// A link that fails once its input runs out.
+    fn link(input: &[u8]) -> ScriptedTerminal {
+        ScriptedTerminal {
+            end: Some(IoCause::Disconnected.into()),
+            ..ScriptedTerminal::new(input)
+        }
+    }
+
+    #[test]
+    fn test_reattach_replays_line() {
+        let mut session = PersistentSession::new(LineEditor::new(64, 10), "> ");
+        assert_eq!(session.poll_read_line(), Poll::Pending);
+        assert!(session.read_line().is_err());
+
+        session.attach(link(b"sta"));
+        assert!(matches!(
+            session.poll_read_line(),
+            Poll::Ready(Err(Error::Io(_)))
+        ));
+        assert!(!session.is_attached());
+        assert_eq!(session.poll_read_line(), Poll::Pending);
+
+        session.attach(link(b"tus\r"));
+        assert_eq!(session.read_line().unwrap(), "status");
+        let output = &session.terminal().unwrap().output;
+        assert!(
+            output.starts_with(b"\r\x1b[J> sta"),
+            "{:?}",
+            String::from_utf8_lossy(output)
+        );
+
+        // A finished line isn't replayed
+        assert!(session.read_line().is_err());
+        session.attach(link(b"\r"));
+        assert_eq!(session.read_line().unwrap(), "");
+        assert!(session.terminal().unwrap().output.starts_with(b"> "));
+    }
+}
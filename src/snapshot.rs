@@ -0,0 +1,63 @@
+//! Saving and restoring an in-progress line.
+//!
+//! [`LineEditor::snapshot`](crate::LineEditor::snapshot) captures the line being
+//! edited when `read_line` was cut short, for example by a USB re-enumeration or
+//! a suspend that drops the link. After
+//! [`LineEditor::restore`](crate::LineEditor::restore), the next `read_line`
+//! redraws that line instead of starting empty, so the user carries on where
+//! they left off. When the same editor is used again,
+//! [`LineEditor::detach`](crate::LineEditor::detach) does this without a
+//! snapshot; see also the [`session`](crate::session) module.
+
+use alloc::string::String;
+
+/// Line contents, cursor and history position of a [`LineEditor`](crate::LineEditor).
+///
+/// # Examples
+///
+/// ```
+/// use editline::LineEditor;
+///
+/// let mut editor = LineEditor::new(1024, 50);
+/// let state = editor.snapshot();
+/// assert_eq!(state.line(), "");
+///
+/// // ... link drops, device sleeps and wakes ...
+/// editor.restore(state);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditorState {
+    pub(crate) line: String,
+    pub(crate) cursor: usize,
+    /// Entry shown while browsing history, and the line it replaced
+    pub(crate) history_view: Option<(usize, String)>,
+}
+
+impl EditorState {
+    /// Creates a state holding `line` with the cursor at byte position `cursor`.
+    ///
+    /// Useful when the line was persisted elsewhere, e.g. in flash across a
+    /// reset. A cursor past the end or inside a character is moved to the end.
+    pub fn new(line: &str, cursor: usize) -> Self {
+        let cursor = if line.is_char_boundary(cursor) {
+            cursor
+        } else {
+            line.len()
+        };
+        EditorState {
+            line: String::from(line),
+            cursor,
+            history_view: None,
+        }
+    }
+
+    /// Returns the line being edited.
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    /// Returns the cursor position in bytes from the start of the line.
+    pub fn cursor_pos(&self) -> usize {
+        self.cursor
+    }
+}
@@ -0,0 +1,426 @@
+//! A line editor that needs no heap.
+//!
+//! [`StaticLineEditor`] keeps the line and its history in fixed-size arrays
+//! inside the struct, so it works without the `alloc` feature, on targets
+//! such as MSP430 or AVR that have no allocator, and it can be placed in a
+//! `static`. It offers the basic subset of [`LineEditor`](crate::LineEditor)'s
+//! editing; use that one where a heap is available.
+
+use crate::width::char_width;
+use crate::{cursor_left_by, cursor_right_by, write_newline, Error, KeyEvent, Result, Terminal};
+
+/// Line editor with inline storage for a line of up to `BUF` bytes and `HIST`
+/// history entries of up to `ENTRY` bytes each.
+///
+/// Keys:
+///
+/// - **Left/Right, Home/End (Ctrl+A/E)**: Move the cursor
+/// - **Backspace/Delete**: Delete characters
+/// - **Ctrl+K / Ctrl+U**: Delete to the end / start of the line
+/// - **Up/Down**: Navigate the history
+/// - **Ctrl+C**: Cancel the line ([`Error::Interrupted`])
+/// - **Ctrl+D**: End of input ([`Error::Eof`])
+/// - **Enter**: Submit the line
+///
+/// Typing past `BUF` bytes rings the bell. Submitted lines are trimmed and
+/// added to the history unless empty, equal to the newest entry, or longer
+/// than `ENTRY` bytes; the oldest entry makes room once `HIST` are stored.
+/// The line is assumed to fit on one row after the prompt.
+///
+/// [`new`](Self::new) is a `const fn`, so the editor can be a `static`
+/// (behind a mutex or critical section) instead of taking up stack.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(unix)]
+/// # {
+/// use editline::{terminals::StdioTerminal, StaticLineEditor};
+///
+/// // 80-byte lines, 8 history entries of up to 40 bytes
+/// let mut editor: StaticLineEditor<80, 8, 40> = StaticLineEditor::new();
+/// let mut terminal = StdioTerminal::new();
+/// let line = editor.read_line_with_prompt(&mut terminal, "> ")?;
+/// # }
+/// # Ok::<(), editline::Error>(())
+/// ```
+pub struct StaticLineEditor<const BUF: usize, const HIST: usize, const ENTRY: usize> {
+    line: [u8; BUF],
+    len: usize,
+    cursor: usize,
+    /// Ring of history entries, `count` of them ending before `next`
+    entries: [[u8; ENTRY]; HIST],
+    lengths: [usize; HIST],
+    count: usize,
+    next: usize,
+    /// History entry shown, counting from the oldest
+    viewing: Option<usize>,
+    /// Line being typed when history browsing started
+    saved: [u8; BUF],
+    saved_len: usize,
+}
+
+impl<const BUF: usize, const HIST: usize, const ENTRY: usize> StaticLineEditor<BUF, HIST, ENTRY> {
+    /// Creates an editor with an empty history.
+    pub const fn new() -> Self {
+        StaticLineEditor {
+            line: [0; BUF],
+            len: 0,
+            cursor: 0,
+            entries: [[0; ENTRY]; HIST],
+            lengths: [0; HIST],
+            count: 0,
+            next: 0,
+            viewing: None,
+            saved: [0; BUF],
+            saved_len: 0,
+        }
+    }
+
+    /// Reads a line, returning it trimmed.
+    ///
+    /// Enters raw mode for the duration. The line is borrowed from the
+    /// editor, so it has to be used or copied before the next call.
+    pub fn read_line<T: Terminal + ?Sized>(&mut self, terminal: &mut T) -> Result<&str> {
+        self.read_line_with_prompt(terminal, "")
+    }
+
+    /// Writes `prompt`, then reads a line like [`read_line`](Self::read_line).
+    pub fn read_line_with_prompt<T: Terminal + ?Sized>(
+        &mut self,
+        terminal: &mut T,
+        prompt: &str,
+    ) -> Result<&str> {
+        self.len = 0;
+        self.cursor = 0;
+        self.viewing = None;
+        terminal.enter_raw_mode()?;
+        let result = terminal
+            .write(prompt.as_bytes())
+            .and_then(|()| self.edit(terminal));
+        terminal.exit_raw_mode()?;
+        result?;
+
+        let line = core::str::from_utf8(&self.line[..self.len])?.trim();
+        let (start, end) = (
+            line.as_ptr() as usize - self.line.as_ptr() as usize,
+            line.len(),
+        );
+        self.add_history(start, end);
+        Ok(core::str::from_utf8(&self.line[start..start + end])?)
+    }
+
+    /// Returns the number of history entries.
+    pub fn history_len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns history entry `index`, counting from the oldest.
+    pub fn history_entry(&self, index: usize) -> Option<&str> {
+        if index >= self.count {
+            return None;
+        }
+        let slot = self.slot(index);
+        core::str::from_utf8(&self.entries[slot][..self.lengths[slot]]).ok()
+    }
+
+    /// Removes all history entries.
+    pub fn clear_history(&mut self) {
+        self.count = 0;
+        self.next = 0;
+    }
+
+    /// Processes keys until the line is submitted.
+    fn edit<T: Terminal + ?Sized>(&mut self, terminal: &mut T) -> Result<()> {
+        loop {
+            terminal.flush()?;
+            match terminal.parse_key_event()? {
+                KeyEvent::Enter | KeyEvent::LineFeed => {
+                    write_newline(terminal)?;
+                    return terminal.flush();
+                }
+                KeyEvent::Ctrl('c') => return Err(Error::Interrupted),
+                KeyEvent::Ctrl('d') => return Err(Error::Eof),
+                KeyEvent::Normal(c) if !c.is_control() => self.insert(terminal, c)?,
+                KeyEvent::Left => {
+                    let start = self.char_before(self.cursor);
+                    cursor_left_by(terminal, self.width(start, self.cursor))?;
+                    self.cursor = start;
+                }
+                KeyEvent::Right => {
+                    let end = self.char_after(self.cursor);
+                    cursor_right_by(terminal, self.width(self.cursor, end))?;
+                    self.cursor = end;
+                }
+                KeyEvent::Home | KeyEvent::Ctrl('a') => {
+                    cursor_left_by(terminal, self.width(0, self.cursor))?;
+                    self.cursor = 0;
+                }
+                KeyEvent::End | KeyEvent::Ctrl('e') => {
+                    cursor_right_by(terminal, self.width(self.cursor, self.len))?;
+                    self.cursor = self.len;
+                }
+                KeyEvent::Backspace if self.cursor > 0 => {
+                    let start = self.char_before(self.cursor);
+                    cursor_left_by(terminal, self.width(start, self.cursor))?;
+                    self.remove(start, self.cursor);
+                    self.redraw_tail(terminal)?;
+                }
+                KeyEvent::Delete if self.cursor < self.len => {
+                    self.remove(self.cursor, self.char_after(self.cursor));
+                    self.redraw_tail(terminal)?;
+                }
+                KeyEvent::Ctrl('k') => {
+                    self.len = self.cursor;
+                    terminal.clear_eol()?;
+                }
+                KeyEvent::Ctrl('u') => {
+                    cursor_left_by(terminal, self.width(0, self.cursor))?;
+                    self.remove(0, self.cursor);
+                    self.redraw_tail(terminal)?;
+                }
+                KeyEvent::Up => self.history_previous(terminal)?,
+                KeyEvent::Down => self.history_next(terminal)?,
+                _ => {}
+            }
+        }
+    }
+
+    fn insert<T: Terminal + ?Sized>(&mut self, terminal: &mut T, c: char) -> Result<()> {
+        let mut buf = [0; 4];
+        let bytes = c.encode_utf8(&mut buf).as_bytes();
+        if self.len + bytes.len() > BUF {
+            return terminal.bell();
+        }
+        self.line
+            .copy_within(self.cursor..self.len, self.cursor + bytes.len());
+        self.line[self.cursor..self.cursor + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+        let start = self.cursor;
+        self.cursor += bytes.len();
+        terminal.write(&self.line[start..self.len])?;
+        cursor_left_by(terminal, self.width(self.cursor, self.len))
+    }
+
+    /// Removes the bytes from `start` to `end`, leaving the cursor at `start`.
+    fn remove(&mut self, start: usize, end: usize) {
+        self.line.copy_within(end..self.len, start);
+        self.len -= end - start;
+        self.cursor = start;
+    }
+
+    /// Rewrites the line from the cursor after a deletion.
+    fn redraw_tail<T: Terminal + ?Sized>(&mut self, terminal: &mut T) -> Result<()> {
+        terminal.write(&self.line[self.cursor..self.len])?;
+        terminal.clear_eol()?;
+        cursor_left_by(terminal, self.width(self.cursor, self.len))
+    }
+
+    /// Replaces the line with history entry `slot`, or with the line saved
+    /// when browsing began if `None`, leaving the cursor at its end.
+    fn recall<T: Terminal + ?Sized>(
+        &mut self,
+        terminal: &mut T,
+        slot: Option<usize>,
+    ) -> Result<()> {
+        cursor_left_by(terminal, self.width(0, self.cursor))?;
+        // Borrowed in place: copies of the arrays would cost a small stack dearly
+        let text = match slot {
+            Some(slot) => &self.entries[slot][..self.lengths[slot]],
+            None => &self.saved[..self.saved_len],
+        };
+        let len = text.len().min(BUF);
+        self.line[..len].copy_from_slice(&text[..len]);
+        self.len = len;
+        self.cursor = len;
+        terminal.write(&self.line[..len])?;
+        terminal.clear_eol()
+    }
+
+    fn history_previous<T: Terminal + ?Sized>(&mut self, terminal: &mut T) -> Result<()> {
+        let index = match self.viewing {
+            None if self.count > 0 => {
+                self.saved[..self.len].copy_from_slice(&self.line[..self.len]);
+                self.saved_len = self.len;
+                self.count - 1
+            }
+            Some(index) if index > 0 => index - 1,
+            _ => return Ok(()),
+        };
+        self.viewing = Some(index);
+        let slot = self.slot(index);
+        self.recall(terminal, Some(slot))
+    }
+
+    fn history_next<T: Terminal + ?Sized>(&mut self, terminal: &mut T) -> Result<()> {
+        let index = match self.viewing {
+            Some(index) => index + 1,
+            None => return Ok(()),
+        };
+        if index < self.count {
+            self.viewing = Some(index);
+            let slot = self.slot(index);
+            self.recall(terminal, Some(slot))
+        } else {
+            // Past the newest entry, the line being typed comes back
+            self.viewing = None;
+            self.recall(terminal, None)
+        }
+    }
+
+    /// Adds the `len` bytes of the line starting at `start` to the history.
+    fn add_history(&mut self, start: usize, len: usize) {
+        let text = &self.line[start..start + len];
+        if HIST == 0
+            || len == 0
+            || len > ENTRY
+            || self
+                .history_entry(self.count.wrapping_sub(1))
+                .map(str::as_bytes)
+                == Some(text)
+        {
+            return;
+        }
+        self.entries[self.next][..len].copy_from_slice(text);
+        self.lengths[self.next] = len;
+        self.next = (self.next + 1) % HIST;
+        self.count = (self.count + 1).min(HIST);
+    }
+
+    /// Ring slot of history entry `index`, counting from the oldest.
+    fn slot(&self, index: usize) -> usize {
+        (self.next + HIST - self.count + index) % HIST
+    }
+
+    /// Start of the character before `pos`.
+    fn char_before(&self, pos: usize) -> usize {
+        let mut start = pos.saturating_sub(1);
+        while start > 0 && self.line[start] & 0xc0 == 0x80 {
+            start -= 1;
+        }
+        start
+    }
+
+    /// End of the character at `pos`.
+    fn char_after(&self, pos: usize) -> usize {
+        let mut end = (pos + 1).min(self.len);
+        while end < self.len && self.line[end] & 0xc0 == 0x80 {
+            end += 1;
+        }
+        end
+    }
+
+    /// Display width of the line between two byte positions.
+    fn width(&self, start: usize, end: usize) -> usize {
+        core::str::from_utf8(&self.line[start..end])
+            .map_or(0, |text| text.chars().map(char_width).sum())
+    }
+}
+
+impl<const BUF: usize, const HIST: usize, const ENTRY: usize> Default
+    for StaticLineEditor<BUF, HIST, ENTRY>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const BUF: usize, const HIST: usize, const ENTRY: usize> core::fmt::Debug
+    for StaticLineEditor<BUF, HIST, ENTRY>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("StaticLineEditor")
+            .field("line", &core::str::from_utf8(&self.line[..self.len]))
+            .field("cursor", &self.cursor)
+            .field("history_len", &self.count)
+            .finish()
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::testing::ScriptedTerminal;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    /// Output with cursor moves shown as `<` and `>` and clears as `$`.
+    fn marked(term: &ScriptedTerminal) -> String {
+        term.output()
+            .replace("\x1b[D", "<")
+            .replace("\x1b[C", ">")
+            .replace("\x1b[K", "$")
+    }
+
+    fn typed(text: &str) -> Vec<KeyEvent> {
+        let mut keys: Vec<KeyEvent> = text.chars().map(KeyEvent::Normal).collect();
+        keys.push(KeyEvent::Enter);
+        keys
+    }
+
+    #[test]
+    fn test_editing() {
+        let mut editor: StaticLineEditor<16, 4, 8> = StaticLineEditor::new();
+        let mut keys = typed("gpio 17");
+        keys.splice(
+            4..4,
+            [
+                KeyEvent::Left,
+                KeyEvent::Left,
+                KeyEvent::Backspace,
+                KeyEvent::Normal('é'),
+                KeyEvent::End,
+            ],
+        );
+        let mut term = ScriptedTerminal::with_keys(&keys);
+        assert_eq!(
+            editor.read_line_with_prompt(&mut term, "> ").unwrap(),
+            "géio 17"
+        );
+        assert_eq!(marked(&term), "> gpio<<<io$<<éio<<>> 17\r\n");
+
+        // Full buffer rings the bell
+        let mut term = ScriptedTerminal::with_keys(&typed("0123456789abcdefXYZ"));
+        assert_eq!(editor.read_line(&mut term).unwrap(), "0123456789abcdef");
+        assert!(term.output.ends_with(b"\x07\x07\x07\r\n"));
+    }
+
+    #[test]
+    fn test_history() {
+        let mut editor: StaticLineEditor<16, 2, 8> = StaticLineEditor::new();
+        for line in ["one", "two", "two", "  ", "much too long", "three"] {
+            let mut term = ScriptedTerminal::with_keys(&typed(line));
+            editor.read_line(&mut term).unwrap();
+        }
+        assert_eq!(editor.history_len(), 2);
+        assert_eq!(
+            (
+                editor.history_entry(0),
+                editor.history_entry(1),
+                editor.history_entry(2)
+            ),
+            (Some("two"), Some("three"), None)
+        );
+
+        let mut keys = typed("x");
+        keys.splice(
+            1..1,
+            [
+                KeyEvent::Up,
+                KeyEvent::Up,
+                KeyEvent::Up,
+                KeyEvent::Down,
+                KeyEvent::Down,
+            ],
+        );
+        let mut term = ScriptedTerminal::with_keys(&keys);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "x");
+        let mut term = ScriptedTerminal::with_keys(&[KeyEvent::Up, KeyEvent::Up, KeyEvent::Enter]);
+        assert_eq!(editor.read_line(&mut term).unwrap(), "three");
+
+        let mut term = ScriptedTerminal::with_keys(&[KeyEvent::Normal('a'), KeyEvent::Ctrl('c')]);
+        assert_eq!(editor.read_line(&mut term), Err(Error::Interrupted));
+        editor.clear_history();
+        assert_eq!(editor.history_entry(0), None);
+    }
+}
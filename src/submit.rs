@@ -0,0 +1,43 @@
+//! Rewriting lines as they are submitted.
+//!
+//! Install a [`SubmitHook`] with
+//! [`LineEditor::set_submit_hook`](crate::LineEditor::set_submit_hook) to
+//! expand aliases, strip comments or normalize whitespace in one place. The
+//! hook runs after Enter, before the line is added to the history and returned,
+//! for both [`LineEditor`](crate::LineEditor) and
+//! [`AsyncLineEditor`](crate::asynch::AsyncLineEditor).
+
+use alloc::string::String;
+
+/// Hook rewriting submitted lines.
+///
+/// Closures of the form `FnMut(&mut String)` implement this trait.
+///
+/// # Examples
+///
+/// Strip `#` comments and expand an alias:
+///
+/// ```
+/// use editline::LineEditor;
+///
+/// let mut editor = LineEditor::new(1024, 50);
+/// editor.set_submit_hook(|line: &mut String| {
+///     if let Some(at) = line.find('#') {
+///         line.truncate(at);
+///     }
+///     if line.trim_end() == "ll" {
+///         *line = "ls -l".to_string();
+///     }
+/// });
+/// ```
+pub trait SubmitHook {
+    /// Rewrites `line`, which is trimmed. What it leaves is stored in the
+    /// history, if not empty, and returned as is.
+    fn on_submit(&mut self, line: &mut String);
+}
+
+impl<F: FnMut(&mut String)> SubmitHook for F {
+    fn on_submit(&mut self, line: &mut String) {
+        self(line)
+    }
+}
@@ -0,0 +1,287 @@
+//! One session on two terminals.
+
+#[cfg(feature = "async")]
+use core::task::{Context, Poll};
+
+#[cfg(feature = "async")]
+use crate::asynch::AsyncTerminal;
+use crate::{Caps, Result, Terminal};
+
+/// Terminal mirroring a session across two backends.
+///
+/// Input is taken from whichever backend has a byte first and output goes to
+/// both, so a device can offer the same REPL on its UART and its USB CDC port
+/// at once. Once a backend has produced a byte it is asked first for the next
+/// one, which keeps an escape sequence from one side in one piece.
+///
+/// As a [`Terminal`], the backends are polled with
+/// [`try_read_byte`](Terminal::try_read_byte), so both must implement it
+/// without blocking. With the `async` feature, two [`AsyncTerminal`]s make an
+/// `AsyncTerminal` as well.
+///
+/// Output errors from one backend don't keep the other from being written;
+/// the first error is returned afterwards.
+///
+/// # Examples
+///
+/// ```ignore
+/// use editline::{LineEditor, TeeTerminal};
+///
+/// let mut terminal = TeeTerminal::new(uart_terminal, usb_terminal);
+/// let mut editor = LineEditor::new(256, 20);
+/// let line = editor.read_line_with_prompt(&mut terminal, "> ")?;
+/// ```
+#[derive(Debug)]
+pub struct TeeTerminal<A, B> {
+    a: A,
+    b: B,
+    /// The backend that produced the last byte
+    last_b: bool,
+}
+
+impl<A, B> TeeTerminal<A, B> {
+    /// Combines two backends.
+    pub fn new(a: A, b: B) -> Self {
+        TeeTerminal {
+            a,
+            b,
+            last_b: false,
+        }
+    }
+
+    /// Returns the first backend.
+    pub fn first(&mut self) -> &mut A {
+        &mut self.a
+    }
+
+    /// Returns the second backend.
+    pub fn second(&mut self) -> &mut B {
+        &mut self.b
+    }
+
+    /// Returns both backends.
+    pub fn into_inner(self) -> (A, B) {
+        (self.a, self.b)
+    }
+}
+
+/// Returns the first error of two results.
+fn both(a: Result<()>, b: Result<()>) -> Result<()> {
+    a.and(b)
+}
+
+impl<A: Terminal, B: Terminal> TeeTerminal<A, B> {
+    /// Reads a byte from the backend that produced the last one, else from
+    /// the other.
+    fn poll_both(&mut self) -> Result<Option<u8>> {
+        for from_b in [self.last_b, !self.last_b] {
+            let byte = if from_b {
+                self.b.try_read_byte()?
+            } else {
+                self.a.try_read_byte()?
+            };
+            if byte.is_some() {
+                self.last_b = from_b;
+                return Ok(byte);
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<A: Terminal, B: Terminal> Terminal for TeeTerminal<A, B> {
+    fn read_byte(&mut self) -> Result<u8> {
+        loop {
+            if let Some(byte) = self.poll_both()? {
+                return Ok(byte);
+            }
+            core::hint::spin_loop();
+        }
+    }
+
+    fn try_read_byte(&mut self) -> Result<Option<u8>> {
+        self.poll_both()
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        both(self.a.write(data), self.b.write(data))
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        both(self.a.flush(), self.b.flush())
+    }
+
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        both(self.a.enter_raw_mode(), self.b.enter_raw_mode())
+    }
+
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        both(self.a.exit_raw_mode(), self.b.exit_raw_mode())
+    }
+
+    fn cursor_left(&mut self) -> Result<()> {
+        both(self.a.cursor_left(), self.b.cursor_left())
+    }
+
+    fn cursor_right(&mut self) -> Result<()> {
+        both(self.a.cursor_right(), self.b.cursor_right())
+    }
+
+    fn clear_eol(&mut self) -> Result<()> {
+        both(self.a.clear_eol(), self.b.clear_eol())
+    }
+
+    fn cursor_up(&mut self) -> Result<()> {
+        both(self.a.cursor_up(), self.b.cursor_up())
+    }
+
+    fn insert_blank(&mut self) -> Result<()> {
+        both(self.a.insert_blank(), self.b.insert_blank())
+    }
+
+    fn delete_char(&mut self) -> Result<()> {
+        both(self.a.delete_char(), self.b.delete_char())
+    }
+
+    fn clear_to_end_of_screen(&mut self) -> Result<()> {
+        both(
+            self.a.clear_to_end_of_screen(),
+            self.b.clear_to_end_of_screen(),
+        )
+    }
+
+    fn clear_screen(&mut self) -> Result<()> {
+        both(self.a.clear_screen(), self.b.clear_screen())
+    }
+
+    fn bell(&mut self) -> Result<()> {
+        both(self.a.bell(), self.b.bell())
+    }
+
+    fn enter_alternate_screen(&mut self) -> Result<()> {
+        both(
+            self.a.enter_alternate_screen(),
+            self.b.enter_alternate_screen(),
+        )
+    }
+
+    fn exit_alternate_screen(&mut self) -> Result<()> {
+        both(
+            self.a.exit_alternate_screen(),
+            self.b.exit_alternate_screen(),
+        )
+    }
+
+    /// Returns the smaller of the two sizes, so lines wrap the same on both.
+    fn size(&mut self) -> Option<(u16, u16)> {
+        match (self.a.size(), self.b.size()) {
+            (Some((ca, ra)), Some((cb, rb))) => Some((ca.min(cb), ra.min(rb))),
+            (a, b) => a.or(b),
+        }
+    }
+
+    fn show_status(&mut self, text: &[u8]) -> Result<()> {
+        both(self.a.show_status(text), self.b.show_status(text))
+    }
+
+    fn clear_status(&mut self) -> Result<()> {
+        both(self.a.clear_status(), self.b.clear_status())
+    }
+
+    /// Returns what both backends can do.
+    fn capabilities(&self) -> Caps {
+        self.a.capabilities().intersection(self.b.capabilities())
+    }
+}
+
+#[cfg(feature = "async")]
+impl<A: AsyncTerminal, B: AsyncTerminal> AsyncTerminal for TeeTerminal<A, B> {
+    fn poll_read_byte(&mut self, cx: &mut Context<'_>) -> Poll<Result<u8>> {
+        // Both are polled when nothing is ready, so both wakers are registered
+        for from_b in [self.last_b, !self.last_b] {
+            let polled = if from_b {
+                self.b.poll_read_byte(cx)
+            } else {
+                self.a.poll_read_byte(cx)
+            };
+            if let Poll::Ready(result) = polled {
+                self.last_b = from_b;
+                return Poll::Ready(result);
+            }
+        }
+        Poll::Pending
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        both(self.a.write(data), self.b.write(data))
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        match (self.a.poll_flush(cx), self.b.poll_flush(cx)) {
+            (Poll::Ready(a), Poll::Ready(b)) => Poll::Ready(both(a, b)),
+            (Poll::Ready(Err(e)), _) | (_, Poll::Ready(Err(e))) => Poll::Ready(Err(e)),
+            _ => Poll::Pending,
+        }
+    }
+
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        both(
+            AsyncTerminal::enter_raw_mode(&mut self.a),
+            AsyncTerminal::enter_raw_mode(&mut self.b),
+        )
+    }
+
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        both(
+            AsyncTerminal::exit_raw_mode(&mut self.a),
+            AsyncTerminal::exit_raw_mode(&mut self.b),
+        )
+    }
+
+    fn capabilities(&self) -> Caps {
+        AsyncTerminal::capabilities(&self.a).intersection(AsyncTerminal::capabilities(&self.b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ScriptedTerminal;
+    use crate::LineEditor;
+
+    /// Backend with input arriving after given numbers of polls, which tee
+    /// must never block on.
+    fn port(input: &[(u32, u8)]) -> ScriptedTerminal {
+        ScriptedTerminal {
+            polled: true,
+            ..ScriptedTerminal::with_stalls(input)
+        }
+    }
+
+    #[test]
+    fn test_tee_reads_either_and_mirrors_output() {
+        // "ab" typed on the UART, then "c" and Enter on USB
+        let uart = port(&[(0, b'a'), (0, b'b'), (100, b'x')]);
+        let usb = port(&[(5, b'c'), (0, b'\r')]);
+        let mut tee = TeeTerminal::new(uart, usb);
+        let mut editor = LineEditor::new(64, 10);
+        assert_eq!(editor.read_line_with_prompt(&mut tee, "> ").unwrap(), "abc");
+
+        let (uart, usb) = tee.into_inner();
+        assert_eq!(uart.output, usb.output);
+        assert!(uart.output.starts_with(b"> a"));
+    }
+
+    #[test]
+    fn test_newline_suits_both() {
+        assert!(Caps::ANSI.with_crlf(false).intersection(Caps::ANSI).crlf);
+        assert!(
+            !Caps::NONE
+                .with_crlf(false)
+                .intersection(Caps::ANSI.with_crlf(false))
+                .crlf
+        );
+        assert_eq!(Caps::ANSI.newline(), b"\r\n");
+        assert_eq!(Caps::ANSI.with_crlf(false).newline(), b"\n");
+    }
+}
@@ -0,0 +1,276 @@
+//! Telnet protocol handling for network consoles.
+
+use crate::{Caps, Result, Terminal};
+
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+
+const ECHO: u8 = 1;
+const SUPPRESS_GO_AHEAD: u8 = 3;
+const NAWS: u8 = 31;
+
+/// Where the input decoder is within a Telnet command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Data,
+    /// After a CR, whose NUL or LF is dropped
+    Cr,
+    Iac,
+    /// After WILL, WONT, DO or DONT
+    Option(u8),
+    Sub,
+    SubIac,
+}
+
+/// Terminal speaking Telnet over a byte stream, for the `telnet` client and
+/// others that default to it, such as PuTTY.
+///
+/// Left alone, a Telnet client edits a whole line locally and echoes it
+/// itself. On [`enter_raw_mode`](Terminal::enter_raw_mode), this wrapper asks
+/// the client to send each key as typed and leave echoing to the server (WILL
+/// ECHO, WILL SUPPRESS-GO-AHEAD), and to report its window size (DO NAWS),
+/// which [`size`](Terminal::size) then returns. Other options the client
+/// offers or asks for are refused.
+///
+/// Commands are removed from the input, the NUL or LF a client sends after CR
+/// is dropped, and 255 bytes in the output are doubled as the protocol
+/// requires.
+///
+/// # Examples
+///
+/// ```no_run
+/// use editline::terminals::WriteAllTerminal;
+/// use editline::{LineEditor, TelnetTerminal};
+/// use std::net::TcpListener;
+///
+/// let (stream, _) = TcpListener::bind("127.0.0.1:2323")?.accept()?;
+/// let mut terminal = TelnetTerminal::new(WriteAllTerminal::new(stream.try_clone()?, stream));
+/// let mut editor = LineEditor::new(1024, 50);
+/// let line = editor.read_line_with_prompt(&mut terminal, "> ")?;
+/// # Ok::<(), editline::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct TelnetTerminal<T> {
+    inner: T,
+    state: State,
+    negotiated: bool,
+    /// Subnegotiation received so far: the option, then up to four bytes
+    sub: [u8; 5],
+    sub_len: usize,
+    /// Window size reported with NAWS
+    size: Option<(u16, u16)>,
+}
+
+impl<T: Terminal> TelnetTerminal<T> {
+    /// Wraps `inner`, the connection to a Telnet client.
+    pub fn new(inner: T) -> Self {
+        TelnetTerminal {
+            inner,
+            state: State::Data,
+            negotiated: false,
+            sub: [0; 5],
+            sub_len: 0,
+            size: None,
+        }
+    }
+
+    /// Returns the wrapped terminal.
+    pub fn inner(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Returns the wrapped terminal, dropping the protocol state.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Runs `byte` through the protocol, returning it if it is data.
+    fn filter(&mut self, byte: u8) -> Result<Option<u8>> {
+        let state = self.state;
+        self.state = State::Data;
+        match (state, byte) {
+            (State::Cr, 0 | b'\n') => {}
+            (State::Data | State::Cr, IAC) => self.state = State::Iac,
+            (State::Data | State::Cr, b'\r') => {
+                self.state = State::Cr;
+                return Ok(Some(byte));
+            }
+            (State::Data | State::Cr, _) => return Ok(Some(byte)),
+            (State::Iac, IAC) => return Ok(Some(byte)),
+            (State::Iac, WILL | WONT | DO | DONT) => self.state = State::Option(byte),
+            (State::Iac, SB) => {
+                self.sub_len = 0;
+                self.state = State::Sub;
+            }
+            (State::Iac, _) => {}
+            (State::Option(command), option) => self.answer(command, option)?,
+            (State::Sub, IAC) => self.state = State::SubIac,
+            (State::Sub, _) | (State::SubIac, IAC) => {
+                if self.sub_len < self.sub.len() {
+                    self.sub[self.sub_len] = byte;
+                    self.sub_len += 1;
+                }
+                self.state = State::Sub;
+            }
+            (State::SubIac, SE) => {
+                if let [NAWS, w1, w0, h1, h0] = self.sub {
+                    if self.sub_len == 5 {
+                        self.size =
+                            Some((u16::from_be_bytes([w1, w0]), u16::from_be_bytes([h1, h0])));
+                    }
+                }
+            }
+            // Malformed subnegotiation, dropped
+            (State::SubIac, _) => {}
+        }
+        Ok(None)
+    }
+
+    /// Refuses options the client offers or asks for, other than the ones
+    /// requested in [`negotiate`](Self::negotiate), whose acknowledgements
+    /// need no answer.
+    fn answer(&mut self, command: u8, option: u8) -> Result<()> {
+        match (command, option) {
+            (DO, ECHO | SUPPRESS_GO_AHEAD)
+            | (WILL, NAWS | SUPPRESS_GO_AHEAD)
+            | (WONT | DONT, _) => Ok(()),
+            (DO, _) => self.inner.write(&[IAC, WONT, option]),
+            (_, _) => self.inner.write(&[IAC, DONT, option]),
+        }
+    }
+
+    /// Asks the client for character-at-a-time input without local echo.
+    fn negotiate(&mut self) -> Result<()> {
+        if !self.negotiated {
+            self.negotiated = true;
+            self.inner
+                .write(&[IAC, WILL, ECHO, IAC, WILL, SUPPRESS_GO_AHEAD, IAC, DO, NAWS])?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Terminal> Terminal for TelnetTerminal<T> {
+    fn read_byte(&mut self) -> Result<u8> {
+        loop {
+            let byte = self.inner.read_byte()?;
+            if let Some(byte) = self.filter(byte)? {
+                return Ok(byte);
+            }
+        }
+    }
+
+    fn try_read_byte(&mut self) -> Result<Option<u8>> {
+        while let Some(byte) = self.inner.try_read_byte()? {
+            if let Some(byte) = self.filter(byte)? {
+                return Ok(Some(byte));
+            }
+        }
+        Ok(None)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        let mut rest = data;
+        while let Some(at) = rest.iter().position(|&byte| byte == IAC) {
+            self.inner.write(&rest[..=at])?;
+            self.inner.write(&[IAC])?;
+            rest = &rest[at + 1..];
+        }
+        self.inner.write(rest)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        self.negotiate()?;
+        self.inner.enter_raw_mode()
+    }
+
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        self.inner.exit_raw_mode()
+    }
+
+    fn cursor_left(&mut self) -> Result<()> {
+        self.inner.cursor_left()
+    }
+
+    fn cursor_right(&mut self) -> Result<()> {
+        self.inner.cursor_right()
+    }
+
+    fn clear_eol(&mut self) -> Result<()> {
+        self.inner.clear_eol()
+    }
+
+    fn insert_blank(&mut self) -> Result<()> {
+        self.inner.insert_blank()
+    }
+
+    fn delete_char(&mut self) -> Result<()> {
+        self.inner.delete_char()
+    }
+
+    fn size(&mut self) -> Option<(u16, u16)> {
+        self.size.or_else(|| self.inner.size())
+    }
+
+    fn capabilities(&self) -> Caps {
+        self.inner.capabilities()
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::testing::ScriptedTerminal;
+    use crate::LineEditor;
+
+    #[test]
+    fn test_telnet_session() {
+        // Acknowledgements, window size, a refused terminal-type offer, an
+        // escaped 255, then "ab" and Enter sent as CR NUL
+        let input = [
+            &[
+                IAC, DO, ECHO, IAC, WILL, NAWS, IAC, SB, NAWS, 0, 100, 0, 30, IAC, SE, IAC, WILL,
+                24,
+            ][..],
+            b"a",
+            &[IAC, 241],
+            b"b\r\0c\r\n",
+        ]
+        .concat();
+        let mut terminal = TelnetTerminal::new(ScriptedTerminal::new(&input));
+        let mut editor = LineEditor::new(64, 10);
+        assert_eq!(editor.read_line(&mut terminal).unwrap(), "ab");
+        assert_eq!(terminal.size(), Some((100, 30)));
+        assert_eq!(editor.read_line(&mut terminal).unwrap(), "c");
+
+        let output = &terminal.inner().output;
+        assert!(output.starts_with(&[
+            IAC,
+            WILL,
+            ECHO,
+            IAC,
+            WILL,
+            SUPPRESS_GO_AHEAD,
+            IAC,
+            DO,
+            NAWS,
+            IAC,
+            DONT,
+            24
+        ]));
+        // Negotiated once, not on every line
+        assert_eq!(output.iter().filter(|&&byte| byte == IAC).count(), 4);
+
+        terminal.write(&[b'x', IAC, b'y']).unwrap();
+        assert!(terminal.inner().output.ends_with(&[b'x', IAC, IAC, b'y']));
+    }
+}
@@ -0,0 +1,205 @@
+//! [`AsyncTerminal`] over `futures-io` streams (`async-io` feature).
+
+use std::io::ErrorKind;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::vec::Vec;
+
+use futures_io::{AsyncRead, AsyncWrite};
+
+use crate::asynch::AsyncTerminal;
+use crate::{Error, IoCause, Result};
+
+/// Terminal reading from an [`AsyncRead`] and writing to an [`AsyncWrite`].
+///
+/// Lets TCP connections, PTYs and pipes from any runtime implementing the
+/// `futures-io` traits serve an [`AsyncLineEditor`](crate::asynch::AsyncLineEditor)
+/// directly. Tokio types can be adapted with `tokio_util::compat`. Reader and
+/// writer may be the two halves of one stream, or the same object passed twice
+/// by reference where the runtime allows it.
+///
+/// The remote end is expected to send raw keystrokes, as a terminal in raw
+/// mode does; raw mode itself is left to whoever owns that terminal.
+///
+/// # Examples
+///
+/// ```ignore
+/// use editline::asynch::AsyncLineEditor;
+/// use editline::terminals::AsyncReadWriteTerminal;
+/// use futures::io::AsyncReadExt;
+///
+/// let (reader, writer) = tcp_stream.split();
+/// let mut terminal = AsyncReadWriteTerminal::new(reader, writer);
+/// let mut editor = AsyncLineEditor::new(1024, 50);
+/// let line = editor.read_line_with_prompt(&mut terminal, "> ").await?;
+/// ```
+#[derive(Debug)]
+pub struct AsyncReadWriteTerminal<R, W> {
+    reader: R,
+    writer: W,
+    input: [u8; 64],
+    /// Read position and end of the buffered input
+    start: usize,
+    end: usize,
+    output: Vec<u8>,
+    /// How much of `output` has been written
+    written: usize,
+}
+
+impl<R: AsyncRead + Unpin, W: AsyncWrite + Unpin> AsyncReadWriteTerminal<R, W> {
+    /// Creates a terminal from a reader and a writer.
+    pub fn new(reader: R, writer: W) -> Self {
+        AsyncReadWriteTerminal {
+            reader,
+            writer,
+            input: [0; 64],
+            start: 0,
+            end: 0,
+            output: Vec::new(),
+            written: 0,
+        }
+    }
+
+    /// Returns the reader and writer, dropping any unread input and unflushed
+    /// output.
+    pub fn into_inner(self) -> (R, W) {
+        (self.reader, self.writer)
+    }
+}
+
+impl<R: AsyncRead + Unpin, W: AsyncWrite + Unpin> AsyncTerminal for AsyncReadWriteTerminal<R, W> {
+    fn poll_read_byte(&mut self, cx: &mut Context<'_>) -> Poll<Result<u8>> {
+        while self.start == self.end {
+            match Pin::new(&mut self.reader).poll_read(cx, &mut self.input) {
+                Poll::Ready(Ok(0)) => return Poll::Ready(Err(Error::Eof)),
+                Poll::Ready(Ok(n)) => {
+                    self.start = 0;
+                    self.end = n;
+                }
+                Poll::Ready(Err(e)) if e.kind() == ErrorKind::Interrupted => {}
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e.into())),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        let byte = self.input[self.start];
+        self.start += 1;
+        Poll::Ready(Ok(byte))
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.output.extend_from_slice(data);
+        Ok(())
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        while self.written < self.output.len() {
+            match Pin::new(&mut self.writer).poll_write(cx, &self.output[self.written..]) {
+                Poll::Ready(Ok(0)) => return Poll::Ready(Err(IoCause::WriteZero.into())),
+                Poll::Ready(Ok(n)) => self.written += n,
+                Poll::Ready(Err(e)) if e.kind() == ErrorKind::Interrupted => {}
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e.into())),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        self.output.clear();
+        self.written = 0;
+        Pin::new(&mut self.writer)
+            .poll_flush(cx)
+            .map_err(Error::from)
+    }
+
+    fn capabilities(&self) -> crate::Caps {
+        crate::Caps::ANSI
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asynch::AsyncLineEditor;
+    use std::future::Future;
+    use std::io;
+    use std::task::{RawWaker, RawWakerVTable, Waker};
+
+    /// Reader handing out a few bytes per call, pending in between.
+    struct Chunks(Vec<&'static [u8]>, bool);
+
+    impl AsyncRead for Chunks {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            self.1 = !self.1;
+            if self.1 {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            if self.0.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+            let chunk = self.0.remove(0);
+            buf[..chunk.len()].copy_from_slice(chunk);
+            Poll::Ready(Ok(chunk.len()))
+        }
+    }
+
+    /// Writer accepting at most three bytes per call.
+    #[derive(Default)]
+    struct Narrow(Vec<u8>);
+
+    impl AsyncWrite for Narrow {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            let n = buf.len().min(3);
+            self.0.extend_from_slice(&buf[..n]);
+            Poll::Ready(Ok(n))
+        }
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+        fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        fn clone(_: *const ()) -> RawWaker {
+            RawWaker::new(std::ptr::null(), &VTABLE)
+        }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        let waker = unsafe { Waker::from_raw(clone(std::ptr::null())) };
+        let mut cx = Context::from_waker(&waker);
+
+        let mut future = Box::pin(future);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn test_read_line_over_streams() {
+        let reader = Chunks(vec![b"ab", b"c\x1b", b"[D", b"X\r"], false);
+        let mut terminal = AsyncReadWriteTerminal::new(reader, Narrow::default());
+        let mut editor = AsyncLineEditor::new(64, 10);
+
+        let line = block_on(editor.read_line_with_prompt(&mut terminal, "> ")).unwrap();
+        assert_eq!(line, "abXc");
+        let (_, writer) = terminal.into_inner();
+        assert!(writer.0.starts_with(b"> a"));
+        assert!(writer.0.ends_with(b"\x1b[D\x1b[@X\r\n"));
+
+        let mut terminal =
+            AsyncReadWriteTerminal::new(Chunks(Vec::new(), false), Narrow::default());
+        assert!(matches!(
+            block_on(editor.read_line(&mut terminal)),
+            Err(Error::Eof)
+        ));
+    }
+}
@@ -0,0 +1,635 @@
+//! In-process terminal driven through channels, for embedding a console in a
+//! GUI.
+//!
+//! A desktop application (egui, iced, ...) runs the editor on a worker thread
+//! with a [`ChannelTerminal`] and keeps the [`ChannelRemote`] from [`channel`]
+//! on its UI thread: key presses and window resizes go in as [`Input`], and
+//! what the editor draws comes back as [`Output`] for the widget to render.
+//! Nothing is decoded from or encoded to a byte stream on the GUI side, so the
+//! widget only has to keep a grid of text and a cursor.
+//!
+//! With the `embassy` feature, [`AsyncChannelTerminal`] does the same over
+//! `embassy_sync` channels for an [`AsyncLineEditor`](crate::asynch::AsyncLineEditor)
+//! running as a task.
+
+#[cfg(feature = "std")]
+use std::collections::VecDeque;
+#[cfg(feature = "std")]
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+#[cfg(feature = "std")]
+use crate::{ansi, Caps, Error, IoCause, KeyEvent, Result, Terminal};
+
+/// Input from the GUI to the editor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    /// A key press, as the widget translated it
+    Key(crate::KeyEvent),
+    /// The widget now shows this many columns and rows
+    Resize(u16, u16),
+}
+
+/// Drawing commands from the editor to the GUI.
+///
+/// Text goes at the cursor and moves it right; a `\r` in it returns the
+/// cursor to the first column and a `\n` moves it down a row, as on a
+/// terminal. The editor ends lines with `\r\n`, as
+/// [`Caps::crlf`](crate::Caps::crlf) asks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Output {
+    /// Text to show at the cursor
+    Text(String),
+    /// Move the cursor left by this many columns
+    CursorLeft(u16),
+    /// Move the cursor right by this many columns
+    CursorRight(u16),
+    /// Move the cursor up by this many rows, keeping its column
+    CursorUp(u16),
+    /// Erase from the cursor to the end of its row
+    ClearEol,
+    /// Erase from the cursor to the end of the screen
+    ClearToEndOfScreen,
+    /// Erase everything and move the cursor to the top-left corner
+    ClearScreen,
+    /// Alert the user
+    Bell,
+    /// Show this on a status line below the input, or hide the status line
+    /// when empty
+    Status(String),
+    /// An escape sequence with no command of its own, such as colors or the
+    /// alternate screen, for widgets that interpret ANSI; others can drop it
+    Escape(Vec<u8>),
+}
+
+/// Splits what the editor wrote into [`Output`]s, passing each to `emit`.
+fn parse_output(data: &[u8], mut emit: impl FnMut(Output)) {
+    let text = |bytes: &[u8]| Output::Text(String::from_utf8_lossy(bytes).into_owned());
+    let mut start = 0;
+    let mut i = 0;
+    while i < data.len() {
+        let (len, output) = match data[i] {
+            0x07 => (1, Output::Bell),
+            0x1b => escape(&data[i..]),
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        if start < i {
+            emit(text(&data[start..i]));
+        }
+        emit(output);
+        i += len;
+        start = i;
+    }
+    if start < data.len() {
+        emit(text(&data[start..]));
+    }
+}
+
+/// Decodes the escape sequence at the start of `data`, returning its length
+/// and meaning.
+fn escape(data: &[u8]) -> (usize, Output) {
+    if data.starts_with(b"\x1b[H\x1b[2J") {
+        return (7, Output::ClearScreen);
+    }
+    let len = sequence_len(data);
+    let output = match (data.get(1), &data[..len]) {
+        (Some(b'['), [.., final_byte]) if len > 2 => {
+            let params = &data[2..len - 1];
+            let count = core::str::from_utf8(params).ok().and_then(|s| {
+                if s.is_empty() {
+                    Some(1)
+                } else {
+                    s.parse().ok()
+                }
+            });
+            match (final_byte, count) {
+                (b'D', Some(n)) => Output::CursorLeft(n),
+                (b'C', Some(n)) => Output::CursorRight(n),
+                (b'A', Some(n)) => Output::CursorUp(n),
+                (b'K', _) if params.is_empty() || params == b"0" => Output::ClearEol,
+                (b'J', _) if params.is_empty() || params == b"0" => Output::ClearToEndOfScreen,
+                _ => Output::Escape(data[..len].to_vec()),
+            }
+        }
+        _ => Output::Escape(data[..len].to_vec()),
+    };
+    (len, output)
+}
+
+/// Length of the escape sequence at the start of `data`, or all of `data` if
+/// it ends first.
+fn sequence_len(data: &[u8]) -> usize {
+    let end = match data.get(1) {
+        None => return 1,
+        // CSI: parameters up to a final byte
+        Some(b'[') => data[2..]
+            .iter()
+            .position(|b| (0x40..=0x7e).contains(b))
+            .map(|at| at + 3),
+        // OSC: up to BEL or ST
+        Some(b']') => data[2..].iter().enumerate().find_map(|(at, &b)| match b {
+            0x07 => Some(at + 3),
+            b'\\' if data[at + 1] == 0x1b => Some(at + 3),
+            _ => None,
+        }),
+        // Character set designation
+        Some(b'('..=b'/') => Some(3),
+        Some(_) => Some(2),
+    };
+    end.map_or(data.len(), |end| end.min(data.len()))
+}
+
+/// Creates a connected [`ChannelTerminal`] and [`ChannelRemote`].
+///
+/// # Examples
+///
+/// ```
+/// use editline::terminals::channel::{self, Output};
+/// use editline::{KeyEvent, LineEditor};
+///
+/// let (mut terminal, remote) = channel::channel();
+/// let editor = std::thread::spawn(move || LineEditor::new(1024, 50).read_line_with_prompt(&mut terminal, "> "));
+///
+/// // In the GUI: forward keys, render output
+/// remote.send_text("hi")?;
+/// remote.send_key(KeyEvent::Enter)?;
+/// assert_eq!(editor.join().unwrap()?, "hi");
+/// assert_eq!(remote.try_output(), Some(Output::Text("> ".to_string())));
+/// # Ok::<(), editline::Error>(())
+/// ```
+#[cfg(feature = "std")]
+pub fn channel() -> (ChannelTerminal, ChannelRemote) {
+    let (input_tx, input_rx) = mpsc::channel();
+    let (output_tx, output_rx) = mpsc::channel();
+    let terminal = ChannelTerminal {
+        input: input_rx,
+        output: output_tx,
+        keys: VecDeque::new(),
+        bytes: VecDeque::new(),
+        size: None,
+    };
+    (
+        terminal,
+        ChannelRemote {
+            input: input_tx,
+            output: output_rx,
+        },
+    )
+}
+
+/// Editor side of an in-process terminal, created with [`channel`].
+///
+/// Keys arrive as [`KeyEvent`]s and need no decoding. Reading blocks until the
+/// GUI sends something, and reports [`Error::Eof`] once the [`ChannelRemote`]
+/// is dropped, e.g. when the window closes. Output fails with
+/// [`IoCause::Disconnected`] then.
+///
+/// The terminal reports the size of the last [`Input::Resize`], so completion
+/// lists and wrapped lines fit the widget.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct ChannelTerminal {
+    input: Receiver<Input>,
+    output: Sender<Output>,
+    /// Keys received while looking for resizes
+    keys: VecDeque<KeyEvent>,
+    /// A key encoded for [`read_byte`](Terminal::read_byte)
+    bytes: VecDeque<u8>,
+    size: Option<(u16, u16)>,
+}
+
+#[cfg(feature = "std")]
+impl ChannelTerminal {
+    fn receive(&mut self, wait: bool) -> Result<Option<Input>> {
+        if wait {
+            return self.input.recv().map(Some).map_err(|_| Error::Eof);
+        }
+        match self.input.try_recv() {
+            Ok(input) => Ok(Some(input)),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(Error::Eof),
+        }
+    }
+
+    /// Takes input until it yields a key, applying resizes on the way.
+    fn next_key(&mut self, wait: bool) -> Result<Option<KeyEvent>> {
+        if let Some(key) = self.keys.pop_front() {
+            return Ok(Some(key));
+        }
+        while let Some(input) = self.receive(wait)? {
+            match input {
+                Input::Key(key) => return Ok(Some(key)),
+                Input::Resize(columns, rows) => self.size = Some((columns, rows)),
+            }
+        }
+        Ok(None)
+    }
+
+    fn send(&self, output: Output) -> Result<()> {
+        self.output
+            .send(output)
+            .map_err(|_| IoCause::Disconnected.into())
+    }
+
+    fn next_byte(&mut self, wait: bool) -> Result<Option<u8>> {
+        if self.bytes.is_empty() {
+            if let Some(key) = self.next_key(wait)? {
+                self.bytes.extend(ansi::encode_key(key, &mut [0; 16]));
+            }
+        }
+        Ok(self.bytes.pop_front())
+    }
+}
+
+#[cfg(feature = "std")]
+impl Terminal for ChannelTerminal {
+    fn read_byte(&mut self) -> Result<u8> {
+        loop {
+            if let Some(byte) = self.next_byte(true)? {
+                return Ok(byte);
+            }
+        }
+    }
+
+    fn try_read_byte(&mut self) -> Result<Option<u8>> {
+        self.next_byte(false)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        let mut result = Ok(());
+        parse_output(data, |output| {
+            if result.is_ok() {
+                result = self.send(output);
+            }
+        });
+        result
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn cursor_left(&mut self) -> Result<()> {
+        self.send(Output::CursorLeft(1))
+    }
+
+    fn cursor_right(&mut self) -> Result<()> {
+        self.send(Output::CursorRight(1))
+    }
+
+    fn clear_eol(&mut self) -> Result<()> {
+        self.send(Output::ClearEol)
+    }
+
+    fn cursor_up(&mut self) -> Result<()> {
+        self.send(Output::CursorUp(1))
+    }
+
+    fn clear_to_end_of_screen(&mut self) -> Result<()> {
+        self.send(Output::ClearToEndOfScreen)
+    }
+
+    fn clear_screen(&mut self) -> Result<()> {
+        self.send(Output::ClearScreen)
+    }
+
+    fn bell(&mut self) -> Result<()> {
+        self.send(Output::Bell)
+    }
+
+    fn parse_key_event(&mut self) -> Result<KeyEvent> {
+        if !self.bytes.is_empty() {
+            // The rest of a key partly taken with read_byte
+            return ansi::parse_key_event(self);
+        }
+        loop {
+            if let Some(key) = self.next_key(true)? {
+                return Ok(key);
+            }
+        }
+    }
+
+    fn size(&mut self) -> Option<(u16, u16)> {
+        // A resize may be queued behind keys not read yet
+        while let Ok(Some(input)) = self.receive(false) {
+            match input {
+                Input::Key(key) => self.keys.push_back(key),
+                Input::Resize(columns, rows) => self.size = Some((columns, rows)),
+            }
+        }
+        self.size
+    }
+
+    fn show_status(&mut self, text: &[u8]) -> Result<()> {
+        self.send(Output::Status(String::from_utf8_lossy(text).into_owned()))
+    }
+
+    fn clear_status(&mut self) -> Result<()> {
+        self.send(Output::Status(String::new()))
+    }
+
+    fn capabilities(&self) -> Caps {
+        Caps::NONE.with_bell(true).with_size(self.size.is_some())
+    }
+}
+
+/// GUI side of an in-process terminal, created with [`channel`].
+///
+/// Sending fails with [`IoCause::Disconnected`] once the [`ChannelTerminal`]
+/// is dropped. Output is read without blocking, e.g. once per frame, with
+/// [`try_output`](Self::try_output); the editor thread can wake the GUI (egui's
+/// `request_repaint`, say) after each line if it needs to be prompt.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct ChannelRemote {
+    input: Sender<Input>,
+    output: Receiver<Output>,
+}
+
+#[cfg(feature = "std")]
+impl ChannelRemote {
+    /// Sends a key press.
+    pub fn send_key(&self, key: KeyEvent) -> Result<()> {
+        self.send(Input::Key(key))
+    }
+
+    /// Types `text`, with `\n` pressing Enter.
+    pub fn send_text(&self, text: &str) -> Result<()> {
+        text.chars().try_for_each(|c| {
+            self.send_key(if c == '\n' {
+                KeyEvent::Enter
+            } else {
+                KeyEvent::Normal(c)
+            })
+        })
+    }
+
+    /// Tells the editor the widget's size in columns and rows.
+    pub fn resize(&self, columns: u16, rows: u16) -> Result<()> {
+        self.send(Input::Resize(columns, rows))
+    }
+
+    /// Returns the next drawing command, if the editor sent one.
+    pub fn try_output(&self) -> Option<Output> {
+        self.output.try_recv().ok()
+    }
+
+    /// Returns the receiving end of the output, to block on or iterate.
+    pub fn output(&self) -> &Receiver<Output> {
+        &self.output
+    }
+
+    fn send(&self, input: Input) -> Result<()> {
+        self.input
+            .send(input)
+            .map_err(|_| IoCause::Disconnected.into())
+    }
+}
+
+#[cfg(feature = "embassy")]
+pub use self::asynch::AsyncChannelTerminal;
+
+#[cfg(feature = "embassy")]
+mod asynch {
+    use alloc::collections::VecDeque;
+    use core::task::{Context, Poll};
+
+    use embassy_sync::blocking_mutex::raw::RawMutex;
+    use embassy_sync::channel::{Receiver, Sender};
+
+    use super::{parse_output, Input, Output};
+    use crate::asynch::AsyncTerminal;
+    use crate::{ansi, Caps, Result};
+
+    /// In-process terminal for an [`AsyncLineEditor`](crate::asynch::AsyncLineEditor),
+    /// over `embassy_sync` channels.
+    ///
+    /// The GUI or another task sends [`Input`]s on one channel and receives
+    /// [`Output`]s on the other. Output is queued by the editor and sent on
+    /// flush, waiting for room in the channel. The async editor doesn't use
+    /// the terminal size, so [`Input::Resize`] is ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// use editline::asynch::AsyncLineEditor;
+    /// use editline::terminals::channel::{AsyncChannelTerminal, Input, Output};
+    /// use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+    /// use embassy_sync::channel::Channel;
+    ///
+    /// static KEYS: Channel<CriticalSectionRawMutex, Input, 8> = Channel::new();
+    /// static SCREEN: Channel<CriticalSectionRawMutex, Output, 32> = Channel::new();
+    ///
+    /// #[embassy_executor::task]
+    /// async fn console() {
+    ///     let mut terminal = AsyncChannelTerminal::new(KEYS.receiver(), SCREEN.sender());
+    ///     let mut editor = AsyncLineEditor::new(256, 20);
+    ///     while let Ok(line) = editor.read_line_with_prompt(&mut terminal, "> ").await {
+    ///         // process line
+    ///     }
+    /// }
+    /// ```
+    pub struct AsyncChannelTerminal<'a, M: RawMutex, const I: usize, const O: usize> {
+        input: Receiver<'a, M, Input, I>,
+        output: Sender<'a, M, Output, O>,
+        /// A key encoded for [`poll_read_byte`](AsyncTerminal::poll_read_byte)
+        bytes: VecDeque<u8>,
+        queued: VecDeque<Output>,
+    }
+
+    impl<'a, M: RawMutex, const I: usize, const O: usize> AsyncChannelTerminal<'a, M, I, O> {
+        /// Creates a terminal reading `input` and sending to `output`.
+        pub fn new(input: Receiver<'a, M, Input, I>, output: Sender<'a, M, Output, O>) -> Self {
+            AsyncChannelTerminal {
+                input,
+                output,
+                bytes: VecDeque::new(),
+                queued: VecDeque::new(),
+            }
+        }
+    }
+
+    impl<M: RawMutex, const I: usize, const O: usize> core::fmt::Debug
+        for AsyncChannelTerminal<'_, M, I, O>
+    {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            f.debug_struct("AsyncChannelTerminal")
+                .field("queued", &self.queued.len())
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl<M: RawMutex, const I: usize, const O: usize> AsyncTerminal
+        for AsyncChannelTerminal<'_, M, I, O>
+    {
+        fn poll_read_byte(&mut self, cx: &mut Context<'_>) -> Poll<Result<u8>> {
+            loop {
+                if let Some(byte) = self.bytes.pop_front() {
+                    return Poll::Ready(Ok(byte));
+                }
+                match self.input.poll_receive(cx) {
+                    Poll::Ready(Input::Key(key)) => {
+                        self.bytes.extend(ansi::encode_key(key, &mut [0; 16]))
+                    }
+                    Poll::Ready(Input::Resize(..)) => {}
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+        }
+
+        fn write(&mut self, data: &[u8]) -> Result<()> {
+            parse_output(data, |output| self.queued.push_back(output));
+            Ok(())
+        }
+
+        fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+            while !self.queued.is_empty() {
+                if self.output.poll_ready_to_send(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                if let Some(output) = self.queued.pop_front() {
+                    if let Err(embassy_sync::channel::TrySendError::Full(output)) =
+                        self.output.try_send(output)
+                    {
+                        self.queued.push_front(output);
+                    }
+                }
+            }
+            Poll::Ready(Ok(()))
+        }
+
+        fn capabilities(&self) -> Caps {
+            Caps::NONE.with_bell(true)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_output() {
+        let mut outputs = Vec::new();
+        parse_output(
+            b"> ab\x1b[D\x1b[3C\x1b[K\x07\x1b[0;32mok\x1b[0m\r\x1b[J\x1b[H\x1b[2J\x1b]0;t\x07x",
+            |o| outputs.push(o),
+        );
+        assert_eq!(
+            outputs,
+            [
+                Output::Text("> ab".into()),
+                Output::CursorLeft(1),
+                Output::CursorRight(3),
+                Output::ClearEol,
+                Output::Bell,
+                Output::Escape(b"\x1b[0;32m".to_vec()),
+                Output::Text("ok".into()),
+                Output::Escape(b"\x1b[0m".to_vec()),
+                Output::Text("\r".into()),
+                Output::ClearToEndOfScreen,
+                Output::ClearScreen,
+                Output::Escape(b"\x1b]0;t\x07".to_vec()),
+                Output::Text("x".into()),
+            ]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_channel_terminal() {
+        use crate::LineEditor;
+
+        let (mut terminal, remote) = channel();
+        remote.resize(100, 30).unwrap();
+        remote.send_text("ac").unwrap();
+        remote.send_key(KeyEvent::Left).unwrap();
+        remote.send_text("b\n").unwrap();
+        let mut editor = LineEditor::new(64, 10);
+        assert_eq!(
+            editor.read_line_with_prompt(&mut terminal, "> ").unwrap(),
+            "abc"
+        );
+        assert_eq!(terminal.size(), Some((100, 30)));
+
+        let outputs: Vec<Output> = remote.output().try_iter().collect();
+        assert_eq!(outputs.first(), Some(&Output::Text("> ".into())));
+        assert!(outputs.contains(&Output::CursorLeft(1)));
+        assert!(
+            !outputs.iter().any(|o| matches!(o, Output::Escape(_))),
+            "{:?}",
+            outputs
+        );
+
+        remote.send_key(KeyEvent::Ctrl('a')).unwrap();
+        assert_eq!(terminal.read_byte().unwrap(), 1);
+        drop(remote);
+        assert!(matches!(terminal.read_byte(), Err(Error::Eof)));
+        assert!(terminal.write(b"x").is_err());
+    }
+
+    #[cfg(feature = "embassy")]
+    #[test]
+    fn test_async_channel_terminal() {
+        use crate::asynch::AsyncLineEditor;
+        use alloc::boxed::Box;
+        use core::future::Future;
+        use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embassy_sync::channel::Channel;
+
+        fn block_on<F: Future>(future: F) -> F::Output {
+            fn clone(_: *const ()) -> RawWaker {
+                RawWaker::new(core::ptr::null(), &VTABLE)
+            }
+            fn noop(_: *const ()) {}
+            static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+            let waker = unsafe { Waker::from_raw(clone(core::ptr::null())) };
+            let mut cx = Context::from_waker(&waker);
+
+            let mut future = Box::pin(future);
+            loop {
+                if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                    return output;
+                }
+            }
+        }
+
+        let keys: &'static Channel<NoopRawMutex, Input, 8> = Box::leak(Box::new(Channel::new()));
+        let screen: &'static Channel<NoopRawMutex, Output, 64> =
+            Box::leak(Box::new(Channel::new()));
+        for key in [
+            crate::KeyEvent::Normal('o'),
+            crate::KeyEvent::Normal('k'),
+            crate::KeyEvent::Enter,
+        ] {
+            keys.try_send(Input::Key(key)).unwrap();
+        }
+        let mut terminal = AsyncChannelTerminal::new(keys.receiver(), screen.sender());
+        let mut editor = AsyncLineEditor::new(64, 10);
+        assert_eq!(
+            block_on(editor.read_line_with_prompt(&mut terminal, "> ")).unwrap(),
+            "ok"
+        );
+        let outputs: Vec<Output> = core::iter::from_fn(|| screen.try_receive().ok()).collect();
+        assert!(
+            outputs.contains(&Output::Text("> ".into())),
+            "{:?}",
+            outputs
+        );
+    }
+}
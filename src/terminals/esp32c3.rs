@@ -0,0 +1,249 @@
+//! ESP32-C3 USB-Serial-JTAG terminal implementation under `esp-hal`.
+//!
+//! The ESP32-C3 has a built-in USB-Serial-JTAG controller, so its USB port
+//! shows up on the host as a serial device with no USB stack in the firmware.
+//! [`UsbSerialJtagTerminal`] drives it with blocking reads and writes and needs
+//! neither `std` nor `alloc`. With the `async` feature,
+//! `AsyncUsbSerialJtagTerminal` drives it from an Embassy task, waking on the
+//! peripheral's receive interrupt instead of spinning.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! use editline::terminals::esp32c3::UsbSerialJtagTerminal;
+//! use editline::LineEditor;
+//! use esp_hal::usb_serial_jtag::UsbSerialJtag;
+//!
+//! let peripherals = esp_hal::init(esp_hal::Config::default());
+//! let mut terminal = UsbSerialJtagTerminal::new(UsbSerialJtag::new(peripherals.USB_DEVICE));
+//! let mut editor = LineEditor::new(256, 20);
+//!
+//! loop {
+//!     match editor.read_line(&mut terminal) {
+//!         Ok(line) => { /* process line */ }
+//!         Err(_) => continue,
+//!     }
+//! }
+//! ```
+
+use crate::{Error, IoCause, Result, Terminal};
+use esp_hal::usb_serial_jtag::UsbSerialJtag;
+use esp_hal::Blocking;
+
+/// USB-Serial-JTAG terminal implementation for the ESP32-C3.
+///
+/// Provides serial communication over the chip's USB port with support for
+/// ANSI escape sequences (arrow keys, cursor control). Output waits for room in
+/// the 64-byte transmit FIFO, which only drains while a host has the port open.
+pub struct UsbSerialJtagTerminal<'d> {
+    serial: UsbSerialJtag<'d, Blocking>,
+}
+
+impl<'d> UsbSerialJtagTerminal<'d> {
+    /// Creates a new terminal from the USB-Serial-JTAG driver.
+    pub fn new(serial: UsbSerialJtag<'d, Blocking>) -> Self {
+        Self { serial }
+    }
+
+    /// Returns the USB-Serial-JTAG driver.
+    pub fn into_inner(self) -> UsbSerialJtag<'d, Blocking> {
+        self.serial
+    }
+}
+
+impl core::fmt::Debug for UsbSerialJtagTerminal<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("UsbSerialJtagTerminal")
+            .finish_non_exhaustive()
+    }
+}
+
+impl Terminal for UsbSerialJtagTerminal<'_> {
+    fn read_byte(&mut self) -> Result<u8> {
+        nb::block!(self.serial.read_byte()).map_err(|_| Error::from(IoCause::Other))
+    }
+
+    fn try_read_byte(&mut self) -> Result<Option<u8>> {
+        match self.serial.read_byte() {
+            Ok(byte) => Ok(Some(byte)),
+            Err(nb::Error::WouldBlock) => Ok(None),
+            Err(nb::Error::Other(_)) => Err(IoCause::Other.into()),
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.serial
+            .write_bytes(data)
+            .map_err(|_| Error::from(IoCause::Other))
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.serial
+            .flush_tx()
+            .map_err(|_| Error::from(IoCause::Other))
+    }
+
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        // USB-Serial-JTAG is always in "raw" mode
+        Ok(())
+    }
+
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        // USB-Serial-JTAG is always in "raw" mode
+        Ok(())
+    }
+
+    fn cursor_left(&mut self) -> Result<()> {
+        self.write(b"\x1b[D")
+    }
+
+    fn cursor_right(&mut self) -> Result<()> {
+        self.write(b"\x1b[C")
+    }
+
+    fn clear_eol(&mut self) -> Result<()> {
+        self.write(b"\x1b[K")
+    }
+
+    fn capabilities(&self) -> crate::Caps {
+        crate::Caps::ANSI
+    }
+}
+
+#[cfg(feature = "async")]
+pub use self::asynch::AsyncUsbSerialJtagTerminal;
+
+#[cfg(feature = "async")]
+mod asynch {
+    use alloc::vec::Vec;
+    use core::future::Future;
+    use core::pin::Pin;
+    use core::task::{Context, Poll};
+
+    use esp_hal::usb_serial_jtag::UsbSerialJtag;
+    use esp_hal::Async;
+
+    use crate::asynch::AsyncTerminal;
+    use crate::{Error, IoCause, Result};
+
+    /// USB-Serial-JTAG terminal for an
+    /// [`AsyncLineEditor`](crate::asynch::AsyncLineEditor), e.g. on Embassy.
+    ///
+    /// Input wakes the task from the receive interrupt, so an idle console
+    /// costs nothing. Output is queued and written to the transmit FIFO as it
+    /// has room; while the FIFO is full the task yields and retries, as the
+    /// peripheral has no interrupt the driver exposes for that.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// use editline::asynch::AsyncLineEditor;
+    /// use editline::terminals::esp32c3::AsyncUsbSerialJtagTerminal;
+    /// use esp_hal::usb_serial_jtag::UsbSerialJtag;
+    ///
+    /// #[embassy_executor::task]
+    /// async fn console(usb: esp_hal::peripherals::USB_DEVICE) {
+    ///     let serial = UsbSerialJtag::new(usb).into_async();
+    ///     let mut terminal = AsyncUsbSerialJtagTerminal::new(serial);
+    ///     let mut editor = AsyncLineEditor::new(256, 20);
+    ///     while let Ok(line) = editor.read_line_with_prompt(&mut terminal, "> ").await {
+    ///         // process line
+    ///     }
+    /// }
+    /// ```
+    pub struct AsyncUsbSerialJtagTerminal<'d> {
+        serial: UsbSerialJtag<'d, Async>,
+        input: [u8; 64],
+        /// Read position and end of the buffered input
+        start: usize,
+        end: usize,
+        output: Vec<u8>,
+        /// How much of `output` has been written
+        written: usize,
+    }
+
+    impl<'d> AsyncUsbSerialJtagTerminal<'d> {
+        /// Creates a terminal from the USB-Serial-JTAG driver in async mode.
+        pub fn new(serial: UsbSerialJtag<'d, Async>) -> Self {
+            AsyncUsbSerialJtagTerminal {
+                serial,
+                input: [0; 64],
+                start: 0,
+                end: 0,
+                output: Vec::new(),
+                written: 0,
+            }
+        }
+
+        /// Returns the USB-Serial-JTAG driver, dropping any unread input and
+        /// unflushed output.
+        pub fn into_inner(self) -> UsbSerialJtag<'d, Async> {
+            self.serial
+        }
+    }
+
+    impl core::fmt::Debug for AsyncUsbSerialJtagTerminal<'_> {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            f.debug_struct("AsyncUsbSerialJtagTerminal")
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl AsyncTerminal for AsyncUsbSerialJtagTerminal<'_> {
+        fn poll_read_byte(&mut self, cx: &mut Context<'_>) -> Poll<Result<u8>> {
+            if self.start == self.end {
+                // The driver's read future only drains the FIFO or registers the
+                // waker with the receive interrupt, so polling a fresh one each
+                // time and dropping it loses nothing.
+                let mut read = embedded_io_async::Read::read(&mut self.serial, &mut self.input);
+                // SAFETY: `read` is a local that is never moved after being pinned.
+                match unsafe { Pin::new_unchecked(&mut read) }.poll(cx) {
+                    Poll::Ready(Ok(n)) => {
+                        self.start = 0;
+                        self.end = n;
+                    }
+                    Poll::Ready(Err(_)) => return Poll::Ready(Err(IoCause::Other.into())),
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+            if self.start == self.end {
+                return Poll::Ready(Err(Error::Eof));
+            }
+            let byte = self.input[self.start];
+            self.start += 1;
+            Poll::Ready(Ok(byte))
+        }
+
+        fn write(&mut self, data: &[u8]) -> Result<()> {
+            self.output.extend_from_slice(data);
+            Ok(())
+        }
+
+        fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+            while self.written < self.output.len() {
+                match self.serial.write_byte_nb(self.output[self.written]) {
+                    Ok(()) => self.written += 1,
+                    Err(nb::Error::WouldBlock) => {
+                        cx.waker().wake_by_ref();
+                        return Poll::Pending;
+                    }
+                    Err(nb::Error::Other(_)) => return Poll::Ready(Err(IoCause::Other.into())),
+                }
+            }
+            self.output.clear();
+            self.written = 0;
+            match self.serial.flush_tx_nb() {
+                Ok(()) => Poll::Ready(Ok(())),
+                Err(nb::Error::WouldBlock) => {
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                }
+                Err(nb::Error::Other(_)) => Poll::Ready(Err(IoCause::Other.into())),
+            }
+        }
+
+        fn capabilities(&self) -> crate::Caps {
+            crate::Caps::ANSI
+        }
+    }
+}
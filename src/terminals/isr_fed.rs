@@ -0,0 +1,165 @@
+//! [`Terminal`] fed by an interrupt handler through a lock-free queue
+//! (`heapless` feature).
+//!
+//! # Splitting the work with an ISR
+//!
+//! The receive interrupt only has to move bytes out of the UART before its FIFO
+//! overflows; editing can wait for the main loop. A single-producer,
+//! single-consumer [`heapless::spsc::Queue`] connects the two without a
+//! `Mutex` or critical section:
+//!
+//! - The ISR owns the [`Producer`](heapless::spsc::Producer) and calls
+//!   `enqueue` for each received byte. That's a bounded, lock-free operation;
+//!   when the queue is full the byte is dropped, so size it for the longest
+//!   burst you expect, e.g. a pasted line.
+//! - The main loop owns an [`IsrFedTerminal`] holding the
+//!   [`Consumer`](heapless::spsc::Consumer), and drives the editor, either
+//!   blocking in [`LineEditor::read_line`](crate::LineEditor::read_line) or
+//!   calling [`LineEditor::poll_read_line`](crate::LineEditor::poll_read_line)
+//!   on each pass.
+//!
+//! Only the producer crosses into interrupt context, and it is `Send` for `u8`.
+//! The [`LineEditor`](crate::LineEditor) and its hooks never do: they aren't
+//! `Send` and don't need to be, as they are created and used on the main loop
+//! only. The editor takes no locks and keeps no global state, so nothing the
+//! ISR does can block it or be blocked by it.
+//!
+//! Output is left to a closure, which can write to the UART directly or feed
+//! a second queue drained by the transmit interrupt.
+
+use heapless::spsc::Consumer;
+
+use crate::{Result, Terminal};
+
+/// Terminal reading bytes an interrupt handler pushed into a
+/// [`heapless::spsc`] queue, and writing through a closure.
+///
+/// See the [module documentation](self) for how the work is split.
+///
+/// # Examples
+///
+/// ```
+/// use editline::terminals::IsrFedTerminal;
+/// use editline::LineEditor;
+/// use heapless::spsc::Queue;
+///
+/// let mut queue: Queue<u8, 64> = Queue::new();
+/// let (mut producer, consumer) = queue.split();
+///
+/// // In the UART receive interrupt:
+/// for &byte in b"help\r" {
+///     let _ = producer.enqueue(byte);
+/// }
+///
+/// // In the main loop:
+/// let mut terminal = IsrFedTerminal::new(consumer, |data: &[u8]| {
+///     // uart.write_all(data)
+///     let _ = data;
+///     Ok(())
+/// });
+/// let mut editor = LineEditor::new(128, 8);
+/// assert_eq!(editor.read_line(&mut terminal)?, "help");
+/// # Ok::<(), editline::Error>(())
+/// ```
+pub struct IsrFedTerminal<'a, W, const N: usize> {
+    input: Consumer<'a, u8, N>,
+    output: W,
+}
+
+impl<'a, W: FnMut(&[u8]) -> Result<()>, const N: usize> IsrFedTerminal<'a, W, N> {
+    /// Creates a terminal reading from `input` and writing with `output`.
+    pub fn new(input: Consumer<'a, u8, N>, output: W) -> Self {
+        IsrFedTerminal { input, output }
+    }
+
+    /// Returns the consumer and the output closure.
+    pub fn into_inner(self) -> (Consumer<'a, u8, N>, W) {
+        (self.input, self.output)
+    }
+}
+
+impl<W: FnMut(&[u8]) -> Result<()>, const N: usize> Terminal for IsrFedTerminal<'_, W, N> {
+    /// Waits, spinning, until the ISR has queued a byte.
+    fn read_byte(&mut self) -> Result<u8> {
+        loop {
+            if let Some(byte) = self.input.dequeue() {
+                return Ok(byte);
+            }
+            core::hint::spin_loop();
+        }
+    }
+
+    fn try_read_byte(&mut self) -> Result<Option<u8>> {
+        Ok(self.input.dequeue())
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        (self.output)(data)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn cursor_left(&mut self) -> Result<()> {
+        self.write(b"\x1b[D")
+    }
+
+    fn cursor_right(&mut self) -> Result<()> {
+        self.write(b"\x1b[C")
+    }
+
+    fn clear_eol(&mut self) -> Result<()> {
+        self.write(b"\x1b[K")
+    }
+
+    fn capabilities(&self) -> crate::Caps {
+        crate::Caps::ANSI
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LineEditor;
+    use alloc::vec::Vec;
+    use core::task::Poll;
+    use heapless::spsc::{Producer, Queue};
+
+    fn assert_send<T: Send>() {}
+
+    #[test]
+    fn test_isr_fed_terminal() {
+        // What the ISR holds, and the terminal itself, can move between contexts
+        assert_send::<Producer<'static, u8, 16>>();
+        assert_send::<IsrFedTerminal<'static, fn(&[u8]) -> Result<()>, 16>>();
+
+        let mut queue: Queue<u8, 16> = Queue::new();
+        let (mut producer, consumer) = queue.split();
+        let mut output = Vec::new();
+        let mut terminal = IsrFedTerminal::new(consumer, |data: &[u8]| {
+            output.extend_from_slice(data);
+            Ok(())
+        });
+        let mut editor = LineEditor::new(64, 4);
+
+        assert!(editor.poll_read_line(&mut terminal, "> ").is_pending());
+        for &byte in b"led on" {
+            producer.enqueue(byte).unwrap();
+        }
+        assert!(editor.poll_read_line(&mut terminal, "> ").is_pending());
+        producer.enqueue(b'\r').unwrap();
+        assert!(
+            matches!(editor.poll_read_line(&mut terminal, "> "), Poll::Ready(Ok(ref line)) if line == "led on")
+        );
+        assert!(output.starts_with(b"> l\x1b[Ke\x1b[K"));
+    }
+}
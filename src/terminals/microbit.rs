@@ -13,12 +13,15 @@
 //! let terminal = from_board(board);
 //! ```
 
-use core::ptr::addr_of_mut;
+use crate::{Error, IoCause, Result, Terminal};
 use core::fmt::Write as FmtWrite;
+use core::ptr::addr_of_mut;
 use core::result::Result::Ok;
-use embedded_io::Read as EmbeddedRead;
-pub use microbit::{Board, hal::uarte::{Baudrate, Parity, Uarte, UarteRx, UarteTx, Instance}};
-use crate::{Terminal, KeyEvent, Result, Error};
+use embedded_io::{Read as EmbeddedRead, ReadReady};
+pub use microbit::{
+    hal::uarte::{Baudrate, Instance, Parity, Uarte, UarteRx, UarteTx},
+    Board,
+};
 
 /// Transmit buffer for UART operations.
 ///
@@ -85,19 +88,41 @@ impl<T: Instance> UarteTerminal<T> {
     /// Returns an error if the UART read operation fails.
     fn read_byte_blocking(&mut self) -> Result<u8> {
         let mut buf = [0u8];
-        self.rx.read_exact(&mut buf).map_err(|_| Error::Io("UART read failed"))?;
+        self.rx
+            .read_exact(&mut buf)
+            .map_err(|_| Error::from(IoCause::Other))?;
         Ok(buf[0])
     }
 }
 
+impl<T: Instance> core::fmt::Debug for UarteTerminal<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("UarteTerminal").finish_non_exhaustive()
+    }
+}
+
 impl<T: Instance> Terminal for UarteTerminal<T> {
     fn read_byte(&mut self) -> Result<u8> {
         self.read_byte_blocking()
     }
 
+    fn try_read_byte(&mut self) -> Result<Option<u8>> {
+        // Starts a receive if none is pending, so the next poll can find a byte
+        if self
+            .rx
+            .read_ready()
+            .map_err(|_| Error::from(IoCause::Other))?
+        {
+            self.read_byte_blocking().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
     fn write(&mut self, data: &[u8]) -> Result<()> {
-        self.tx.write_str(core::str::from_utf8(data).map_err(|_| Error::InvalidUtf8)?)
-            .map_err(|_| Error::Io("UART write failed"))
+        self.tx
+            .write_str(core::str::from_utf8(data).map_err(|_| Error::InvalidUtf8)?)
+            .map_err(|_| Error::from(IoCause::Other))
     }
 
     fn flush(&mut self) -> Result<()> {
@@ -127,97 +152,8 @@ impl<T: Instance> Terminal for UarteTerminal<T> {
         self.write(b"\x1b[K")
     }
 
-    fn parse_key_event(&mut self) -> Result<KeyEvent> {
-        let c = self.read_byte_blocking()?;
-
-        // Enter/Return
-        if c == b'\r' || c == b'\n' {
-            return Ok(KeyEvent::Enter);
-        }
-
-        // Backspace
-        if c == 127 || c == 8 {
-            return Ok(KeyEvent::Backspace);
-        }
-
-        // ESC sequences
-        if c == 27 {
-            // Try to read next byte for escape sequence (non-blocking)
-            let mut buf = [0u8];
-            if self.rx.read(&mut buf).is_ok() {
-                let c2 = buf[0];
-
-                // Alt+Backspace
-                if c2 == 127 || c2 == 8 {
-                    return Ok(KeyEvent::AltBackspace);
-                }
-
-                // ESC[ sequences (ANSI)
-                if c2 == b'[' {
-                    if let Ok(c3) = self.read_byte_blocking() {
-                        match c3 {
-                            b'A' => return Ok(KeyEvent::Up),
-                            b'B' => return Ok(KeyEvent::Down),
-                            b'C' => return Ok(KeyEvent::Right),
-                            b'D' => return Ok(KeyEvent::Left),
-                            b'H' => return Ok(KeyEvent::Home),
-                            b'F' => return Ok(KeyEvent::End),
-                            b'3' => {
-                                if let Ok(c4) = self.read_byte_blocking() {
-                                    if c4 == b'~' {
-                                        return Ok(KeyEvent::Delete);
-                                    }
-                                }
-                            }
-                            // Extended sequences like ESC[1;5D (Ctrl+Left)
-                            b'1' => {
-                                if let Ok(semicolon) = self.read_byte_blocking() {
-                                    if semicolon == b';' {
-                                        if let Ok(modifier) = self.read_byte_blocking() {
-                                            if modifier == b'5' { // Ctrl modifier
-                                                if let Ok(final_byte) = self.read_byte_blocking() {
-                                                    match final_byte {
-                                                        b'D' => return Ok(KeyEvent::CtrlLeft),
-                                                        b'C' => return Ok(KeyEvent::CtrlRight),
-                                                        _ => {} // Unknown Ctrl+key combo, drain
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                                // If we get here, drain the rest of the sequence
-                                return Ok(KeyEvent::Normal('\0'));
-                            }
-                            // Unknown escape sequence - consume until we hit a letter or tilde
-                            _ => {
-                                let mut byte = c3;
-                                // Drain sequence: read until we get a letter (A-Z, a-z) or tilde
-                                while !byte.is_ascii_alphabetic() && byte != b'~' {
-                                    if let Ok(b) = self.read_byte_blocking() {
-                                        byte = b;
-                                    } else {
-                                        break;
-                                    }
-                                }
-                                // Return null to ignore this unknown sequence
-                                return Ok(KeyEvent::Normal('\0'));
-                            }
-                        }
-                    }
-                }
-            }
-            // If we got ESC but couldn't parse a valid sequence, ignore it
-            return Ok(KeyEvent::Normal('\0'));
-        }
-
-        // Normal printable character
-        if (32..127).contains(&c) {
-            return Ok(KeyEvent::Normal(c as char));
-        }
-
-        // Unknown/control character - treat as null
-        Ok(KeyEvent::Normal('\0'))
+    fn capabilities(&self) -> crate::Caps {
+        crate::Caps::ANSI
     }
 }
 
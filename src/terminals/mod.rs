@@ -7,6 +7,15 @@
 //! - **micro:bit v2**: `UarteTerminal` for UART-based serial communication
 //! - **Raspberry Pi Pico (RP2040 USB CDC)**: `UsbCdcTerminal` for USB CDC serial communication
 //! - **Raspberry Pi Pico 2 (RP2350 USB CDC)**: `UsbCdcTerminal` for USB CDC serial communication
+//! - **nRF52840 (USB CDC)**: `UsbCdcTerminal` for USB CDC serial communication, e.g. on the nRF52840 Dongle
+//! - **USB CDC**: `UsbCdcTerminal` on any `usb-device` bus, such as imxrt-hal's on the Teensy 4.x
+//! - **`embedded-hal-nb` UARTs**: `SerialTerminal`, e.g. for avr-hal's USARTs and imxrt-hal's LPUARTs, without `alloc`
+//! - **ESP32-C3 (esp-hal)**: `UsbSerialJtagTerminal` for the USB-Serial-JTAG port, with an async flavor for Embassy
+//! - **Async streams**: `AsyncReadWriteTerminal` over `futures-io` readers and writers
+//! - **Streams**: [`WriteAllTerminal`] over `std::io` readers and writers that may write partially
+//! - **Serial ports**: `SerialPortTerminal` on host serial ports through the `serialport` crate
+//! - **GUI embedding**: [`channel::ChannelTerminal`] exchanging keys and drawing commands with a widget over channels
+//! - **Interrupt-fed input**: `IsrFedTerminal` reading bytes an ISR pushed into a `heapless` queue
 //!
 //! Each implementation handles platform-specific details like raw mode setup,
 //! key event parsing, and cursor control.
@@ -15,7 +24,7 @@
 mod unix;
 
 #[cfg(all(unix, feature = "std"))]
-pub use unix::StdioTerminal;
+pub use unix::{restore_terminal, StdioTerminal};
 
 #[cfg(all(windows, feature = "std"))]
 mod windows;
@@ -29,14 +38,65 @@ pub mod microbit;
 #[cfg(feature = "microbit")]
 pub use microbit::UarteTerminal;
 
-#[cfg(feature = "rp_pico_usb")]
-pub mod rp_pico_usb;
+#[cfg(feature = "usb-cdc")]
+mod usb_cdc;
+
+#[cfg(feature = "usb-cdc")]
+pub use usb_cdc::UsbCdcTerminal;
 
 #[cfg(feature = "rp_pico_usb")]
-pub use rp_pico_usb::UsbCdcTerminal;
+pub mod rp_pico_usb;
 
 #[cfg(feature = "rp_pico2_usb")]
 pub mod rp_pico2_usb;
 
-#[cfg(feature = "rp_pico2_usb")]
-pub use rp_pico2_usb::UsbCdcTerminal;
+#[cfg(feature = "nrf52840_usb")]
+pub mod nrf52840_usb;
+
+#[cfg(feature = "nb-serial")]
+mod nb_serial;
+
+#[cfg(feature = "nb-serial")]
+pub use nb_serial::SerialTerminal;
+
+#[cfg(feature = "esp32c3")]
+pub mod esp32c3;
+
+#[cfg(feature = "esp32c3")]
+pub use esp32c3::UsbSerialJtagTerminal;
+
+#[cfg(all(feature = "esp32c3", feature = "async"))]
+pub use esp32c3::AsyncUsbSerialJtagTerminal;
+
+#[cfg(feature = "std")]
+mod write_all;
+
+#[cfg(feature = "std")]
+pub use write_all::WriteAllTerminal;
+
+#[cfg(all(feature = "std", feature = "serialport"))]
+mod serial_port;
+
+#[cfg(all(feature = "std", feature = "serialport"))]
+pub use serial_port::SerialPortTerminal;
+
+#[cfg(any(feature = "std", feature = "embassy"))]
+pub mod channel;
+
+#[cfg(feature = "std")]
+pub use channel::ChannelTerminal;
+
+#[cfg(feature = "embassy")]
+pub use channel::AsyncChannelTerminal;
+
+#[cfg(feature = "heapless")]
+pub mod isr_fed;
+
+#[cfg(feature = "heapless")]
+pub use isr_fed::IsrFedTerminal;
+
+#[cfg(feature = "async-io")]
+mod async_io;
+
+#[cfg(feature = "async-io")]
+pub use async_io::AsyncReadWriteTerminal;
@@ -0,0 +1,108 @@
+//! Serial terminal implementation over the `embedded-hal-nb` serial traits.
+//!
+//! [`SerialTerminal`] works with any UART driver implementing
+//! [`embedded_hal_nb::serial::Read`] and [`Write`](embedded_hal_nb::serial::Write),
+//! such as avr-hal's `Usart` (what `arduino_hal::default_serial!` returns) and
+//! imxrt-hal's `Lpuart` on the Teensy 4.x. It needs neither `std` nor `alloc`:
+//! paired with a [`StaticLineEditor`](crate::StaticLineEditor) it fits boards
+//! like the Arduino Uno, whose 2 KiB of RAM leave no room for a heap.
+
+use crate::{Error, IoCause, Result, Terminal};
+use embedded_hal_nb::nb;
+use embedded_hal_nb::serial::{Error as SerialError, ErrorKind, Read, Write};
+
+/// Serial terminal implementation for any `embedded-hal-nb` UART.
+///
+/// Reads and writes block until the UART is ready, except
+/// [`try_read_byte`](Terminal::try_read_byte), which returns `None` while no
+/// byte has arrived, for [`poll_read_line`](crate::LineEditor::poll_read_line)
+/// in a superloop. UARTs with a small receive
+/// FIFO, like the AVR USART's two bytes, can overrun while the editor redraws,
+/// e.g. during a pasted line; that is reported as [`IoCause::InvalidData`].
+///
+/// # Type Parameters
+///
+/// * `S` - The serial port type (e.g. `arduino_hal::Usart<..>` or
+///   `imxrt_hal::lpuart::Lpuart<..>`)
+pub struct SerialTerminal<S> {
+    serial: S,
+}
+
+impl<S: Read<u8> + Write<u8>> SerialTerminal<S> {
+    /// Creates a new terminal from a configured serial port.
+    pub fn new(serial: S) -> Self {
+        Self { serial }
+    }
+
+    /// Returns the serial port.
+    pub fn into_inner(self) -> S {
+        self.serial
+    }
+}
+
+impl<S> core::fmt::Debug for SerialTerminal<S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SerialTerminal").finish_non_exhaustive()
+    }
+}
+
+/// Maps a serial error to the editor's error type.
+fn serial_error<E: SerialError>(error: E) -> Error {
+    match error.kind() {
+        ErrorKind::Overrun | ErrorKind::FrameFormat | ErrorKind::Parity | ErrorKind::Noise => {
+            Error::from(IoCause::InvalidData)
+        }
+        _ => Error::from(IoCause::Other),
+    }
+}
+
+impl<S: Read<u8> + Write<u8>> Terminal for SerialTerminal<S> {
+    fn read_byte(&mut self) -> Result<u8> {
+        nb::block!(self.serial.read()).map_err(serial_error)
+    }
+
+    fn try_read_byte(&mut self) -> Result<Option<u8>> {
+        match self.serial.read() {
+            Ok(byte) => Ok(Some(byte)),
+            Err(nb::Error::WouldBlock) => Ok(None),
+            Err(nb::Error::Other(error)) => Err(serial_error(error)),
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        for &byte in data {
+            nb::block!(self.serial.write(byte)).map_err(serial_error)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        nb::block!(self.serial.flush()).map_err(serial_error)
+    }
+
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        // UART is always in "raw" mode
+        Ok(())
+    }
+
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        // UART is always in "raw" mode
+        Ok(())
+    }
+
+    fn cursor_left(&mut self) -> Result<()> {
+        self.write(b"\x1b[D")
+    }
+
+    fn cursor_right(&mut self) -> Result<()> {
+        self.write(b"\x1b[C")
+    }
+
+    fn clear_eol(&mut self) -> Result<()> {
+        self.write(b"\x1b[K")
+    }
+
+    fn capabilities(&self) -> crate::Caps {
+        crate::Caps::ANSI
+    }
+}
@@ -0,0 +1,22 @@
+//! nRF52840 USB CDC terminal implementation.
+//!
+//! This implementation provides a [`Terminal`](crate::Terminal) for nRF52840 boards
+//! such as the nRF52840 Dongle, using the chip's USBD peripheral (through
+//! `nrf-usbd`) for USB CDC serial communication.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use editline::terminals::nrf52840_usb::UsbCdcTerminal;
+//! use nrf52840_hal::{clocks::Clocks, pac, usbd::{UsbPeripheral, Usbd}};
+//! use usbd_serial::SerialPort;
+//!
+//! let periph = pac::Peripherals::take().unwrap();
+//! let clocks = Clocks::new(periph.CLOCK).enable_ext_hfosc();
+//! let usb_bus = Usbd::new(UsbPeripheral::new(periph.USBD, &clocks));
+//! let serial_port = SerialPort::new(&usb_bus);
+//! // Build `usb_device` with `UsbDeviceBuilder::new(&usb_bus, ..)`...
+//! let terminal = UsbCdcTerminal::new(usb_device, serial_port);
+//! ```
+
+pub use super::usb_cdc::UsbCdcTerminal;
@@ -0,0 +1,153 @@
+//! [`Terminal`] over a host serial port (`serialport` feature).
+
+use std::boxed::Box;
+use std::io::{ErrorKind, Read, Write};
+use std::time::Duration;
+
+use serialport::SerialPort;
+
+use crate::{Error, IoCause, Result, Terminal};
+
+/// How long a read waits before checking again; reads retry until a byte comes.
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Terminal on a serial port opened with the [`serialport`] crate, such as
+/// `/dev/ttyUSB0` or `COM3`.
+///
+/// The user sits at the other end of the line: a terminal emulator on a
+/// second machine or a console server, or a device whose serial console is
+/// taken over by a desktop tool. Ports are opened in raw mode by `serialport`,
+/// so raw mode needs nothing more here; cursor movement uses ANSI sequences.
+///
+/// Reads block until a byte arrives, retrying the port's read timeout, and
+/// [`try_read_byte`](Terminal::try_read_byte) only reads what is already
+/// buffered, so the terminal also works with
+/// [`LineEditor::poll_read_line`](crate::LineEditor::poll_read_line) in a tool
+/// that forwards device output between keystrokes.
+///
+/// # Examples
+///
+/// ```no_run
+/// use editline::terminals::SerialPortTerminal;
+/// use editline::LineEditor;
+///
+/// let mut terminal = SerialPortTerminal::open("/dev/ttyUSB0", 115200)?;
+/// let mut editor = LineEditor::new(1024, 50);
+/// let line = editor.read_line_with_prompt(&mut terminal, "> ")?;
+/// # Ok::<(), editline::Error>(())
+/// ```
+pub struct SerialPortTerminal {
+    port: Box<dyn SerialPort>,
+}
+
+impl SerialPortTerminal {
+    /// Opens `path` at `baud_rate` with 8 data bits, no parity and one stop bit.
+    ///
+    /// Use [`new`](Self::new) for other settings.
+    pub fn open(path: &str, baud_rate: u32) -> Result<Self> {
+        serialport::new(path, baud_rate)
+            .timeout(READ_TIMEOUT)
+            .open()
+            .map(Self::new)
+            .map_err(port_error)
+    }
+
+    /// Creates a terminal on a port the application opened itself.
+    pub fn new(port: Box<dyn SerialPort>) -> Self {
+        SerialPortTerminal { port }
+    }
+
+    /// Returns the port, e.g. to change its baud rate or control lines.
+    pub fn port_mut(&mut self) -> &mut dyn SerialPort {
+        &mut *self.port
+    }
+
+    /// Returns the port.
+    pub fn into_inner(self) -> Box<dyn SerialPort> {
+        self.port
+    }
+
+    fn read_one(&mut self) -> Result<Option<u8>> {
+        let mut byte = [0];
+        match self.port.read(&mut byte) {
+            Ok(0) => Ok(None),
+            Ok(_) => Ok(Some(byte[0])),
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    ErrorKind::TimedOut | ErrorKind::Interrupted | ErrorKind::WouldBlock
+                ) =>
+            {
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl core::fmt::Debug for SerialPortTerminal {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SerialPortTerminal")
+            .field("port", &self.port.name())
+            .finish()
+    }
+}
+
+/// Maps a `serialport` error to the editor's error type.
+fn port_error(error: serialport::Error) -> Error {
+    match error.kind() {
+        serialport::ErrorKind::NoDevice => IoCause::Disconnected.into(),
+        serialport::ErrorKind::Io(kind) => std::io::Error::from(kind).into(),
+        _ => IoCause::Other.into(),
+    }
+}
+
+impl Terminal for SerialPortTerminal {
+    fn read_byte(&mut self) -> Result<u8> {
+        loop {
+            if let Some(byte) = self.read_one()? {
+                return Ok(byte);
+            }
+        }
+    }
+
+    fn try_read_byte(&mut self) -> Result<Option<u8>> {
+        if self.port.bytes_to_read().map_err(port_error)? == 0 {
+            return Ok(None);
+        }
+        self.read_one()
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.port.write_all(data).map_err(Error::from)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.port.flush().map_err(Error::from)
+    }
+
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        // serialport opens ports in raw mode
+        Ok(())
+    }
+
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn cursor_left(&mut self) -> Result<()> {
+        self.write(b"\x1b[D")
+    }
+
+    fn cursor_right(&mut self) -> Result<()> {
+        self.write(b"\x1b[C")
+    }
+
+    fn clear_eol(&mut self) -> Result<()> {
+        self.write(b"\x1b[K")
+    }
+
+    fn capabilities(&self) -> crate::Caps {
+        crate::Caps::ANSI
+    }
+}
@@ -4,8 +4,50 @@
 //! input without echo) and ANSI escape sequences for cursor control.
 
 use crate::{KeyEvent, Terminal};
-use std::io::{self, Read, Write};
+use std::io::{self, Write};
+use std::mem::MaybeUninit;
 use std::os::unix::io::AsRawFd;
+use std::ptr::{addr_of, addr_of_mut};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Settings of stdin before raw mode, for [`restore_terminal`]; only valid
+/// while `SIGNAL_RESTORE` is set
+static mut SIGNAL_TERMIOS: MaybeUninit<libc::termios> = MaybeUninit::uninit();
+static SIGNAL_RESTORE: AtomicBool = AtomicBool::new(false);
+
+/// Size of the buffer input is read into, enough for most pastes in one read
+const INPUT_BUFFER: usize = 1024;
+
+/// Signals that end a process by default and after which the terminal is restored
+const FATAL_SIGNALS: [i32; 4] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP, libc::SIGQUIT];
+
+/// Takes stdin out of raw mode if a [`StdioTerminal`] left it there.
+///
+/// Only async-signal-safe calls are made, so an application's own `SIGTERM`
+/// or `SIGINT` handler can call this before exiting, to keep a killed REPL
+/// from leaving the shell in raw mode. Outside raw mode it does nothing.
+/// [`StdioTerminal::restore_on_signals`] installs handlers that do this.
+pub fn restore_terminal() {
+    if SIGNAL_RESTORE.swap(false, Ordering::SeqCst) {
+        unsafe {
+            libc::tcsetattr(
+                libc::STDIN_FILENO,
+                libc::TCSANOW,
+                addr_of!(SIGNAL_TERMIOS).cast(),
+            );
+        }
+    }
+}
+
+/// Restores the terminal, then lets the signal take its default course.
+extern "C" fn restore_and_raise(signal: i32) {
+    restore_terminal();
+    // SA_RESETHAND has put back the default action
+    unsafe {
+        libc::raise(signal);
+    }
+}
 
 /// Unix terminal using stdin/stdout with termios.
 ///
@@ -13,6 +55,13 @@ use std::os::unix::io::AsRawFd;
 /// (Linux, macOS, BSD) using standard input/output with termios for raw mode
 /// and ANSI escape sequences for cursor control.
 ///
+/// Input is read from the descriptor as many bytes at a time as are
+/// available, so a paste or an escape sequence takes one `read(2)`, and
+/// nothing waits in `io::Stdin`'s own buffer. Bytes read ahead stay with the
+/// terminal for the next key, also across lines; an application reading
+/// stdin itself between lines won't see them. While input is waiting, the
+/// cursor position isn't queried, so the reply can't arrive among it.
+///
 /// # Examples
 ///
 /// ```no_run
@@ -24,6 +73,17 @@ pub struct StdioTerminal {
     stdin: io::Stdin,
     stdout: io::Stdout,
     original_termios: Option<libc::termios>,
+    flow_control: bool,
+    /// How long to wait for the rest of an escape sequence, in tenths of a second
+    escape_timeout: Option<u8>,
+    dumb: bool,
+    /// Bytes read but not yet consumed, `input[input_pos..input_len]`
+    input: [u8; INPUT_BUFFER],
+    input_pos: usize,
+    input_len: usize,
+    /// Bytes that arrived ahead of a reply to a query, in reverse order; read
+    /// before `input`
+    typed_ahead: Vec<u8>,
 }
 
 impl StdioTerminal {
@@ -36,13 +96,188 @@ impl StdioTerminal {
             stdin: io::stdin(),
             stdout: io::stdout(),
             original_termios: None,
+            flow_control: false,
+            escape_timeout: None,
+            dumb: std::env::var("TERM").map_or(false, |term| term == "dumb"),
+            input: [0; INPUT_BUFFER],
+            input_pos: 0,
+            input_len: 0,
+            typed_ahead: Vec::new(),
         }
     }
 
+    /// Keeps XON/XOFF software flow control (Ctrl+S / Ctrl+Q) enabled in raw mode.
+    ///
+    /// By default raw mode disables it so that Ctrl+S reaches the editor for forward
+    /// history search. Enable this for real serial links that rely on software flow
+    /// control; takes effect on the next [`Terminal::enter_raw_mode`](crate::Terminal::enter_raw_mode).
+    pub fn set_flow_control(&mut self, enabled: bool) {
+        self.flow_control = enabled;
+    }
+
+    /// Installs handlers for `SIGINT`, `SIGTERM`, `SIGHUP` and `SIGQUIT` that
+    /// [restore the terminal](restore_terminal) before the signal ends the process.
+    ///
+    /// Raw mode turns off the signals Ctrl+C and Ctrl+\\ would send, but the
+    /// process can still be killed, e.g. when the SSH session drops. Signals
+    /// that already have a handler or are ignored are left alone; applications
+    /// with their own handlers can call [`restore_terminal`] from them instead.
+    pub fn restore_on_signals(&mut self) -> crate::Result<()> {
+        for &signal in &FATAL_SIGNALS {
+            unsafe {
+                let mut old: libc::sigaction = std::mem::zeroed();
+                if libc::sigaction(signal, std::ptr::null(), &mut old) != 0 {
+                    return Err(io::Error::last_os_error().into());
+                }
+                if old.sa_sigaction != libc::SIG_DFL {
+                    continue;
+                }
+
+                let mut action: libc::sigaction = std::mem::zeroed();
+                action.sa_sigaction = restore_and_raise as extern "C" fn(i32) as libc::sighandler_t;
+                action.sa_flags = libc::SA_RESETHAND;
+                libc::sigemptyset(&mut action.sa_mask);
+                if libc::sigaction(signal, &action, std::ptr::null_mut()) != 0 {
+                    return Err(io::Error::last_os_error().into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets how long to wait for the next byte of an escape sequence.
+    ///
+    /// By default the editor waits for it indefinitely, so ESC alone only
+    /// counts once a second ESC follows. With a timeout, a sequence that stops
+    /// arriving ends early and a lone ESC is reported as
+    /// [`KeyEvent::Escape`]. The timeout is carried out with termios `VTIME`,
+    /// so it is rounded up to tenths of a second, at most 25.5 seconds. Over
+    /// high-latency links such as BLE or networked serial bridges, choose it
+    /// long enough that arrow keys don't split into ESC and letters.
+    pub fn set_escape_timeout(&mut self, timeout: Option<Duration>) {
+        self.escape_timeout = timeout.map(|timeout| {
+            let tenths = (timeout.as_millis() + 99) / 100;
+            tenths.clamp(1, u128::from(u8::MAX)) as u8
+        });
+    }
+
+    /// Reads a byte, or `None` if none arrives within `tenths` of a second.
+    fn read_byte_within(&mut self, tenths: u8) -> crate::Result<Option<u8>> {
+        if let Some(byte) = self.buffered_byte() {
+            return Ok(Some(byte));
+        }
+        let fd = self.stdin.as_raw_fd();
+        let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(fd, &mut termios) } != 0 {
+            // Not a terminal, e.g. a pipe, where VTIME doesn't apply
+            if !self.stdin_ready(i32::from(tenths) * 100)? {
+                return Ok(None);
+            }
+            return self.read_byte_internal().map(Some);
+        }
+
+        // Return when nothing arrives within the timeout
+        let saved = termios;
+        termios.c_cc[libc::VMIN] = 0;
+        termios.c_cc[libc::VTIME] = tenths;
+        if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &termios) } != 0 {
+            return Err(crate::Error::from(io::Error::last_os_error()));
+        }
+
+        let result = self.fill_input();
+
+        unsafe {
+            libc::tcsetattr(fd, libc::TCSANOW, &saved);
+        }
+        result?;
+        Ok(self.buffered_byte())
+    }
+
     fn read_byte_internal(&mut self) -> crate::Result<u8> {
-        let mut buf = [0u8; 1];
-        self.stdin.read_exact(&mut buf).map_err(crate::Error::from)?;
-        Ok(buf[0])
+        if let Some(byte) = self.buffered_byte() {
+            return Ok(byte);
+        }
+        self.fill_input()?;
+        self.buffered_byte().ok_or(crate::Error::Eof)
+    }
+
+    /// Returns `true` if input is waiting, read ahead or not yet read.
+    fn input_waiting(&self) -> bool {
+        if !self.typed_ahead.is_empty() || self.input_pos < self.input_len {
+            return true;
+        }
+        self.stdin_ready(0).unwrap_or(false)
+    }
+
+    /// Waits up to `millis` for stdin to become readable, which includes
+    /// reaching end of file.
+    fn stdin_ready(&self, millis: i32) -> crate::Result<bool> {
+        let mut poll = libc::pollfd {
+            fd: self.stdin.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        match unsafe { libc::poll(&mut poll, 1, millis) } {
+            n if n >= 0 => Ok(n > 0),
+            _ => {
+                let error = io::Error::last_os_error();
+                if error.kind() == io::ErrorKind::Interrupted {
+                    Ok(false)
+                } else {
+                    Err(error.into())
+                }
+            }
+        }
+    }
+
+    /// Takes the next byte already read, if any.
+    fn buffered_byte(&mut self) -> Option<u8> {
+        if let Some(byte) = self.typed_ahead.pop() {
+            return Some(byte);
+        }
+        if self.input_pos == self.input_len {
+            return None;
+        }
+        self.input_pos += 1;
+        Some(self.input[self.input_pos - 1])
+    }
+
+    /// Reads whatever input is available into the empty buffer, leaving it
+    /// empty at end of file or when a `VTIME` timeout expires.
+    ///
+    /// The descriptor is read directly: `io::Stdin` keeps a buffer of its own
+    /// that `poll` can't see, which would hide input from [`Self::stdin_ready`].
+    fn fill_input(&mut self) -> crate::Result<()> {
+        let fd = self.stdin.as_raw_fd();
+        let len = loop {
+            let n = unsafe { libc::read(fd, self.input.as_mut_ptr().cast(), self.input.len()) };
+            if n >= 0 {
+                break n as usize;
+            }
+            let error = io::Error::last_os_error();
+            if error.kind() != io::ErrorKind::Interrupted {
+                return Err(error.into());
+            }
+        };
+        self.input_pos = 0;
+        self.input_len = len;
+        Ok(())
+    }
+}
+
+// termios only implements Debug with libc's extra_traits feature
+impl std::fmt::Debug for StdioTerminal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StdioTerminal")
+            .field("raw_mode", &self.original_termios.is_some())
+            .field("flow_control", &self.flow_control)
+            .field("escape_timeout", &self.escape_timeout)
+            .field("dumb", &self.dumb)
+            .field(
+                "buffered",
+                &(self.typed_ahead.len() + self.input_len - self.input_pos),
+            )
+            .finish()
     }
 }
 
@@ -75,8 +310,15 @@ impl Terminal for StdioTerminal {
                 return Err(io::Error::last_os_error().into());
             }
 
-            // Save original settings
+            // Save original settings, also for restore_terminal
             self.original_termios = Some(termios);
+            if fd == libc::STDIN_FILENO {
+                SIGNAL_RESTORE.store(false, Ordering::SeqCst);
+                addr_of_mut!(SIGNAL_TERMIOS)
+                    .cast::<libc::termios>()
+                    .write(termios);
+                SIGNAL_RESTORE.store(true, Ordering::SeqCst);
+            }
 
             // Disable canonical mode, echo, and signal generation
             // ICANON: disable line buffering (read char-by-char)
@@ -84,6 +326,11 @@ impl Terminal for StdioTerminal {
             // ISIG: disable signal generation (Ctrl-C, Ctrl-Z, etc.)
             termios.c_lflag &= !(libc::ECHO | libc::ICANON | libc::ISIG);
 
+            // IXON: XON/XOFF flow control would swallow Ctrl+S and Ctrl+Q
+            if !self.flow_control {
+                termios.c_iflag &= !libc::IXON;
+            }
+
             // Set minimum characters and timeout
             termios.c_cc[libc::VMIN] = 1;
             termios.c_cc[libc::VTIME] = 0;
@@ -93,12 +340,16 @@ impl Terminal for StdioTerminal {
             }
         }
 
+        debug!("entered raw mode");
         Ok(())
     }
 
     fn exit_raw_mode(&mut self) -> crate::Result<()> {
         if let Some(original) = self.original_termios {
             let fd = self.stdin.as_raw_fd();
+            if fd == libc::STDIN_FILENO {
+                SIGNAL_RESTORE.store(false, Ordering::SeqCst);
+            }
 
             unsafe {
                 if libc::tcsetattr(fd, libc::TCSAFLUSH, &original) != 0 {
@@ -107,6 +358,7 @@ impl Terminal for StdioTerminal {
             }
 
             self.original_termios = None;
+            debug!("left raw mode");
         }
 
         Ok(())
@@ -124,110 +376,83 @@ impl Terminal for StdioTerminal {
         self.write(b"\x1b[K")
     }
 
-    fn parse_key_event(&mut self) -> crate::Result<KeyEvent> {
-        let c = self.read_byte_internal()?;
+    fn size(&mut self) -> Option<(u16, u16)> {
+        let fd = self.stdout.as_raw_fd();
 
-        // Enter/Return
-        if c == b'\r' || c == b'\n' {
-            return Ok(KeyEvent::Enter);
+        unsafe {
+            let mut ws: libc::winsize = std::mem::zeroed();
+            if libc::ioctl(fd, libc::TIOCGWINSZ, &mut ws) != 0 || ws.ws_col == 0 {
+                return None;
+            }
+            Some((ws.ws_col, ws.ws_row))
         }
+    }
 
-        // Ctrl-D (EOT - End of Transmission)
-        // Standard Unix convention: EOF signal, should exit REPL
-        if c == 4 {
-            return Err(crate::Error::Eof);
-        }
+    fn try_read_byte(&mut self) -> crate::Result<Option<u8>> {
+        self.read_byte_within(0)
+    }
 
-        // Ctrl-C (ETX - End of Text / Interrupt)
-        // Standard Unix convention: interrupt signal, should cancel current line
-        if c == 3 {
-            return Err(crate::Error::Interrupted);
+    fn parse_key_event(&mut self) -> crate::Result<KeyEvent> {
+        match self.escape_timeout {
+            Some(tenths) if self.original_termios.is_some() => {
+                crate::ansi::parse_key_event_within(self, |terminal| {
+                    terminal.read_byte_within(tenths)
+                })
+            }
+            _ => crate::ansi::parse_key_event(self),
         }
+    }
 
-        // Backspace
-        if c == 127 || c == 8 {
-            return Ok(KeyEvent::Backspace);
+    fn get_cursor_position(&mut self) -> Option<(u16, u16)> {
+        // Only ask in raw mode, where the reply isn't echoed or line-buffered,
+        // and when it goes to the terminal rather than a pipe or file
+        if self.dumb
+            || self.original_termios.is_none()
+            || unsafe { libc::isatty(self.stdout.as_raw_fd()) } != 1
+        {
+            return None;
+        }
+        // Keys already waiting, such as the rest of a paste, go to the editor
+        // first; the prompt is then assumed to start at column 0
+        if self.input_waiting() {
+            return None;
         }
 
-        // ESC sequences
-        if c == 27 {
-            // Read next byte
-            let c2 = self.read_byte_internal()?;
-
-            // Alt+Backspace
-            if c2 == 127 || c2 == 8 {
-                return Ok(KeyEvent::AltBackspace);
-            }
-
-            // ESC[ sequences (ANSI)
-            if c2 == b'[' {
-                let c3 = self.read_byte_internal()?;
-
-                match c3 {
-                    b'A' => return Ok(KeyEvent::Up),
-                    b'B' => return Ok(KeyEvent::Down),
-                    b'C' => return Ok(KeyEvent::Right),
-                    b'D' => return Ok(KeyEvent::Left),
-                    b'H' => return Ok(KeyEvent::Home),
-                    b'F' => return Ok(KeyEvent::End),
-                    b'1' => {
-                        let c4 = self.read_byte_internal()?;
-                        if c4 == b'~' {
-                            return Ok(KeyEvent::Home);
-                        } else if c4 == b';' {
-                            // Ctrl+key sequences
-                            let c5 = self.read_byte_internal()?;
-                            if c5 == b'5' {
-                                let c6 = self.read_byte_internal()?;
-                                match c6 {
-                                    b'C' => return Ok(KeyEvent::CtrlRight),
-                                    b'D' => return Ok(KeyEvent::CtrlLeft),
-                                    _ => {}
-                                }
-                            }
-                        }
-                    }
-                    b'3' => {
-                        let c4 = self.read_byte_internal()?;
-                        if c4 == b'~' {
-                            return Ok(KeyEvent::Delete);
-                        } else if c4 == b';' {
-                            let c5 = self.read_byte_internal()?;
-                            if c5 == b'5' {
-                                let c6 = self.read_byte_internal()?;
-                                if c6 == b'~' {
-                                    return Ok(KeyEvent::CtrlDelete);
-                                }
-                            }
-                        }
-                    }
-                    b'4' => {
-                        let c4 = self.read_byte_internal()?;
-                        if c4 == b'~' {
-                            return Ok(KeyEvent::End);
-                        }
-                    }
-                    _ => {}
-                }
-            }
+        let fd = self.stdin.as_raw_fd();
+        let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(fd, &mut termios) } != 0 {
+            return None;
+        }
 
-            // Unknown escape sequence - treat as normal char
-            if (32..127).contains(&c2) {
-                if let Ok(ch) = std::str::from_utf8(&[c2]) {
-                    if let Some(ch) = ch.chars().next() {
-                        return Ok(KeyEvent::Normal(ch));
-                    }
-                }
-            }
+        // Give up after 200ms in case the terminal never replies
+        let saved = termios;
+        termios.c_cc[libc::VMIN] = 0;
+        termios.c_cc[libc::VTIME] = 2;
+        if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &termios) } != 0 {
+            return None;
         }
 
-        // Normal printable character
-        if (32..127).contains(&c) {
-            return Ok(KeyEvent::Normal(c as char));
+        // Keep keys typed ahead of the reply for the editor
+        let mut typed_ahead = Vec::new();
+        let position = crate::ansi::query_cursor_position(self, |byte| typed_ahead.push(byte));
+        self.typed_ahead.extend(typed_ahead.iter().rev());
+
+        unsafe {
+            libc::tcsetattr(fd, libc::TCSANOW, &saved);
         }
+        position.ok()
+    }
 
-        // Unknown/control character - ignore
-        Ok(KeyEvent::Normal('\0'))
+    /// Raw mode leaves output processing on, so `\n` starts a new line.
+    /// TERM=dumb terminals (e.g. Emacs shell buffers) can't address the next
+    /// row, which also keeps the status line off.
+    fn capabilities(&self) -> crate::Caps {
+        let caps = if self.dumb {
+            crate::Caps::NONE.with_size(true).with_bell(true)
+        } else {
+            crate::Caps::ANSI.with_size(true)
+        };
+        caps.with_crlf(false)
     }
 }
 
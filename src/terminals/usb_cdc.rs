@@ -0,0 +1,208 @@
+//! USB CDC terminal implementation shared by the boards with a `usb-device` driver.
+//!
+//! [`UsbCdcTerminal`] works with any [`UsbBus`](usb_device::bus::UsbBus), so the
+//! board modules (`rp_pico_usb`, `rp_pico2_usb`, `nrf52840_usb`) only show how
+//! to set up their bus. Other boards, such as the Teensy 4.x with imxrt-hal's
+//! `usbd::BusAdapter`, use it directly with the `usb-cdc` feature.
+
+use crate::{IoCause, Result, Terminal};
+use usb_device::prelude::*;
+use usbd_serial::SerialPort;
+
+/// USB CDC terminal implementation for any `usb-device` bus.
+///
+/// Provides serial communication over USB CDC with support for ANSI escape
+/// sequences (arrow keys, cursor control). The USB device appears as a
+/// virtual COM port on the host computer.
+///
+/// # Type Parameters
+///
+/// * `B` - The USB bus type (e.g. `rp2040_hal::usb::UsbBus` or `nrf52840_hal::usbd::Usbd<..>`)
+pub struct UsbCdcTerminal<'a, B: usb_device::bus::UsbBus> {
+    usb_device: UsbDevice<'a, B>,
+    serial_port: SerialPort<'a, B>,
+    read_buffer: [u8; 64],
+    read_pos: usize,
+    read_len: usize,
+}
+
+impl<'a, B: usb_device::bus::UsbBus> UsbCdcTerminal<'a, B> {
+    /// Creates a new USB CDC terminal.
+    ///
+    /// # Arguments
+    ///
+    /// * `usb_device` - The configured USB device
+    /// * `serial_port` - The USB CDC serial port
+    pub fn new(usb_device: UsbDevice<'a, B>, serial_port: SerialPort<'a, B>) -> Self {
+        Self {
+            usb_device,
+            serial_port,
+            read_buffer: [0u8; 64],
+            read_pos: 0,
+            read_len: 0,
+        }
+    }
+
+    /// Waits for a terminal connection (DTR signal) with a delay.
+    ///
+    /// This blocks until the host terminal program opens the serial port,
+    /// which sets the DTR (Data Terminal Ready) signal. This matches the behavior
+    /// of the C SDK's `stdio_usb_connected()` function.
+    ///
+    /// After DTR is detected, adds a 200ms delay to ensure the terminal is fully
+    /// ready before data transmission begins, preventing garbled output.
+    ///
+    /// # Arguments
+    ///
+    /// * `timer` - A timer instance that implements CountDown trait
+    #[cfg(feature = "embedded-hal")]
+    pub fn wait_for_connection(&mut self, timer: &mut impl embedded_hal::delay::DelayNs) {
+        // Wait for DTR (Data Terminal Ready) - set when picocom opens the port
+        loop {
+            self.usb_device.poll(&mut [&mut self.serial_port]);
+            if self.serial_port.dtr() {
+                break;
+            }
+        }
+
+        // Add 200ms delay after DTR detected for terminal stability
+        // We'll do this in smaller chunks while polling USB
+        for _ in 0..20 {
+            self.usb_device.poll(&mut [&mut self.serial_port]);
+            timer.delay_ms(10);
+        }
+    }
+
+    /// Polls the USB device and reads available data into the internal buffer.
+    fn poll_usb(&mut self) {
+        if self.usb_device.poll(&mut [&mut self.serial_port]) {
+            // Try to read into buffer if we've consumed all previous data
+            if self.read_pos >= self.read_len {
+                match self.serial_port.read(&mut self.read_buffer) {
+                    Ok(count) if count > 0 => {
+                        self.read_len = count;
+                        self.read_pos = 0;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Reads a single byte from the USB serial port, blocking until available.
+    fn read_byte_blocking(&mut self) -> Result<u8> {
+        loop {
+            // If we have buffered data, return it
+            if self.read_pos < self.read_len {
+                let byte = self.read_buffer[self.read_pos];
+                self.read_pos += 1;
+                return Ok(byte);
+            }
+
+            // Otherwise poll USB until we get data
+            self.poll_usb();
+        }
+    }
+
+    /// Waits for USB to be configured and ready.
+    ///
+    /// This method blocks until the USB device reaches the `Configured` state.
+    /// Note: This happens during USB enumeration when the device is plugged in,
+    /// NOT when a terminal program connects to it.
+    pub fn wait_until_configured(&mut self) {
+        // Wait for USB to be configured
+        loop {
+            if self.usb_device.poll(&mut [&mut self.serial_port])
+                && self.usb_device.state() == UsbDeviceState::Configured
+            {
+                break;
+            }
+        }
+    }
+}
+
+impl<'a, B: usb_device::bus::UsbBus> core::fmt::Debug for UsbCdcTerminal<'a, B> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("UsbCdcTerminal").finish_non_exhaustive()
+    }
+}
+
+impl<'a, B: usb_device::bus::UsbBus> Terminal for UsbCdcTerminal<'a, B> {
+    fn read_byte(&mut self) -> Result<u8> {
+        self.read_byte_blocking()
+    }
+
+    fn try_read_byte(&mut self) -> Result<Option<u8>> {
+        if self.read_pos >= self.read_len {
+            self.poll_usb();
+        }
+        if self.read_pos < self.read_len {
+            let byte = self.read_buffer[self.read_pos];
+            self.read_pos += 1;
+            return Ok(Some(byte));
+        }
+        Ok(None)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        let mut written = 0;
+        while written < data.len() {
+            // Poll USB to keep it responsive
+            self.poll_usb();
+
+            // Try to write remaining data
+            match self.serial_port.write(&data[written..]) {
+                Ok(count) => {
+                    written += count;
+                }
+                Err(UsbError::WouldBlock) => {
+                    // Buffer full, keep polling until space available
+                    continue;
+                }
+                Err(UsbError::InvalidState) => {
+                    // Not configured: the host has gone away
+                    return Err(IoCause::Disconnected.into());
+                }
+                Err(_) => {
+                    return Err(IoCause::Other.into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        let _ = self.serial_port.flush();
+        // Poll USB several times to ensure data is transmitted
+        for _ in 0..10 {
+            self.poll_usb();
+        }
+        Ok(())
+    }
+
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        // USB CDC is always in "raw" mode
+        Ok(())
+    }
+
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        // USB CDC is always in "raw" mode
+        Ok(())
+    }
+
+    fn cursor_left(&mut self) -> Result<()> {
+        self.write(b"\x1b[D")
+    }
+
+    fn cursor_right(&mut self) -> Result<()> {
+        self.write(b"\x1b[C")
+    }
+
+    fn clear_eol(&mut self) -> Result<()> {
+        self.write(b"\x1b[K")
+    }
+
+    fn capabilities(&self) -> crate::Caps {
+        crate::Caps::ANSI
+    }
+}
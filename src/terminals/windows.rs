@@ -2,28 +2,59 @@
 //!
 //! This implementation uses Windows Console API functions to enable raw mode
 //! (disabling line input and echo) and control the cursor position directly.
+//! When stdin or stdout isn't a console, e.g. under mintty or with redirected
+//! output, it talks ANSI over the standard streams instead.
 
 use crate::{KeyEvent, Terminal};
+use std::collections::VecDeque;
 use std::io::{self, Write};
-use winapi::um::consoleapi::{GetConsoleMode, ReadConsoleInputW, SetConsoleMode, SetConsoleCtrlHandler, WriteConsoleA};
-use winapi::um::handleapi::INVALID_HANDLE_VALUE;
+use winapi::shared::winerror::ERROR_BROKEN_PIPE;
+use winapi::um::consoleapi::{
+    GetConsoleMode, GetNumberOfConsoleInputEvents, ReadConsoleInputW, SetConsoleCtrlHandler,
+    SetConsoleMode, WriteConsoleW,
+};
+use winapi::um::fileapi::{GetFileType, ReadFile};
+use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+use winapi::um::namedpipeapi::PeekNamedPipe;
 use winapi::um::processenv::GetStdHandle;
-use winapi::um::winbase::{STD_INPUT_HANDLE, STD_OUTPUT_HANDLE};
+use winapi::um::winbase::{FILE_TYPE_PIPE, STD_INPUT_HANDLE, STD_OUTPUT_HANDLE};
 use winapi::um::wincon::{
-    FillConsoleOutputAttribute, FillConsoleOutputCharacterA, GetConsoleScreenBufferInfo, SetConsoleCursorPosition,
-    CONSOLE_SCREEN_BUFFER_INFO, ENABLE_ECHO_INPUT, ENABLE_LINE_INPUT,
-    ENABLE_PROCESSED_INPUT, ENABLE_WINDOW_INPUT, INPUT_RECORD, KEY_EVENT, LEFT_CTRL_PRESSED,
-    RIGHT_CTRL_PRESSED,
+    CreateConsoleScreenBuffer, FillConsoleOutputAttribute, FillConsoleOutputCharacterA,
+    GetConsoleScreenBufferInfo, SetConsoleActiveScreenBuffer, SetConsoleCursorPosition,
+    CONSOLE_SCREEN_BUFFER_INFO, CONSOLE_TEXTMODE_BUFFER, ENABLE_ECHO_INPUT, ENABLE_LINE_INPUT,
+    ENABLE_PROCESSED_INPUT, ENABLE_WINDOW_INPUT, INPUT_RECORD, KEY_EVENT, LEFT_ALT_PRESSED,
+    LEFT_CTRL_PRESSED, RIGHT_ALT_PRESSED, RIGHT_CTRL_PRESSED, SHIFT_PRESSED,
+};
+use winapi::um::wincontypes::{COORD, KEY_EVENT_RECORD};
+use winapi::um::winnt::{FILE_SHARE_READ, FILE_SHARE_WRITE, GENERIC_READ, GENERIC_WRITE, HANDLE};
+use winapi::um::winuser::{
+    VK_BACK, VK_DELETE, VK_DOWN, VK_END, VK_ESCAPE, VK_F1, VK_F12, VK_HOME, VK_INSERT, VK_LEFT,
+    VK_NEXT, VK_PRIOR, VK_RETURN, VK_RIGHT, VK_SPACE, VK_TAB, VK_UP,
 };
-use winapi::um::wincontypes::KEY_EVENT_RECORD;
-use winapi::um::winuser::{VK_BACK, VK_DELETE, VK_DOWN, VK_END, VK_HOME, VK_LEFT, VK_RETURN, VK_RIGHT, VK_UP};
-use winapi::um::winnt::HANDLE;
 
 /// Windows terminal using stdin/stdout with Console API.
 ///
 /// Provides a [`Terminal`](crate::Terminal) implementation for Windows
 /// using the native Console API for raw mode and cursor control.
 ///
+/// The console has no bracketed paste, but a paste arrives as a burst of key
+/// records that are all waiting at once. Those are read together and reported
+/// between [`KeyEvent::PasteStart`] and [`KeyEvent::PasteEnd`], so the editor
+/// shows the pasted text with a single repaint. A record standing for several
+/// repeats of a held key counts as that many key presses, so repeated
+/// characters are inserted the same way.
+///
+/// Console output is collected until [`flush`](crate::Terminal::flush) or a
+/// cursor operation needs it on screen, and then written with a single
+/// `WriteConsoleW` call, as UTF-8 converted to UTF-16.
+///
+/// If stdin or stdout isn't a console, the Console API can't be used. Output to
+/// a pipe, as with mintty and other MSYS/Cygwin terminals, is then taken to be
+/// a terminal understanding ANSI sequences, with keys decoded from the bytes
+/// read from stdin; raw mode is left to the pty, so run console programs
+/// through `winpty` there for key-by-key input. Output to a file or `TERM=dumb`
+/// gets plain text without a status line, like on Unix.
+///
 /// # Examples
 ///
 /// ```no_run
@@ -36,21 +67,55 @@ pub struct StdioTerminal {
     stdout_handle: HANDLE,
     original_mode: Option<u32>,
     ctrl_handler_disabled: bool,
+    /// Output handle of the main screen while a separate buffer is active
+    main_screen: Option<HANDLE>,
+    /// Both handles are consoles; otherwise ANSI goes over the standard streams
+    console: bool,
+    /// Not a console and not a pty either, such as output to a file
+    dumb: bool,
+    /// Key-down records read but not yet turned into events
+    records: VecDeque<KEY_EVENT_RECORD>,
+    /// The queued records are a paste, to be ended with `PasteEnd`
+    pasting: bool,
+    /// Console output written but not yet sent
+    output: Vec<u8>,
+}
+
+// KEY_EVENT_RECORD doesn't implement Debug
+impl std::fmt::Debug for StdioTerminal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StdioTerminal")
+            .field("raw_mode", &self.original_mode.is_some())
+            .field("console", &self.console)
+            .field("dumb", &self.dumb)
+            .field("queued", &self.records.len())
+            .finish_non_exhaustive()
+    }
 }
 
 impl StdioTerminal {
     /// Creates a new Windows terminal using stdin/stdout handles.
     ///
-    /// # Panics
-    ///
-    /// Panics if the standard handles cannot be obtained (extremely rare).
+    /// Handles that aren't consoles, or are missing altogether, select the ANSI
+    /// fallback described above; missing ones make reads and writes fail.
     pub fn new() -> Self {
         unsafe {
             let stdin_handle = GetStdHandle(STD_INPUT_HANDLE);
             let stdout_handle = GetStdHandle(STD_OUTPUT_HANDLE);
 
-            if stdin_handle == INVALID_HANDLE_VALUE || stdout_handle == INVALID_HANDLE_VALUE {
-                panic!("Failed to get standard handles: {:?}", io::Error::last_os_error());
+            let mut mode: u32 = 0;
+            let console = is_valid(stdin_handle)
+                && is_valid(stdout_handle)
+                && GetConsoleMode(stdin_handle, &mut mode) != 0
+                && GetConsoleMode(stdout_handle, &mut mode) != 0;
+            let pty = is_valid(stdout_handle) && GetFileType(stdout_handle) == FILE_TYPE_PIPE;
+            let dumb =
+                !console && (!pty || std::env::var("TERM").map_or(false, |term| term == "dumb"));
+            if !console {
+                debug!(
+                    "standard handles aren't a console, using ANSI over stdio (dumb: {})",
+                    dumb
+                );
             }
 
             Self {
@@ -58,51 +123,363 @@ impl StdioTerminal {
                 stdout_handle,
                 original_mode: None,
                 ctrl_handler_disabled: false,
+                main_screen: None,
+                console,
+                dumb,
+                records: VecDeque::new(),
+                pasting: false,
+                output: Vec::new(),
             }
         }
     }
 }
 
-impl Default for StdioTerminal {
-    fn default() -> Self {
-        Self::new()
+impl StdioTerminal {
+    /// Waits for console input and queues the key-down records of everything
+    /// waiting, which is more than one key when text is pasted.
+    fn read_records(&mut self) -> crate::Result<()> {
+        self.send_output()?;
+        unsafe {
+            let mut available: u32 = 0;
+            if GetNumberOfConsoleInputEvents(self.stdin_handle, &mut available) == 0 {
+                return Err(io::Error::last_os_error().into());
+            }
+
+            // Blocks for the first record when none is waiting
+            let mut input_records: Vec<INPUT_RECORD> =
+                vec![std::mem::zeroed(); available.clamp(1, 1024) as usize];
+            let mut events_read: u32 = 0;
+            if ReadConsoleInputW(
+                self.stdin_handle,
+                input_records.as_mut_ptr(),
+                input_records.len() as u32,
+                &mut events_read,
+            ) == 0
+            {
+                return Err(io::Error::last_os_error().into());
+            }
+
+            if events_read == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "EOF").into());
+            }
+
+            // Only keep key down events, once per repeat of a held key
+            for input_record in &input_records[..events_read as usize] {
+                if input_record.EventType != KEY_EVENT {
+                    continue;
+                }
+                let key_event = *input_record.Event.KeyEvent();
+                if key_event.bKeyDown != 0 {
+                    for _ in 0..key_event.wRepeatCount.max(1) {
+                        self.records.push_back(key_event);
+                    }
+                }
+            }
+        }
+
+        Ok(())
     }
-}
 
-impl Terminal for StdioTerminal {
-    fn read_byte(&mut self) -> crate::Result<u8> {
-        // This method is not used on Windows - we use ReadConsoleInputW instead
-        // But we need to implement it for the trait
-        Err(crate::Error::Io("read_byte not used on Windows"))
+    /// Reads console input until the next key-down event.
+    fn read_key_down(&mut self) -> crate::Result<KEY_EVENT_RECORD> {
+        loop {
+            if let Some(key_event) = self.records.pop_front() {
+                return Ok(key_event);
+            }
+            self.read_records()?;
+        }
     }
 
-    fn write(&mut self, data: &[u8]) -> crate::Result<()> {
-        if data.is_empty() {
+    /// Reads a byte of input that isn't a console, such as a mintty pipe.
+    ///
+    /// The handle is read directly: `io::Stdin` keeps a buffer of its own that
+    /// `PeekNamedPipe` can't see, which would hide input from
+    /// [`Self::stdin_waiting`].
+    fn read_stdin_byte(&mut self) -> crate::Result<u8> {
+        let mut byte = 0u8;
+        let mut read: u32 = 0;
+        let ok = unsafe {
+            ReadFile(
+                self.stdin_handle,
+                (&mut byte as *mut u8).cast(),
+                1,
+                &mut read,
+                std::ptr::null_mut(),
+            )
+        };
+        if ok == 0 {
+            let error = io::Error::last_os_error();
+            // The other end closing the pipe ends the input
+            if error.raw_os_error() == Some(ERROR_BROKEN_PIPE as i32) {
+                return Err(crate::Error::Eof);
+            }
+            return Err(error.into());
+        }
+        if read == 0 {
+            return Err(crate::Error::Eof);
+        }
+        Ok(byte)
+    }
+
+    /// Returns `true` if reading input that isn't a console won't block: a
+    /// pipe with bytes waiting or closed, or a file.
+    fn stdin_waiting(&self) -> bool {
+        if unsafe { GetFileType(self.stdin_handle) } != FILE_TYPE_PIPE {
+            return true;
+        }
+        let mut available: u32 = 0;
+        let null = std::ptr::null_mut();
+        // Fails once the other end has closed the pipe, which a read reports
+        unsafe {
+            PeekNamedPipe(
+                self.stdin_handle,
+                null,
+                0,
+                null.cast(),
+                &mut available,
+                null.cast(),
+            ) == 0
+                || available > 0
+        }
+    }
+
+    /// Reads console input until a key the editor knows is pressed.
+    fn read_key(&mut self) -> crate::Result<KeyEvent> {
+        loop {
+            if self.records.is_empty() {
+                if self.pasting {
+                    self.pasting = false;
+                    return Ok(KeyEvent::PasteEnd);
+                }
+                self.read_records()?;
+                // More than one character at once is pasted rather than typed
+                let text = self
+                    .records
+                    .iter()
+                    .filter(|record| unsafe { *record.uChar.UnicodeChar() } >= 32)
+                    .count();
+                if text > 1 {
+                    self.pasting = true;
+                    return Ok(KeyEvent::PasteStart);
+                }
+                continue;
+            }
+            let key_event = self.read_key_down()?;
+
+            unsafe {
+                let vk_code = key_event.wVirtualKeyCode;
+                let ctrl_pressed =
+                    (key_event.dwControlKeyState & (LEFT_CTRL_PRESSED | RIGHT_CTRL_PRESSED)) != 0;
+                let shift_pressed = (key_event.dwControlKeyState & SHIFT_PRESSED) != 0;
+                let alt_pressed =
+                    (key_event.dwControlKeyState & (LEFT_ALT_PRESSED | RIGHT_ALT_PRESSED)) != 0;
+                let char_code = *key_event.uChar.UnicodeChar();
+
+                // Ctrl+letter (VK codes 'A'..='Z' are 0x41..=0x5A); Ctrl+C and
+                // Ctrl+D are turned into Interrupted/Eof by the editor. AltGr also
+                // reports Ctrl, so require an actual control character.
+                if ctrl_pressed && (0x41..=0x5A).contains(&vk_code) && char_code < 32 {
+                    return Ok(KeyEvent::Ctrl((vk_code as u8 - b'A' + b'a') as char));
+                }
+                if ctrl_pressed && vk_code as i32 == VK_SPACE {
+                    return Ok(KeyEvent::Ctrl(' '));
+                }
+
+                // Handle special keys
+                match vk_code as i32 {
+                    VK_RETURN => return Ok(KeyEvent::Enter),
+                    VK_BACK => return Ok(KeyEvent::Backspace),
+                    VK_TAB if shift_pressed => return Ok(KeyEvent::BackTab),
+                    VK_TAB => return Ok(KeyEvent::Tab),
+                    VK_ESCAPE => return Ok(KeyEvent::Escape),
+                    VK_INSERT => return Ok(KeyEvent::Insert),
+                    VK_PRIOR => return Ok(KeyEvent::PageUp),
+                    VK_NEXT => return Ok(KeyEvent::PageDown),
+                    VK_F1..=VK_F12 => return Ok(KeyEvent::F((vk_code as i32 - VK_F1 + 1) as u8)),
+                    VK_DELETE => {
+                        if ctrl_pressed {
+                            return Ok(KeyEvent::CtrlDelete);
+                        } else {
+                            return Ok(KeyEvent::Delete);
+                        }
+                    }
+                    VK_LEFT => {
+                        if ctrl_pressed {
+                            return Ok(KeyEvent::CtrlLeft);
+                        } else {
+                            return Ok(KeyEvent::Left);
+                        }
+                    }
+                    VK_RIGHT => {
+                        if ctrl_pressed {
+                            return Ok(KeyEvent::CtrlRight);
+                        } else {
+                            return Ok(KeyEvent::Right);
+                        }
+                    }
+                    VK_UP => return Ok(KeyEvent::Up),
+                    VK_DOWN => return Ok(KeyEvent::Down),
+                    VK_HOME => {
+                        if ctrl_pressed {
+                            return Ok(KeyEvent::CtrlHome);
+                        } else if shift_pressed {
+                            return Ok(KeyEvent::ShiftHome);
+                        } else {
+                            return Ok(KeyEvent::Home);
+                        }
+                    }
+                    VK_END => {
+                        if ctrl_pressed {
+                            return Ok(KeyEvent::CtrlEnd);
+                        } else if shift_pressed {
+                            return Ok(KeyEvent::ShiftEnd);
+                        } else {
+                            return Ok(KeyEvent::End);
+                        }
+                    }
+                    _ => {}
+                }
+
+                // Normal printable character; AltGr reports Ctrl as well
+                if (32..127).contains(&char_code) {
+                    if alt_pressed && !ctrl_pressed {
+                        return Ok(KeyEvent::Alt(char_code as u8 as char));
+                    }
+                    return Ok(KeyEvent::Normal(char_code as u8 as char));
+                }
+
+                // Ignore other characters
+            }
+        }
+    }
+
+    /// Sends the output collected since the last call in one `WriteConsoleW`.
+    fn send_output(&mut self) -> crate::Result<()> {
+        if self.output.is_empty() {
             return Ok(());
         }
+        let text: Vec<u16> = String::from_utf8_lossy(&self.output)
+            .encode_utf16()
+            .collect();
+        self.output.clear();
+
+        let mut sent = 0;
+        while sent < text.len() {
+            let mut written: u32 = 0;
+            unsafe {
+                if WriteConsoleW(
+                    self.stdout_handle,
+                    text[sent..].as_ptr() as *const _,
+                    (text.len() - sent) as u32,
+                    &mut written,
+                    std::ptr::null_mut(),
+                ) == 0
+                {
+                    return Err(io::Error::last_os_error().into());
+                }
+            }
+            if written == 0 {
+                return Err(io::Error::from(io::ErrorKind::WriteZero).into());
+            }
+            sent += written as usize;
+        }
 
+        Ok(())
+    }
+
+    /// Blanks `count` cells starting at `from` with the given attributes,
+    /// leaving the cursor at `from`.
+    fn fill(&mut self, from: COORD, count: u32, attributes: u16) -> crate::Result<()> {
         unsafe {
             let mut written: u32 = 0;
-            if WriteConsoleA(
+            if FillConsoleOutputCharacterA(
                 self.stdout_handle,
-                data.as_ptr() as *const _,
-                data.len() as u32,
+                b' ' as i8,
+                count,
+                from,
                 &mut written,
-                std::ptr::null_mut(),
             ) == 0
             {
                 return Err(io::Error::last_os_error().into());
             }
+            if FillConsoleOutputAttribute(self.stdout_handle, attributes, count, from, &mut written)
+                == 0
+            {
+                return Err(io::Error::last_os_error().into());
+            }
+            if SetConsoleCursorPosition(self.stdout_handle, from) == 0 {
+                return Err(io::Error::last_os_error().into());
+            }
         }
 
         Ok(())
     }
+}
+
+/// Returns `true` for a handle `GetStdHandle` actually returned.
+fn is_valid(handle: HANDLE) -> bool {
+    handle != INVALID_HANDLE_VALUE && !handle.is_null()
+}
+
+impl Default for StdioTerminal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Terminal for StdioTerminal {
+    fn read_byte(&mut self) -> crate::Result<u8> {
+        if !self.console {
+            return self.read_stdin_byte();
+        }
+
+        // Key events are normally decoded by parse_key_event; this returns the
+        // ASCII code of the next key press that has one (used by quoted insert)
+        loop {
+            let key_event = self.read_key_down()?;
+            let char_code = unsafe { *key_event.uChar.UnicodeChar() };
+            if char_code != 0 && char_code < 128 {
+                return Ok(char_code as u8);
+            }
+        }
+    }
+
+    /// Console input arrives as key records, which the byte decoder behind
+    /// [`LineEditor::poll_read_line`](crate::LineEditor::poll_read_line) can't
+    /// read, so on a console this fails with
+    /// [`IoCause::Unsupported`](crate::IoCause::Unsupported).
+    fn try_read_byte(&mut self) -> crate::Result<Option<u8>> {
+        if self.console {
+            return Err(crate::IoCause::Unsupported.into());
+        }
+        if !self.stdin_waiting() {
+            return Ok(None);
+        }
+        self.read_stdin_byte().map(Some)
+    }
+
+    fn write(&mut self, data: &[u8]) -> crate::Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        if !self.console {
+            return io::stdout().write_all(data).map_err(|e| e.into());
+        }
+        self.output.extend_from_slice(data);
+        Ok(())
+    }
 
     fn flush(&mut self) -> crate::Result<()> {
+        self.send_output()?;
         io::stdout().flush().map_err(|e| e.into())
     }
 
     fn enter_raw_mode(&mut self) -> crate::Result<()> {
+        // A pty does its own line discipline
+        if !self.console {
+            return Ok(());
+        }
+
         unsafe {
             let mut mode: u32 = 0;
             if GetConsoleMode(self.stdin_handle, &mut mode) == 0 {
@@ -112,7 +489,11 @@ impl Terminal for StdioTerminal {
             self.original_mode = Some(mode);
 
             // Disable line input, echo, and window input events
-            let new_mode = mode & !(ENABLE_LINE_INPUT | ENABLE_ECHO_INPUT | ENABLE_PROCESSED_INPUT | ENABLE_WINDOW_INPUT);
+            let new_mode = mode
+                & !(ENABLE_LINE_INPUT
+                    | ENABLE_ECHO_INPUT
+                    | ENABLE_PROCESSED_INPUT
+                    | ENABLE_WINDOW_INPUT);
 
             if SetConsoleMode(self.stdin_handle, new_mode) == 0 {
                 return Err(io::Error::last_os_error().into());
@@ -124,10 +505,12 @@ impl Terminal for StdioTerminal {
             }
         }
 
+        debug!("entered raw mode");
         Ok(())
     }
 
     fn exit_raw_mode(&mut self) -> crate::Result<()> {
+        let sent = self.send_output();
         unsafe {
             // Re-enable Ctrl-C signal handler
             if self.ctrl_handler_disabled {
@@ -140,13 +523,19 @@ impl Terminal for StdioTerminal {
                     return Err(io::Error::last_os_error().into());
                 }
                 self.original_mode = None;
+                debug!("left raw mode");
             }
         }
 
-        Ok(())
+        sent
     }
 
     fn cursor_left(&mut self) -> crate::Result<()> {
+        if !self.console {
+            return self.write(b"\x1b[D");
+        }
+        self.send_output()?;
+
         unsafe {
             let mut csbi: CONSOLE_SCREEN_BUFFER_INFO = std::mem::zeroed();
             if GetConsoleScreenBufferInfo(self.stdout_handle, &mut csbi) == 0 {
@@ -167,6 +556,11 @@ impl Terminal for StdioTerminal {
     }
 
     fn cursor_right(&mut self) -> crate::Result<()> {
+        if !self.console {
+            return self.write(b"\x1b[C");
+        }
+        self.send_output()?;
+
         unsafe {
             let mut csbi: CONSOLE_SCREEN_BUFFER_INFO = std::mem::zeroed();
             if GetConsoleScreenBufferInfo(self.stdout_handle, &mut csbi) == 0 {
@@ -185,6 +579,11 @@ impl Terminal for StdioTerminal {
     }
 
     fn clear_eol(&mut self) -> crate::Result<()> {
+        if !self.console {
+            return self.write(b"\x1b[K");
+        }
+        self.send_output()?;
+
         unsafe {
             let mut csbi: CONSOLE_SCREEN_BUFFER_INFO = std::mem::zeroed();
             if GetConsoleScreenBufferInfo(self.stdout_handle, &mut csbi) == 0 {
@@ -228,103 +627,254 @@ impl Terminal for StdioTerminal {
         Ok(())
     }
 
-    fn parse_key_event(&mut self) -> crate::Result<KeyEvent> {
-        loop {
+    fn cursor_up(&mut self) -> crate::Result<()> {
+        if !self.console {
+            return self.write(b"\x1b[A");
+        }
+        self.send_output()?;
+
+        unsafe {
+            let mut csbi: CONSOLE_SCREEN_BUFFER_INFO = std::mem::zeroed();
+            if GetConsoleScreenBufferInfo(self.stdout_handle, &mut csbi) == 0 {
+                return Err(io::Error::last_os_error().into());
+            }
+
+            let mut coord = csbi.dwCursorPosition;
+            if coord.Y > 0 {
+                coord.Y -= 1;
+            }
+
+            if SetConsoleCursorPosition(self.stdout_handle, coord) == 0 {
+                return Err(io::Error::last_os_error().into());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn clear_to_end_of_screen(&mut self) -> crate::Result<()> {
+        if !self.console {
+            return self.write(b"\x1b[J");
+        }
+        self.send_output()?;
+
+        unsafe {
+            let mut csbi: CONSOLE_SCREEN_BUFFER_INFO = std::mem::zeroed();
+            if GetConsoleScreenBufferInfo(self.stdout_handle, &mut csbi) == 0 {
+                return Err(io::Error::last_os_error().into());
+            }
+
+            // Rest of the cursor's row plus every row below it in the window
+            let coord = csbi.dwCursorPosition;
+            let rows_below = (csbi.srWindow.Bottom - coord.Y).max(0) as u32;
+            let count = (csbi.dwSize.X - coord.X) as u32 + rows_below * csbi.dwSize.X as u32;
+            self.fill(coord, count, csbi.wAttributes)?;
+        }
+
+        Ok(())
+    }
+
+    fn clear_screen(&mut self) -> crate::Result<()> {
+        if !self.console {
+            return self.write(b"\x1b[H\x1b[2J");
+        }
+        self.send_output()?;
+
+        unsafe {
+            let mut csbi: CONSOLE_SCREEN_BUFFER_INFO = std::mem::zeroed();
+            if GetConsoleScreenBufferInfo(self.stdout_handle, &mut csbi) == 0 {
+                return Err(io::Error::last_os_error().into());
+            }
+
+            let top = COORD {
+                X: 0,
+                Y: csbi.srWindow.Top,
+            };
+            let rows = (csbi.srWindow.Bottom - csbi.srWindow.Top + 1) as u32;
+            self.fill(top, rows * csbi.dwSize.X as u32, csbi.wAttributes)?;
+
+            if SetConsoleCursorPosition(self.stdout_handle, top) == 0 {
+                return Err(io::Error::last_os_error().into());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn size(&mut self) -> Option<(u16, u16)> {
+        unsafe {
+            let mut csbi: CONSOLE_SCREEN_BUFFER_INFO = std::mem::zeroed();
+            if GetConsoleScreenBufferInfo(self.stdout_handle, &mut csbi) == 0 {
+                return None;
+            }
+
+            let window = csbi.srWindow;
+            let columns = (window.Right - window.Left + 1) as u16;
+            let rows = (window.Bottom - window.Top + 1) as u16;
+            Some((columns, rows))
+        }
+    }
+
+    // The console has no alternate screen; a second screen buffer does the same
+    fn enter_alternate_screen(&mut self) -> crate::Result<()> {
+        if !self.console {
+            return if self.dumb {
+                Ok(())
+            } else {
+                self.write(b"\x1b[?1049h")
+            };
+        }
+        if self.main_screen.is_some() {
+            return Ok(());
+        }
+        self.send_output()?;
+
+        unsafe {
+            let buffer = CreateConsoleScreenBuffer(
+                GENERIC_READ | GENERIC_WRITE,
+                FILE_SHARE_READ | FILE_SHARE_WRITE,
+                std::ptr::null(),
+                CONSOLE_TEXTMODE_BUFFER,
+                std::ptr::null_mut(),
+            );
+            if buffer == INVALID_HANDLE_VALUE {
+                return Err(io::Error::last_os_error().into());
+            }
+            if SetConsoleActiveScreenBuffer(buffer) == 0 {
+                let error = io::Error::last_os_error();
+                CloseHandle(buffer);
+                return Err(error.into());
+            }
+
+            self.main_screen = Some(self.stdout_handle);
+            self.stdout_handle = buffer;
+        }
+
+        Ok(())
+    }
+
+    fn exit_alternate_screen(&mut self) -> crate::Result<()> {
+        if !self.console {
+            return if self.dumb {
+                Ok(())
+            } else {
+                self.write(b"\x1b[?1049l")
+            };
+        }
+        self.send_output()?;
+        if let Some(main) = self.main_screen.take() {
             unsafe {
-                let mut input_record: INPUT_RECORD = std::mem::zeroed();
-                let mut events_read: u32 = 0;
-
-                if ReadConsoleInputW(
-                    self.stdin_handle,
-                    &mut input_record,
-                    1,
-                    &mut events_read,
-                ) == 0
-                {
+                let buffer = std::mem::replace(&mut self.stdout_handle, main);
+                if SetConsoleActiveScreenBuffer(main) == 0 {
                     return Err(io::Error::last_os_error().into());
                 }
+                CloseHandle(buffer);
+            }
+        }
 
-                if events_read == 0 {
-                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "EOF").into());
-                }
+        Ok(())
+    }
 
-                // Only process keyboard events
-                if input_record.EventType != KEY_EVENT {
-                    continue;
-                }
+    fn get_cursor_position(&mut self) -> Option<(u16, u16)> {
+        self.send_output().ok()?;
+        unsafe {
+            let mut csbi: CONSOLE_SCREEN_BUFFER_INFO = std::mem::zeroed();
+            if GetConsoleScreenBufferInfo(self.stdout_handle, &mut csbi) == 0 {
+                return None;
+            }
 
-                let key_event: KEY_EVENT_RECORD = *input_record.Event.KeyEvent();
+            let coord = csbi.dwCursorPosition;
+            Some((
+                (coord.X - csbi.srWindow.Left) as u16,
+                (coord.Y - csbi.srWindow.Top) as u16,
+            ))
+        }
+    }
 
-                // Only process key down events
-                if key_event.bKeyDown == 0 {
-                    continue;
-                }
+    fn show_status(&mut self, text: &[u8]) -> crate::Result<()> {
+        if !self.console {
+            return if self.dumb {
+                Ok(())
+            } else {
+                crate::ansi::show_status(self, text)
+            };
+        }
+        self.send_output()?;
 
-                let vk_code = key_event.wVirtualKeyCode;
-                let ctrl_pressed = (key_event.dwControlKeyState & (LEFT_CTRL_PRESSED | RIGHT_CTRL_PRESSED)) != 0;
-                let char_code = *key_event.uChar.UnicodeChar();
+        unsafe {
+            let mut csbi: CONSOLE_SCREEN_BUFFER_INFO = std::mem::zeroed();
+            if GetConsoleScreenBufferInfo(self.stdout_handle, &mut csbi) == 0 {
+                return Err(io::Error::last_os_error().into());
+            }
 
-                // Check for Ctrl+C first (VK code 'C' = 0x43)
-                if ctrl_pressed && vk_code == 0x43 {
-                    return Err(io::Error::new(
-                        io::ErrorKind::Interrupted,
-                        "Interrupted (Ctrl-C)"
-                    ).into());
-                }
+            // No row below the cursor in the screen buffer: nowhere to draw
+            let saved = csbi.dwCursorPosition;
+            if saved.Y + 1 >= csbi.dwSize.Y {
+                return Ok(());
+            }
 
-                // Check for Ctrl+D (VK code 'D' = 0x44)
-                if ctrl_pressed && vk_code == 0x44 {
-                    return Err(io::Error::new(
-                        io::ErrorKind::UnexpectedEof,
-                        "EOF (Ctrl-D)"
-                    ).into());
-                }
+            if SetConsoleCursorPosition(
+                self.stdout_handle,
+                COORD {
+                    X: 0,
+                    Y: saved.Y + 1,
+                },
+            ) == 0
+            {
+                return Err(io::Error::last_os_error().into());
+            }
+            self.write(text)?;
+            self.clear_eol()?;
 
-                // Handle special keys
-                match vk_code as i32 {
-                    VK_RETURN => return Ok(KeyEvent::Enter),
-                    VK_BACK => return Ok(KeyEvent::Backspace),
-                    VK_DELETE => {
-                        if ctrl_pressed {
-                            return Ok(KeyEvent::CtrlDelete);
-                        } else {
-                            return Ok(KeyEvent::Delete);
-                        }
-                    }
-                    VK_LEFT => {
-                        if ctrl_pressed {
-                            return Ok(KeyEvent::CtrlLeft);
-                        } else {
-                            return Ok(KeyEvent::Left);
-                        }
-                    }
-                    VK_RIGHT => {
-                        if ctrl_pressed {
-                            return Ok(KeyEvent::CtrlRight);
-                        } else {
-                            return Ok(KeyEvent::Right);
-                        }
-                    }
-                    VK_UP => return Ok(KeyEvent::Up),
-                    VK_DOWN => return Ok(KeyEvent::Down),
-                    VK_HOME => return Ok(KeyEvent::Home),
-                    VK_END => return Ok(KeyEvent::End),
-                    _ => {}
-                }
+            if SetConsoleCursorPosition(self.stdout_handle, saved) == 0 {
+                return Err(io::Error::last_os_error().into());
+            }
+        }
 
-                // Normal printable character
-                if char_code >= 32 && char_code < 127 {
-                    return Ok(KeyEvent::Normal(char_code as u8 as char));
-                }
+        Ok(())
+    }
 
-                // Ignore other characters
-            }
+    fn clear_status(&mut self) -> crate::Result<()> {
+        if !self.console {
+            return if self.dumb {
+                Ok(())
+            } else {
+                crate::ansi::clear_status(self)
+            };
         }
+        self.show_status(b"")
+    }
+
+    fn parse_key_event(&mut self) -> crate::Result<KeyEvent> {
+        let event = if self.console {
+            self.read_key()?
+        } else {
+            crate::ansi::parse_key_event(self)?
+        };
+        debug!("key {:?}", event);
+        Ok(event)
+    }
+
+    /// The console is driven through its API, which has no colors or
+    /// bracketed paste here. Output is processed, so `\n` starts a new line.
+    fn capabilities(&self) -> crate::Caps {
+        let caps = if self.dumb {
+            crate::Caps::NONE.with_bell(true)
+        } else if !self.console {
+            crate::Caps::ANSI
+        } else {
+            crate::Caps::NONE
+                .with_cursor_addressing(true)
+                .with_size(true)
+                .with_bell(true)
+        };
+        caps.with_crlf(false)
     }
 }
 
 impl Drop for StdioTerminal {
     fn drop(&mut self) {
+        let _ = self.exit_alternate_screen();
         let _ = self.exit_raw_mode();
     }
 }
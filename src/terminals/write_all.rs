@@ -0,0 +1,230 @@
+//! [`Terminal`] over `std::io` readers and writers that may write partially.
+
+use std::io::{ErrorKind, Read, Write};
+use std::vec::Vec;
+
+use crate::{Error, IoCause, IoError, Result, Terminal};
+
+/// Error for output that can't be written without blocking.
+const WOULD_BLOCK: Error = Error::Io(IoError::new(IoCause::WouldBlock));
+
+/// Terminal reading from a [`Read`] and writing to a [`Write`] that may accept
+/// only part of the data per call.
+///
+/// [`Terminal::write`] must write all of its data or fail, while
+/// [`Write::write`] may stop short, return 0 or, when non-blocking, fail with
+/// [`WouldBlock`](ErrorKind::WouldBlock) halfway. This adapter loops over
+/// short writes, reports a writer that accepts nothing as an error instead of
+/// spinning, and retries [`Interrupted`](ErrorKind::Interrupted) calls.
+///
+/// `WouldBlock` before any byte of a write has gone out fails the write, so
+/// repeating it, e.g. through a [`RetryPolicy`](crate::RetryPolicy), doesn't
+/// duplicate output. Once part of it has gone out, the rest is kept and sent
+/// ahead of the next write or flush. On the input side, `WouldBlock` makes
+/// [`try_read_byte`](Terminal::try_read_byte) return `None`, so a non-blocking
+/// reader works with [`LineEditor::poll_read_line`](crate::LineEditor::poll_read_line).
+///
+/// Like `AsyncReadWriteTerminal`, it leaves raw mode to whoever owns the other
+/// end and sends ANSI sequences for cursor movement.
+///
+/// # Examples
+///
+/// ```no_run
+/// use editline::terminals::WriteAllTerminal;
+/// use editline::LineEditor;
+/// use std::net::TcpListener;
+///
+/// let (stream, _) = TcpListener::bind("127.0.0.1:2323")?.accept()?;
+/// let mut terminal = WriteAllTerminal::new(stream.try_clone()?, stream);
+/// let mut editor = LineEditor::new(1024, 50);
+/// let line = editor.read_line_with_prompt(&mut terminal, "> ")?;
+/// # Ok::<(), editline::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct WriteAllTerminal<R, W> {
+    reader: R,
+    writer: W,
+    /// Rest of a write interrupted by `WouldBlock`
+    pending: Vec<u8>,
+}
+
+impl<R: Read, W: Write> WriteAllTerminal<R, W> {
+    /// Creates a terminal from a reader and a writer.
+    pub fn new(reader: R, writer: W) -> Self {
+        WriteAllTerminal {
+            reader,
+            writer,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Returns the reader and writer, dropping any output still pending.
+    pub fn into_inner(self) -> (R, W) {
+        (self.reader, self.writer)
+    }
+
+    /// Reads a byte, or `None` if the reader would block.
+    fn read_some(&mut self) -> Result<Option<u8>> {
+        let mut byte = [0];
+        loop {
+            match self.reader.read(&mut byte) {
+                Ok(0) => return Err(Error::Eof),
+                Ok(_) => return Ok(Some(byte[0])),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(None),
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Writes as much of `data` as possible, returning how much was written
+    /// before the writer would block.
+    fn write_some(&mut self, data: &[u8]) -> Result<usize> {
+        let mut written = 0;
+        while written < data.len() {
+            match self.writer.write(&data[written..]) {
+                Ok(0) => return Err(IoCause::WriteZero.into()),
+                Ok(n) => written += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(written)
+    }
+
+    /// Sends the pending rest of an earlier write.
+    fn drain(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let pending = core::mem::take(&mut self.pending);
+        let written = self.write_some(&pending);
+        self.pending = pending;
+        let written = written?;
+        self.pending.drain(..written);
+        if self.pending.is_empty() {
+            Ok(())
+        } else {
+            Err(WOULD_BLOCK)
+        }
+    }
+}
+
+impl<R: Read, W: Write> Terminal for WriteAllTerminal<R, W> {
+    fn read_byte(&mut self) -> Result<u8> {
+        self.read_some()?.ok_or(WOULD_BLOCK)
+    }
+
+    fn try_read_byte(&mut self) -> Result<Option<u8>> {
+        self.read_some()
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.drain()?;
+        let written = self.write_some(data)?;
+        if written == 0 && !data.is_empty() {
+            return Err(WOULD_BLOCK);
+        }
+        self.pending.extend_from_slice(&data[written..]);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.drain()?;
+        loop {
+            match self.writer.flush() {
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Err(WOULD_BLOCK),
+                result => return result.map_err(Error::from),
+            }
+        }
+    }
+
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn cursor_left(&mut self) -> Result<()> {
+        self.write(b"\x1b[D")
+    }
+
+    fn cursor_right(&mut self) -> Result<()> {
+        self.write(b"\x1b[C")
+    }
+
+    fn clear_eol(&mut self) -> Result<()> {
+        self.write(b"\x1b[K")
+    }
+
+    fn capabilities(&self) -> crate::Caps {
+        crate::Caps::ANSI
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LineEditor;
+    use std::io;
+
+    /// Writer accepting at most three bytes per call, and none every other call.
+    #[derive(Default)]
+    struct Choppy {
+        output: Vec<u8>,
+        blocked: bool,
+    }
+
+    impl Write for Choppy {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.blocked = !self.blocked;
+            if self.blocked {
+                return Err(ErrorKind::WouldBlock.into());
+            }
+            let n = buf.len().min(3);
+            self.output.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_partial_writes() {
+        let mut terminal = WriteAllTerminal::new(&b""[..], Choppy::default());
+        assert!(matches!(terminal.write(b"hello"), Err(WOULD_BLOCK)));
+        terminal.write(b"hello").unwrap();
+        assert_eq!(terminal.pending, b"lo");
+        assert_eq!(terminal.writer.output, b"hel");
+        terminal.flush().unwrap();
+        assert_eq!(terminal.writer.output, b"hello");
+
+        let mut terminal = WriteAllTerminal::new(&b"ab\x1b[DX\r"[..], Vec::new());
+        let mut editor = LineEditor::new(64, 10);
+        assert_eq!(
+            editor.read_line_with_prompt(&mut terminal, "> ").unwrap(),
+            "aXb"
+        );
+        assert!(terminal.writer.starts_with(b"> a"));
+        assert!(terminal.writer.ends_with(b"\x1b[D\x1b[@X\r\n"));
+        assert!(matches!(editor.read_line(&mut terminal), Err(Error::Eof)));
+
+        struct Full;
+        impl Write for Full {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Ok(0)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        assert!(
+            matches!(WriteAllTerminal::new(&b""[..], Full).write(b"x"), Err(Error::Io(e)) if e.cause() == IoCause::WriteZero)
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! Scripted terminal shared by the unit tests.
+
+use alloc::collections::VecDeque;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+use crate::{Caps, Error, KeyEvent, Result, Terminal};
+
+/// Terminal reading a fixed script and recording everything written to it.
+///
+/// Cursor moves and clears are written as ANSI sequences. Once the script
+/// runs out, reads fail with `end`, or with `end` set to `None`, polls find
+/// nothing waiting, as on a link that stays open.
+pub(crate) struct ScriptedTerminal {
+    /// Bytes to read, `None` being a poll that finds nothing waiting
+    pub input: VecDeque<Option<u8>>,
+    /// Key events returned instead of decoding `input`
+    pub keys: Option<VecDeque<KeyEvent>>,
+    /// Everything written
+    pub output: Vec<u8>,
+    /// Also gets everything written, e.g. to interleave it with a wrapper's
+    /// own log
+    pub log: Option<Rc<RefCell<Vec<u8>>>>,
+    /// Error once the script runs out
+    pub end: Option<Error>,
+    /// Reads must not block: `read_byte` panics
+    pub polled: bool,
+    pub caps: Caps,
+}
+
+impl ScriptedTerminal {
+    pub fn new(input: &[u8]) -> Self {
+        ScriptedTerminal {
+            input: input.iter().copied().map(Some).collect(),
+            keys: None,
+            output: Vec::new(),
+            log: None,
+            end: Some(Error::Eof),
+            polled: false,
+            caps: Caps::NONE,
+        }
+    }
+
+    /// Terminal returning `keys` from `parse_key_event`.
+    pub fn with_keys(keys: &[KeyEvent]) -> Self {
+        ScriptedTerminal {
+            keys: Some(keys.iter().copied().collect()),
+            ..Self::new(b"")
+        }
+    }
+
+    /// Terminal where each byte is only read after the given number of polls
+    /// find nothing.
+    pub fn with_stalls(input: &[(u32, u8)]) -> Self {
+        let mut terminal = Self::new(b"");
+        for &(stalls, byte) in input {
+            terminal.input.extend((0..stalls).map(|_| None));
+            terminal.input.push_back(Some(byte));
+        }
+        terminal
+    }
+
+    /// Adds bytes to read.
+    pub fn feed(&mut self, input: &[u8]) {
+        self.input.extend(input.iter().copied().map(Some));
+    }
+
+    pub fn output(&self) -> String {
+        String::from_utf8_lossy(&self.output).into_owned()
+    }
+
+    fn exhausted(&self) -> Error {
+        self.end.expect("read would wait forever")
+    }
+}
+
+impl Terminal for ScriptedTerminal {
+    fn read_byte(&mut self) -> Result<u8> {
+        assert!(!self.polled, "read_byte must not block");
+        loop {
+            match self.input.pop_front() {
+                Some(Some(byte)) => return Ok(byte),
+                Some(None) => {}
+                None => return Err(self.exhausted()),
+            }
+        }
+    }
+    fn try_read_byte(&mut self) -> Result<Option<u8>> {
+        match self.input.pop_front() {
+            Some(byte) => Ok(byte),
+            None => self.end.map_or(Ok(None), Err),
+        }
+    }
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.output.extend_from_slice(data);
+        if let Some(log) = &self.log {
+            log.borrow_mut().extend_from_slice(data);
+        }
+        Ok(())
+    }
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+    fn enter_raw_mode(&mut self) -> Result<()> {
+        Ok(())
+    }
+    fn exit_raw_mode(&mut self) -> Result<()> {
+        Ok(())
+    }
+    fn cursor_left(&mut self) -> Result<()> {
+        self.write(b"\x1b[D")
+    }
+    fn cursor_right(&mut self) -> Result<()> {
+        self.write(b"\x1b[C")
+    }
+    fn clear_eol(&mut self) -> Result<()> {
+        self.write(b"\x1b[K")
+    }
+    fn parse_key_event(&mut self) -> Result<KeyEvent> {
+        match self.keys.as_mut().map(VecDeque::pop_front) {
+            Some(Some(key)) => Ok(key),
+            Some(None) => Err(self.exhausted()),
+            None => crate::ansi::parse_key_event(self),
+        }
+    }
+    fn capabilities(&self) -> Caps {
+        self.caps
+    }
+}
+
+#[cfg(feature = "async")]
+impl crate::asynch::AsyncTerminal for ScriptedTerminal {
+    fn poll_read_byte(&mut self, cx: &mut core::task::Context<'_>) -> core::task::Poll<Result<u8>> {
+        use core::task::Poll;
+
+        match self.input.pop_front() {
+            Some(Some(byte)) => Poll::Ready(Ok(byte)),
+            Some(None) => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            None => Poll::Ready(Err(self.exhausted())),
+        }
+    }
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        Terminal::write(self, data)
+    }
+    fn poll_flush(&mut self, _: &mut core::task::Context<'_>) -> core::task::Poll<Result<()>> {
+        core::task::Poll::Ready(Ok(()))
+    }
+}
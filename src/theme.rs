@@ -0,0 +1,293 @@
+//! Colors and text attributes used when rendering.
+//!
+//! A [`Theme`] assigns a [`Style`] to each part of the display the editor draws
+//! itself: the prompt, inline hints, the match in an incremental search, the
+//! matched part of completion candidates, and error indicators such as a failed
+//! search. Install one with [`LineEditor::set_theme`](crate::LineEditor::set_theme).
+//!
+//! The default theme is colorless and writes no escape sequences at all, so it is
+//! safe on terminals without ANSI color support. Styles are emitted as SGR
+//! sequences (`ESC [ ... m`), and only to terminals whose
+//! [`capabilities`](crate::Terminal::capabilities) include colors.
+
+use alloc::string::String;
+use core::fmt::Write;
+
+use crate::{write_visible, Result, Terminal};
+
+/// Terminal color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    BrightBlack,
+    BrightRed,
+    BrightGreen,
+    BrightYellow,
+    BrightBlue,
+    BrightMagenta,
+    BrightCyan,
+    BrightWhite,
+    /// Entry of the 256-color palette.
+    Indexed(u8),
+    /// 24-bit color.
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    /// Appends the SGR parameters selecting this color; `base` is 30 for the
+    /// foreground and 40 for the background.
+    fn write_sgr(self, out: &mut String, base: u8) {
+        let basic = match self {
+            Color::Black => 0,
+            Color::Red => 1,
+            Color::Green => 2,
+            Color::Yellow => 3,
+            Color::Blue => 4,
+            Color::Magenta => 5,
+            Color::Cyan => 6,
+            Color::White => 7,
+            Color::BrightBlack => 60,
+            Color::BrightRed => 61,
+            Color::BrightGreen => 62,
+            Color::BrightYellow => 63,
+            Color::BrightBlue => 64,
+            Color::BrightMagenta => 65,
+            Color::BrightCyan => 66,
+            Color::BrightWhite => 67,
+            Color::Indexed(n) => {
+                let _ = write!(out, ";{};5;{}", base + 8, n);
+                return;
+            }
+            Color::Rgb(r, g, b) => {
+                let _ = write!(out, ";{};2;{};{};{}", base + 8, r, g, b);
+                return;
+            }
+        };
+        let _ = write!(out, ";{}", base + basic);
+    }
+}
+
+/// Colors and attributes applied to a piece of text.
+///
+/// Built with chained calls starting from [`Style::new`], which is plain text.
+///
+/// # Examples
+///
+/// ```
+/// use editline::theme::{Color, Style};
+///
+/// let style = Style::new().fg(Color::Cyan).bold();
+/// assert!(!style.is_plain());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub underline: bool,
+    pub reverse: bool,
+}
+
+impl Style {
+    /// Returns a style with no colors or attributes.
+    pub const fn new() -> Self {
+        Style {
+            fg: None,
+            bg: None,
+            bold: false,
+            dim: false,
+            underline: false,
+            reverse: false,
+        }
+    }
+
+    /// Sets the foreground color.
+    pub const fn fg(mut self, color: Color) -> Self {
+        self.fg = Some(color);
+        self
+    }
+
+    /// Sets the background color.
+    pub const fn bg(mut self, color: Color) -> Self {
+        self.bg = Some(color);
+        self
+    }
+
+    /// Adds bold (increased intensity).
+    pub const fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    /// Adds dim (decreased intensity).
+    pub const fn dim(mut self) -> Self {
+        self.dim = true;
+        self
+    }
+
+    /// Adds underline.
+    pub const fn underline(mut self) -> Self {
+        self.underline = true;
+        self
+    }
+
+    /// Adds reverse video (swapped foreground and background).
+    pub const fn reverse(mut self) -> Self {
+        self.reverse = true;
+        self
+    }
+
+    /// Returns `true` if this style changes nothing, so no escape sequences are
+    /// written for it.
+    pub fn is_plain(&self) -> bool {
+        *self == Style::new()
+    }
+
+    /// Writes the SGR sequence switching to this style.
+    pub(crate) fn begin<T: Terminal + ?Sized>(&self, terminal: &mut T) -> Result<()> {
+        if self.is_plain() || !terminal.capabilities().colors {
+            return Ok(());
+        }
+
+        let mut sgr = String::from("\x1b[0");
+        for (on, code) in [
+            (self.bold, 1),
+            (self.dim, 2),
+            (self.underline, 4),
+            (self.reverse, 7),
+        ] {
+            if on {
+                let _ = write!(sgr, ";{}", code);
+            }
+        }
+        if let Some(fg) = self.fg {
+            fg.write_sgr(&mut sgr, 30);
+        }
+        if let Some(bg) = self.bg {
+            bg.write_sgr(&mut sgr, 40);
+        }
+        sgr.push('m');
+        terminal.write(sgr.as_bytes())
+    }
+
+    /// Writes the SGR reset if [`begin`](Self::begin) wrote anything.
+    pub(crate) fn end<T: Terminal + ?Sized>(&self, terminal: &mut T) -> Result<()> {
+        if self.is_plain() || !terminal.capabilities().colors {
+            Ok(())
+        } else {
+            terminal.write(b"\x1b[0m")
+        }
+    }
+
+    /// Writes `text` in this style, rendering control characters in caret notation.
+    pub(crate) fn paint<T: Terminal>(&self, terminal: &mut T, text: &[u8]) -> Result<()> {
+        self.begin(terminal)?;
+        write_visible(terminal, text)?;
+        self.end(terminal)
+    }
+}
+
+/// Styles for each part of the display drawn by the editor.
+///
+/// # Examples
+///
+/// ```
+/// use editline::LineEditor;
+/// use editline::theme::{Color, Style, Theme};
+///
+/// let mut editor = LineEditor::new(1024, 50);
+/// editor.set_theme(Theme {
+///     prompt: Style::new().fg(Color::Green).bold(),
+///     hint: Style::new().fg(Color::BrightBlack),
+///     ..Theme::default()
+/// });
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Theme {
+    /// The prompt passed to [`read_line_with_prompt`](crate::LineEditor::read_line_with_prompt).
+    pub prompt: Style,
+    /// Inline hints after the end of the line.
+    pub hint: Style,
+    /// The matched text in the entry found by an incremental search.
+    pub selection: Style,
+    /// The part of each listed completion candidate that matched the typed word.
+    pub completion_highlight: Style,
+    /// Error indicators, such as the label of a failed search.
+    pub error: Style,
+}
+
+impl Theme {
+    /// Returns the colorless default theme.
+    pub const fn new() -> Self {
+        Theme {
+            prompt: Style::new(),
+            hint: Style::new(),
+            selection: Style::new(),
+            completion_highlight: Style::new(),
+            error: Style::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ScriptedTerminal;
+    use crate::Caps;
+
+    fn painted(style: Style, text: &str) -> String {
+        let mut term = ScriptedTerminal {
+            caps: Caps::ANSI,
+            ..ScriptedTerminal::new(b"")
+        };
+        style.paint(&mut term, text.as_bytes()).unwrap();
+        term.output()
+    }
+
+    #[test]
+    fn test_plain_style_writes_no_escapes() {
+        assert_eq!(painted(Style::new(), "> "), "> ");
+        assert_eq!(Theme::default(), Theme::new());
+    }
+
+    #[test]
+    fn test_sgr_sequences() {
+        assert_eq!(
+            painted(Style::new().fg(Color::Red), "x"),
+            "\x1b[0;31mx\x1b[0m"
+        );
+        assert_eq!(
+            painted(
+                Style::new()
+                    .bold()
+                    .fg(Color::BrightCyan)
+                    .bg(Color::Indexed(236)),
+                "x"
+            ),
+            "\x1b[0;1;96;48;5;236mx\x1b[0m"
+        );
+        assert_eq!(
+            painted(Style::new().fg(Color::Rgb(1, 2, 3)).reverse(), "x"),
+            "\x1b[0;7;38;2;1;2;3mx\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_no_escapes_without_colors() {
+        let mut term = ScriptedTerminal {
+            caps: Caps::ANSI.with_colors(false),
+            ..ScriptedTerminal::new(b"")
+        };
+        Style::new().fg(Color::Red).paint(&mut term, b"x").unwrap();
+        assert_eq!(term.output, b"x");
+        assert!(!Caps::NONE.intersection(Caps::ANSI).colors);
+    }
+}
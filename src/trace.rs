@@ -0,0 +1,24 @@
+//! Debug events for diagnosing terminal problems.
+//!
+//! With the `tracing` or `log` feature, the editor reports raw-mode
+//! transitions, every key it decodes and every byte sequence it doesn't
+//! understand at debug level, under the `editline` target prefix. Users of a
+//! misbehaving terminal emulator can then send a log instead of a
+//! reproduction. `tracing` takes precedence if both are enabled; with neither,
+//! the events compile to nothing.
+
+/// Emits a debug-level event, with `format!`-style arguments.
+macro_rules! debug {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)*);
+        #[cfg(all(feature = "log", not(feature = "tracing")))]
+        log::debug!($($arg)*);
+        #[cfg(not(any(feature = "log", feature = "tracing")))]
+        {
+            if false {
+                let _ = format_args!($($arg)*);
+            }
+        }
+    }};
+}
@@ -0,0 +1,148 @@
+//! Display width of characters.
+//!
+//! A small built-in table covering the East Asian wide and fullwidth blocks,
+//! common emoji, and combining marks, so that the cursor arithmetic matches what
+//! terminals draw without pulling in a Unicode database.
+
+/// Ranges of characters drawn two columns wide.
+const WIDE: &[(u32, u32)] = &[
+    (0x1100, 0x115f), // Hangul Jamo initials
+    (0x231a, 0x231b), // watch, hourglass
+    (0x2329, 0x232a), // angle brackets
+    (0x23e9, 0x23ec),
+    (0x23f0, 0x23f0),
+    (0x23f3, 0x23f3),
+    (0x25fd, 0x25fe),
+    (0x2614, 0x2615),
+    (0x2648, 0x2653),
+    (0x26a1, 0x26a1),
+    (0x26aa, 0x26ab),
+    (0x26bd, 0x26be),
+    (0x26c4, 0x26c5),
+    (0x26ce, 0x26ce),
+    (0x26d4, 0x26d4),
+    (0x26ea, 0x26ea),
+    (0x26f2, 0x26f5),
+    (0x26fa, 0x26fd),
+    (0x2705, 0x2705),
+    (0x270a, 0x270b),
+    (0x2728, 0x2728),
+    (0x274c, 0x274c),
+    (0x2753, 0x2755),
+    (0x2757, 0x2757),
+    (0x2795, 0x2797),
+    (0x27b0, 0x27b0),
+    (0x27bf, 0x27bf),
+    (0x2b1b, 0x2b1c),
+    (0x2b50, 0x2b50),
+    (0x2b55, 0x2b55),
+    (0x2e80, 0x303e), // CJK radicals, punctuation
+    (0x3041, 0x33ff), // kana, CJK compatibility
+    (0x3400, 0x4dbf), // CJK extension A
+    (0x4e00, 0x9fff), // CJK unified ideographs
+    (0xa000, 0xa4cf), // Yi
+    (0xa960, 0xa97f), // Hangul Jamo extended A
+    (0xac00, 0xd7a3), // Hangul syllables
+    (0xf900, 0xfaff), // CJK compatibility ideographs
+    (0xfe10, 0xfe19), // vertical forms
+    (0xfe30, 0xfe6f), // CJK compatibility forms, small forms
+    (0xff00, 0xff60), // fullwidth forms
+    (0xffe0, 0xffe6),
+    (0x16fe0, 0x16fe4),
+    (0x17000, 0x18cff), // Tangut
+    (0x1b000, 0x1b2ff), // kana supplement
+    (0x1f004, 0x1f004),
+    (0x1f0cf, 0x1f0cf),
+    (0x1f18e, 0x1f18e),
+    (0x1f191, 0x1f19a),
+    (0x1f200, 0x1f251), // enclosed ideographs
+    (0x1f300, 0x1f64f), // pictographs, emoticons
+    (0x1f680, 0x1f6ff), // transport and map symbols
+    (0x1f7e0, 0x1f7eb),
+    (0x1f90c, 0x1f9ff), // supplemental symbols and pictographs
+    (0x1fa70, 0x1faff),
+    (0x20000, 0x2fffd), // CJK extensions B-F
+    (0x30000, 0x3fffd), // CJK extension G
+];
+
+/// Ranges of characters that take no column of their own.
+const ZERO: &[(u32, u32)] = &[
+    (0x0300, 0x036f), // combining diacritical marks
+    (0x0483, 0x0489),
+    (0x0591, 0x05bd),
+    (0x0610, 0x061a),
+    (0x064b, 0x065f),
+    (0x0e31, 0x0e31),
+    (0x0e34, 0x0e3a),
+    (0x0e47, 0x0e4e),
+    (0x1160, 0x11ff), // Hangul Jamo medials and finals
+    (0x1ab0, 0x1aff),
+    (0x1dc0, 0x1dff),
+    (0x200b, 0x200f), // zero-width space, joiners, marks
+    (0x20d0, 0x20ff), // combining marks for symbols
+    (0xfe00, 0xfe0f), // variation selectors
+    (0xfe20, 0xfe2f), // combining half marks
+    (0xfeff, 0xfeff), // byte order mark
+    (0xe0100, 0xe01ef),
+];
+
+fn in_table(table: &[(u32, u32)], c: u32) -> bool {
+    table
+        .binary_search_by(|&(lo, hi)| {
+            if hi < c {
+                core::cmp::Ordering::Less
+            } else if lo > c {
+                core::cmp::Ordering::Greater
+            } else {
+                core::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+/// Number of columns `c` occupies on screen.
+///
+/// Control characters count as two, since the editor shows them in caret
+/// notation, and C1 controls as four (`M-^X`).
+pub(crate) fn char_width(c: char) -> usize {
+    let code = c as u32;
+    if code < 0x20 || code == 0x7f {
+        2
+    } else if (0x80..0xa0).contains(&code) {
+        4
+    } else if code < 0x300 {
+        1
+    } else if in_table(ZERO, code) {
+        0
+    } else if in_table(WIDE, code) {
+        2
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tables_are_sorted() {
+        for table in [WIDE, ZERO] {
+            assert!(table.iter().all(|&(lo, hi)| lo <= hi));
+            assert!(table.windows(2).all(|w| w[0].1 < w[1].0));
+        }
+    }
+
+    #[test]
+    fn test_char_width() {
+        assert_eq!(char_width('a'), 1);
+        assert_eq!(char_width('ä'), 1);
+        assert_eq!(char_width('\t'), 2);
+        assert_eq!(char_width('\u{9b}'), 4);
+        assert_eq!(char_width('\u{301}'), 0);
+        assert_eq!(char_width('漢'), 2);
+        assert_eq!(char_width('한'), 2);
+        assert_eq!(char_width('Ａ'), 2);
+        assert_eq!(char_width('😀'), 2);
+    }
+}